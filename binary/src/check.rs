@@ -0,0 +1,196 @@
+use crate::errors::TraceError;
+use crate::Flag;
+use crate::FlagGroup;
+use crate::ReadMemory;
+use crate::RegisterState;
+use crate::Word;
+use crate::HALF_OFFSET;
+use ark_ff::PrimeField;
+
+/// Replays every step of an execution trace against memory. Returns the first
+/// step where an instruction's assertion doesn't hold or where the registers
+/// of the next step don't follow from the instruction's pc/ap/fp update rules.
+/// See section 4.5 of <https://eprint.iacr.org/2021/1063.pdf>.
+pub fn check_trace<F: PrimeField>(
+    register_states: &[RegisterState],
+    memory: &impl ReadMemory<F>,
+) -> Result<(), TraceError> {
+    for (step, &RegisterState { pc, ap, fp }) in register_states.iter().enumerate() {
+        let word = read(memory, step, pc)?;
+        if !is_valid_instruction(&word) {
+            return Err(TraceError::InvalidInstruction { step, pc });
+        }
+
+        // check all operands are in memory before using `Word`'s getters
+        let offset = |base: usize, offset: u16| {
+            (base.checked_add(offset as usize))
+                .and_then(|address| address.checked_sub(HALF_OFFSET))
+                .ok_or(TraceError::InvalidAddress { step, pc })
+        };
+        let dst_base = if word.get_flag(Flag::DstReg) { fp } else { ap };
+        let dst_addr = offset(dst_base, word.get_off_dst())?;
+        let op0_base = if word.get_flag(Flag::Op0Reg) { fp } else { ap };
+        let op0_addr = offset(op0_base, word.get_off_op0())?;
+        let op0 = read(memory, step, op0_addr)?;
+        let op1_base = match word.get_flag_group(FlagGroup::Op1Src) {
+            0 => usize::try_from(op0.0).map_err(|_| TraceError::InvalidAddress { step, pc })?,
+            1 => pc,
+            2 => fp,
+            _ => ap,
+        };
+        let op1_addr = offset(op1_base, word.get_off_op1())?;
+        read(memory, step, dst_addr)?;
+        read(memory, step, op1_addr)?;
+
+        let dst = word.get_dst(ap, fp, memory);
+        let op0 = op0.into_felt();
+        let op1 = word.get_op1(pc, ap, fp, memory);
+        let res = word.get_res(pc, ap, fp, memory);
+        let size = F::from(1 + word.get_flag(Flag::Op1Imm) as u8);
+        let [pc_felt, ap_felt, fp_felt] = [pc, ap, fp].map(|v| F::from(v as u64));
+
+        let opcode = word.get_flag_group(FlagGroup::Opcode);
+        match opcode {
+            // call
+            1 if dst != fp_felt || op0 != pc_felt + size => {
+                return Err(TraceError::CallFailed { step, pc })
+            }
+            // assert_eq
+            4 if dst != res => return Err(TraceError::AssertEqFailed { step, pc }),
+            _ => {}
+        }
+
+        // the last step has no next registers to check
+        let Some(next) = register_states.get(step + 1) else {
+            break;
+        };
+
+        let next_pc = match word.get_flag_group(FlagGroup::PcUpdate) {
+            0 => pc_felt + size,
+            1 => res,
+            2 => pc_felt + res,
+            _ if dst.is_zero() => pc_felt + size,
+            _ => pc_felt + op1,
+        };
+        let next_ap = match word.get_flag_group(FlagGroup::ApUpdate) {
+            _ if opcode == 1 => ap_felt + F::from(2u8),
+            0 => ap_felt,
+            1 => ap_felt + res,
+            _ => ap_felt + F::one(),
+        };
+        let next_fp = match opcode {
+            1 => ap_felt + F::from(2u8),
+            2 => dst,
+            _ => fp_felt,
+        };
+
+        for (register, expected, actual) in [
+            ("pc", next_pc, next.pc),
+            ("ap", next_ap, next.ap),
+            ("fp", next_fp, next.fp),
+        ] {
+            if expected != F::from(actual as u64) {
+                return Err(TraceError::InvalidRegister {
+                    step: step + 1,
+                    register,
+                    value: actual,
+                    pc,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+fn read<F: PrimeField>(
+    memory: &impl ReadMemory<F>,
+    step: usize,
+    address: usize,
+) -> Result<Word<F>, TraceError> {
+    memory
+        .get(address)
+        .ok_or(TraceError::MissingMemory { step, address })
+}
+
+/// Returns false if the instruction has an invalid combination of flags
+fn is_valid_instruction<F>(word: &Word<F>) -> bool {
+    let op1_src = word.get_flag_group(FlagGroup::Op1Src);
+    let res_logic = word.get_flag_group(FlagGroup::ResLogic);
+    let pc_update = word.get_flag_group(FlagGroup::PcUpdate);
+    let ap_update = word.get_flag_group(FlagGroup::ApUpdate);
+    let opcode = word.get_flag_group(FlagGroup::Opcode);
+    word.0.bit_len() <= 63
+        && matches!(op1_src, 0 | 1 | 2 | 4)
+        && (op1_src != 1 || word.get_off_op1() as usize == HALF_OFFSET + 1)
+        && matches!(res_logic, 0..=2)
+        && matches!(pc_update, 0 | 1 | 2 | 4)
+        && matches!(ap_update, 0..=2)
+        && matches!(opcode, 0 | 1 | 2 | 4)
+        // `res` is unused by conditional jumps
+        && (pc_update != 4 || (res_logic == 0 && opcode == 0 && ap_update != 1))
+        // call always increments ap by 2
+        && (opcode != 1 || ap_update == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_trace;
+    use crate::errors::TraceError;
+    use crate::Memory;
+    use crate::RegisterState;
+    use crate::RegisterStates;
+    use crate::Word;
+    use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp;
+    use ruint::aliases::U256;
+    use std::fs::File;
+
+    fn read_execution() -> (Vec<RegisterState>, Memory<Fp>) {
+        let path = |path: &str| format!("{}/../{path}", env!("CARGO_MANIFEST_DIR"));
+        let trace_file = File::open(path("example/trace.bin")).unwrap();
+        let memory_file = File::open(path("example/memory.bin")).unwrap();
+        let register_states = RegisterStates::from_reader(trace_file).unwrap();
+        let memory = Memory::from_reader(memory_file).unwrap();
+        (register_states.to_vec(), memory)
+    }
+
+    #[test]
+    fn array_sum_trace_is_valid() {
+        let (register_states, memory) = read_execution();
+
+        assert!(check_trace(&register_states, &memory).is_ok());
+    }
+
+    #[test]
+    fn corrupted_register_is_rejected() {
+        let (mut register_states, memory) = read_execution();
+        // step 2 is a `call` so ap of step 3 must be 2 more than ap of step 2
+        register_states[3].ap += 1;
+
+        let err = check_trace(&register_states, &memory).unwrap_err();
+
+        assert!(matches!(
+            err,
+            TraceError::InvalidRegister {
+                step: 3,
+                register: "ap",
+                value: 50,
+                pc: 24,
+            }
+        ));
+    }
+
+    #[test]
+    fn corrupted_memory_is_rejected() {
+        let (register_states, mut memory) = read_execution();
+        // step 5 is `[ap + 0] = <imm>; ap++` at pc 26 which writes to address 50
+        let value = memory[50].unwrap().0;
+        memory.insert(50, Word::new(value + U256::from(1)));
+
+        let err = check_trace(&register_states, &memory).unwrap_err();
+
+        assert!(matches!(
+            err,
+            TraceError::AssertEqFailed { step: 5, pc: 26 }
+        ));
+    }
+}
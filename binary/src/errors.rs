@@ -1,3 +1,4 @@
+use crate::Layout;
 use ruint::aliases::U256;
 use std::error::Error;
 use std::fmt::Display;
@@ -19,3 +20,288 @@ impl Display for InvalidFieldElementError {
 }
 
 impl Error for InvalidFieldElementError {}
+
+#[derive(Debug, Clone, Copy)]
+pub enum MemoryPageError {
+    /// Page ids must be consecutive starting from the main page (page 0)
+    MissingPage(u32),
+    /// Non-main pages must cover a continuous range of addresses
+    NotContinuous { page: u32, address: u32 },
+}
+
+impl Display for MemoryPageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingPage(page) => write!(f, "public memory page {page} has no entries"),
+            Self::NotContinuous { page, address } => write!(
+                f,
+                "public memory page {page} is not continuous at address {address}"
+            ),
+        }
+    }
+}
+
+impl Error for MemoryPageError {}
+
+#[derive(Debug, Clone, Copy)]
+pub enum PublicInputError {
+    InvalidRangeCheckBounds {
+        rc_min: u16,
+        rc_max: u16,
+    },
+    /// The number of steps must be a power of two
+    InvalidNumSteps(u64),
+    /// A builtin segment required by the layout is missing
+    MissingSegment(&'static str),
+    /// Public input has a segment for a builtin the layout doesn't have
+    UnsupportedBuiltin {
+        layout: Layout,
+        builtin: &'static str,
+    },
+    /// The dynamic layout requires `dynamic_params`
+    MissingDynamicParams,
+    /// Public input has both `dynamic_params` and `layout_params`
+    DuplicateDynamicParams,
+    /// A segment's stop pointer is before the start of the segment
+    InvalidStopPointer {
+        segment: &'static str,
+        begin_addr: u32,
+        stop_ptr: u32,
+    },
+    /// A segment ends after the start of the next segment
+    OverlappingSegments(&'static str, &'static str),
+    /// A builtin segment has more instances than fit in the trace
+    InsufficientCapacity {
+        builtin: &'static str,
+        instances: u32,
+        capacity: u64,
+    },
+    /// Public memory has no entry for address 1 (used for padding)
+    MissingPadding,
+    /// A continuous public memory page is missing an address
+    InvalidMemoryPage(MemoryPageError),
+}
+
+impl Display for PublicInputError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidRangeCheckBounds { rc_min, rc_max } => {
+                write!(f, "rc_min {rc_min} is greater than rc_max {rc_max}")
+            }
+            Self::InvalidNumSteps(n_steps) => {
+                write!(f, "n_steps {n_steps} is not a power of two")
+            }
+            Self::MissingSegment(name) => {
+                write!(f, "{name} segment is required by the layout but is missing")
+            }
+            Self::InvalidStopPointer {
+                segment,
+                begin_addr,
+                stop_ptr,
+            } => write!(
+                f,
+                "{segment} segment stop pointer {stop_ptr} is before its start {begin_addr}"
+            ),
+            Self::UnsupportedBuiltin { layout, builtin } => {
+                write!(
+                    f,
+                    "{builtin} builtin is not supported by the {layout} layout"
+                )
+            }
+            Self::MissingDynamicParams => write!(f, "dynamic layout requires dynamic_params"),
+            Self::DuplicateDynamicParams => {
+                write!(f, "public input has both dynamic_params and layout_params")
+            }
+            Self::OverlappingSegments(first, second) => {
+                write!(f, "{first} segment overlaps the {second} segment")
+            }
+            Self::InsufficientCapacity {
+                builtin,
+                instances,
+                capacity,
+            } => write!(
+                f,
+                "{builtin} segment has {instances} instances but n_steps only has room for {capacity}"
+            ),
+            Self::MissingPadding => write!(f, "public memory has no entry for address 1"),
+            Self::InvalidMemoryPage(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl Error for PublicInputError {}
+
+#[derive(Debug, Clone, Copy)]
+pub enum PrivateInputError {
+    /// Memory has an ECDSA instance without a signature
+    MissingSignature(u32),
+    /// A builtin segment's stop pointer is before its start
+    InvalidSegment(&'static str),
+    /// A builtin instance doesn't match the values in memory
+    InconsistentInstance { builtin: &'static str, index: u32 },
+    /// Memory has a builtin instance that's missing from the private input
+    MissingInstance { builtin: &'static str, index: u32 },
+}
+
+impl Display for PrivateInputError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingSignature(index) => {
+                write!(f, "ecdsa instance {index} has no signature")
+            }
+            Self::InvalidSegment(builtin) => {
+                write!(f, "{builtin} segment ends before it starts")
+            }
+            Self::InconsistentInstance { builtin, index } => write!(
+                f,
+                "{builtin} instance {index} doesn't match the values in memory"
+            ),
+            Self::MissingInstance { builtin, index } => {
+                write!(
+                    f,
+                    "{builtin} instance {index} is missing from the private input"
+                )
+            }
+        }
+    }
+}
+
+impl Error for PrivateInputError {}
+
+#[derive(Debug)]
+pub enum BinaryParseError {
+    /// The reader failed for a reason other than reaching the end of input
+    Io(std::io::Error),
+    /// The input ended part way through a record
+    Truncated {
+        offset: u64,
+        record_size: usize,
+        bytes_read: usize,
+    },
+    /// A memory value is not a valid field element
+    InvalidFieldElement {
+        offset: u64,
+        error: InvalidFieldElementError,
+    },
+    /// A memory address exceeds the Cairo address space
+    InvalidAddress { offset: u64, address: u64 },
+    /// A register value in a trace exceeds the Cairo address space
+    InvalidRegister { offset: u64, value: u64 },
+}
+
+impl Display for BinaryParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "failed to read binary input: {error}"),
+            Self::Truncated {
+                offset,
+                record_size,
+                bytes_read,
+            } => write!(
+                f,
+                "truncated record at byte offset {offset}: expected {record_size} bytes but only {bytes_read} remain"
+            ),
+            Self::InvalidFieldElement { offset, error } => {
+                write!(f, "invalid memory value at byte offset {offset}: {error}")
+            }
+            Self::InvalidAddress { offset, address } => write!(
+                f,
+                "address {address} at byte offset {offset} exceeds the maximum address {}",
+                u32::MAX
+            ),
+            Self::InvalidRegister { offset, value } => write!(
+                f,
+                "register value {value} at byte offset {offset} exceeds the maximum address {}",
+                u32::MAX
+            ),
+        }
+    }
+}
+
+impl Error for BinaryParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::InvalidFieldElement { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for BinaryParseError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum InstructionDecodeError {
+    /// The word isn't a valid instruction encoding
+    InvalidFlags,
+}
+
+impl Display for InstructionDecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidFlags => write!(f, "invalid instruction encoding"),
+        }
+    }
+}
+
+impl Error for InstructionDecodeError {}
+
+#[derive(Debug, Clone, Copy)]
+pub enum TraceError {
+    /// An instruction or one of its operands is missing from memory
+    MissingMemory { step: usize, address: usize },
+    /// The instruction at pc has an invalid combination of flags
+    InvalidInstruction { step: usize, pc: usize },
+    /// An operand address of the instruction at pc is out of range
+    InvalidAddress { step: usize, pc: usize },
+    /// `assert_eq` instruction doesn't hold
+    AssertEqFailed { step: usize, pc: usize },
+    /// `call` instruction didn't push fp and the return pc
+    CallFailed { step: usize, pc: usize },
+    /// A register doesn't follow from the previous step's instruction
+    InvalidRegister {
+        step: usize,
+        register: &'static str,
+        value: usize,
+        pc: usize,
+    },
+}
+
+impl Display for TraceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingMemory { step, address } => {
+                write!(f, "step {step}: memory at address {address} is missing")
+            }
+            Self::InvalidInstruction { step, pc } => {
+                write!(f, "step {step}: invalid instruction at pc {pc}")
+            }
+            Self::InvalidAddress { step, pc } => write!(
+                f,
+                "step {step}: operand address of the instruction at pc {pc} is out of range"
+            ),
+            Self::AssertEqFailed { step, pc } => {
+                write!(f, "step {step}: assert_eq failed at pc {pc}")
+            }
+            Self::CallFailed { step, pc } => write!(
+                f,
+                "step {step}: call at pc {pc} didn't push the frame pointer and return pc"
+            ),
+            Self::InvalidRegister {
+                step,
+                register,
+                value,
+                pc,
+            } => write!(
+                f,
+                "step {step}: {register} {value} doesn't follow from the instruction at pc {pc}"
+            ),
+        }
+    }
+}
+
+impl Error for TraceError {}
@@ -0,0 +1,358 @@
+use crate::errors::InstructionDecodeError;
+use crate::Flag;
+use crate::FlagGroup;
+use crate::Word;
+use crate::HALF_OFFSET;
+use ark_ff::PrimeField;
+use num_bigint::BigUint;
+use std::fmt::Display;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Register {
+    Ap,
+    Fp,
+}
+
+/// Memory cell at an offset from a register e.g. `[fp + -3]`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Operand {
+    pub register: Register,
+    pub offset: i16,
+}
+
+impl Operand {
+    pub const fn new(register: Register, offset: i16) -> Self {
+        Self { register, offset }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op1<F> {
+    /// Immediate value stored after the instruction. The value isn't part of
+    /// the instruction word so is empty until set with [Instruction::with_imm].
+    Imm(Option<F>),
+    /// Memory cell at an offset from ap or fp
+    Register(Operand),
+    /// Memory cell at an offset from the address stored in op0 e.g.
+    /// `[[fp + -3] + 2]`
+    Deref(Operand, i16),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Res<F> {
+    Op1(Op1<F>),
+    Add(Operand, Op1<F>),
+    Mul(Operand, Op1<F>),
+}
+
+/// Decoded Cairo instruction
+/// <https://eprint.iacr.org/2021/1063.pdf> section 5
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction<F> {
+    /// `dst = res`
+    AssertEq {
+        dst: Operand,
+        res: Res<F>,
+        ap_inc: bool,
+    },
+    /// `jmp abs res` or `jmp rel res`
+    Jmp {
+        relative: bool,
+        res: Res<F>,
+        ap_inc: bool,
+    },
+    /// `jmp rel offset if condition != 0`
+    Jnz {
+        offset: Op1<F>,
+        condition: Operand,
+        ap_inc: bool,
+    },
+    /// `call abs target` or `call rel target`
+    Call {
+        relative: bool,
+        target: Op1<F>,
+    },
+    Ret,
+    /// `ap += res`
+    AddAp {
+        res: Res<F>,
+    },
+}
+
+impl<F> Instruction<F> {
+    /// Number of memory cells used by the instruction (instructions with an
+    /// immediate take two cells)
+    pub fn size(&self) -> usize {
+        1 + self.has_imm() as usize
+    }
+
+    /// Returns true if op1 is the value in the memory cell after the
+    /// instruction
+    pub fn has_imm(&self) -> bool {
+        matches!(self.op1(), Some(Op1::Imm(_)))
+    }
+
+    /// Sets the value of the instruction's immediate (the memory cell after
+    /// the instruction). Instructions without an immediate are unchanged.
+    pub fn with_imm(mut self, value: F) -> Self {
+        if let Some(Op1::Imm(imm)) = self.op1_mut() {
+            *imm = Some(value);
+        }
+        self
+    }
+
+    fn op1(&self) -> Option<&Op1<F>> {
+        match self {
+            Self::AssertEq { res, .. } | Self::Jmp { res, .. } | Self::AddAp { res } => match res {
+                Res::Op1(op1) | Res::Add(_, op1) | Res::Mul(_, op1) => Some(op1),
+            },
+            Self::Jnz { offset, .. } => Some(offset),
+            Self::Call { target, .. } => Some(target),
+            Self::Ret => None,
+        }
+    }
+
+    fn op1_mut(&mut self) -> Option<&mut Op1<F>> {
+        match self {
+            Self::AssertEq { res, .. } | Self::Jmp { res, .. } | Self::AddAp { res } => match res {
+                Res::Op1(op1) | Res::Add(_, op1) | Res::Mul(_, op1) => Some(op1),
+            },
+            Self::Jnz { offset, .. } => Some(offset),
+            Self::Call { target, .. } => Some(target),
+            Self::Ret => None,
+        }
+    }
+}
+
+impl<F: PrimeField> Word<F> {
+    /// Decodes the instruction. The value of an immediate is stored in the
+    /// memory cell after the instruction and can be set with
+    /// [Instruction::with_imm].
+    pub fn decode(&self) -> Result<Instruction<F>, InstructionDecodeError> {
+        use InstructionDecodeError::*;
+        if self.0.bit_len() > 63 {
+            return Err(InvalidFlags);
+        }
+
+        let offset = |biased_offset: u16| (biased_offset as i32 - HALF_OFFSET as i32) as i16;
+        let register = |flag| match self.get_flag(flag) {
+            true => Register::Fp,
+            false => Register::Ap,
+        };
+        let dst = Operand::new(register(Flag::DstReg), offset(self.get_off_dst()));
+        let op0 = Operand::new(register(Flag::Op0Reg), offset(self.get_off_op0()));
+        let off_op1 = offset(self.get_off_op1());
+        let op1 = match self.get_flag_group(FlagGroup::Op1Src) {
+            0 => Op1::Deref(op0, off_op1),
+            1 if off_op1 == 1 => Op1::Imm(None),
+            2 => Op1::Register(Operand::new(Register::Fp, off_op1)),
+            4 => Op1::Register(Operand::new(Register::Ap, off_op1)),
+            _ => return Err(InvalidFlags),
+        };
+        let res_logic = self.get_flag_group(FlagGroup::ResLogic);
+        let res = match res_logic {
+            0 => Res::Op1(op1),
+            1 => Res::Add(op0, op1),
+            2 => Res::Mul(op0, op1),
+            _ => return Err(InvalidFlags),
+        };
+
+        let pc_update = self.get_flag_group(FlagGroup::PcUpdate);
+        let ap_update = self.get_flag_group(FlagGroup::ApUpdate);
+        let ap_inc = ap_update == 2;
+        let relative = pc_update == 2;
+        let instruction = match (self.get_flag_group(FlagGroup::Opcode), pc_update, ap_update) {
+            (4, 0, 0 | 2) => Instruction::AssertEq { dst, res, ap_inc },
+            (0, 1 | 2, 0 | 2) => Instruction::Jmp {
+                relative,
+                res,
+                ap_inc,
+            },
+            (0, 4, 0 | 2) if res_logic == 0 => Instruction::Jnz {
+                offset: op1,
+                condition: dst,
+                ap_inc,
+            },
+            (0, 0, 1) => Instruction::AddAp { res },
+            // call pushes fp and the return pc to [ap] and [ap + 1]
+            (1, 1 | 2, 0)
+                if res_logic == 0
+                    && dst == Operand::new(Register::Ap, 0)
+                    && op0 == Operand::new(Register::Ap, 1) =>
+            {
+                Instruction::Call {
+                    relative,
+                    target: op1,
+                }
+            }
+            // ret restores fp and pc from [fp - 2] and [fp - 1]
+            (2, 1, 0)
+                if dst == Operand::new(Register::Fp, -2)
+                    && res == Res::Op1(Op1::Register(Operand::new(Register::Fp, -1))) =>
+            {
+                Instruction::Ret
+            }
+            _ => return Err(InvalidFlags),
+        };
+        Ok(instruction)
+    }
+}
+
+impl Display for Register {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ap => write!(f, "ap"),
+            Self::Fp => write!(f, "fp"),
+        }
+    }
+}
+
+impl Display for Operand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{} + {}]", self.register, self.offset)
+    }
+}
+
+impl<F: PrimeField> Display for Op1<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Imm(Some(value)) => write!(f, "{}", SignedFelt(*value)),
+            Self::Imm(None) => write!(f, "<imm>"),
+            Self::Register(operand) => write!(f, "{operand}"),
+            Self::Deref(op0, offset) => write!(f, "[{op0} + {offset}]"),
+        }
+    }
+}
+
+impl<F: PrimeField> Display for Res<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Op1(op1) => write!(f, "{op1}"),
+            Self::Add(op0, op1) => write!(f, "{op0} + {op1}"),
+            Self::Mul(op0, op1) => write!(f, "{op0} * {op1}"),
+        }
+    }
+}
+
+impl<F: PrimeField> Display for Instruction<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ap_inc = match self {
+            Self::AssertEq { dst, res, ap_inc } => {
+                write!(f, "{dst} = {res}")?;
+                *ap_inc
+            }
+            Self::Jmp {
+                relative,
+                res,
+                ap_inc,
+            } => {
+                let mode = if *relative { "rel" } else { "abs" };
+                write!(f, "jmp {mode} {res}")?;
+                *ap_inc
+            }
+            Self::Jnz {
+                offset,
+                condition,
+                ap_inc,
+            } => {
+                write!(f, "jmp rel {offset} if {condition} != 0")?;
+                *ap_inc
+            }
+            Self::Call { relative, target } => {
+                let mode = if *relative { "rel" } else { "abs" };
+                write!(f, "call {mode} {target}")?;
+                false
+            }
+            Self::Ret => {
+                write!(f, "ret")?;
+                false
+            }
+            Self::AddAp { res } => {
+                write!(f, "ap += {res}")?;
+                false
+            }
+        };
+        if ap_inc {
+            write!(f, "; ap++")?;
+        }
+        Ok(())
+    }
+}
+
+/// Displays field elements in the upper half of the field as negative values
+/// (the same as Cairo)
+pub struct SignedFelt<F>(pub F);
+
+impl<F: PrimeField> Display for SignedFelt<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value: BigUint = self.0.into_bigint().into();
+        let half_modulus: BigUint = F::MODULUS_MINUS_ONE_DIV_TWO.into();
+        if value > half_modulus {
+            let negated: BigUint = (-self.0).into_bigint().into();
+            write!(f, "-{negated}")
+        } else {
+            write!(f, "{value}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::CompiledProgram;
+    use crate::Word;
+    use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp;
+    use ruint::uint;
+    use std::fs::File;
+
+    #[test]
+    fn assert_eq_with_immediate_is_displayed() {
+        let word = Word::<Fp>::new(uint!(0x482680017ffd8000_U256));
+
+        let instruction = word.decode().unwrap();
+
+        assert_eq!(2, instruction.size());
+        assert_eq!(
+            "[ap + 0] = [fp + -3] + <imm>; ap++",
+            instruction.to_string()
+        );
+        assert_eq!(
+            "[ap + 0] = [fp + -3] + 5; ap++",
+            instruction.with_imm(Fp::from(5u8)).to_string()
+        );
+    }
+
+    #[test]
+    fn word_over_63_bits_is_not_decoded() {
+        let word = Word::<Fp>::new(uint!(0x8000000000000000_U256));
+
+        assert!(word.decode().is_err());
+    }
+
+    #[test]
+    fn array_sum_is_disassembled() {
+        let path = format!("{}/../example/array-sum.json", env!("CARGO_MANIFEST_DIR"));
+        let program: CompiledProgram<Fp> =
+            serde_json::from_reader(File::open(path).unwrap()).unwrap();
+
+        let instructions = program.disassemble();
+
+        let expected = [
+            (0, "ap += 0"),
+            (2, "call rel 21"),
+            (4, "jmp rel 0"),
+            (6, "ap += 1"),
+            (8, "ret"),
+            (9, "jmp rel 5 if [fp + -3] != 0"),
+        ];
+        for ((pc, instruction), (expected_pc, expected)) in instructions.iter().zip(expected) {
+            assert_eq!(expected_pc, *pc);
+            assert_eq!(expected, instruction.unwrap().to_string());
+        }
+        // every value of the program is an instruction or an immediate
+        let size = instructions
+            .iter()
+            .map(|(_, i)| i.unwrap().size())
+            .sum::<usize>();
+        assert_eq!(program.data.len(), size);
+    }
+}
@@ -1,4 +1,4 @@
-#![feature(buf_read_has_data_left, int_roundings)]
+#![feature(int_roundings)]
 
 extern crate alloc;
 
@@ -8,26 +8,54 @@ use ark_ff::PrimeField;
 use ark_serialize::CanonicalDeserialize;
 use ark_serialize::CanonicalSerialize;
 use ark_serialize::Valid;
+use core::iter::zip;
+use mmap::REGISTER_STATE_RECORD_SIZE;
 use num_bigint::BigUint;
 use ruint::aliases::U256;
 use ruint::uint;
 use serde::Deserialize;
 use serde::Serialize;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fmt::Debug;
 use std::fmt::Display;
-use std::io::BufRead;
 use std::io::BufReader;
 use std::io::Read;
 use std::marker::PhantomData;
 use std::ops::Deref;
+use std::ops::Index;
 use std::path::PathBuf;
-use utils::deserialize_hex_str;
-use utils::deserialize_hex_str_memory_entries;
+use utils::check_field_element;
 use utils::deserialize_vec_hex_str;
 use utils::field_bytes;
+use utils::hex_str;
+use utils::read_address;
+use utils::read_record;
+use utils::read_register;
 
+mod check;
 mod errors;
+mod instruction;
+mod mmap;
 mod utils;
 
+pub use check::check_trace;
+pub use errors::BinaryParseError;
+pub use errors::InstructionDecodeError;
+pub use errors::InvalidFieldElementError;
+pub use errors::MemoryPageError;
+pub use errors::PrivateInputError;
+pub use errors::PublicInputError;
+pub use errors::TraceError;
+pub use instruction::Instruction;
+pub use instruction::Op1;
+pub use instruction::Operand;
+pub use instruction::Register;
+pub use instruction::Res;
+pub use instruction::SignedFelt;
+pub use mmap::MmapMemory;
+pub use mmap::MmapRegisterStates;
+
 // https://eprint.iacr.org/2021/1063.pdf figure 3
 /// Word offset of `off_DST`
 pub const OFF_DST_BIT_OFFSET: usize = 0;
@@ -49,6 +77,7 @@ pub const HALF_OFFSET: usize = 2usize.pow(15);
 
 /// Holds register values
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[repr(C)]
 pub struct RegisterState {
     pub ap: usize,
     pub fp: usize,
@@ -67,6 +96,7 @@ pub enum Layout {
     RecursiveLargeOutput = 5,
     AllSolidity = 6,
     StarknetWithKeccak = 7,
+    Dynamic = 8,
 }
 
 impl Display for Layout {
@@ -83,30 +113,137 @@ impl Display for Layout {
                 Self::RecursiveLargeOutput => "recursive_large_output",
                 Self::AllSolidity => "all_solidity",
                 Self::StarknetWithKeccak => "starknet_with_keccak",
+                Self::Dynamic => "dynamic",
             }
         )
     }
 }
 
 impl Layout {
-    const SHARP_CODE_STARKNET: u128 = 8319381555716711796;
-    const SHARP_CODE_RECURSIVE: u128 = 2110234636557836973669;
-
-    // Returns the unique code used by SHARP associated to this layout
-    pub const fn sharp_code(&self) -> u128 {
+    const ALL: [Self; 9] = [
+        Self::Plain,
+        Self::Small,
+        Self::Dex,
+        Self::Recursive,
+        Self::Starknet,
+        Self::RecursiveLargeOutput,
+        Self::AllSolidity,
+        Self::StarknetWithKeccak,
+        Self::Dynamic,
+    ];
+
+    /// Returns the names of the builtins in this layout. Builtins are in the
+    /// order their segments are allocated by the Cairo runner.
+    pub const fn builtins(&self) -> &'static [&'static str] {
         match self {
-            Self::Starknet => Self::SHARP_CODE_STARKNET,
-            Self::Recursive => Self::SHARP_CODE_RECURSIVE,
-            _ => unimplemented!(),
+            Self::Plain => &[],
+            Self::Small | Self::Dex => &["output", "pedersen", "range_check", "ecdsa"],
+            Self::Recursive => &["output", "pedersen", "range_check", "bitwise"],
+            Self::Starknet => &[
+                "output",
+                "pedersen",
+                "range_check",
+                "ecdsa",
+                "bitwise",
+                "ec_op",
+                "poseidon",
+            ],
+            Self::RecursiveLargeOutput => {
+                &["output", "pedersen", "range_check", "bitwise", "poseidon"]
+            }
+            Self::AllSolidity => &[
+                "output",
+                "pedersen",
+                "range_check",
+                "ecdsa",
+                "bitwise",
+                "ec_op",
+            ],
+            Self::StarknetWithKeccak => &[
+                "output",
+                "pedersen",
+                "range_check",
+                "ecdsa",
+                "bitwise",
+                "ec_op",
+                "keccak",
+                "poseidon",
+            ],
+            // builtins of the dynamic layout are determined by its parameters
+            Self::Dynamic => &[],
         }
     }
 
-    pub const fn from_sharp_code(code: u128) -> Self {
-        match code {
-            Self::SHARP_CODE_STARKNET => Self::Starknet,
-            Self::SHARP_CODE_RECURSIVE => Self::Recursive,
-            _ => unimplemented!(),
-        }
+    /// Returns the number of steps per instance of a builtin in this layout
+    /// (the same as cairo-lang). Returns none for the output builtin, builtins
+    /// that aren't in the layout and the dynamic layout (ratios are part of its
+    /// parameters).
+    pub fn builtin_ratio(&self, builtin: &str) -> Option<u32> {
+        let ratios: &[(&str, u32)] = match self {
+            Self::Plain | Self::Dynamic => &[],
+            Self::Small | Self::Dex => &[("pedersen", 8), ("range_check", 8), ("ecdsa", 512)],
+            Self::Recursive => &[("pedersen", 128), ("range_check", 8), ("bitwise", 8)],
+            Self::Starknet => &[
+                ("pedersen", 32),
+                ("range_check", 16),
+                ("ecdsa", 2048),
+                ("bitwise", 64),
+                ("ec_op", 1024),
+                ("poseidon", 32),
+            ],
+            Self::RecursiveLargeOutput => &[
+                ("pedersen", 128),
+                ("range_check", 8),
+                ("bitwise", 8),
+                ("poseidon", 8),
+            ],
+            Self::AllSolidity => &[
+                ("pedersen", 8),
+                ("range_check", 8),
+                ("ecdsa", 512),
+                ("bitwise", 256),
+                ("ec_op", 256),
+            ],
+            Self::StarknetWithKeccak => &[
+                ("pedersen", 32),
+                ("range_check", 16),
+                ("ecdsa", 2048),
+                ("bitwise", 64),
+                ("ec_op", 1024),
+                ("keccak", 2048),
+                ("poseidon", 32),
+            ],
+        };
+        ratios
+            .iter()
+            .find(|(name, _)| *name == builtin)
+            .map(|&(_, ratio)| ratio)
+    }
+
+    /// Returns the unique code used by SHARP associated to this layout.
+    /// The code is the layout's name interpreted as a big-endian integer e.g.
+    /// the code for the `starknet` layout is `0x737461726b6e6574`.
+    pub fn sharp_code(&self) -> U256 {
+        U256::try_from_be_slice(self.to_string().as_bytes()).unwrap()
+    }
+
+    /// Returns the layout associated to a SHARP code (if any)
+    pub fn from_sharp_code(code: U256) -> Option<Self> {
+        Self::ALL.into_iter().find(|layout| layout.sharp_code() == code)
+    }
+
+    /// Returns the code used to serialize the layout. This is the SHARP code if
+    /// it fits in a u128 (the serialized format of earlier versions) otherwise
+    /// the layout's discriminant. Discriminants can't collide with SHARP codes
+    /// since all layout names are longer than a byte.
+    fn serialization_code(&self) -> u128 {
+        u128::try_from(self.sharp_code()).unwrap_or(*self as u128)
+    }
+
+    fn from_serialization_code(code: u128) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|layout| layout.serialization_code() == code)
     }
 }
 
@@ -116,7 +253,7 @@ impl CanonicalSerialize for Layout {
         writer: W,
         compress: ark_serialize::Compress,
     ) -> Result<(), ark_serialize::SerializationError> {
-        self.sharp_code()
+        self.serialization_code()
             .to_be_bytes()
             .serialize_with_mode(writer, compress)
     }
@@ -138,49 +275,146 @@ impl CanonicalDeserialize for Layout {
         compress: ark_serialize::Compress,
         validate: ark_serialize::Validate,
     ) -> Result<Self, ark_serialize::SerializationError> {
-        Ok(Self::from_sharp_code(u128::from_be_bytes(
-            <[u8; 16]>::deserialize_with_mode(reader, compress, validate)?,
-        )))
+        let code = u128::from_be_bytes(<[u8; 16]>::deserialize_with_mode(
+            reader, compress, validate,
+        )?);
+        Self::from_serialization_code(code).ok_or(ark_serialize::SerializationError::InvalidData)
     }
 }
 
 #[derive(Debug)]
-pub struct RegisterStates(Vec<RegisterState>);
+pub struct RegisterStates(RegisterStatesStorage);
+
+#[derive(Debug)]
+enum RegisterStatesStorage {
+    Owned(Vec<RegisterState>),
+    Mapped(MmapRegisterStates),
+}
 
 impl RegisterStates {
     /// Parses trace data in the format outputted by a `cairo-run`.
-    pub fn from_reader(r: impl Read) -> Self {
-        // TODO: errors
+    pub fn from_reader(r: impl Read) -> Result<Self, BinaryParseError> {
         let mut reader = BufReader::new(r);
         let mut register_states = Vec::new();
-        while reader.has_data_left().unwrap() {
-            let entry: RegisterState = bincode::deserialize_from(&mut reader).unwrap();
-            register_states.push(entry);
+        let mut record = [0; REGISTER_STATE_RECORD_SIZE];
+        let mut offset = 0;
+        while read_record(&mut reader, &mut record, offset)? {
+            let ap = read_register(&record[0..8], offset)?;
+            let fp = read_register(&record[8..16], offset + 8)?;
+            let pc = read_register(&record[16..24], offset + 16)?;
+            register_states.push(RegisterState { ap, fp, pc });
+            offset += REGISTER_STATE_RECORD_SIZE as u64;
         }
-        RegisterStates(register_states)
+        Ok(RegisterStates(RegisterStatesStorage::Owned(
+            register_states,
+        )))
+    }
+}
+
+impl From<Vec<RegisterState>> for RegisterStates {
+    fn from(register_states: Vec<RegisterState>) -> Self {
+        RegisterStates(RegisterStatesStorage::Owned(register_states))
+    }
+}
+
+impl From<MmapRegisterStates> for RegisterStates {
+    fn from(register_states: MmapRegisterStates) -> Self {
+        RegisterStates(RegisterStatesStorage::Mapped(register_states))
     }
 }
 
 impl Deref for RegisterStates {
-    type Target = Vec<RegisterState>;
+    type Target = [RegisterState];
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        match &self.0 {
+            RegisterStatesStorage::Owned(register_states) => register_states,
+            RegisterStatesStorage::Mapped(register_states) => register_states,
+        }
     }
 }
 
+/// Partial memory of a Cairo program. Values are held in one dense vector per
+/// memory segment so a high address in one segment doesn't require allocating
+/// every address below it.
+#[derive(Debug)]
+pub struct Memory<F> {
+    /// Dense memory chunks ordered by their first address. Each chunk spans up
+    /// to the first address of the next chunk.
+    chunks: Vec<MemoryChunk<F>>,
+    none: Option<Word<F>>,
+}
+
 #[derive(Debug)]
-pub struct Memory<F>(Vec<Option<Word<F>>>);
+struct MemoryChunk<F> {
+    begin_addr: usize,
+    // TODO: DOC: None used for nondeterministic values?
+    words: Vec<Option<Word<F>>>,
+}
 
 impl<F: Field> Memory<F> {
+    /// Creates empty memory with a dense chunk for each segment. Addresses
+    /// outside all segments (e.g. segments that aren't listed in
+    /// [MemorySegments]) belong to the preceding chunk.
+    pub fn new(memory_segments: &MemorySegments) -> Self {
+        let mut memory = Self::default();
+        let mut segments = memory_segments.iter().collect::<Vec<Segment>>();
+        segments.sort_by_key(|segment| segment.begin_addr);
+        for Segment {
+            begin_addr,
+            stop_ptr,
+        } in segments
+        {
+            let begin_addr = begin_addr as usize;
+            if begin_addr == memory.chunks.last().unwrap().begin_addr {
+                continue;
+            }
+            let len = (stop_ptr as usize).saturating_sub(begin_addr);
+            memory.chunks.push(MemoryChunk {
+                begin_addr,
+                words: Vec::with_capacity(len),
+            });
+        }
+        memory
+    }
+
     /// Parses the partial memory data outputted by a `cairo-run`.
-    pub fn from_reader(r: impl Read) -> Self
+    /// All values are stored in a single dense chunk. Prefer
+    /// [Memory::from_reader_with_segments] if the memory segments are known.
+    pub fn from_reader(r: impl Read) -> Result<Self, BinaryParseError>
+    where
+        F: PrimeField,
+    {
+        Self::default().read_entries(r)
+    }
+
+    /// Parses the partial memory data outputted by a `cairo-run` into a dense
+    /// chunk per memory segment.
+    pub fn from_reader_with_segments(
+        r: impl Read,
+        memory_segments: &MemorySegments,
+    ) -> Result<Self, BinaryParseError>
+    where
+        F: PrimeField,
+    {
+        Self::new(memory_segments).read_entries(r)
+    }
+
+    /// Builds memory from a mapped `cairo-run` memory file with a dense chunk
+    /// per memory segment.
+    pub fn from_mmap(partial_memory: &MmapMemory<F>, memory_segments: &MemorySegments) -> Self
+    where
+        F: PrimeField,
+    {
+        let mut memory = Self::new(memory_segments);
+        memory.extend(partial_memory.iter());
+        memory
+    }
+
+    fn read_entries(mut self, r: impl Read) -> Result<Self, BinaryParseError>
     where
         F: PrimeField,
     {
-        // TODO: errors
-        // TODO: each builtin has its own memory segment.
-        // check it also contains other builtins
         // this file contains the contiguous memory segments:
         // - program
         // - execution
@@ -188,35 +422,145 @@ impl<F: Field> Memory<F> {
         // - builtin 1
         // - ...
         let mut reader = BufReader::new(r);
-        let mut partial_memory = Vec::new();
-        let mut max_address = 0;
-        let mut word_bytes = Vec::new();
-        word_bytes.resize(field_bytes::<F>(), 0);
-        while reader.has_data_left().unwrap() {
-            // TODO: ensure always deserializes u64 and both are always little-endian
-            let address = bincode::deserialize_from(&mut reader).unwrap();
-            reader.read_exact(&mut word_bytes).unwrap();
-            let word = U256::try_from_le_slice(&word_bytes).unwrap();
-            partial_memory.push((address, Word::new(word)));
-            max_address = std::cmp::max(max_address, address);
+        // each record is a little-endian u64 address followed by a
+        // little-endian field element
+        let mut record = vec![0; 8 + field_bytes::<F>()];
+        let mut offset = 0;
+        while read_record(&mut reader, &mut record, offset)? {
+            let address = read_address(&record[0..8], offset)?;
+            let word = U256::try_from_le_slice(&record[8..]).unwrap();
+            let word = check_field_element::<F>(word).map_err(|error| {
+                BinaryParseError::InvalidFieldElement {
+                    offset: offset + 8,
+                    error,
+                }
+            })?;
+            self.insert(address, Word::new(word));
+            offset += record.len() as u64;
+        }
+        self.shrink_to_fit();
+        Ok(self)
+    }
+
+    fn extend(&mut self, entries: impl IntoIterator<Item = (usize, Word<F>)>) {
+        for (address, word) in entries {
+            self.insert(address, word);
+        }
+        self.shrink_to_fit();
+    }
+
+    /// Sets the value at `address`
+    pub fn insert(&mut self, address: usize, word: Word<F>) {
+        let i = self.chunk_index(address);
+        let chunk = &mut self.chunks[i];
+        let offset = address - chunk.begin_addr;
+        if offset >= chunk.words.len() {
+            chunk.words.resize(offset + 1, None);
+        }
+        chunk.words[offset] = Some(word);
+    }
+
+    /// Returns one past the highest address that has a value
+    pub fn len(&self) -> usize {
+        self.chunks
+            .iter()
+            .rev()
+            .find(|chunk| !chunk.words.is_empty())
+            .map_or(0, |chunk| chunk.begin_addr + chunk.words.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterates over the values of all addresses in `[0, self.len())`
+    pub fn iter(&self) -> impl Iterator<Item = &Option<Word<F>>> {
+        let len = self.len();
+        let mut next_addr = 0;
+        self.chunks
+            .iter()
+            .flat_map(move |chunk| {
+                let gap = chunk.begin_addr.saturating_sub(next_addr);
+                next_addr = chunk.begin_addr + chunk.words.len();
+                core::iter::repeat(&self.none).take(gap).chain(&chunk.words)
+            })
+            .take(len)
+    }
+
+    fn chunk_index(&self, address: usize) -> usize {
+        // the first chunk begins at address 0 so this never underflows
+        self.chunks
+            .partition_point(|chunk| chunk.begin_addr <= address)
+            - 1
+    }
+
+    fn shrink_to_fit(&mut self) {
+        for chunk in &mut self.chunks {
+            chunk.words.shrink_to_fit();
         }
+    }
+}
+
+impl<F: PrimeField> From<&MmapMemory<F>> for Memory<F> {
+    /// Builds memory from a mapped `cairo-run` memory file. All values are
+    /// stored in a single dense chunk.
+    fn from(partial_memory: &MmapMemory<F>) -> Self {
+        let mut memory = Self::default();
+        memory.extend(partial_memory.iter());
+        memory
+    }
+}
 
-        // TODO: DOC: None used for nondeterministic values?
-        let mut memory = vec![None; max_address + 1];
-        for (address, word) in partial_memory {
-            // TODO: once arkworks v4 release remove num_bigint
-            memory[address] = Some(word);
+impl<F> Default for Memory<F> {
+    fn default() -> Self {
+        Memory {
+            chunks: vec![MemoryChunk {
+                begin_addr: 0,
+                words: Vec::new(),
+            }],
+            none: None,
         }
+    }
+}
+
+/// Random access to the partial memory of a Cairo program by address
+pub trait ReadMemory<F>: Debug {
+    /// Returns the value at `address` or `None` if the address has no value
+    fn get(&self, address: usize) -> Option<Word<F>>;
+
+    /// Returns one past the highest address that has a value
+    fn end_address(&self) -> usize;
+}
+
+impl<F: Field> ReadMemory<F> for Memory<F> {
+    fn get(&self, address: usize) -> Option<Word<F>> {
+        self[address]
+    }
 
-        Memory(memory)
+    fn end_address(&self) -> usize {
+        self.len()
     }
 }
 
-impl<F: Field> Deref for Memory<F> {
-    type Target = Vec<Option<Word<F>>>;
+impl<F, M: ReadMemory<F> + ?Sized> ReadMemory<F> for Box<M> {
+    fn get(&self, address: usize) -> Option<Word<F>> {
+        (**self).get(address)
+    }
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
+    fn end_address(&self) -> usize {
+        (**self).end_address()
+    }
+}
+
+impl<F: Field> Index<usize> for Memory<F> {
+    type Output = Option<Word<F>>;
+
+    fn index(&self, address: usize) -> &Self::Output {
+        let chunk = &self.chunks[self.chunk_index(address)];
+        chunk
+            .words
+            .get(address - chunk.begin_addr)
+            .unwrap_or(&self.none)
     }
 }
 
@@ -292,29 +636,180 @@ pub struct Segment {
     pub stop_ptr: u32,
 }
 
-#[derive(Deserialize, Clone, Copy, Debug, CanonicalDeserialize, CanonicalSerialize)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, CanonicalDeserialize, CanonicalSerialize)]
 pub struct MemorySegments {
     pub program: Segment,
     pub execution: Segment,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub output: Option<Segment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub pedersen: Option<Segment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub range_check: Option<Segment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub ecdsa: Option<Segment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub bitwise: Option<Segment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub ec_op: Option<Segment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keccak: Option<Segment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub poseidon: Option<Segment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub range_check96: Option<Segment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub add_mod: Option<Segment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mul_mod: Option<Segment>,
 }
 
-#[derive(Deserialize, Clone, Debug, CanonicalDeserialize, CanonicalSerialize)]
-#[serde(bound = "F: PrimeField")]
+impl MemorySegments {
+    /// Names of all builtin segments in the order they are allocated by the
+    /// Cairo runner
+    pub const BUILTIN_NAMES: [&'static str; 11] = [
+        "output",
+        "pedersen",
+        "range_check",
+        "ecdsa",
+        "bitwise",
+        "ec_op",
+        "keccak",
+        "poseidon",
+        "range_check96",
+        "add_mod",
+        "mul_mod",
+    ];
+
+    /// Iterates over all segments that are present
+    pub fn iter(&self) -> impl Iterator<Item = Segment> {
+        self.iter_with_names().map(|(_, segment)| segment)
+    }
+
+    /// Iterates over all segments that are present along with their names.
+    /// Segments are in the order they are allocated by the Cairo runner.
+    pub fn iter_with_names(&self) -> impl Iterator<Item = (&'static str, Segment)> {
+        [
+            ("program", Some(self.program)),
+            ("execution", Some(self.execution)),
+            ("output", self.output),
+            ("pedersen", self.pedersen),
+            ("range_check", self.range_check),
+            ("ecdsa", self.ecdsa),
+            ("bitwise", self.bitwise),
+            ("ec_op", self.ec_op),
+            ("keccak", self.keccak),
+            ("poseidon", self.poseidon),
+            ("range_check96", self.range_check96),
+            ("add_mod", self.add_mod),
+            ("mul_mod", self.mul_mod),
+        ]
+        .into_iter()
+        .filter_map(|(name, segment)| Some((name, segment?)))
+    }
+}
+
+/// A continuous page of public memory
+/// Besides the main page all public memory pages are continuous e.g. the
+/// output of each task run by the bootloader.
+#[derive(
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    CanonicalSerialize,
+    CanonicalDeserialize,
+)]
+pub struct MemoryPage {
+    pub start_address: u32,
+    pub size: u32,
+}
+
+impl MemoryPage {
+    pub fn contains(&self, address: u32) -> bool {
+        (self.start_address..self.start_address + self.size).contains(&address)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, CanonicalDeserialize, CanonicalSerialize)]
+#[serde(
+    bound = "F: PrimeField",
+    try_from = "utils::RunnerAirPublicInput<F>",
+    into = "utils::RunnerAirPublicInput<F>"
+)]
 pub struct AirPublicInput<F: Field> {
     pub rc_min: u16,
     pub rc_max: u16,
     pub n_steps: u64,
     pub layout: Layout,
     pub memory_segments: MemorySegments,
-    #[serde(deserialize_with = "deserialize_hex_str_memory_entries")]
+    /// Entries of all public memory pages
     pub public_memory: Vec<MemoryEntry<F>>,
+    /// Public memory pages other than the main page (page 0)
+    pub memory_pages: Vec<MemoryPage>,
+    /// Parameters of the dynamic layout. None for all other layouts.
+    pub dynamic_params: Option<DynamicParams>,
+    /// Key of the dynamic layout parameters in the runner's JSON
+    pub dynamic_params_key: DynamicParamsKey,
+}
+
+/// Parameters of the dynamic layout e.g. `uses_pedersen_builtin` or
+/// `cpu_component_step`
+pub type DynamicParams = BTreeMap<String, u64>;
+
+/// Key the runner writes the dynamic layout parameters under. Different
+/// versions of the runner use different keys (or none at all) and write
+/// `null` for other layouts. The key is kept so a public input is written
+/// back exactly as the runner wrote it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DynamicParamsKey {
+    /// No key is written (earlier versions of cairo-run)
+    #[default]
+    Omitted,
+    /// `dynamic_params` (cairo-run)
+    DynamicParams,
+    /// `layout_params` (earlier versions of cairo-vm)
+    LayoutParams,
+}
+
+impl DynamicParamsKey {
+    const ALL: [Self; 3] = [Self::Omitted, Self::DynamicParams, Self::LayoutParams];
+}
+
+impl CanonicalSerialize for DynamicParamsKey {
+    fn serialize_with_mode<W: ark_serialize::Write>(
+        &self,
+        writer: W,
+        compress: ark_serialize::Compress,
+    ) -> Result<(), ark_serialize::SerializationError> {
+        (*self as u8).serialize_with_mode(writer, compress)
+    }
+
+    fn serialized_size(&self, _compress: ark_serialize::Compress) -> usize {
+        core::mem::size_of::<u8>()
+    }
+}
+
+impl Valid for DynamicParamsKey {
+    fn check(&self) -> Result<(), ark_serialize::SerializationError> {
+        Ok(())
+    }
+}
+
+impl CanonicalDeserialize for DynamicParamsKey {
+    fn deserialize_with_mode<R: Read>(
+        reader: R,
+        compress: ark_serialize::Compress,
+        validate: ark_serialize::Validate,
+    ) -> Result<Self, ark_serialize::SerializationError> {
+        let code = u8::deserialize_with_mode(reader, compress, validate)?;
+        (Self::ALL.into_iter())
+            .find(|&key| key as u8 == code)
+            .ok_or(ark_serialize::SerializationError::InvalidData)
+    }
 }
 
 impl<F: Field> AirPublicInput<F> {
@@ -334,45 +829,315 @@ impl<F: Field> AirPublicInput<F> {
         self.memory_segments.execution.stop_ptr
     }
 
-    pub fn public_memory_padding(&self) -> MemoryEntry<F> {
-        *self.public_memory.iter().find(|e| e.address == 1).unwrap()
+    /// Returns the public memory entry at address 1. This entry is used to pad
+    /// the public memory.
+    pub fn public_memory_padding(&self) -> Result<MemoryEntry<F>, PublicInputError> {
+        (self.public_memory.iter())
+            .find(|e| e.address == 1)
+            .copied()
+            .ok_or(PublicInputError::MissingPadding)
+    }
+
+    /// Returns the values in the output builtin's segment ordered by address
+    /// (i.e. the program's output). The output segment is part of the public
+    /// memory. Returns an empty list if the layout has no output builtin.
+    pub fn program_output(&self) -> Vec<F> {
+        let Some(Segment {
+            begin_addr,
+            stop_ptr,
+        }) = self.memory_segments.output
+        else {
+            return Vec::new();
+        };
+        let mut entries = (self.public_memory.iter())
+            .filter(|entry| (begin_addr..stop_ptr).contains(&entry.address))
+            .collect::<Vec<&MemoryEntry<F>>>();
+        entries.sort_by_key(|entry| entry.address);
+        entries.into_iter().map(|entry| entry.value).collect()
+    }
+
+    /// Returns true if the layout has the builtin. Builtins of the dynamic
+    /// layout are determined by its `uses_<builtin>_builtin` parameters.
+    pub fn has_builtin(&self, builtin: &str) -> bool {
+        match (self.layout, &self.dynamic_params) {
+            (Layout::Dynamic, Some(params)) => params
+                .get(&format!("uses_{builtin}_builtin"))
+                .is_some_and(|&uses_builtin| uses_builtin != 0),
+            (layout, _) => layout.builtins().contains(&builtin),
+        }
+    }
+
+    /// Checks the public input is well formed. AIRs assume the public input is
+    /// valid.
+    pub fn validate(&self) -> Result<(), PublicInputError> {
+        use PublicInputError::*;
+        if self.rc_min > self.rc_max {
+            return Err(InvalidRangeCheckBounds {
+                rc_min: self.rc_min,
+                rc_max: self.rc_max,
+            });
+        }
+
+        if !self.n_steps.is_power_of_two() {
+            return Err(InvalidNumSteps(self.n_steps));
+        }
+
+        if self.layout == Layout::Dynamic && self.dynamic_params.is_none() {
+            return Err(MissingDynamicParams);
+        }
+
+        let segments = self.memory_segments.iter_with_names();
+        for builtin in MemorySegments::BUILTIN_NAMES {
+            let is_present = segments.clone().any(|(name, _)| name == builtin);
+            match (self.has_builtin(builtin), is_present) {
+                (true, false) => return Err(MissingSegment(builtin)),
+                (false, true) => {
+                    return Err(UnsupportedBuiltin {
+                        layout: self.layout,
+                        builtin,
+                    })
+                }
+                _ => {}
+            }
+        }
+
+        let mut prev: Option<(&'static str, Segment)> = None;
+        for (name, segment) in segments {
+            if segment.stop_ptr < segment.begin_addr {
+                return Err(InvalidStopPointer {
+                    segment: name,
+                    begin_addr: segment.begin_addr,
+                    stop_ptr: segment.stop_ptr,
+                });
+            }
+            if let Some((prev_name, prev_segment)) = prev {
+                if prev_segment.stop_ptr > segment.begin_addr {
+                    return Err(OverlappingSegments(prev_name, name));
+                }
+            }
+            prev = Some((name, segment));
+        }
+
+        // builtins can't use more instances than the trace has room for
+        for (builtin, segment) in self.memory_segments.iter_with_names() {
+            let ratio = self.layout.builtin_ratio(builtin);
+            let (Some(ratio), Some(cells)) = (ratio, builtin_cells_per_instance(builtin)) else {
+                continue;
+            };
+            let instances = (segment.stop_ptr - segment.begin_addr).div_ceil(cells);
+            let capacity = self.n_steps / u64::from(ratio);
+            if u64::from(instances) > capacity {
+                return Err(InsufficientCapacity {
+                    builtin,
+                    instances,
+                    capacity,
+                });
+            }
+        }
+
+        self.public_memory_padding()?;
+
+        self.continuous_page_values().map_err(InvalidMemoryPage)?;
+
+        Ok(())
+    }
+
+    /// Returns the page of each public memory entry in the order of
+    /// [Self::public_memory]. The main page is page 0 and continuous pages are
+    /// numbered from 1 in the order of [Self::memory_pages].
+    pub fn paged_public_memory(&self) -> Vec<(u32, MemoryEntry<F>)> {
+        // continuous pages ordered by their start address
+        let pages = zip(1.., &self.memory_pages)
+            .map(|(id, page)| (page.start_address, (id, page)))
+            .collect::<BTreeMap<u32, (u32, &MemoryPage)>>();
+        self.public_memory
+            .iter()
+            .map(|&entry| {
+                let page = (pages.range(..=entry.address).next_back())
+                    .filter(|(_, (_, page))| page.contains(entry.address))
+                    .map_or(0, |(_, &(id, _))| id);
+                (page, entry)
+            })
+            .collect()
+    }
+
+    /// Returns the entries of each public memory page. The main page is first
+    /// followed by the entries of each continuous page ordered by address.
+    pub fn pages(&self) -> Vec<Vec<MemoryEntry<F>>> {
+        let mut pages = vec![Vec::new(); 1 + self.memory_pages.len()];
+        for (page, entry) in self.paged_public_memory() {
+            pages[page as usize].push(entry);
+        }
+        for entries in &mut pages[1..] {
+            entries.sort_unstable_by_key(|e| e.address);
+        }
+        pages
+    }
+
+    /// Returns the entries of the main public memory page
+    pub fn main_page(&self) -> Vec<MemoryEntry<F>> {
+        self.pages().swap_remove(0)
+    }
+
+    /// Returns the values of each continuous public memory page
+    /// The address of the i-th value of a page is implicitly
+    /// `page.start_address + i`. Errors if a page doesn't have exactly one
+    /// entry for each of its addresses.
+    pub fn continuous_page_values(&self) -> Result<Vec<Vec<F>>, MemoryPageError> {
+        let pages = zip(&self.memory_pages, self.pages().into_iter().skip(1));
+        zip(1.., pages)
+            .map(|(id, (page, entries))| {
+                // entries are ordered by address so the first gap or duplicate is the
+                // first missing address
+                let missing_address = zip(page.start_address.., &entries)
+                    .find(|&(address, entry)| entry.address != address)
+                    .map(|(address, _)| address)
+                    .or_else(|| {
+                        let num_entries = entries.len() as u32;
+                        (num_entries < page.size).then_some(page.start_address + num_entries)
+                    });
+                match missing_address {
+                    Some(address) => Err(MemoryPageError::NotContinuous { page: id, address }),
+                    None => Ok(entries.into_iter().map(|e| e.value).collect()),
+                }
+            })
+            .collect()
+    }
+}
+
+/// A builtin instance that occupies consecutive cells of its builtin's memory
+/// segment
+pub trait BuiltinInstance {
+    /// Memory addresses of the instance's cells
+    type Addrs;
+
+    /// Number of memory cells used by each instance
+    fn cells_per_instance() -> u32;
+
+    fn index(&self) -> u32;
+
+    /// Returns the addresses of the instance's cells in a memory segment that
+    /// starts at `segment_begin`
+    fn mem_addrs(&self, segment_begin: u32) -> Self::Addrs;
+
+    /// Address of the first cell of the instance
+    fn instance_addr(&self, segment_begin: u32) -> u32 {
+        segment_begin + self.index() * Self::cells_per_instance()
     }
 }
 
-#[derive(Deserialize, Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EcdsaAddrs {
+    pub pubkey: u32,
+    pub message: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PedersenAddrs {
+    pub a: u32,
+    pub b: u32,
+    pub output: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BitwiseAddrs {
+    pub x: u32,
+    pub y: u32,
+    /// Address of `x & y`
+    pub and: u32,
+    /// Address of `x ^ y`
+    pub xor: u32,
+    /// Address of `x | y`
+    pub or: u32,
+}
+
+/// Addresses of an EC op instance for `r = p + m * q`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EcOpAddrs {
+    pub p_x: u32,
+    pub p_y: u32,
+    pub q_x: u32,
+    pub q_y: u32,
+    pub m: u32,
+    pub r_x: u32,
+    pub r_y: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoseidonAddrs {
+    pub input0: u32,
+    pub input1: u32,
+    pub input2: u32,
+    pub output0: u32,
+    pub output1: u32,
+    pub output2: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeccakAddrs {
+    pub inputs: [u32; 8],
+    pub outputs: [u32; 8],
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Signature {
-    #[serde(deserialize_with = "deserialize_hex_str")]
+    #[serde(with = "hex_str")]
     pub r: U256,
-    #[serde(deserialize_with = "deserialize_hex_str")]
+    #[serde(with = "hex_str")]
     pub w: U256,
 }
 
-#[derive(Deserialize, Clone, Copy, Debug)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
 pub struct EcdsaInstance {
     pub index: u32,
-    #[serde(rename = "pubkey", deserialize_with = "deserialize_hex_str")]
+    #[serde(rename = "pubkey", with = "hex_str")]
     pub pubkey_x: U256,
-    #[serde(rename = "msg", deserialize_with = "deserialize_hex_str")]
+    #[serde(rename = "msg", with = "hex_str")]
     pub message: U256,
     #[serde(rename = "signature_input")]
     pub signature: Signature,
 }
 
 impl EcdsaInstance {
-    /// Get the memory address for this instance
-    /// Output is of the form (pubkey_addr, msg_addr)
-    pub fn mem_addr(&self, ecdsa_segment_addr: u32) -> (u32, u32) {
-        let instance_offset = ecdsa_segment_addr + self.index * 2;
-        (instance_offset, instance_offset + 1)
+    pub fn new_empty(index: u32) -> Self {
+        Self {
+            index,
+            pubkey_x: U256::ZERO,
+            message: U256::ZERO,
+            signature: Signature {
+                r: U256::ZERO,
+                w: U256::ZERO,
+            },
+        }
+    }
+}
+
+impl BuiltinInstance for EcdsaInstance {
+    type Addrs = EcdsaAddrs;
+
+    fn cells_per_instance() -> u32 {
+        2
+    }
+
+    fn index(&self) -> u32 {
+        self.index
+    }
+
+    fn mem_addrs(&self, segment_begin: u32) -> EcdsaAddrs {
+        let instance_addr = self.instance_addr(segment_begin);
+        EcdsaAddrs {
+            pubkey: instance_addr,
+            message: instance_addr + 1,
+        }
     }
 }
 
-#[derive(Deserialize, Clone, Copy, Debug)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
 pub struct PedersenInstance {
     pub index: u32,
-    #[serde(rename = "x", deserialize_with = "deserialize_hex_str")]
+    #[serde(rename = "x", with = "hex_str")]
     pub a: U256,
-    #[serde(rename = "y", deserialize_with = "deserialize_hex_str")]
+    #[serde(rename = "y", with = "hex_str")]
     pub b: U256,
 }
 
@@ -384,19 +1149,33 @@ impl PedersenInstance {
             b: U256::ZERO,
         }
     }
+}
+
+impl BuiltinInstance for PedersenInstance {
+    type Addrs = PedersenAddrs;
+
+    fn cells_per_instance() -> u32 {
+        3
+    }
+
+    fn index(&self) -> u32 {
+        self.index
+    }
 
-    /// Get the memory address for this instance
-    /// Output is of the form (a_addr, b_addr, output_addr)
-    pub fn mem_addr(&self, pedersen_segment_addr: u32) -> (u32, u32, u32) {
-        let instance_offset = pedersen_segment_addr + self.index * 3;
-        (instance_offset, instance_offset + 1, instance_offset + 2)
+    fn mem_addrs(&self, segment_begin: u32) -> PedersenAddrs {
+        let instance_addr = self.instance_addr(segment_begin);
+        PedersenAddrs {
+            a: instance_addr,
+            b: instance_addr + 1,
+            output: instance_addr + 2,
+        }
     }
 }
 
-#[derive(Deserialize, Clone, Copy, Debug)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
 pub struct RangeCheckInstance {
     pub index: u32,
-    #[serde(deserialize_with = "deserialize_hex_str")]
+    #[serde(with = "hex_str")]
     pub value: U256,
 }
 
@@ -407,19 +1186,30 @@ impl RangeCheckInstance {
             value: U256::ZERO,
         }
     }
+}
+
+impl BuiltinInstance for RangeCheckInstance {
+    type Addrs = u32;
+
+    fn cells_per_instance() -> u32 {
+        1
+    }
+
+    fn index(&self) -> u32 {
+        self.index
+    }
 
-    /// Get the memory address for this instance
-    pub fn mem_addr(&self, range_check_segment_addr: u32) -> u32 {
-        range_check_segment_addr + self.index
+    fn mem_addrs(&self, segment_begin: u32) -> u32 {
+        self.instance_addr(segment_begin)
     }
 }
 
-#[derive(Deserialize, Clone, Copy, Debug)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
 pub struct BitwiseInstance {
     pub index: u32,
-    #[serde(deserialize_with = "deserialize_hex_str")]
+    #[serde(with = "hex_str")]
     pub x: U256,
-    #[serde(deserialize_with = "deserialize_hex_str")]
+    #[serde(with = "hex_str")]
     pub y: U256,
 }
 
@@ -431,64 +1221,93 @@ impl BitwiseInstance {
             y: U256::ZERO,
         }
     }
+}
+
+impl BuiltinInstance for BitwiseInstance {
+    type Addrs = BitwiseAddrs;
+
+    fn cells_per_instance() -> u32 {
+        5
+    }
 
-    /// Get the memory address for this instance
-    /// Output is of the form (x_addr, y_addr, x&y_addr, x^y_addr, x|y_addr)
-    // TODO: better to use struct. Could cause bug if user gets ordering wrong.
-    pub fn mem_addr(&self, bitwise_segment_addr: u32) -> (u32, u32, u32, u32, u32) {
-        let instance_offset = bitwise_segment_addr + self.index * 5;
-        (
-            instance_offset,
-            instance_offset + 1,
-            instance_offset + 2,
-            instance_offset + 3,
-            instance_offset + 4,
-        )
+    fn index(&self) -> u32 {
+        self.index
+    }
+
+    fn mem_addrs(&self, segment_begin: u32) -> BitwiseAddrs {
+        let instance_addr = self.instance_addr(segment_begin);
+        BitwiseAddrs {
+            x: instance_addr,
+            y: instance_addr + 1,
+            and: instance_addr + 2,
+            xor: instance_addr + 3,
+            or: instance_addr + 4,
+        }
     }
 }
 
 /// Elliptic Curve operation instance for `p + m * q` on an elliptic curve
-#[derive(Deserialize, Clone, Copy, Debug)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
 pub struct EcOpInstance {
     pub index: u32,
-    #[serde(deserialize_with = "deserialize_hex_str")]
+    #[serde(with = "hex_str")]
     pub p_x: U256,
-    #[serde(deserialize_with = "deserialize_hex_str")]
+    #[serde(with = "hex_str")]
     pub p_y: U256,
-    #[serde(deserialize_with = "deserialize_hex_str")]
+    #[serde(with = "hex_str")]
     pub q_x: U256,
-    #[serde(deserialize_with = "deserialize_hex_str")]
+    #[serde(with = "hex_str")]
     pub q_y: U256,
-    #[serde(deserialize_with = "deserialize_hex_str")]
+    #[serde(with = "hex_str")]
     pub m: U256,
 }
 
 impl EcOpInstance {
-    /// Get the memory address for this instance
-    /// Output is of the form (p_x_addr, p_y_addr, q_x_addr, q_y_addr, m_addr,
-    /// r_x_addr, r_y_addr)
-    pub fn mem_addr(&self, ec_op_segment_addr: u32) -> (u32, u32, u32, u32, u32, u32, u32) {
-        let instance_offset = ec_op_segment_addr + self.index * 7;
-        (
-            instance_offset,
-            instance_offset + 1,
-            instance_offset + 2,
-            instance_offset + 3,
-            instance_offset + 4,
-            instance_offset + 5,
-            instance_offset + 6,
-        )
+    pub fn new_empty(index: u32) -> Self {
+        Self {
+            index,
+            p_x: U256::ZERO,
+            p_y: U256::ZERO,
+            q_x: U256::ZERO,
+            q_y: U256::ZERO,
+            m: U256::ZERO,
+        }
     }
 }
 
-#[derive(Deserialize, Clone, Copy, Debug)]
+impl BuiltinInstance for EcOpInstance {
+    type Addrs = EcOpAddrs;
+
+    fn cells_per_instance() -> u32 {
+        7
+    }
+
+    fn index(&self) -> u32 {
+        self.index
+    }
+
+    fn mem_addrs(&self, segment_begin: u32) -> EcOpAddrs {
+        let instance_addr = self.instance_addr(segment_begin);
+        EcOpAddrs {
+            p_x: instance_addr,
+            p_y: instance_addr + 1,
+            q_x: instance_addr + 2,
+            q_y: instance_addr + 3,
+            m: instance_addr + 4,
+            r_x: instance_addr + 5,
+            r_y: instance_addr + 6,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
 pub struct PoseidonInstance {
     pub index: u32,
-    #[serde(rename = "input_s0", deserialize_with = "deserialize_hex_str")]
+    #[serde(rename = "input_s0", with = "hex_str")]
     pub input0: U256,
-    #[serde(rename = "input_s1", deserialize_with = "deserialize_hex_str")]
+    #[serde(rename = "input_s1", with = "hex_str")]
     pub input1: U256,
-    #[serde(rename = "input_s2", deserialize_with = "deserialize_hex_str")]
+    #[serde(rename = "input_s2", with = "hex_str")]
     pub input2: U256,
 }
 
@@ -501,48 +1320,379 @@ impl PoseidonInstance {
             input2: U256::ZERO,
         }
     }
+}
 
-    /// Get the memory address for this instance
-    /// Output is of the form (input0_addr, input1_addr, input2_addr,
-    /// output0_addr, output1_addr, output2_addr)
-    pub fn mem_addr(&self, poseidon_segment_addr: u32) -> (u32, u32, u32, u32, u32, u32) {
-        let instance_offset = poseidon_segment_addr + self.index * 6;
-        (
-            instance_offset,
-            instance_offset + 1,
-            instance_offset + 2,
-            instance_offset + 3,
-            instance_offset + 4,
-            instance_offset + 5,
-        )
+impl BuiltinInstance for PoseidonInstance {
+    type Addrs = PoseidonAddrs;
+
+    fn cells_per_instance() -> u32 {
+        6
     }
-}
 
-#[derive(Debug, Deserialize)]
-pub struct AirPrivateInput {
-    pub trace_path: PathBuf,
-    pub memory_path: PathBuf,
+    fn index(&self) -> u32 {
+        self.index
+    }
+
+    fn mem_addrs(&self, segment_begin: u32) -> PoseidonAddrs {
+        let instance_addr = self.instance_addr(segment_begin);
+        PoseidonAddrs {
+            input0: instance_addr,
+            input1: instance_addr + 1,
+            input2: instance_addr + 2,
+            output0: instance_addr + 3,
+            output1: instance_addr + 4,
+            output2: instance_addr + 5,
+        }
+    }
+}
+
+/// Keccak-f[1600] permutation instance. The 1600 bit state is split into 8
+/// little-endian 200 bit words.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeccakInstance {
+    pub index: u32,
+    #[serde(rename = "input_s0", with = "hex_str")]
+    pub input0: U256,
+    #[serde(rename = "input_s1", with = "hex_str")]
+    pub input1: U256,
+    #[serde(rename = "input_s2", with = "hex_str")]
+    pub input2: U256,
+    #[serde(rename = "input_s3", with = "hex_str")]
+    pub input3: U256,
+    #[serde(rename = "input_s4", with = "hex_str")]
+    pub input4: U256,
+    #[serde(rename = "input_s5", with = "hex_str")]
+    pub input5: U256,
+    #[serde(rename = "input_s6", with = "hex_str")]
+    pub input6: U256,
+    #[serde(rename = "input_s7", with = "hex_str")]
+    pub input7: U256,
+}
+
+impl KeccakInstance {
+    pub fn new_empty(index: u32) -> Self {
+        Self {
+            index,
+            input0: U256::ZERO,
+            input1: U256::ZERO,
+            input2: U256::ZERO,
+            input3: U256::ZERO,
+            input4: U256::ZERO,
+            input5: U256::ZERO,
+            input6: U256::ZERO,
+            input7: U256::ZERO,
+        }
+    }
+
+    /// Returns the 8 input words of the instance
+    pub fn inputs(&self) -> [U256; 8] {
+        [
+            self.input0,
+            self.input1,
+            self.input2,
+            self.input3,
+            self.input4,
+            self.input5,
+            self.input6,
+            self.input7,
+        ]
+    }
+}
+
+impl BuiltinInstance for KeccakInstance {
+    type Addrs = KeccakAddrs;
+
+    fn cells_per_instance() -> u32 {
+        16
+    }
+
+    fn index(&self) -> u32 {
+        self.index
+    }
+
+    fn mem_addrs(&self, segment_begin: u32) -> KeccakAddrs {
+        let instance_addr = self.instance_addr(segment_begin);
+        KeccakAddrs {
+            inputs: std::array::from_fn(|i| instance_addr + i as u32),
+            outputs: std::array::from_fn(|i| instance_addr + 8 + i as u32),
+        }
+    }
+}
+
+/// Builtin instances of an execution. Builtins without instances are omitted
+/// when serialized.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AirPrivateInput {
+    pub trace_path: PathBuf,
+    pub memory_path: PathBuf,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub pedersen: Vec<PedersenInstance>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub range_check: Vec<RangeCheckInstance>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub ecdsa: Vec<EcdsaInstance>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub bitwise: Vec<BitwiseInstance>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub ec_op: Vec<EcOpInstance>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keccak: Vec<KeccakInstance>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub poseidon: Vec<PoseidonInstance>,
 }
 
+impl AirPrivateInput {
+    /// Derives builtin instances from the values in each builtin's memory
+    /// segment. Instances with missing inputs are skipped (the same as the
+    /// Cairo runner). ECDSA signatures aren't stored in memory so they're
+    /// looked up by instance index in `signatures`. The trace and memory paths
+    /// are left empty.
+    pub fn from_memory<F: PrimeField>(
+        memory: &impl ReadMemory<F>,
+        memory_segments: &MemorySegments,
+        signatures: &BTreeMap<u32, Signature>,
+    ) -> Result<Self, PrivateInputError> {
+        let read = |address: u32| memory.get(address as usize).map(|word| word.0);
+
+        let mut pedersen = Vec::new();
+        if let Some(segment) = memory_segments.pedersen {
+            for index in 0..num_instances::<PedersenInstance>("pedersen", segment)? {
+                let instance = PedersenInstance::new_empty(index);
+                let addrs = instance.mem_addrs(segment.begin_addr);
+                if let (Some(a), Some(b)) = (read(addrs.a), read(addrs.b)) {
+                    pedersen.push(PedersenInstance { index, a, b });
+                }
+            }
+        }
+
+        let mut range_check = Vec::new();
+        if let Some(segment) = memory_segments.range_check {
+            for index in 0..num_instances::<RangeCheckInstance>("range_check", segment)? {
+                let instance = RangeCheckInstance::new_empty(index);
+                if let Some(value) = read(instance.mem_addrs(segment.begin_addr)) {
+                    range_check.push(RangeCheckInstance { index, value });
+                }
+            }
+        }
+
+        let mut ecdsa = Vec::new();
+        if let Some(segment) = memory_segments.ecdsa {
+            for index in 0..num_instances::<EcdsaInstance>("ecdsa", segment)? {
+                let instance = EcdsaInstance::new_empty(index);
+                let addrs = instance.mem_addrs(segment.begin_addr);
+                if let (Some(pubkey_x), Some(message)) = (read(addrs.pubkey), read(addrs.message)) {
+                    let signature = *signatures
+                        .get(&index)
+                        .ok_or(PrivateInputError::MissingSignature(index))?;
+                    ecdsa.push(EcdsaInstance {
+                        index,
+                        pubkey_x,
+                        message,
+                        signature,
+                    });
+                }
+            }
+        }
+
+        let mut bitwise = Vec::new();
+        if let Some(segment) = memory_segments.bitwise {
+            for index in 0..num_instances::<BitwiseInstance>("bitwise", segment)? {
+                let instance = BitwiseInstance::new_empty(index);
+                let addrs = instance.mem_addrs(segment.begin_addr);
+                if let (Some(x), Some(y)) = (read(addrs.x), read(addrs.y)) {
+                    bitwise.push(BitwiseInstance { index, x, y });
+                }
+            }
+        }
+
+        let mut ec_op = Vec::new();
+        if let Some(segment) = memory_segments.ec_op {
+            for index in 0..num_instances::<EcOpInstance>("ec_op", segment)? {
+                let instance = EcOpInstance::new_empty(index);
+                let addrs = instance.mem_addrs(segment.begin_addr);
+                let inputs = [addrs.p_x, addrs.p_y, addrs.q_x, addrs.q_y, addrs.m].map(read);
+                if let [Some(p_x), Some(p_y), Some(q_x), Some(q_y), Some(m)] = inputs {
+                    ec_op.push(EcOpInstance {
+                        index,
+                        p_x,
+                        p_y,
+                        q_x,
+                        q_y,
+                        m,
+                    });
+                }
+            }
+        }
+
+        let mut keccak = Vec::new();
+        if let Some(segment) = memory_segments.keccak {
+            for index in 0..num_instances::<KeccakInstance>("keccak", segment)? {
+                let instance = KeccakInstance::new_empty(index);
+                let inputs = instance.mem_addrs(segment.begin_addr).inputs.map(read);
+                if inputs.iter().all(Option::is_some) {
+                    let [input0, input1, input2, input3, input4, input5, input6, input7] =
+                        inputs.map(Option::unwrap);
+                    keccak.push(KeccakInstance {
+                        index,
+                        input0,
+                        input1,
+                        input2,
+                        input3,
+                        input4,
+                        input5,
+                        input6,
+                        input7,
+                    });
+                }
+            }
+        }
+
+        let mut poseidon = Vec::new();
+        if let Some(segment) = memory_segments.poseidon {
+            for index in 0..num_instances::<PoseidonInstance>("poseidon", segment)? {
+                let instance = PoseidonInstance::new_empty(index);
+                let addrs = instance.mem_addrs(segment.begin_addr);
+                let inputs = [addrs.input0, addrs.input1, addrs.input2].map(read);
+                if let [Some(input0), Some(input1), Some(input2)] = inputs {
+                    poseidon.push(PoseidonInstance {
+                        index,
+                        input0,
+                        input1,
+                        input2,
+                    });
+                }
+            }
+        }
+
+        Ok(Self {
+            trace_path: PathBuf::new(),
+            memory_path: PathBuf::new(),
+            pedersen,
+            range_check,
+            ecdsa,
+            bitwise,
+            ec_op,
+            keccak,
+            poseidon,
+        })
+    }
+
+    /// Checks the builtin instances are the same as the instances derived from
+    /// memory. Returns the first instance that's inconsistent or missing.
+    pub fn check_memory<F: PrimeField>(
+        &self,
+        memory: &impl ReadMemory<F>,
+        memory_segments: &MemorySegments,
+    ) -> Result<(), PrivateInputError> {
+        // signatures can't be checked against memory so the supplied ones are
+        // used. An ecdsa instance in memory without a signature is missing from
+        // the private input.
+        let signatures = (self.ecdsa.iter())
+            .map(|instance| (instance.index, instance.signature))
+            .collect::<BTreeMap<u32, Signature>>();
+        let derived =
+            Self::from_memory(memory, memory_segments, &signatures).map_err(|err| match err {
+                PrivateInputError::MissingSignature(index) => PrivateInputError::MissingInstance {
+                    builtin: "ecdsa",
+                    index,
+                },
+                err => err,
+            })?;
+        check_instances("pedersen", &self.pedersen, &derived.pedersen)?;
+        check_instances("range_check", &self.range_check, &derived.range_check)?;
+        check_instances("ecdsa", &self.ecdsa, &derived.ecdsa)?;
+        check_instances("bitwise", &self.bitwise, &derived.bitwise)?;
+        check_instances("ec_op", &self.ec_op, &derived.ec_op)?;
+        check_instances("keccak", &self.keccak, &derived.keccak)?;
+        check_instances("poseidon", &self.poseidon, &derived.poseidon)
+    }
+}
+
+/// Number of memory cells used by each instance of a builtin. Returns none for
+/// the output builtin and builtins without an instance type.
+fn builtin_cells_per_instance(builtin: &str) -> Option<u32> {
+    match builtin {
+        "pedersen" => Some(PedersenInstance::cells_per_instance()),
+        "range_check" => Some(RangeCheckInstance::cells_per_instance()),
+        "ecdsa" => Some(EcdsaInstance::cells_per_instance()),
+        "bitwise" => Some(BitwiseInstance::cells_per_instance()),
+        "ec_op" => Some(EcOpInstance::cells_per_instance()),
+        "keccak" => Some(KeccakInstance::cells_per_instance()),
+        "poseidon" => Some(PoseidonInstance::cells_per_instance()),
+        _ => None,
+    }
+}
+
+/// Number of instances in a builtin's memory segment. Errors if the segment's
+/// stop pointer is before its start.
+fn num_instances<T: BuiltinInstance>(
+    builtin: &'static str,
+    segment: Segment,
+) -> Result<u32, PrivateInputError> {
+    let size = (segment.stop_ptr)
+        .checked_sub(segment.begin_addr)
+        .ok_or(PrivateInputError::InvalidSegment(builtin))?;
+    Ok(size.div_ceil(T::cells_per_instance()))
+}
+
+/// Checks the supplied instances of a builtin match the instances derived from
+/// memory. Derived instances must be ordered by index.
+fn check_instances<T: BuiltinInstance + PartialEq>(
+    builtin: &'static str,
+    supplied: &[T],
+    derived: &[T],
+) -> Result<(), PrivateInputError> {
+    for instance in supplied {
+        let index = instance.index();
+        match derived.binary_search_by_key(&index, T::index) {
+            Ok(i) if derived[i] == *instance => {}
+            _ => return Err(PrivateInputError::InconsistentInstance { builtin, index }),
+        }
+    }
+
+    let supplied_indices = supplied.iter().map(T::index).collect::<BTreeSet<u32>>();
+    match derived
+        .iter()
+        .map(T::index)
+        .find(|i| !supplied_indices.contains(i))
+    {
+        Some(index) => Err(PrivateInputError::MissingInstance { builtin, index }),
+        None => Ok(()),
+    }
+}
+
 #[derive(Clone, Deserialize, Debug)]
 #[serde(bound = "F: PrimeField")]
 pub struct CompiledProgram<F: Field> {
     #[serde(deserialize_with = "deserialize_vec_hex_str")]
     pub data: Vec<F>,
     pub prime: String,
+    /// Builtins used by the program's main function (in order)
+    #[serde(default)]
+    pub builtins: Vec<String>,
+    /// Scope of the program's main function e.g. `__main__`
+    #[serde(default)]
+    pub main_scope: String,
+    #[serde(default)]
+    pub identifiers: BTreeMap<String, Identifier>,
+}
+
+/// Identifier of a compiled program. Only the pc of functions and labels is
+/// kept.
+#[derive(Clone, Deserialize, Debug)]
+pub struct Identifier {
+    pub pc: Option<usize>,
 }
 
 impl<F: Field> CompiledProgram<F> {
+    /// Returns the pc of the program's main function
+    pub fn main(&self) -> Option<usize> {
+        let main = format!("{}.main", self.main_scope);
+        self.identifiers
+            .get(&main)
+            .and_then(|identifier| identifier.pc)
+    }
+
     pub fn program_memory(&self) -> Vec<MemoryEntry<F>> {
         self.data
             .iter()
@@ -558,6 +1708,30 @@ impl<F: Field> CompiledProgram<F> {
     }
 }
 
+impl<F: PrimeField> CompiledProgram<F> {
+    /// Decodes the program's instructions and returns them with their offset
+    /// in the program. Values that aren't instructions (e.g. constants) are
+    /// returned as none.
+    pub fn disassemble(&self) -> Vec<(usize, Option<Instruction<F>>)> {
+        let mut instructions = Vec::new();
+        let mut pc = 0;
+        while let Some(&value) = self.data.get(pc) {
+            let word = Word::<F>::new(U256::from::<BigUint>(value.into()));
+            // an instruction missing its immediate at the end of the program is
+            // treated as data
+            let imm = self.data.get(pc + 1).copied();
+            let instruction = match word.decode() {
+                Ok(instruction) if !instruction.has_imm() => Some(instruction),
+                Ok(instruction) => imm.map(|imm| instruction.with_imm(imm)),
+                Err(_) => None,
+            };
+            instructions.push((pc, instruction));
+            pc += instruction.map_or(1, |instruction| instruction.size());
+        }
+        instructions
+    }
+}
+
 /// Represents a Cairo word
 /// Value is a field element in the range `[0, Fp::MODULUS)`
 /// Stored as a U256 to make binary decompositions more efficient
@@ -646,18 +1820,18 @@ impl<F: PrimeField> Word<F> {
         Word(word, PhantomData)
     }
 
-    pub fn get_op0(&self, ap: usize, fp: usize, mem: &Memory<F>) -> F {
-        mem[self.get_op0_addr(ap, fp)].unwrap().into_felt()
+    pub fn get_op0(&self, ap: usize, fp: usize, mem: &impl ReadMemory<F>) -> F {
+        mem.get(self.get_op0_addr(ap, fp)).unwrap().into_felt()
     }
 
-    pub fn get_dst(&self, ap: usize, fp: usize, mem: &Memory<F>) -> F {
-        mem[self.get_dst_addr(ap, fp)].unwrap().into_felt()
+    pub fn get_dst(&self, ap: usize, fp: usize, mem: &impl ReadMemory<F>) -> F {
+        mem.get(self.get_dst_addr(ap, fp)).unwrap().into_felt()
     }
 
-    pub fn get_op1_addr(&self, pc: usize, ap: usize, fp: usize, mem: &Memory<F>) -> usize {
+    pub fn get_op1_addr(&self, pc: usize, ap: usize, fp: usize, mem: &impl ReadMemory<F>) -> usize {
         self.get_off_op1() as usize
             + match self.get_flag_group(FlagGroup::Op1Src) {
-                0 => usize::try_from(mem[self.get_op0_addr(ap, fp)].unwrap().0).unwrap(),
+                0 => usize::try_from(mem.get(self.get_op0_addr(ap, fp)).unwrap().0).unwrap(),
                 1 => pc,
                 2 => fp,
                 4 => ap,
@@ -666,11 +1840,13 @@ impl<F: PrimeField> Word<F> {
             - HALF_OFFSET
     }
 
-    pub fn get_op1(&self, pc: usize, ap: usize, fp: usize, mem: &Memory<F>) -> F {
-        mem[self.get_op1_addr(pc, ap, fp, mem)].unwrap().into_felt()
+    pub fn get_op1(&self, pc: usize, ap: usize, fp: usize, mem: &impl ReadMemory<F>) -> F {
+        mem.get(self.get_op1_addr(pc, ap, fp, mem))
+            .unwrap()
+            .into_felt()
     }
 
-    pub fn get_res(&self, pc: usize, ap: usize, fp: usize, mem: &Memory<F>) -> F {
+    pub fn get_res(&self, pc: usize, ap: usize, fp: usize, mem: &impl ReadMemory<F>) -> F {
         let pc_update = self.get_flag_group(FlagGroup::PcUpdate);
         let res_logic = self.get_flag_group(FlagGroup::ResLogic);
         match pc_update {
@@ -689,8 +1865,11 @@ impl<F: PrimeField> Word<F> {
                 }
             }
             0..=2 => {
-                let op0: F = mem[self.get_op0_addr(ap, fp)].unwrap().into_felt();
-                let op1: F = mem[self.get_op1_addr(pc, ap, fp, mem)].unwrap().into_felt();
+                let op0: F = mem.get(self.get_op0_addr(ap, fp)).unwrap().into_felt();
+                let op1: F = mem
+                    .get(self.get_op1_addr(pc, ap, fp, mem))
+                    .unwrap()
+                    .into_felt();
                 match res_logic {
                     0 => op1,
                     1 => op0 + op1,
@@ -702,7 +1881,7 @@ impl<F: PrimeField> Word<F> {
         }
     }
 
-    pub fn get_tmp0(&self, ap: usize, fp: usize, mem: &Memory<F>) -> F {
+    pub fn get_tmp0(&self, ap: usize, fp: usize, mem: &impl ReadMemory<F>) -> F {
         if self.get_flag(Flag::PcJnz) {
             self.get_dst(ap, fp, mem)
         } else {
@@ -711,7 +1890,7 @@ impl<F: PrimeField> Word<F> {
         }
     }
 
-    pub fn get_tmp1(&self, pc: usize, ap: usize, fp: usize, mem: &Memory<F>) -> F {
+    pub fn get_tmp1(&self, pc: usize, ap: usize, fp: usize, mem: &impl ReadMemory<F>) -> F {
         self.get_tmp0(ap, fp, mem) * self.get_res(pc, ap, fp, mem)
     }
 
@@ -770,3 +1949,848 @@ pub enum Flag {
     // 0 - padding to make flag cells a power-of-2
     Zero = 15,
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::AirPrivateInput;
+    use crate::AirPublicInput;
+    use crate::BinaryParseError;
+    use crate::BitwiseAddrs;
+    use crate::BitwiseInstance;
+    use crate::BuiltinInstance;
+    use crate::CompiledProgram;
+    use crate::EcdsaInstance;
+    use crate::Layout;
+    use crate::Memory;
+    use crate::MemoryPage;
+    use crate::MemoryPageError;
+    use crate::MemorySegments;
+    use crate::MmapMemory;
+    use crate::MmapRegisterStates;
+    use crate::PrivateInputError;
+    use crate::PublicInputError;
+    use crate::ReadMemory;
+    use crate::RegisterStates;
+    use crate::Segment;
+    use crate::Signature;
+    use crate::Word;
+    use ark_ff::Field;
+    use ark_ff::MontFp;
+    use ark_ff::PrimeField;
+    use ark_serialize::CanonicalDeserialize;
+    use ark_serialize::CanonicalSerialize;
+    use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp;
+    use num_bigint::BigUint;
+    use ruint::aliases::U256;
+    use serde_json::Value;
+    use std::collections::BTreeMap;
+    use std::fs::File;
+    use std::path::PathBuf;
+
+    /// Encodes a memory file record the same way as `cairo-run`
+    fn memory_record(address: u64, value: U256) -> Vec<u8> {
+        [address.to_le_bytes().as_slice(), &value.to_le_bytes::<32>()].concat()
+    }
+
+    fn example_path(path: &str) -> String {
+        format!("{}/../{path}", env!("CARGO_MANIFEST_DIR"))
+    }
+
+    fn read_json(path: &str) -> Value {
+        serde_json::from_reader(File::open(example_path(path)).unwrap()).unwrap()
+    }
+
+    /// Writes `bytes` to a file in the temp directory that's unique to a test
+    fn write_temp_file(name: &str, bytes: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("sandstorm-{}-{name}", std::process::id()));
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    /// Checks a public input is serialized to the same JSON as the Cairo runner
+    fn assert_public_input_round_trip(path: &str) {
+        let expected = read_json(path);
+        let public_input: AirPublicInput<Fp> = serde_json::from_value(expected.clone()).unwrap();
+        let actual = serde_json::to_value(public_input).unwrap();
+
+        assert_eq!(expected, actual);
+    }
+
+    /// Checks a private input is serialized to the same JSON as the Cairo
+    /// runner besides builtins without instances (which are omitted)
+    fn assert_private_input_round_trip(path: &str) {
+        let mut expected = read_json(path);
+        let private_input: AirPrivateInput = serde_json::from_value(expected.clone()).unwrap();
+
+        let actual = serde_json::to_value(private_input).unwrap();
+
+        let expected_fields = expected.as_object_mut().unwrap();
+        expected_fields.retain(|_, value| value.as_array().map_or(true, |v| !v.is_empty()));
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn public_input_round_trips() {
+        assert_public_input_round_trip("example/air-public-input.json");
+    }
+
+    #[test]
+    fn bootloader_public_input_round_trips() {
+        assert_public_input_round_trip("example/bootloader/air-public-input.json");
+    }
+
+    #[test]
+    fn public_input_without_dynamic_params_round_trips() {
+        let mut json = read_json("example/air-public-input.json");
+        json.as_object_mut().unwrap().remove("dynamic_params");
+
+        let public_input: AirPublicInput<Fp> = serde_json::from_value(json.clone()).unwrap();
+
+        assert_eq!(json, serde_json::to_value(public_input).unwrap());
+    }
+
+    #[test]
+    fn public_input_with_both_dynamic_params_keys_is_rejected() {
+        let mut json = read_json("example/air-public-input.json");
+        json["layout_params"] = Value::Null;
+
+        let err = serde_json::from_value::<AirPublicInput<Fp>>(json)
+            .err()
+            .unwrap();
+
+        assert_eq!(
+            PublicInputError::DuplicateDynamicParams.to_string(),
+            err.to_string()
+        );
+    }
+
+    /// Reads the bootloader public input with the output moved to continuous
+    /// pages. `pages` maps an output address to its page.
+    fn read_paged_bootloader_public_input(pages: &[(u32, u32)]) -> Value {
+        let mut json = read_json("example/bootloader/air-public-input.json");
+        for entry in json["public_memory"].as_array_mut().unwrap() {
+            let address = entry["address"].as_u64().unwrap() as u32;
+            if let Some((_, page)) = pages.iter().find(|(a, _)| *a == address) {
+                entry["page"] = Value::from(*page);
+            }
+        }
+        json
+    }
+
+    #[test]
+    fn multi_page_public_input_round_trips() {
+        let json = read_paged_bootloader_public_input(&[(1177, 1), (1178, 2), (1179, 2)]);
+
+        let public_input: AirPublicInput<Fp> = serde_json::from_value(json.clone()).unwrap();
+        let pages = public_input.pages();
+
+        assert_eq!(
+            vec![
+                MemoryPage {
+                    start_address: 1177,
+                    size: 1
+                },
+                MemoryPage {
+                    start_address: 1178,
+                    size: 2
+                },
+            ],
+            public_input.memory_pages
+        );
+        assert_eq!(3, pages.len());
+        assert_eq!(public_input.public_memory.len() - 3, pages[0].len());
+        assert!(pages[0].iter().all(|e| !(1177..1180).contains(&e.address)));
+        assert_eq!(
+            vec![1177],
+            pages[1].iter().map(|e| e.address).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![1178, 1179],
+            pages[2].iter().map(|e| e.address).collect::<Vec<_>>()
+        );
+        assert_eq!(json, serde_json::to_value(public_input).unwrap());
+    }
+
+    #[test]
+    fn incomplete_continuous_page_is_rejected() {
+        let json = read_paged_bootloader_public_input(&[(1177, 1), (1178, 2), (1179, 2)]);
+        let mut public_input: AirPublicInput<Fp> = serde_json::from_value(json).unwrap();
+        // address 1180 has no public memory entry
+        public_input.memory_pages[1].size = 3;
+
+        let err = public_input.continuous_page_values().unwrap_err();
+
+        assert!(matches!(
+            err,
+            MemoryPageError::NotContinuous {
+                page: 2,
+                address: 1180
+            }
+        ));
+        assert!(matches!(
+            public_input.validate(),
+            Err(PublicInputError::InvalidMemoryPage(_))
+        ));
+    }
+
+    #[test]
+    fn public_input_with_missing_page_is_rejected() {
+        // page ids are untrusted so a large id must not be used to allocate pages
+        let json = read_paged_bootloader_public_input(&[(1177, u32::MAX)]);
+
+        let err = serde_json::from_value::<AirPublicInput<Fp>>(json).unwrap_err();
+
+        assert_eq!(MemoryPageError::MissingPage(1).to_string(), err.to_string());
+    }
+
+    #[test]
+    fn non_continuous_page_is_rejected() {
+        let json = read_paged_bootloader_public_input(&[(1177, 1), (1179, 1)]);
+
+        let err = serde_json::from_value::<AirPublicInput<Fp>>(json).unwrap_err();
+
+        let expected = MemoryPageError::NotContinuous {
+            page: 1,
+            address: 1179,
+        };
+        assert_eq!(expected.to_string(), err.to_string());
+    }
+
+    /// Validates the array-sum public input after applying `modify`
+    fn validate_modified(
+        modify: impl FnOnce(&mut AirPublicInput<Fp>),
+    ) -> Result<(), PublicInputError> {
+        let file = File::open(example_path("example/air-public-input.json")).unwrap();
+        let mut public_input: AirPublicInput<Fp> = serde_json::from_reader(file).unwrap();
+        modify(&mut public_input);
+        public_input.validate()
+    }
+
+    #[test]
+    fn example_public_input_is_valid() {
+        assert!(validate_modified(|_| {}).is_ok());
+    }
+
+    #[test]
+    fn inverted_range_check_bounds_are_rejected() {
+        let err = validate_modified(|public_input| public_input.rc_min = public_input.rc_max + 1);
+
+        assert!(matches!(
+            err,
+            Err(PublicInputError::InvalidRangeCheckBounds {
+                rc_min: 32771,
+                rc_max: 32770
+            })
+        ));
+    }
+
+    #[test]
+    fn num_steps_that_is_not_a_power_of_two_is_rejected() {
+        let err = validate_modified(|public_input| public_input.n_steps = 100);
+
+        assert!(matches!(err, Err(PublicInputError::InvalidNumSteps(100))));
+    }
+
+    #[test]
+    fn dynamic_layout_without_params_is_rejected() {
+        let err = validate_modified(|public_input| public_input.layout = Layout::Dynamic);
+
+        assert!(matches!(err, Err(PublicInputError::MissingDynamicParams)));
+    }
+
+    #[test]
+    fn missing_builtin_segment_is_rejected() {
+        let err = validate_modified(|public_input| public_input.memory_segments.bitwise = None);
+
+        assert!(matches!(
+            err,
+            Err(PublicInputError::MissingSegment("bitwise"))
+        ));
+    }
+
+    #[test]
+    fn segment_of_builtin_not_in_layout_is_rejected() {
+        let err = validate_modified(|public_input| {
+            public_input.memory_segments.ecdsa = Some(Segment {
+                begin_addr: 20000,
+                stop_ptr: 20000,
+            })
+        });
+
+        assert!(matches!(
+            err,
+            Err(PublicInputError::UnsupportedBuiltin {
+                layout: Layout::Recursive,
+                builtin: "ecdsa"
+            })
+        ));
+    }
+
+    #[test]
+    fn stop_pointer_before_segment_start_is_rejected() {
+        let err = validate_modified(|public_input| {
+            let range_check = public_input.memory_segments.range_check.as_mut().unwrap();
+            range_check.stop_ptr = range_check.begin_addr - 1;
+        });
+
+        assert!(matches!(
+            err,
+            Err(PublicInputError::InvalidStopPointer {
+                segment: "range_check",
+                begin_addr: 460,
+                stop_ptr: 459
+            })
+        ));
+    }
+
+    #[test]
+    fn overlapping_segments_are_rejected() {
+        let err = validate_modified(|public_input| {
+            public_input
+                .memory_segments
+                .pedersen
+                .as_mut()
+                .unwrap()
+                .stop_ptr = 461;
+        });
+
+        assert!(matches!(
+            err,
+            Err(PublicInputError::OverlappingSegments(
+                "pedersen",
+                "range_check"
+            ))
+        ));
+    }
+
+    #[test]
+    fn builtin_instances_exceeding_capacity_are_rejected() {
+        // the recursive layout has a bitwise instance every 8 steps
+        let err = validate_modified(|public_input| {
+            public_input.n_steps = 8;
+            let bitwise = public_input.memory_segments.bitwise.as_mut().unwrap();
+            bitwise.stop_ptr = bitwise.begin_addr + 2 * BitwiseInstance::cells_per_instance();
+        });
+
+        assert!(matches!(
+            err,
+            Err(PublicInputError::InsufficientCapacity {
+                builtin: "bitwise",
+                instances: 2,
+                capacity: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn public_memory_without_padding_is_rejected() {
+        let err = validate_modified(|public_input| {
+            public_input
+                .public_memory
+                .retain(|entry| entry.address != 1)
+        });
+
+        assert!(matches!(err, Err(PublicInputError::MissingPadding)));
+    }
+
+    #[test]
+    fn private_input_round_trips() {
+        assert_private_input_round_trip("example/air-private-input.json");
+    }
+
+    #[test]
+    fn bootloader_private_input_round_trips() {
+        assert_private_input_round_trip("example/bootloader/air-private-input.json");
+    }
+
+    #[test]
+    fn private_input_without_builtins_round_trips() {
+        // the plain layout has no builtins
+        let json = r#"{"trace_path":"trace.bin","memory_path":"memory.bin"}"#;
+
+        let private_input: AirPrivateInput = serde_json::from_str(json).unwrap();
+
+        assert_eq!(json, serde_json::to_string(&private_input).unwrap());
+    }
+
+    fn read_bootloader_execution() -> (AirPrivateInput, MemorySegments, Memory<Fp>) {
+        let public_input: AirPublicInput<Fp> =
+            serde_json::from_value(read_json("example/bootloader/air-public-input.json")).unwrap();
+        let private_input =
+            serde_json::from_value(read_json("example/bootloader/air-private-input.json")).unwrap();
+        let memory_segments = public_input.memory_segments;
+        let memory_file = File::open(example_path("example/bootloader/memory.bin")).unwrap();
+        let memory = Memory::from_reader_with_segments(memory_file, &memory_segments).unwrap();
+        (private_input, memory_segments, memory)
+    }
+
+    #[test]
+    fn layouts_serialize_as_u128() {
+        let mut bytes = Vec::new();
+        Layout::Starknet.serialize_compressed(&mut bytes).unwrap();
+
+        // same encoding as before SHARP codes were widened to 256 bits
+        assert_eq!(0x737461726b6e6574u128.to_be_bytes().to_vec(), bytes);
+        for layout in Layout::ALL {
+            let mut bytes = Vec::new();
+            layout.serialize_compressed(&mut bytes).unwrap();
+            assert_eq!(16, bytes.len());
+            assert_eq!(layout, Layout::deserialize_compressed(&*bytes).unwrap());
+        }
+    }
+
+    #[test]
+    fn main_pc_is_read_from_identifiers() {
+        let program: CompiledProgram<Fp> =
+            serde_json::from_value(read_json("example/array-sum.json")).unwrap();
+
+        assert_eq!(Some(23), program.main());
+    }
+
+    #[test]
+    fn bootloader_program_output() {
+        let public_input: AirPublicInput<Fp> =
+            serde_json::from_value(read_json("example/bootloader/air-public-input.json")).unwrap();
+
+        let output = public_input.program_output();
+
+        let expected = [
+            MontFp!("2962621603719000361370283216422448934312521782617806945663080079725495842070"),
+            MontFp!("37341341331504021525228390428349719127283617351070997452015539964478373189"),
+            Fp::ZERO,
+        ];
+        assert_eq!(expected.as_slice(), output);
+    }
+
+    #[test]
+    fn empty_program_output() {
+        let public_input: AirPublicInput<Fp> =
+            serde_json::from_value(read_json("example/air-public-input.json")).unwrap();
+
+        assert!(public_input.program_output().is_empty());
+    }
+
+    #[test]
+    fn bitwise_addrs_follow_instance_layout() {
+        let instance = BitwiseInstance::new_empty(2);
+
+        let addrs = instance.mem_addrs(100);
+
+        let expected = BitwiseAddrs {
+            x: 110,
+            y: 111,
+            and: 112,
+            xor: 113,
+            or: 114,
+        };
+        assert_eq!(expected, addrs);
+    }
+
+    #[test]
+    fn bootloader_instances_derived_from_memory() {
+        let (private_input, memory_segments, memory) = read_bootloader_execution();
+
+        let derived =
+            AirPrivateInput::from_memory(&memory, &memory_segments, &BTreeMap::new()).unwrap();
+
+        assert_eq!(2, derived.pedersen.len());
+        assert_eq!(private_input.pedersen, derived.pedersen);
+        assert!(private_input
+            .check_memory(&memory, &memory_segments)
+            .is_ok());
+    }
+
+    /// Bootloader execution with an ecdsa instance in a segment after the
+    /// bootloader's memory
+    fn ecdsa_execution() -> (MemorySegments, Memory<Fp>, EcdsaInstance) {
+        let (_, memory_segments, mut memory) = read_bootloader_execution();
+        let begin_addr = memory.len() as u32;
+        let instance = EcdsaInstance {
+            index: 0,
+            pubkey_x: U256::from(5),
+            message: U256::from(7),
+            signature: Signature {
+                r: U256::from(11),
+                w: U256::from(13),
+            },
+        };
+        let addrs = instance.mem_addrs(begin_addr);
+        memory.insert(addrs.pubkey as usize, Word::new(instance.pubkey_x));
+        memory.insert(addrs.message as usize, Word::new(instance.message));
+        let memory_segments = MemorySegments {
+            ecdsa: Some(Segment {
+                begin_addr,
+                stop_ptr: begin_addr + 2,
+            }),
+            ..memory_segments
+        };
+        (memory_segments, memory, instance)
+    }
+
+    #[test]
+    fn ecdsa_instances_derived_from_memory_and_signatures() {
+        let (memory_segments, memory, instance) = ecdsa_execution();
+        let signatures = BTreeMap::from([(0, instance.signature)]);
+
+        let derived = AirPrivateInput::from_memory(&memory, &memory_segments, &signatures).unwrap();
+
+        assert_eq!(vec![instance], derived.ecdsa);
+    }
+
+    #[test]
+    fn ecdsa_instance_without_signature_is_rejected() {
+        let (memory_segments, memory, _) = ecdsa_execution();
+
+        let err =
+            AirPrivateInput::from_memory(&memory, &memory_segments, &BTreeMap::new()).unwrap_err();
+
+        assert!(matches!(err, PrivateInputError::MissingSignature(0)));
+    }
+
+    #[test]
+    fn ecdsa_instance_missing_from_private_input_is_rejected() {
+        let (memory_segments, memory, _) = ecdsa_execution();
+        let (private_input, ..) = read_bootloader_execution();
+
+        let err = private_input
+            .check_memory(&memory, &memory_segments)
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            PrivateInputError::MissingInstance {
+                builtin: "ecdsa",
+                index: 0
+            }
+        ));
+    }
+
+    #[test]
+    fn segment_ending_before_its_start_is_rejected() {
+        let (_, memory_segments, memory) = read_bootloader_execution();
+        let pedersen = memory_segments.pedersen.unwrap();
+        let memory_segments = MemorySegments {
+            pedersen: Some(Segment {
+                begin_addr: pedersen.begin_addr,
+                stop_ptr: pedersen.begin_addr - 1,
+            }),
+            ..memory_segments
+        };
+
+        let err =
+            AirPrivateInput::from_memory(&memory, &memory_segments, &BTreeMap::new()).unwrap_err();
+
+        assert!(matches!(err, PrivateInputError::InvalidSegment("pedersen")));
+    }
+
+    #[test]
+    fn inconsistent_instance_is_rejected() {
+        let (mut private_input, memory_segments, memory) = read_bootloader_execution();
+        private_input.pedersen[1].a += U256::from(1);
+
+        let err = private_input
+            .check_memory(&memory, &memory_segments)
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            PrivateInputError::InconsistentInstance {
+                builtin: "pedersen",
+                index: 1
+            }
+        ));
+    }
+
+    #[test]
+    fn truncated_trace_is_rejected() {
+        let mut trace = vec![[1u64, 1, 5].map(u64::to_le_bytes).concat(); 2].concat();
+        trace.truncate(trace.len() - 3);
+
+        let err = RegisterStates::from_reader(&*trace).unwrap_err();
+
+        assert!(matches!(
+            err,
+            BinaryParseError::Truncated {
+                offset: 24,
+                record_size: 24,
+                bytes_read: 21
+            }
+        ));
+    }
+
+    #[test]
+    fn absurd_register_value_is_rejected() {
+        let trace = [1u64, 1 << 40, 5].map(u64::to_le_bytes).concat();
+
+        let err = RegisterStates::from_reader(&*trace).unwrap_err();
+
+        assert!(matches!(
+            err,
+            BinaryParseError::InvalidRegister {
+                offset: 8,
+                value: 0x100_0000_0000
+            }
+        ));
+    }
+
+    #[test]
+    fn truncated_memory_is_rejected() {
+        let mut memory = memory_record(1, U256::from(7));
+        memory.pop();
+
+        let err = Memory::<Fp>::from_reader(&*memory).unwrap_err();
+
+        assert!(matches!(
+            err,
+            BinaryParseError::Truncated {
+                offset: 0,
+                record_size: 40,
+                bytes_read: 39
+            }
+        ));
+    }
+
+    #[test]
+    fn out_of_field_memory_value_is_rejected() {
+        let modulus = U256::from(BigUint::from(Fp::MODULUS));
+        let memory = [memory_record(1, U256::from(7)), memory_record(2, modulus)].concat();
+
+        let err = Memory::<Fp>::from_reader(&*memory).unwrap_err();
+
+        match err {
+            BinaryParseError::InvalidFieldElement { offset, error } => {
+                assert_eq!(48, offset);
+                assert_eq!(modulus, error.value);
+            }
+            err => panic!("unexpected error: {err}"),
+        }
+    }
+
+    #[test]
+    fn absurd_memory_address_is_rejected() {
+        let memory = memory_record(u64::MAX, U256::from(7));
+
+        let err = Memory::<Fp>::from_reader(&*memory).unwrap_err();
+
+        assert!(matches!(
+            err,
+            BinaryParseError::InvalidAddress {
+                offset: 0,
+                address: u64::MAX
+            }
+        ));
+    }
+
+    #[test]
+    fn mapped_trace_matches_trace_reader() {
+        let path = example_path("example/trace.bin");
+        let expected = RegisterStates::from_reader(File::open(&path).unwrap()).unwrap();
+
+        let actual = MmapRegisterStates::open(&path).unwrap();
+
+        assert!(!actual.is_empty());
+        assert_eq!(*expected, *actual);
+    }
+
+    #[test]
+    fn mapped_memory_matches_memory_reader() {
+        let path = example_path("example/memory.bin");
+        let expected = Memory::<Fp>::from_reader(File::open(&path).unwrap()).unwrap();
+
+        let actual = MmapMemory::<Fp>::open(&path).unwrap();
+
+        assert_eq!(expected.len(), actual.end_address());
+        assert_eq!(expected.len(), actual.max_address() + 1);
+        for address in 0..expected.len() + 10 {
+            let expected_value = expected.get(address).map(|word| word.0);
+            let actual_value = actual.get(address).map(|word| word.0);
+            assert_eq!(expected_value, actual_value, "address {address}");
+        }
+    }
+
+    #[test]
+    fn mapped_memory_uses_last_record_of_an_address() {
+        let memory = [
+            memory_record(3, U256::from(7)),
+            memory_record(1, U256::from(8)),
+            memory_record(3, U256::from(9)),
+        ]
+        .concat();
+        let path = write_temp_file("duplicate-memory.bin", &memory);
+
+        let memory = MmapMemory::<Fp>::open(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(3, memory.len());
+        assert_eq!(4, memory.end_address());
+        assert!(memory.get(0).is_none());
+        assert_eq!(Some(U256::from(8)), memory.get(1).map(|word| word.0));
+        assert!(memory.get(2).is_none());
+        assert_eq!(Some(U256::from(9)), memory.get(3).map(|word| word.0));
+    }
+
+    #[test]
+    fn mapped_memory_looks_up_sparse_addresses() {
+        let memory = [
+            memory_record(u32::MAX.into(), U256::from(7)),
+            memory_record(1, U256::from(8)),
+        ]
+        .concat();
+        let path = write_temp_file("sparse-memory.bin", &memory);
+
+        let memory = MmapMemory::<Fp>::open(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(u32::MAX as usize, memory.max_address());
+        assert_eq!(u32::MAX as usize + 1, memory.end_address());
+        assert_eq!(Some(U256::from(8)), memory.get(1).map(|word| word.0));
+        assert!(memory.get(2).is_none());
+        assert!(memory.get(u32::MAX as usize - 1).is_none());
+        let max_value = memory.get(u32::MAX as usize).map(|word| word.0);
+        assert_eq!(Some(U256::from(7)), max_value);
+        assert!(memory.get(u32::MAX as usize + 1).is_none());
+    }
+
+    #[test]
+    fn truncated_mapped_trace_is_rejected() {
+        let mut trace = std::fs::read(example_path("example/trace.bin")).unwrap();
+        trace.truncate(trace.len() - 3);
+        let path = write_temp_file("truncated-trace.bin", &trace);
+
+        let err = MmapRegisterStates::open(&path).unwrap_err();
+        std::fs::remove_file(path).unwrap();
+
+        match err {
+            BinaryParseError::Truncated {
+                offset,
+                record_size,
+                bytes_read,
+            } => {
+                assert_eq!((trace.len() - 21) as u64, offset);
+                assert_eq!(24, record_size);
+                assert_eq!(21, bytes_read);
+            }
+            err => panic!("unexpected error: {err}"),
+        }
+    }
+
+    #[test]
+    fn truncated_mapped_memory_is_rejected() {
+        let mut memory = std::fs::read(example_path("example/memory.bin")).unwrap();
+        memory.pop();
+        let path = write_temp_file("truncated-memory.bin", &memory);
+
+        let err = MmapMemory::<Fp>::open(&path).unwrap_err();
+        std::fs::remove_file(path).unwrap();
+
+        match err {
+            BinaryParseError::Truncated {
+                offset,
+                record_size,
+                bytes_read,
+            } => {
+                assert_eq!((memory.len() - 39) as u64, offset);
+                assert_eq!(40, record_size);
+                assert_eq!(39, bytes_read);
+            }
+            err => panic!("unexpected error: {err}"),
+        }
+    }
+
+    /// Segments with a gap between the program and execution segments and a
+    /// pedersen segment at the end
+    fn sparse_memory_segments() -> MemorySegments {
+        let segment = |begin_addr, stop_ptr| Segment {
+            begin_addr,
+            stop_ptr,
+        };
+        MemorySegments {
+            program: segment(1, 4),
+            execution: segment(10, 14),
+            output: None,
+            pedersen: Some(segment(20, 23)),
+            range_check: None,
+            ecdsa: None,
+            bitwise: None,
+            ec_op: None,
+            keccak: None,
+            poseidon: None,
+            range_check96: None,
+            add_mod: None,
+            mul_mod: None,
+        }
+    }
+
+    fn values(memory: &Memory<Fp>) -> Vec<Option<U256>> {
+        memory.iter().map(|word| word.map(|word| word.0)).collect()
+    }
+
+    #[test]
+    fn empty_memory_has_no_values() {
+        let memory = Memory::<Fp>::new(&sparse_memory_segments());
+
+        assert!(memory.is_empty());
+        assert_eq!(0, memory.iter().count());
+        assert!(memory[0].is_none());
+        assert!(memory[15].is_none());
+        assert!(memory[usize::MAX].is_none());
+    }
+
+    #[test]
+    fn sparse_inserts_across_chunks() {
+        let mut memory = Memory::<Fp>::new(&sparse_memory_segments());
+        // out of order and past the end of the program and pedersen segments
+        let entries = [(25, 5), (2, 1), (12, 3), (5, 2), (21, 4)];
+        for (address, value) in entries {
+            memory.insert(address, Word::new(U256::from(value)));
+        }
+
+        assert_eq!(26, memory.len());
+        let mut expected = vec![None; 26];
+        for (address, value) in entries {
+            expected[address] = Some(U256::from(value));
+        }
+        assert_eq!(expected, values(&memory));
+        for (address, value) in expected.into_iter().enumerate() {
+            assert_eq!(
+                value,
+                memory[address].map(|word| word.0),
+                "address {address}"
+            );
+        }
+        assert!(memory[26].is_none());
+        assert!(memory[1000].is_none());
+    }
+
+    #[test]
+    fn insert_overwrites_existing_value() {
+        let mut memory = Memory::<Fp>::new(&sparse_memory_segments());
+        memory.insert(11, Word::new(U256::from(1)));
+        memory.insert(11, Word::new(U256::from(2)));
+
+        assert_eq!(12, memory.len());
+        assert_eq!(Some(U256::from(2)), memory[11].map(|word| word.0));
+        assert!(memory[10].is_none());
+    }
+
+    #[test]
+    fn chunked_memory_matches_single_chunk() {
+        let (_, memory_segments, chunked) = read_bootloader_execution();
+        let path = example_path("example/bootloader/memory.bin");
+        let dense = Memory::<Fp>::from_reader(File::open(path).unwrap()).unwrap();
+
+        assert_eq!(dense.len(), chunked.len());
+        assert_eq!(values(&dense), values(&chunked));
+        // every segment boundary is read from the right chunk
+        for segment in memory_segments.iter() {
+            for address in [segment.begin_addr, segment.stop_ptr].map(|a| a as usize) {
+                assert_eq!(
+                    dense[address].map(|word| word.0),
+                    chunked[address].map(|word| word.0),
+                    "address {address}"
+                );
+            }
+        }
+        assert!(chunked[chunked.len()].is_none());
+    }
+}
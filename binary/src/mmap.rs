@@ -0,0 +1,185 @@
+use crate::errors::BinaryParseError;
+use crate::utils::check_field_element;
+use crate::utils::field_bytes;
+use crate::utils::read_address;
+use crate::utils::read_register;
+use crate::ReadMemory;
+use crate::RegisterState;
+use crate::Word;
+use ark_ff::PrimeField;
+use memmap2::Mmap;
+use ruint::aliases::U256;
+use std::fs::File;
+use std::marker::PhantomData;
+use std::mem::align_of;
+use std::mem::size_of;
+use std::ops::Deref;
+use std::path::Path;
+
+/// Number of bytes in each `(ap, fp, pc)` record of a trace file
+pub const REGISTER_STATE_RECORD_SIZE: usize = 3 * 8;
+
+// `cairo-run` writes each register as a little-endian u64. Mapped trace files
+// are reinterpreted as a slice of `RegisterState` so this must hold.
+const _: () = assert!(
+    cfg!(target_endian = "little")
+        && size_of::<RegisterState>() == REGISTER_STATE_RECORD_SIZE
+        && align_of::<RegisterState>() == 8,
+    "memory mapped traces require a little-endian 64-bit target"
+);
+
+/// Maps a file into memory. Note the file must not be modified while the map
+/// is alive.
+fn map_file(path: &Path) -> Result<Mmap, BinaryParseError> {
+    let file = File::open(path)?;
+    Ok(unsafe { Mmap::map(&file)? })
+}
+
+/// Checks a mapped file contains a whole number of records
+fn check_whole_records(len: usize, record_size: usize) -> Result<(), BinaryParseError> {
+    let remainder = len % record_size;
+    if remainder != 0 {
+        return Err(BinaryParseError::Truncated {
+            offset: (len - remainder) as u64,
+            record_size,
+            bytes_read: remainder,
+        });
+    }
+    Ok(())
+}
+
+/// Register states of a `cairo-run` trace file read directly from a memory
+/// map rather than copied onto the heap.
+#[derive(Debug)]
+pub struct MmapRegisterStates(Mmap);
+
+impl MmapRegisterStates {
+    /// Maps the trace file at `path`. Every record is validated up front so
+    /// later accesses can't fail.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, BinaryParseError> {
+        let mmap = map_file(path.as_ref())?;
+        check_whole_records(mmap.len(), REGISTER_STATE_RECORD_SIZE)?;
+        for (i, register) in mmap.chunks_exact(8).enumerate() {
+            read_register(register, (i * 8) as u64)?;
+        }
+        Ok(Self(mmap))
+    }
+}
+
+impl Deref for MmapRegisterStates {
+    type Target = [RegisterState];
+
+    fn deref(&self) -> &Self::Target {
+        let len = self.0.len() / REGISTER_STATE_RECORD_SIZE;
+        if len == 0 {
+            // empty maps aren't guaranteed to be aligned
+            return &[];
+        }
+        // SAFETY: maps are page aligned, records have the same layout as
+        // `RegisterState` (asserted above) and any bit pattern is a valid usize
+        unsafe { std::slice::from_raw_parts(self.0.as_ptr().cast(), len) }
+    }
+}
+
+/// Partial memory of a `cairo-run` memory file read directly from a memory
+/// map. Unlike [crate::Memory::from_reader] no copy of the values is made.
+/// Values are looked up by address through an index of the file's records.
+/// The index has an entry per record (not per address) so sparse memory with
+/// large addresses doesn't need an index spanning the whole address space.
+#[derive(Debug)]
+pub struct MmapMemory<F> {
+    mmap: Mmap,
+    max_address: usize,
+    /// `(address, record)` of each address with a value ordered by address
+    records: Vec<(u32, usize)>,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: PrimeField> MmapMemory<F> {
+    /// Number of bytes in each `(address, value)` record of a memory file
+    pub const RECORD_SIZE: usize = 8 + field_bytes::<F>();
+
+    /// Maps the memory file at `path`. Every record is validated up front so
+    /// later accesses can't fail. If an address appears more than once the
+    /// last record is used (the same as [crate::Memory::from_reader]).
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, BinaryParseError> {
+        let mmap = map_file(path.as_ref())?;
+        check_whole_records(mmap.len(), Self::RECORD_SIZE)?;
+        let mut records = Vec::with_capacity(mmap.len() / Self::RECORD_SIZE);
+        for (i, record) in mmap.chunks_exact(Self::RECORD_SIZE).enumerate() {
+            let offset = (i * Self::RECORD_SIZE) as u64;
+            let address = read_address(&record[0..8], offset)?;
+            let value = U256::try_from_le_slice(&record[8..]).unwrap();
+            check_field_element::<F>(value).map_err(|error| {
+                BinaryParseError::InvalidFieldElement {
+                    offset: offset + 8,
+                    error,
+                }
+            })?;
+            // addresses are checked to fit in a u32 by `read_address`
+            records.push((address as u32, i));
+        }
+
+        // the sort is stable so the last record of an address is kept
+        records.sort_by_key(|&(address, _)| address);
+        records.dedup_by(|next, prev| {
+            let is_duplicate = next.0 == prev.0;
+            if is_duplicate {
+                prev.1 = next.1;
+            }
+            is_duplicate
+        });
+        let max_address = records.last().map_or(0, |&(address, _)| address as usize);
+
+        Ok(Self {
+            mmap,
+            max_address,
+            records,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Returns the largest address that has a value
+    pub fn max_address(&self) -> usize {
+        self.max_address
+    }
+
+    /// Returns the number of `(address, value)` records
+    pub fn len(&self) -> usize {
+        self.mmap.len() / Self::RECORD_SIZE
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the `(address, value)` record at `index` in file order
+    pub fn record(&self, index: usize) -> Option<(usize, Word<F>)> {
+        let start = index.checked_mul(Self::RECORD_SIZE)?;
+        let record = self.mmap.get(start..start + Self::RECORD_SIZE)?;
+        let address = u64::from_le_bytes(record[0..8].try_into().unwrap()) as usize;
+        let value = U256::try_from_le_slice(&record[8..]).unwrap();
+        Some((address, Word::new(value)))
+    }
+
+    /// Iterates the `(address, value)` records in file order
+    pub fn iter(&self) -> impl Iterator<Item = (usize, Word<F>)> + '_ {
+        (0..self.len()).map(|i| self.record(i).unwrap())
+    }
+}
+
+impl<F: PrimeField> ReadMemory<F> for MmapMemory<F> {
+    fn get(&self, address: usize) -> Option<Word<F>> {
+        let address = u32::try_from(address).ok()?;
+        let i = (self.records)
+            .binary_search_by_key(&address, |&(address, _)| address)
+            .ok()?;
+        self.record(self.records[i].1).map(|(_, word)| word)
+    }
+
+    fn end_address(&self) -> usize {
+        self.records
+            .last()
+            .map_or(0, |&(address, _)| address as usize + 1)
+    }
+}
@@ -1,21 +1,32 @@
+use crate::errors::BinaryParseError;
 use crate::errors::InvalidFieldElementError;
+use crate::errors::MemoryPageError;
+use crate::errors::PublicInputError;
+use crate::AirPublicInput;
+use crate::DynamicParams;
+use crate::DynamicParamsKey;
+use crate::Layout;
 use crate::MemoryEntry;
+use crate::MemoryPage;
+use crate::MemorySegments;
 use alloc::vec::Vec;
 use ark_ff::PrimeField;
+use core::iter::zip;
 use num_bigint::BigUint;
 use ruint::aliases::U256;
 use serde::de;
 use serde::Deserialize;
 use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
 use serde_json::value::Number;
+use std::collections::BTreeMap;
+use std::io::ErrorKind;
+use std::io::Read;
 
 fn try_felt_from_u256<F: PrimeField>(value: U256) -> Result<F, InvalidFieldElementError> {
-    let modulus = U256::from::<BigUint>(F::MODULUS.into());
-    if value < modulus {
-        Ok(From::<BigUint>::from(value.into()))
-    } else {
-        Err(InvalidFieldElementError { value, modulus })
-    }
+    let value = check_field_element::<F>(value)?;
+    Ok(From::<BigUint>::from(value.into()))
 }
 
 /// Deserializes a hex string into a field element
@@ -26,6 +37,26 @@ pub fn deserialize_hex_str_as_field_element<'de, D: Deserializer<'de>, F: PrimeF
     try_felt_from_u256(num).map_err(de::Error::custom)
 }
 
+/// Serializes a field element into a hex string
+pub fn serialize_field_element_as_hex_str<S: Serializer, F: PrimeField>(
+    value: &F,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serialize_hex_str(&U256::from::<BigUint>((*value).into()), serializer)
+}
+
+/// Serializes a big integer into a hex string in the same format as the Cairo
+/// runner e.g. `0x0` or `0x1f`
+pub fn serialize_hex_str<S: Serializer>(value: &U256, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format!("{:#x}", BigUint::from(*value)))
+}
+
+/// (De)serializes big integers as hex strings with `#[serde(with = "hex_str")]`
+pub mod hex_str {
+    pub use super::deserialize_hex_str as deserialize;
+    pub use super::serialize_hex_str as serialize;
+}
+
 /// Deserializes a hex string into a big integer
 pub fn deserialize_hex_str<'de, D: Deserializer<'de>>(deserializer: D) -> Result<U256, D::Error> {
     let hex_str = String::deserialize(deserializer)?;
@@ -33,22 +64,194 @@ pub fn deserialize_hex_str<'de, D: Deserializer<'de>>(deserializer: D) -> Result
 }
 
 /// Deserializes a list of memory entries of the form
-/// `{value: "0x...", address: ...}`
-pub fn deserialize_hex_str_memory_entries<'de, D: Deserializer<'de>, F: PrimeField>(
+/// `{value: "0x...", address: ..., page: ...}`
+/// Output is of the form `(page, entry)`. Entries without a page belong to the
+/// main page (page 0).
+pub fn deserialize_hex_str_paged_memory_entries<'de, D: Deserializer<'de>, F: PrimeField>(
     deserializer: D,
-) -> Result<Vec<MemoryEntry<F>>, D::Error> {
+) -> Result<Vec<(u32, MemoryEntry<F>)>, D::Error> {
     #[derive(Deserialize)]
     struct Entry<F: PrimeField> {
         #[serde(deserialize_with = "deserialize_hex_str_as_field_element")]
         pub value: F,
         pub address: u32,
+        #[serde(default)]
+        pub page: u32,
     }
     let v = Vec::deserialize(deserializer)?;
     Ok(v.into_iter()
-        .map(|Entry { address, value }| MemoryEntry { address, value })
+        .map(
+            |Entry {
+                 address,
+                 value,
+                 page,
+             }| (page, MemoryEntry { address, value }),
+        )
         .collect())
 }
 
+/// Serializes a list of memory entries of the form `(page, entry)` into
+/// `{address: ..., value: "0x...", page: ...}`
+#[allow(clippy::ptr_arg)]
+pub fn serialize_hex_str_paged_memory_entries<S: Serializer, F: PrimeField>(
+    entries: &Vec<(u32, MemoryEntry<F>)>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    #[derive(Serialize)]
+    struct Entry<F: PrimeField> {
+        pub address: u32,
+        #[serde(serialize_with = "serialize_field_element_as_hex_str")]
+        pub value: F,
+        pub page: u32,
+    }
+    serializer.collect_seq(
+        entries
+            .iter()
+            .map(|&(page, MemoryEntry { address, value })| Entry {
+                address,
+                value,
+                page,
+            }),
+    )
+}
+
+/// Public input in the format output by the Cairo runner
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "F: PrimeField")]
+pub struct RunnerAirPublicInput<F: PrimeField> {
+    pub layout: Layout,
+    pub rc_min: u16,
+    pub rc_max: u16,
+    pub n_steps: u64,
+    pub memory_segments: MemorySegments,
+    #[serde(
+        serialize_with = "serialize_hex_str_paged_memory_entries",
+        deserialize_with = "deserialize_hex_str_paged_memory_entries"
+    )]
+    pub public_memory: Vec<(u32, MemoryEntry<F>)>,
+    /// Params of the dynamic layout (`null` for other layouts). `None` if the
+    /// key is missing, which is the case for older versions of cairo-run.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_present",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub dynamic_params: Option<Option<DynamicParams>>,
+    /// Name of `dynamic_params` in older versions of cairo-vm
+    #[serde(
+        default,
+        deserialize_with = "deserialize_present",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub layout_params: Option<Option<DynamicParams>>,
+}
+
+/// Deserializes a value whose key is present. Used with `#[serde(default)]` to
+/// distinguish a missing key (`None`) from a `null` value (`Some(None)`).
+fn deserialize_present<'de, D: Deserializer<'de>, T: Deserialize<'de>>(
+    deserializer: D,
+) -> Result<Option<T>, D::Error> {
+    T::deserialize(deserializer).map(Some)
+}
+
+impl<F: PrimeField> TryFrom<RunnerAirPublicInput<F>> for AirPublicInput<F> {
+    type Error = PublicInputError;
+
+    fn try_from(runner_input: RunnerAirPublicInput<F>) -> Result<Self, PublicInputError> {
+        let RunnerAirPublicInput {
+            rc_min,
+            rc_max,
+            n_steps,
+            layout,
+            memory_segments,
+            public_memory,
+            dynamic_params,
+            layout_params,
+        } = runner_input;
+
+        let (dynamic_params_key, dynamic_params) = match (dynamic_params, layout_params) {
+            (None, None) => (DynamicParamsKey::Omitted, None),
+            (Some(params), None) => (DynamicParamsKey::DynamicParams, params),
+            (None, Some(params)) => (DynamicParamsKey::LayoutParams, params),
+            (Some(_), Some(_)) => return Err(PublicInputError::DuplicateDynamicParams),
+        };
+
+        // group the addresses of each non-main page
+        let mut page_addresses = BTreeMap::<u32, Vec<u32>>::new();
+        for &(page, entry) in &public_memory {
+            if page != 0 {
+                page_addresses.entry(page).or_default().push(entry.address);
+            }
+        }
+
+        let mut memory_pages = Vec::new();
+        for (expected_page, (page, mut addresses)) in zip(1.., page_addresses) {
+            if page != expected_page {
+                let err = MemoryPageError::MissingPage(expected_page);
+                return Err(PublicInputError::InvalidMemoryPage(err));
+            }
+            addresses.sort_unstable();
+            let start_address = addresses[0];
+            for (expected, &address) in zip(start_address.., &addresses) {
+                if address != expected {
+                    let err = MemoryPageError::NotContinuous { page, address };
+                    return Err(PublicInputError::InvalidMemoryPage(err));
+                }
+            }
+            memory_pages.push(MemoryPage {
+                start_address,
+                size: addresses.len() as u32,
+            });
+        }
+
+        Ok(AirPublicInput {
+            rc_min,
+            rc_max,
+            n_steps,
+            layout,
+            memory_segments,
+            public_memory: public_memory.into_iter().map(|(_, entry)| entry).collect(),
+            memory_pages,
+            dynamic_params,
+            dynamic_params_key,
+        })
+    }
+}
+
+impl<F: PrimeField> From<AirPublicInput<F>> for RunnerAirPublicInput<F> {
+    fn from(air_public_input: AirPublicInput<F>) -> Self {
+        // entries outside of the non-main pages belong to the main page (page 0)
+        let public_memory = air_public_input.paged_public_memory();
+        let AirPublicInput {
+            rc_min,
+            rc_max,
+            n_steps,
+            layout,
+            memory_segments,
+            dynamic_params,
+            dynamic_params_key,
+            ..
+        } = air_public_input;
+
+        let (dynamic_params, layout_params) = match dynamic_params_key {
+            DynamicParamsKey::Omitted => (None, None),
+            DynamicParamsKey::DynamicParams => (Some(dynamic_params), None),
+            DynamicParamsKey::LayoutParams => (None, Some(dynamic_params)),
+        };
+
+        RunnerAirPublicInput {
+            layout,
+            rc_min,
+            rc_max,
+            n_steps,
+            memory_segments,
+            public_memory,
+            dynamic_params,
+            layout_params,
+        }
+    }
+}
+
 /// Deserializes a list of hex strings into a list of big integers
 pub fn deserialize_vec_hex_str<'de, D: Deserializer<'de>, F: PrimeField>(
     deserializer: D,
@@ -88,3 +291,60 @@ pub fn _deserialize_vec_big_uint<'de, D: Deserializer<'de>>(
 pub const fn field_bytes<F: PrimeField>() -> usize {
     F::MODULUS_BIT_SIZE.next_multiple_of(8) as usize / 8
 }
+
+/// Reads a fixed size record into `buf`. Returns `false` if the reader was
+/// already exhausted and errors if the reader ends part way through a record.
+pub fn read_record(
+    reader: &mut impl Read,
+    buf: &mut [u8],
+    offset: u64,
+) -> Result<bool, BinaryParseError> {
+    let mut bytes_read = 0;
+    while bytes_read < buf.len() {
+        match reader.read(&mut buf[bytes_read..]) {
+            Ok(0) => break,
+            Ok(n) => bytes_read += n,
+            Err(error) if error.kind() == ErrorKind::Interrupted => {}
+            Err(error) => return Err(error.into()),
+        }
+    }
+    match bytes_read {
+        0 => Ok(false),
+        n if n == buf.len() => Ok(true),
+        bytes_read => Err(BinaryParseError::Truncated {
+            offset,
+            record_size: buf.len(),
+            bytes_read,
+        }),
+    }
+}
+
+/// Decodes a little-endian `u64` address written by `cairo-run`. Addresses
+/// must fit in a `u32` (the same bound as public memory addresses).
+pub fn read_address(bytes: &[u8], offset: u64) -> Result<usize, BinaryParseError> {
+    let address = u64::from_le_bytes(bytes.try_into().unwrap());
+    if address > u64::from(u32::MAX) {
+        return Err(BinaryParseError::InvalidAddress { offset, address });
+    }
+    Ok(address as usize)
+}
+
+/// Decodes a little-endian `u64` register value (ap, fp or pc) written by
+/// `cairo-run`. Registers hold addresses so have the same bound.
+pub fn read_register(bytes: &[u8], offset: u64) -> Result<usize, BinaryParseError> {
+    let value = u64::from_le_bytes(bytes.try_into().unwrap());
+    if value > u64::from(u32::MAX) {
+        return Err(BinaryParseError::InvalidRegister { offset, value });
+    }
+    Ok(value as usize)
+}
+
+/// Returns the value if it is less than the modulus of the field
+pub fn check_field_element<F: PrimeField>(value: U256) -> Result<U256, InvalidFieldElementError> {
+    let modulus = U256::from::<BigUint>(F::MODULUS.into());
+    if value < modulus {
+        Ok(value)
+    } else {
+        Err(InvalidFieldElementError { value, modulus })
+    }
+}
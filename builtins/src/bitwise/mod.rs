@@ -1,5 +1,8 @@
+use std::error::Error;
+use std::fmt::Display;
 use std::ops::Deref;
 
+use ark_ff::PrimeField;
 use binary::BitwiseInstance;
 use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp;
 use num_bigint::BigUint;
@@ -52,6 +55,71 @@ impl<const SPACING: usize> InstanceTrace<SPACING> {
     }
 }
 
+/// Maximum number of bits in the inputs of [InstanceTrace64]
+pub const INPUT64_BITS: usize = 63;
+
+/// A bitwise instance with an input that's too large for [InstanceTrace64]
+#[derive(Debug, Clone, Copy)]
+pub struct InputTooLargeError {
+    pub index: u32,
+}
+
+impl Display for InputTooLargeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "bitwise instance {} has an input that exceeds {INPUT64_BITS} bits",
+            self.index
+        )
+    }
+}
+
+impl Error for InputTooLargeError {}
+
+/// Trace of a bitwise instance over a generic field.
+/// Inputs are limited to 63 bits so every value and partition has a unique
+/// representation in fields as small as 64 bits (e.g. Goldilocks).
+#[derive(Clone, Debug)]
+pub struct InstanceTrace64<F: PrimeField, const SPACING: usize> {
+    pub instance: BitwiseInstance,
+    pub x: F,
+    pub y: F,
+    pub x_and_y: F,
+    pub x_xor_y: F,
+    pub x_or_y: F,
+    pub x_partition: Partition64<SPACING>,
+    pub y_partition: Partition64<SPACING>,
+    pub x_and_y_partition: Partition64<SPACING>,
+    pub x_xor_y_partition: Partition64<SPACING>,
+}
+
+impl<F: PrimeField, const SPACING: usize> InstanceTrace64<F, SPACING> {
+    pub fn new(instance: BitwiseInstance) -> Result<Self, InputTooLargeError> {
+        let BitwiseInstance { index, x, y } = instance;
+        if x.bit_len() > INPUT64_BITS || y.bit_len() > INPUT64_BITS {
+            return Err(InputTooLargeError { index });
+        }
+        let x = x.as_limbs()[0];
+        let y = y.as_limbs()[0];
+        let x_and_y = x & y;
+        let x_xor_y = x ^ y;
+        let x_or_y = x | y;
+
+        Ok(Self {
+            instance,
+            x: x.into(),
+            y: y.into(),
+            x_and_y: x_and_y.into(),
+            x_xor_y: x_xor_y.into(),
+            x_or_y: x_or_y.into(),
+            x_partition: Partition64::new(x),
+            y_partition: Partition64::new(y),
+            x_and_y_partition: Partition64::new(x_and_y),
+            x_xor_y_partition: Partition64::new(x_xor_y),
+        })
+    }
+}
+
 /// Partitions of a 64 bit integer
 /// For example to break up the 64 bit binary integer `v` with spacing 4:
 /// ```text
@@ -135,6 +203,9 @@ pub fn dilute<const SPACING: usize>(v: U256) -> U256 {
 #[cfg(test)]
 mod tests {
     use crate::bitwise::dilute;
+    use crate::bitwise::InstanceTrace64;
+    use binary::BitwiseInstance;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fp;
     use ruint::aliases::U256;
 
     #[test]
@@ -143,4 +214,49 @@ mod tests {
 
         assert_eq!(U256::from(0b0001_0000_0001u32), dilute::<4>(input))
     }
+
+    #[test]
+    fn instance_trace64_partitions_recombine() {
+        let x = U256::from(0x7fff_0000_ffff_1234u64);
+        let y = U256::from(0x1234_5678_9abc_def0u64);
+        let trace = InstanceTrace64::<Fp, 4>::new(BitwiseInstance { index: 0, x, y }).unwrap();
+
+        for (partition, expected) in [
+            (trace.x_partition, 0x7fff_0000_ffff_1234u64),
+            (trace.y_partition, 0x1234_5678_9abc_def0),
+            (
+                trace.x_and_y_partition,
+                0x7fff_0000_ffff_1234 & 0x1234_5678_9abc_def0,
+            ),
+            (
+                trace.x_xor_y_partition,
+                0x7fff_0000_ffff_1234 ^ 0x1234_5678_9abc_def0,
+            ),
+        ] {
+            let recombined = partition
+                .iter()
+                .enumerate()
+                .fold(0, |acc, (s, segment)| acc + (segment << s));
+            assert_eq!(expected, recombined);
+        }
+        assert_eq!(trace.x_or_y, trace.x_and_y + trace.x_xor_y);
+    }
+
+    #[test]
+    fn instance_trace64_rejects_64_bit_inputs() {
+        let x = U256::ZERO;
+        let y = U256::from(1u64 << 63);
+
+        let err = InstanceTrace64::<Fp, 4>::new(BitwiseInstance { index: 7, x, y }).unwrap_err();
+
+        assert_eq!(7, err.index);
+    }
+
+    #[test]
+    fn instance_trace64_accepts_63_bit_inputs() {
+        let x = U256::from(u64::MAX >> 1);
+        let y = U256::from(u64::MAX >> 1);
+
+        assert!(InstanceTrace64::<Fp, 4>::new(BitwiseInstance { index: 0, x, y }).is_ok());
+    }
 }
@@ -0,0 +1,408 @@
+use crate::ec_op::mimic_ec_mad_air;
+use crate::keccak;
+use crate::pedersen::pedersen_hash;
+use crate::poseidon;
+use crate::utils::curve::StarkwareCurve;
+use ark_ec::short_weierstrass::Affine;
+use ark_ff::Field;
+use binary::AirPrivateInput;
+use binary::BuiltinInstance;
+use binary::MemorySegments;
+use binary::ReadMemory;
+use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp;
+use num_bigint::BigUint;
+use ruint::aliases::U256;
+use std::error::Error;
+use std::fmt::Display;
+
+/// A builtin instance with a value in memory that doesn't match the value
+/// computed by the builtin
+#[derive(Debug, Clone, Copy)]
+pub struct BuiltinOutputError {
+    pub builtin: &'static str,
+    pub index: u32,
+    pub address: u32,
+}
+
+impl Display for BuiltinOutputError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self {
+            builtin,
+            index,
+            address,
+        } = self;
+        write!(
+            f,
+            "{builtin} instance {index} has an invalid value at address {address}"
+        )
+    }
+}
+
+impl Error for BuiltinOutputError {}
+
+/// Recomputes the output of every pedersen, bitwise, ec_op, keccak and poseidon
+/// instance and compares it with the output cells in memory. Output cells that
+/// aren't in memory are skipped since the prover fills them in. Instances with
+/// inputs the builtin can't process (e.g. a keccak word over 200 bits) are
+/// reported at the address of that input.
+pub fn check_builtin_outputs<F: Field>(
+    private_input: &AirPrivateInput,
+    memory: &impl ReadMemory<F>,
+    memory_segments: &MemorySegments,
+) -> Result<(), BuiltinOutputError> {
+    let check = |builtin, index, address: u32, expected: U256| match memory.get(address as usize) {
+        Some(word) if word.0 != expected => Err(BuiltinOutputError {
+            builtin,
+            index,
+            address,
+        }),
+        _ => Ok(()),
+    };
+
+    if let Some(segment) = memory_segments.pedersen {
+        for instance in &private_input.pedersen {
+            let addrs = instance.mem_addrs(segment.begin_addr);
+            let output = pedersen_hash(u256_to_fp(instance.a), u256_to_fp(instance.b));
+            check("pedersen", instance.index, addrs.output, fp_to_u256(output))?;
+        }
+    }
+
+    if let Some(segment) = memory_segments.bitwise {
+        for instance in &private_input.bitwise {
+            let addrs = instance.mem_addrs(segment.begin_addr);
+            let (x, y) = (instance.x, instance.y);
+            check("bitwise", instance.index, addrs.and, x & y)?;
+            check("bitwise", instance.index, addrs.xor, x ^ y)?;
+            check("bitwise", instance.index, addrs.or, x | y)?;
+        }
+    }
+
+    if let Some(segment) = memory_segments.ec_op {
+        for instance in &private_input.ec_op {
+            let addrs = instance.mem_addrs(segment.begin_addr);
+            let error = |address| BuiltinOutputError {
+                builtin: "ec_op",
+                index: instance.index,
+                address,
+            };
+            let p = Affine::<StarkwareCurve>::new_unchecked(
+                u256_to_fp(instance.p_x),
+                u256_to_fp(instance.p_y),
+            );
+            let q = Affine::<StarkwareCurve>::new_unchecked(
+                u256_to_fp(instance.q_x),
+                u256_to_fp(instance.q_y),
+            );
+            if !p.is_on_curve() {
+                return Err(error(addrs.p_x));
+            }
+            if !q.is_on_curve() {
+                return Err(error(addrs.q_x));
+            }
+            let r: Affine<StarkwareCurve> =
+                mimic_ec_mad_air(u256_to_fp(instance.m), q.into(), p.into())
+                    .ok_or(error(addrs.r_x))?
+                    .into();
+            check("ec_op", instance.index, addrs.r_x, fp_to_u256(r.x))?;
+            check("ec_op", instance.index, addrs.r_y, fp_to_u256(r.y))?;
+        }
+    }
+
+    if let Some(segment) = memory_segments.keccak {
+        for instance in &private_input.keccak {
+            let addrs = instance.mem_addrs(segment.begin_addr);
+            let outputs = keccak::permute(instance.inputs()).map_err(|err| BuiltinOutputError {
+                builtin: "keccak",
+                index: instance.index,
+                address: addrs.inputs[err.index],
+            })?;
+            for (address, output) in addrs.outputs.into_iter().zip(outputs) {
+                check("keccak", instance.index, address, output)?;
+            }
+        }
+    }
+
+    if let Some(segment) = memory_segments.poseidon {
+        for instance in &private_input.poseidon {
+            let addrs = instance.mem_addrs(segment.begin_addr);
+            let inputs = [instance.input0, instance.input1, instance.input2].map(u256_to_fp);
+            let outputs = poseidon::permute(inputs);
+            for (address, output) in [addrs.output0, addrs.output1, addrs.output2]
+                .into_iter()
+                .zip(outputs)
+            {
+                check("poseidon", instance.index, address, fp_to_u256(output))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn u256_to_fp(v: U256) -> Fp {
+    BigUint::from(v).into()
+}
+
+fn fp_to_u256(v: Fp) -> U256 {
+    U256::from(BigUint::from(v))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_builtin_outputs;
+    use crate::pedersen::pedersen_hash;
+    use binary::AirPrivateInput;
+    use binary::AirPublicInput;
+    use binary::BitwiseInstance;
+    use binary::BuiltinInstance;
+    use binary::EcOpInstance;
+    use binary::KeccakInstance;
+    use binary::Memory;
+    use binary::MemorySegments;
+    use binary::PedersenInstance;
+    use binary::PoseidonInstance;
+    use binary::Segment;
+    use binary::Word;
+    use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp;
+    use num_bigint::BigUint;
+    use ruint::aliases::U256;
+    use ruint::uint;
+    use std::fs::File;
+    use std::path::PathBuf;
+
+    const PEDERSEN_BEGIN_ADDR: u32 = 100;
+
+    fn pedersen_execution(output: U256) -> (AirPrivateInput, MemorySegments, Memory<Fp>) {
+        let instance = PedersenInstance {
+            index: 0,
+            a: uint!(1_U256),
+            b: uint!(2_U256),
+        };
+        let segment = Segment {
+            begin_addr: PEDERSEN_BEGIN_ADDR,
+            stop_ptr: PEDERSEN_BEGIN_ADDR + 3,
+        };
+        let empty_segment = Segment {
+            begin_addr: 1,
+            stop_ptr: 1,
+        };
+        let memory_segments = MemorySegments {
+            program: empty_segment,
+            execution: empty_segment,
+            output: None,
+            pedersen: Some(segment),
+            range_check: None,
+            ecdsa: None,
+            bitwise: None,
+            ec_op: None,
+            keccak: None,
+            poseidon: None,
+            range_check96: None,
+            add_mod: None,
+            mul_mod: None,
+        };
+        let mut memory = Memory::default();
+        let addrs = instance.mem_addrs(PEDERSEN_BEGIN_ADDR);
+        memory.insert(addrs.a as usize, Word::new(instance.a));
+        memory.insert(addrs.b as usize, Word::new(instance.b));
+        memory.insert(addrs.output as usize, Word::new(output));
+        let private_input = AirPrivateInput {
+            trace_path: PathBuf::new(),
+            memory_path: PathBuf::new(),
+            pedersen: vec![instance],
+            range_check: Vec::new(),
+            ecdsa: Vec::new(),
+            bitwise: Vec::new(),
+            ec_op: Vec::new(),
+            keccak: Vec::new(),
+            poseidon: Vec::new(),
+        };
+        (private_input, memory_segments, memory)
+    }
+
+    #[test]
+    fn correct_pedersen_output_passes() {
+        let hash = pedersen_hash(Fp::from(1u8), Fp::from(2u8));
+        let (private_input, memory_segments, memory) =
+            pedersen_execution(U256::from(BigUint::from(hash)));
+
+        assert!(check_builtin_outputs(&private_input, &memory, &memory_segments).is_ok());
+    }
+
+    #[test]
+    fn wrong_pedersen_output_is_reported() {
+        let (private_input, memory_segments, memory) = pedersen_execution(uint!(3_U256));
+
+        let err = check_builtin_outputs(&private_input, &memory, &memory_segments).unwrap_err();
+
+        assert_eq!("pedersen", err.builtin);
+        assert_eq!(0, err.index);
+        assert_eq!(PEDERSEN_BEGIN_ADDR + 2, err.address);
+    }
+
+    fn example_path(path: &str) -> String {
+        format!("{}/../{path}", env!("CARGO_MANIFEST_DIR"))
+    }
+
+    fn read_bootloader_execution() -> (AirPrivateInput, MemorySegments, Memory<Fp>) {
+        let public_input_file =
+            File::open(example_path("example/bootloader/air-public-input.json")).unwrap();
+        let public_input: AirPublicInput<Fp> = serde_json::from_reader(public_input_file).unwrap();
+        let private_input_file =
+            File::open(example_path("example/bootloader/air-private-input.json")).unwrap();
+        let private_input = serde_json::from_reader(private_input_file).unwrap();
+        let memory_segments = public_input.memory_segments;
+        let memory_file = File::open(example_path("example/bootloader/memory.bin")).unwrap();
+        let memory = Memory::from_reader_with_segments(memory_file, &memory_segments).unwrap();
+        (private_input, memory_segments, memory)
+    }
+
+    fn write_cells(memory: &mut Memory<Fp>, cells: impl IntoIterator<Item = (u32, U256)>) {
+        for (address, value) in cells {
+            memory.insert(address as usize, Word::new(value));
+        }
+    }
+
+    #[test]
+    fn bootloader_outputs_pass() {
+        let (private_input, memory_segments, memory) = read_bootloader_execution();
+
+        assert!(check_builtin_outputs(&private_input, &memory, &memory_segments).is_ok());
+    }
+
+    #[test]
+    fn corrupted_bootloader_pedersen_output_is_reported() {
+        let (private_input, memory_segments, mut memory) = read_bootloader_execution();
+        let segment = memory_segments.pedersen.unwrap();
+        let addrs = private_input.pedersen[1].mem_addrs(segment.begin_addr);
+        let output = memory[addrs.output as usize].unwrap().0;
+        write_cells(&mut memory, [(addrs.output, output + uint!(1_U256))]);
+
+        let err = check_builtin_outputs(&private_input, &memory, &memory_segments).unwrap_err();
+
+        assert_eq!("pedersen", err.builtin);
+        assert_eq!(1, err.index);
+        assert_eq!(addrs.output, err.address);
+    }
+
+    #[test]
+    fn corrupted_bitwise_output_is_reported() {
+        let (mut private_input, memory_segments, mut memory) = read_bootloader_execution();
+        let segment = memory_segments.bitwise.unwrap();
+        let instance = BitwiseInstance {
+            index: 0,
+            x: uint!(12_U256),
+            y: uint!(10_U256),
+        };
+        let addrs = instance.mem_addrs(segment.begin_addr);
+        // `12 ^ 10` is 6
+        write_cells(
+            &mut memory,
+            [
+                (addrs.x, instance.x),
+                (addrs.y, instance.y),
+                (addrs.and, uint!(8_U256)),
+                (addrs.xor, uint!(7_U256)),
+                (addrs.or, uint!(14_U256)),
+            ],
+        );
+        private_input.bitwise.push(instance);
+
+        let err = check_builtin_outputs(&private_input, &memory, &memory_segments).unwrap_err();
+
+        assert_eq!("bitwise", err.builtin);
+        assert_eq!(0, err.index);
+        assert_eq!(addrs.xor, err.address);
+    }
+
+    #[test]
+    fn corrupted_ec_op_output_is_reported() {
+        let (mut private_input, memory_segments, mut memory) = read_bootloader_execution();
+        let segment = memory_segments.ec_op.unwrap();
+        // instance from a `cairo-run` execution
+        let instance = EcOpInstance {
+            index: 0,
+            p_x: uint!(0x2f59f202bc803b15260caea833f1d77b01f819b83e7bca04441cbe2fb99ebaa_U256),
+            p_y: uint!(0x2f09f1db80a70686619d18de76994148ef9868c0d869121d3f99839ac000cb5_U256),
+            q_x: uint!(0x1ef15c18599971b7beced415a40f0c7deacfd9b0d1819e03d723d8bc943cfca_U256),
+            q_y: uint!(0x5668060aa49730b7be4801df46ec62de53ecd11abe43a32873000c36e8dc1f_U256),
+            m: uint!(0x4be8d5e6a89581244171bb35108b89ccb781dd31284e5eb37855eddea4b1581_U256),
+        };
+        let addrs = instance.mem_addrs(segment.begin_addr);
+        write_cells(
+            &mut memory,
+            [
+                (addrs.p_x, instance.p_x),
+                (addrs.p_y, instance.p_y),
+                (addrs.q_x, instance.q_x),
+                (addrs.q_y, instance.q_y),
+                (addrs.m, instance.m),
+                (addrs.r_x, instance.p_x),
+            ],
+        );
+        private_input.ec_op.push(instance);
+
+        let err = check_builtin_outputs(&private_input, &memory, &memory_segments).unwrap_err();
+
+        assert_eq!("ec_op", err.builtin);
+        assert_eq!(0, err.index);
+        assert_eq!(addrs.r_x, err.address);
+    }
+
+    #[test]
+    fn corrupted_keccak_output_is_reported() {
+        let (mut private_input, memory_segments, mut memory) = read_bootloader_execution();
+        // the bootloader's layout has no keccak builtin so the segment is placed
+        // after the bootloader's memory
+        let begin_addr = memory.len() as u32;
+        let memory_segments = MemorySegments {
+            keccak: Some(Segment {
+                begin_addr,
+                stop_ptr: begin_addr + KeccakInstance::cells_per_instance(),
+            }),
+            ..memory_segments
+        };
+        let instance = KeccakInstance::new_empty(0);
+        let addrs = instance.mem_addrs(begin_addr);
+        // the permutation of the zero state isn't zero
+        let inputs = addrs.inputs.map(|address| (address, U256::ZERO));
+        write_cells(&mut memory, inputs);
+        write_cells(&mut memory, [(addrs.outputs[0], U256::ZERO)]);
+        private_input.keccak.push(instance);
+
+        let err = check_builtin_outputs(&private_input, &memory, &memory_segments).unwrap_err();
+
+        assert_eq!("keccak", err.builtin);
+        assert_eq!(0, err.index);
+        assert_eq!(addrs.outputs[0], err.address);
+    }
+
+    #[test]
+    fn corrupted_poseidon_output_is_reported() {
+        let (mut private_input, memory_segments, mut memory) = read_bootloader_execution();
+        let segment = memory_segments.poseidon.unwrap();
+        let instance = PoseidonInstance {
+            index: 0,
+            input0: uint!(1_U256),
+            input1: uint!(2_U256),
+            input2: uint!(3_U256),
+        };
+        let addrs = instance.mem_addrs(segment.begin_addr);
+        write_cells(
+            &mut memory,
+            [
+                (addrs.input0, instance.input0),
+                (addrs.input1, instance.input1),
+                (addrs.input2, instance.input2),
+                (addrs.output0, instance.input0),
+            ],
+        );
+        private_input.poseidon.push(instance);
+
+        let err = check_builtin_outputs(&private_input, &memory, &memory_segments).unwrap_err();
+
+        assert_eq!("poseidon", err.builtin);
+        assert_eq!(0, err.index);
+        assert_eq!(addrs.output0, err.address);
+    }
+}
@@ -136,6 +136,19 @@ fn gen_ec_mad_steps(
     res
 }
 
+/// Computes `r = p + m * q` of an EC op instance with points given as `[x, y]`.
+/// Returns None if either point isn't on the curve or the AIR can't compute
+/// `r`.
+pub fn compute_r(p: [Fp; 2], q: [Fp; 2], m: Fp) -> Option<[Fp; 2]> {
+    let p = Affine::<StarkwareCurve>::new_unchecked(p[0], p[1]);
+    let q = Affine::<StarkwareCurve>::new_unchecked(q[0], q[1]);
+    if !p.is_on_curve() || !q.is_on_curve() {
+        return None;
+    }
+    let r = Affine::from(mimic_ec_mad_air(m, q.into(), p.into())?);
+    Some([r.x, r.y])
+}
+
 /// Computes `p + m * q` using the same steps as the AIR
 /// Returns None if and only if the AIR errors.
 pub(crate) fn mimic_ec_mad_air(
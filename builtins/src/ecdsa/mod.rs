@@ -268,14 +268,26 @@ fn gen_dummy_instance(index: u32) -> EcdsaInstance {
     unreachable!()
 }
 
+/// Returns true if the signature of an instance is valid for its message and
+/// public key. Signatures with a `w` that has no inverse are invalid.
+pub fn verify_instance(instance: &EcdsaInstance) -> bool {
+    let message = Fp::from(BigUint::from(instance.message));
+    let pubkey_x = Fp::from(BigUint::from(instance.pubkey_x));
+    let r = Fp::from(BigUint::from(instance.signature.r));
+    let w = Fr::from(BigUint::from(instance.signature.w));
+    match w.inverse() {
+        Some(s) => verify(message, r, s, pubkey_x).is_some(),
+        None => false,
+    }
+}
+
 /// Verifies a signature
 /// Returns the associated public key if the signature is valid
 /// Returns None if the signature is invalid
 /// based on: https://github.com/starkware-libs/starkex-resources/blob/844ac3dcb1f735451457f7eecc6e37cd96d1cb2d/crypto/starkware/crypto/signature/signature.py#L192
 fn verify(msg_hash: Fp, r: Fp, s: Fr, pubkey_x: Fp) -> Option<Affine<StarkwareCurve>> {
     let w = s.inverse().unwrap();
-    let (y1, y0) =
-        Affine::<StarkwareCurve>::get_ys_from_x_unchecked(pubkey_x).expect("not on the curve");
+    let (y1, y0) = Affine::<StarkwareCurve>::get_ys_from_x_unchecked(pubkey_x)?;
 
     #[allow(clippy::tuple_array_conversions)]
     for pubkey_y in [y1, y0] {
@@ -291,9 +303,13 @@ fn verify(msg_hash: Fp, r: Fp, s: Fr, pubkey_x: Fp) -> Option<Affine<StarkwareCu
         // errors here as well.
         let shift_point = Projective::from(SHIFT_POINT);
         let generator = StarkwareCurve::GENERATOR.into();
-        let zg = mimic_ec_mad_air(msg_hash.into(), generator, -shift_point).unwrap();
-        let rq = mimic_ec_mad_air(r.into(), pubkey.into(), shift_point).unwrap();
-        let wb = mimic_ec_mad_air(w.into(), zg + rq, shift_point).unwrap();
+        let zg = mimic_ec_mad_air(msg_hash.into(), generator, -shift_point)?;
+        let Some(rq) = mimic_ec_mad_air(r.into(), pubkey.into(), shift_point) else {
+            continue;
+        };
+        let Some(wb) = mimic_ec_mad_air(w.into(), zg + rq, shift_point) else {
+            continue;
+        };
         let x = (wb - shift_point).into_affine().x;
         if r == x {
             return Some(pubkey);
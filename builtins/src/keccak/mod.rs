@@ -0,0 +1,226 @@
+//! Keccak builtin. Applies the Keccak-f[1600] permutation to a 1600 bit state.
+//! Reference: <https://keccak.team/keccak_specs_summary.html>
+
+use binary::KeccakInstance;
+use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp;
+use num_bigint::BigUint;
+use ruint::aliases::U256;
+use ruint::uint;
+use std::error::Error;
+use std::fmt::Display;
+
+/// Number of rounds in the Keccak-f[1600] permutation
+pub const NUM_ROUNDS: usize = 24;
+
+/// Number of bits in each of the 8 input (and output) words of an instance
+pub const WORD_BITS: usize = 200;
+
+/// Number of bytes in each of the 8 input (and output) words of an instance
+const WORD_BYTES: usize = WORD_BITS / 8;
+
+/// Round constants for the ι step
+pub const ROUND_CONSTANTS: [u64; NUM_ROUNDS] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808A,
+    0x8000000080008000,
+    0x000000000000808B,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008A,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000A,
+    0x000000008000808B,
+    0x800000000000008B,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800A,
+    0x800000008000000A,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+/// Rotation offsets `r[x][y]` for the ρ step
+const ROTATION_OFFSETS: [[u32; 5]; 5] = [
+    [0, 36, 3, 41, 18],
+    [1, 44, 10, 45, 2],
+    [62, 6, 43, 15, 61],
+    [28, 55, 25, 21, 56],
+    [27, 20, 39, 8, 14],
+];
+
+/// Keccak state of 25 64-bit lanes. Lane `(x, y)` is stored at `x + 5 * y`.
+pub type State = [u64; 25];
+
+/// An input word that doesn't fit in 200 bits
+#[derive(Debug, Clone, Copy)]
+pub struct WordTooLargeError {
+    /// Position of the word in the instance's inputs
+    pub index: usize,
+}
+
+impl Display for WordTooLargeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "keccak input word {} exceeds {WORD_BITS} bits",
+            self.index
+        )
+    }
+}
+
+impl Error for WordTooLargeError {}
+
+#[derive(Clone, Debug)]
+pub struct InstanceTrace {
+    pub instance: KeccakInstance,
+    pub input: [Fp; 8],
+    pub output: [Fp; 8],
+    /// The state before each round followed by the final state
+    pub round_states: Vec<State>,
+}
+
+impl InstanceTrace {
+    pub fn new(instance: KeccakInstance) -> Result<Self, WordTooLargeError> {
+        let inputs = instance.inputs();
+        let mut state = words_to_state(inputs)?;
+        let mut round_states = Vec::with_capacity(NUM_ROUNDS + 1);
+        round_states.push(state);
+        for round_constant in ROUND_CONSTANTS {
+            state = round(state, round_constant);
+            round_states.push(state);
+        }
+        let outputs = state_to_words(state);
+
+        Ok(Self {
+            instance,
+            input: inputs.map(|v| BigUint::from(v).into()),
+            output: outputs.map(|v| BigUint::from(v).into()),
+            round_states,
+        })
+    }
+}
+
+/// Applies the Keccak-f[1600] permutation to the 8 little-endian 200 bit
+/// words of a builtin instance
+pub fn permute(words: [U256; 8]) -> Result<[U256; 8], WordTooLargeError> {
+    Ok(state_to_words(keccak_f(words_to_state(words)?)))
+}
+
+/// Keccak-f[1600] permutation
+pub fn keccak_f(mut state: State) -> State {
+    for round_constant in ROUND_CONSTANTS {
+        state = round(state, round_constant);
+    }
+    state
+}
+
+/// Converts 8 little-endian 200 bit words into a Keccak state
+pub fn words_to_state(words: [U256; 8]) -> Result<State, WordTooLargeError> {
+    let mut bytes = [0; 200];
+    for (index, (chunk, word)) in bytes.chunks_exact_mut(WORD_BYTES).zip(words).enumerate() {
+        if word >= uint!(1_U256) << WORD_BITS {
+            return Err(WordTooLargeError { index });
+        }
+        chunk.copy_from_slice(&word.to_le_bytes::<32>()[..WORD_BYTES]);
+    }
+    let mut state = [0; 25];
+    for (lane, chunk) in state.iter_mut().zip(bytes.chunks_exact(8)) {
+        *lane = u64::from_le_bytes(chunk.try_into().unwrap());
+    }
+    Ok(state)
+}
+
+/// Converts a Keccak state into 8 little-endian 200 bit words
+pub fn state_to_words(state: State) -> [U256; 8] {
+    let mut bytes = [0; 200];
+    for (chunk, lane) in bytes.chunks_exact_mut(8).zip(state) {
+        chunk.copy_from_slice(&lane.to_le_bytes());
+    }
+    let mut words = [U256::ZERO; 8];
+    for (word, chunk) in words.iter_mut().zip(bytes.chunks_exact(WORD_BYTES)) {
+        *word = U256::try_from_le_slice(chunk).unwrap();
+    }
+    words
+}
+
+/// Single round of Keccak-f[1600]
+fn round(mut a: State, round_constant: u64) -> State {
+    // θ step
+    let c: [u64; 5] = std::array::from_fn(|x| a[x] ^ a[x + 5] ^ a[x + 10] ^ a[x + 15] ^ a[x + 20]);
+    let d: [u64; 5] = std::array::from_fn(|x| c[(x + 4) % 5] ^ c[(x + 1) % 5].rotate_left(1));
+    for (i, lane) in a.iter_mut().enumerate() {
+        *lane ^= d[i % 5];
+    }
+
+    // ρ and π steps
+    let mut b = [0; 25];
+    for (x, offsets) in ROTATION_OFFSETS.iter().enumerate() {
+        for (y, &offset) in offsets.iter().enumerate() {
+            b[y + 5 * ((2 * x + 3 * y) % 5)] = a[x + 5 * y].rotate_left(offset);
+        }
+    }
+
+    // χ step
+    for (i, lane) in a.iter_mut().enumerate() {
+        let (x, y) = (i % 5, i / 5);
+        *lane = b[i] ^ (!b[(x + 1) % 5 + 5 * y] & b[(x + 2) % 5 + 5 * y]);
+    }
+
+    // ι step
+    a[0] ^= round_constant;
+    a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::keccak_f;
+    use super::permute;
+    use ruint::aliases::U256;
+    use ruint::uint;
+
+    #[test]
+    fn zero_state_matches_reference() {
+        // Source: https://keccak.team/files/KeccakF-1600-IntermediateValues.txt
+        let state = keccak_f([0; 25]);
+
+        assert_eq!(0xF1258F7940E1DDE7, state[0]);
+        assert_eq!(0x84D5CCF933C0478A, state[1]);
+        assert_eq!(0xD598261EA65AA9EE, state[2]);
+    }
+
+    #[test]
+    fn permute_matches_cairo_runner() {
+        let input = [1u64, 2, 3, 4, 5, 6, 7, 8].map(U256::from);
+
+        let output = permute(input).unwrap();
+
+        let expected = [
+            uint!(0x5437ca4807beb9df3871c8467f0c8c2ac42e1f2100e18198f6_U256),
+            uint!(0x7a753f70755cbbde7882962e5969b2874c2dff11a91716ab31_U256),
+            uint!(0xe561082c7d6621e7480f773a54870b0dab0ad6151b08ee303a_U256),
+            uint!(0xb744cd390af9518a46a3d88b6003f7393c7ead3f9a131638be_U256),
+            uint!(0xf16079da5c848e9f6b99afdf72720169e5209e171f8ef7582e_U256),
+            uint!(0xe108fbbcea9d86a6d76f01b63c33ffffd896ad8e2b71026060_U256),
+            uint!(0x9c7aa8a62187936713012ec096925a78b1c1a140ab7a061ca3_U256),
+            uint!(0xd5774bd1793a6b3940c0a54888a6a3c1e57251a7e727590b40_U256),
+        ];
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn oversized_word_is_rejected() {
+        let mut input = [U256::ZERO; 8];
+        input[5] = uint!(1_U256) << 200;
+
+        let err = permute(input).unwrap_err();
+
+        assert_eq!(5, err.index);
+    }
+}
@@ -149,7 +149,7 @@ fn gen_half_full_round_states(
 
 /// Computes the Poseidon hash using StarkWare's parameters. Source:
 /// <https://extgit.iaik.tugraz.at/krypto/hadeshash/-/blob/master/code/starkadperm_x5_256_3.sage>
-fn permute(input: [Fp; 3]) -> [Fp; 3] {
+pub fn permute(input: [Fp; 3]) -> [Fp; 3] {
     let mut state = input;
     let mut round = 0;
     // first full rounds
@@ -1,6 +1,28 @@
 use binary::RangeCheckInstance;
 use ruint::aliases::U256;
 use ruint::uint;
+use std::error::Error;
+use std::fmt::Display;
+
+/// A range check instance with a value that doesn't fit in the parts of
+/// [InstanceTrace]
+#[derive(Debug, Clone, Copy)]
+pub struct ValueTooLargeError {
+    pub index: u32,
+    pub num_bits: usize,
+}
+
+impl Display for ValueTooLargeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "range check instance {} has a value that exceeds {} bits",
+            self.index, self.num_bits
+        )
+    }
+}
+
+impl Error for ValueTooLargeError {}
 
 #[derive(Clone, Debug)]
 pub struct InstanceTrace<const NUM_PARTS: usize> {
@@ -9,9 +31,15 @@ pub struct InstanceTrace<const NUM_PARTS: usize> {
 }
 
 impl<const NUM_PARTS: usize> InstanceTrace<NUM_PARTS> {
-    pub fn new(instance: RangeCheckInstance) -> Self {
+    pub fn new(instance: RangeCheckInstance) -> Result<Self, ValueTooLargeError> {
         let value = instance.value;
-        assert!(value < uint!(1_U256) << (NUM_PARTS * 16));
+        let num_bits = NUM_PARTS * 16;
+        if value >= uint!(1_U256) << num_bits {
+            return Err(ValueTooLargeError {
+                index: instance.index,
+                num_bits,
+            });
+        }
 
         // decompose value into u16 parts
         let mask = U256::from(u16::MAX);
@@ -22,6 +50,34 @@ impl<const NUM_PARTS: usize> InstanceTrace<NUM_PARTS> {
                 .unwrap();
         }
 
-        Self { instance, parts }
+        Ok(Self { instance, parts })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InstanceTrace;
+    use binary::RangeCheckInstance;
+    use ruint::aliases::U256;
+
+    #[test]
+    fn decomposes_value_into_parts() {
+        let value = U256::from(0x1234_5678u32);
+        let instance = RangeCheckInstance { index: 0, value };
+
+        let trace = InstanceTrace::<2>::new(instance).unwrap();
+
+        assert_eq!([0x1234, 0x5678], trace.parts);
+    }
+
+    #[test]
+    fn rejects_value_larger_than_parts() {
+        let value = U256::from(1u64 << 32);
+        let instance = RangeCheckInstance { index: 3, value };
+
+        let err = InstanceTrace::<2>::new(instance).unwrap_err();
+
+        assert_eq!(3, err.index);
+        assert_eq!(32, err.num_bits);
     }
 }
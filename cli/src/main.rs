@@ -2,18 +2,27 @@ use ark_ff::Field;
 use ark_ff::PrimeField;
 use ark_serialize::CanonicalDeserialize;
 use ark_serialize::CanonicalSerialize;
+use binary::check_trace;
 use binary::AirPrivateInput;
 use binary::AirPublicInput;
 use binary::CompiledProgram;
 use binary::Layout;
-use binary::Memory;
+use binary::MmapMemory;
+use binary::MmapRegisterStates;
 use binary::RegisterStates;
+use binary::SignedFelt;
+use builtins::check::check_builtin_outputs;
+use crypto::program_hash::ProgramHash;
+use layouts::CairoTrace;
 use layouts::CairoWitness;
+use ministark::air::AirConfig;
 use ministark::stark::Stark;
 use ministark::Proof;
 use ministark::ProofOptions;
 use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481;
+use num_bigint::BigUint;
 use sandstorm::claims;
+use std::collections::BTreeMap;
 use std::fs;
 use std::fs::File;
 use std::io::Write;
@@ -32,21 +41,39 @@ const GOLDILOCKS_PRIME_HEX_STR: &str = "0xffffffff00000001";
 #[derive(StructOpt, Debug)]
 #[structopt(name = "sandstorm", about = "cairo prover")]
 struct SandstormOptions {
+    /// Required by the prove and verify commands
     #[structopt(long, parse(from_os_str))]
-    program: PathBuf,
+    program: Option<PathBuf>,
+    /// Required by the prove and verify commands
     #[structopt(long, parse(from_os_str))]
-    air_public_input: PathBuf,
+    air_public_input: Option<PathBuf>,
     #[structopt(subcommand)]
     command: Command,
 }
 
+/// Files of the execution to check or prove
+#[derive(StructOpt, Debug)]
+struct ExecutionOptions {
+    /// Builtin instances are derived from memory if this isn't given. Required
+    /// if the execution uses the ecdsa builtin since signatures aren't stored
+    /// in memory.
+    #[structopt(long, parse(from_os_str))]
+    air_private_input: Option<PathBuf>,
+    /// Overrides the trace path of the private input
+    #[structopt(long, parse(from_os_str))]
+    trace: Option<PathBuf>,
+    /// Overrides the memory path of the private input
+    #[structopt(long, parse(from_os_str))]
+    memory: Option<PathBuf>,
+}
+
 #[derive(StructOpt, Debug)]
 enum Command {
     Prove {
         #[structopt(long, parse(from_os_str))]
         output: PathBuf,
-        #[structopt(long, parse(from_os_str))]
-        air_private_input: PathBuf,
+        #[structopt(flatten)]
+        execution: ExecutionOptions,
         // TODO: add validation to the proof options
         #[structopt(long, default_value = "65")]
         num_queries: u8,
@@ -59,12 +86,36 @@ enum Command {
         #[structopt(long, default_value = "16")]
         fri_max_remainder_coeffs: u8,
     },
+    /// Checks the trace, memory and builtin instances of an execution without
+    /// generating a proof
+    Check {
+        #[structopt(flatten)]
+        execution: ExecutionOptions,
+    },
     Verify {
         #[structopt(long, parse(from_os_str))]
         proof: PathBuf,
         #[structopt(long, default_value = "80")]
         required_security_bits: u8,
     },
+    /// Prints the Cairo assembly of a compiled program
+    Disasm {
+        #[structopt(long, parse(from_os_str))]
+        program: PathBuf,
+    },
+    /// Prints the program output stored in a public input
+    Output {
+        #[structopt(long, parse(from_os_str))]
+        air_public_input: PathBuf,
+    },
+    /// Prints the hash of a compiled program used by SHARP facts
+    ProgramHash {
+        #[structopt(long, parse(from_os_str))]
+        program: PathBuf,
+        /// Use Poseidon instead of Pedersen (used by newer bootloaders)
+        #[structopt(long)]
+        use_poseidon: bool,
+    },
 }
 
 fn main() {
@@ -75,6 +126,18 @@ fn main() {
         command,
     } = SandstormOptions::from_args();
 
+    match &command {
+        Command::Disasm { program } => return disasm(program),
+        Command::Output { air_public_input } => return print_output(air_public_input),
+        Command::ProgramHash {
+            program,
+            use_poseidon,
+        } => return print_program_hash(program, *use_poseidon),
+        _ => {}
+    }
+
+    let program = program.expect("--program is required");
+    let air_public_input = air_public_input.expect("--air-public-input is required");
     let program_file = File::open(program).expect("could not open program file");
     let air_public_input_file = File::open(air_public_input).expect("could not open public input");
     let program_json: serde_json::Value = serde_json::from_reader(program_file).unwrap();
@@ -87,17 +150,43 @@ fn main() {
             let air_public_input: AirPublicInput<Fp> =
                 serde_json::from_reader(air_public_input_file).unwrap();
             match air_public_input.layout {
+                Layout::Plain => {
+                    use claims::plain::EthVerifierClaim;
+                    let claim = EthVerifierClaim::new(program, air_public_input)
+                        .unwrap_or_else(|err| panic!("invalid public input: {err}"));
+                    execute_command(command, claim);
+                }
                 Layout::Starknet => {
                     use claims::starknet::EthVerifierClaim;
-                    let claim = EthVerifierClaim::new(program, air_public_input);
+                    let claim = EthVerifierClaim::new(program, air_public_input)
+                        .unwrap_or_else(|err| panic!("invalid public input: {err}"));
                     execute_command(command, claim);
                 }
                 Layout::Recursive => {
                     use claims::recursive::CairoVerifierClaim;
-                    let claim = CairoVerifierClaim::new(program, air_public_input);
+                    let claim = CairoVerifierClaim::new(program, air_public_input)
+                        .unwrap_or_else(|err| panic!("invalid public input: {err}"));
+                    execute_command(command, claim);
+                }
+                Layout::RecursiveLargeOutput => {
+                    use claims::recursive_large_output::CairoVerifierClaim;
+                    let claim = CairoVerifierClaim::new(program, air_public_input)
+                        .unwrap_or_else(|err| panic!("invalid public input: {err}"));
+                    execute_command(command, claim);
+                }
+                Layout::Small => {
+                    use claims::small::EthVerifierClaim;
+                    let claim = EthVerifierClaim::new(program, air_public_input)
+                        .unwrap_or_else(|err| panic!("invalid public input: {err}"));
                     execute_command(command, claim);
                 }
-                _ => unimplemented!(),
+                Layout::Dex => {
+                    use claims::dex::EthVerifierClaim;
+                    let claim = EthVerifierClaim::new(program, air_public_input)
+                        .unwrap_or_else(|err| panic!("invalid public input: {err}"));
+                    execute_command(command, claim);
+                }
+                layout => unsupported_layout(layout),
             }
         }
         #[cfg(feature = "experimental_claims")]
@@ -119,30 +208,57 @@ fn main() {
                     type M = MatrixMerkleTreeImpl<Sha256HashFn>;
                     type P = PublicCoinImpl<Fq3, Sha256HashFn>;
                     type C = CairoClaim<Fp, A, T, M, P>;
-                    let claim = C::new(program, air_public_input);
+                    let claim = C::new(program, air_public_input)
+                        .unwrap_or_else(|err| panic!("invalid public input: {err}"));
                     execute_command(command, claim);
                 }
                 Layout::Starknet => {
                     unimplemented!("'starknet' layout does not support Goldilocks field")
                 }
                 Layout::Recursive => {
-                    unimplemented!("'recursive' layout does not support Goldilocks field")
+                    // proven with `recursive_generic` which has no pedersen builtin
+                    if (air_public_input.memory_segments.pedersen)
+                        .is_some_and(|segment| segment.stop_ptr > segment.begin_addr)
+                    {
+                        eprintln!(
+                            "layout recursive can't use the pedersen builtin with Goldilocks field (see README)"
+                        );
+                        std::process::exit(1)
+                    }
+                    type A = layouts::recursive_generic::AirConfig<Fp, Fq3>;
+                    type T = layouts::recursive_generic::ExecutionTrace<Fp, Fq3>;
+                    type M = MatrixMerkleTreeImpl<Sha256HashFn>;
+                    type P = PublicCoinImpl<Fq3, Sha256HashFn>;
+                    type C = CairoClaim<Fp, A, T, M, P>;
+                    let claim = C::new(program, air_public_input)
+                        .unwrap_or_else(|err| panic!("invalid public input: {err}"));
+                    execute_command(command, claim);
                 }
-                layout => unimplemented!("layout {layout} is not supported yet"),
+                layout => unsupported_layout(layout),
             }
         }
         prime => unimplemented!("prime field p={prime} is not supported yet. Consider enabling the \"experimental_claims\" feature."),
     }
 }
 
+/// Exits with an error for layouts that have no AIR. Sandstorm can encode the
+/// public input of these layouts but can't prove or verify them.
+fn unsupported_layout(layout: Layout) -> ! {
+    eprintln!("layout {layout} can't be proven or verified by sandstorm (see README)");
+    std::process::exit(1)
+}
+
 fn execute_command<Fp: PrimeField, Claim: Stark<Fp = Fp, Witness = CairoWitness<Fp>>>(
     command: Command,
     claim: Claim,
-) {
+) where
+    Claim::AirConfig: AirConfig<PublicInputs = AirPublicInput<Fp>>,
+    Claim::Trace: CairoTrace,
+{
     match command {
         Command::Prove {
             output,
-            air_private_input,
+            execution,
             num_queries,
             lde_blowup_factor,
             proof_of_work_bits,
@@ -156,15 +272,80 @@ fn execute_command<Fp: PrimeField, Claim: Stark<Fp = Fp, Witness = CairoWitness<
                 fri_folding_factor,
                 fri_max_remainder_coeffs,
             );
-            prove(options, &air_private_input, &output, claim)
+            prove(options, &execution, &output, claim)
+        }
+        Command::Check { execution } => {
+            read_execution(&execution, &claim);
+            println!("Execution is valid");
         }
         Command::Verify {
             proof,
             required_security_bits,
         } => verify(required_security_bits, &proof, claim),
+        Command::Disasm { .. } | Command::Output { .. } | Command::ProgramHash { .. } => {
+            unreachable!()
+        }
+    }
+}
+
+fn disasm(program_path: &PathBuf) {
+    let program_file = File::open(program_path).expect("could not open program file");
+    let program_json: serde_json::Value = serde_json::from_reader(program_file).unwrap();
+    let prime: String = serde_json::from_value(program_json["prime"].clone()).unwrap();
+    match prime.to_lowercase().as_str() {
+        STARKWARE_PRIME_HEX_STR => {
+            use p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp;
+            let program: CompiledProgram<Fp> = serde_json::from_value(program_json).unwrap();
+            print_instructions(&program);
+        }
+        #[cfg(feature = "experimental_claims")]
+        GOLDILOCKS_PRIME_HEX_STR => {
+            use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+            let program: CompiledProgram<Fp> = serde_json::from_value(program_json).unwrap();
+            print_instructions(&program);
+        }
+        prime => unimplemented!("prime field p={prime} is not supported yet"),
     }
 }
 
+fn print_instructions<Fp: PrimeField>(program: &CompiledProgram<Fp>) {
+    for (pc, instruction) in program.disassemble() {
+        match instruction {
+            Some(instruction) => println!("{pc}: {instruction};"),
+            // values that aren't instructions are printed as data words
+            None => println!("{pc}: dw {};", SignedFelt(program.data[pc])),
+        }
+    }
+}
+
+fn print_output(air_public_input_path: &PathBuf) {
+    use p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp;
+    let air_public_input_file =
+        File::open(air_public_input_path).expect("could not open public input");
+    let air_public_input: AirPublicInput<Fp> =
+        serde_json::from_reader(air_public_input_file).unwrap();
+    air_public_input
+        .validate()
+        .unwrap_or_else(|err| panic!("invalid public input: {err}"));
+    // same format as `cairo-run --print_output`
+    println!("Program output:");
+    for value in air_public_input.program_output() {
+        println!("  {}", SignedFelt(value));
+    }
+}
+
+fn print_program_hash(program_path: &PathBuf, use_poseidon: bool) {
+    use p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp;
+    let program_file = File::open(program_path).expect("could not open program file");
+    let program: CompiledProgram<Fp> = serde_json::from_reader(program_file).unwrap();
+    let hash = if use_poseidon {
+        *program.hash_poseidon()
+    } else {
+        *program.hash_pedersen()
+    };
+    println!("{:#x}", BigUint::from(hash));
+}
+
 fn verify<Claim: Stark<Fp = impl Field>>(
     required_security_bits: u8,
     proof_path: &PathBuf,
@@ -177,25 +358,77 @@ fn verify<Claim: Stark<Fp = impl Field>>(
     println!("Proof verified in: {:?}", now.elapsed());
 }
 
+/// Reads the execution and checks it's consistent before spending time on a
+/// proof. Builtin instances are derived from memory if there's no private
+/// input.
+fn read_execution<Fp: PrimeField, Claim: Stark<Fp = Fp, Witness = CairoWitness<Fp>>>(
+    execution: &ExecutionOptions,
+    claim: &Claim,
+) -> (AirPrivateInput, MmapRegisterStates, MmapMemory<Fp>)
+where
+    Claim::AirConfig: AirConfig<PublicInputs = AirPublicInput<Fp>>,
+    Claim::Trace: CairoTrace,
+{
+    let private_input = execution.air_private_input.as_ref().map(|path| {
+        let private_input_file = File::open(path).expect("could not open private input file");
+        serde_json::from_reader::<_, AirPrivateInput>(private_input_file).unwrap()
+    });
+
+    let trace_path = (execution.trace.clone())
+        .or_else(|| private_input.as_ref().map(|input| input.trace_path.clone()))
+        .expect("--trace is required without --air-private-input");
+    let register_states = MmapRegisterStates::open(&trace_path)
+        .unwrap_or_else(|err| panic!("could not parse trace file: {err}"));
+
+    let memory_segments = claim.get_public_inputs().memory_segments;
+    let memory_path = (execution.memory.clone())
+        .or_else(|| {
+            private_input
+                .as_ref()
+                .map(|input| input.memory_path.clone())
+        })
+        .expect("--memory is required without --air-private-input");
+    let memory = MmapMemory::<Fp>::open(&memory_path)
+        .unwrap_or_else(|err| panic!("could not parse memory file: {err}"));
+
+    // the execution is checked and proven from the mapped files in place
+    check_trace(&register_states, &memory).unwrap_or_else(|err| panic!("invalid trace: {err}"));
+    let private_input = match private_input {
+        Some(private_input) => {
+            private_input
+                .check_memory(&memory, &memory_segments)
+                .unwrap_or_else(|err| panic!("invalid private input: {err}"));
+            private_input
+        }
+        None => {
+            let derived = AirPrivateInput::from_memory(&memory, &memory_segments, &BTreeMap::new())
+                .unwrap_or_else(|err| panic!("could not derive private input: {err}"));
+            AirPrivateInput {
+                trace_path,
+                memory_path,
+                ..derived
+            }
+        }
+    };
+    check_builtin_outputs(&private_input, &memory, &memory_segments)
+        .unwrap_or_else(|err| panic!("invalid builtin output: {err}"));
+    <Claim::Trace as CairoTrace>::check_private_input(&private_input)
+        .unwrap_or_else(|err| panic!("layout can't prove execution: {err}"));
+
+    (private_input, register_states, memory)
+}
+
 fn prove<Fp: PrimeField, Claim: Stark<Fp = Fp, Witness = CairoWitness<Fp>>>(
     options: ProofOptions,
-    private_input_path: &PathBuf,
+    execution: &ExecutionOptions,
     output_path: &PathBuf,
     claim: Claim,
-) {
-    let private_input_file =
-        File::open(private_input_path).expect("could not open private input file");
-    let private_input: AirPrivateInput = serde_json::from_reader(private_input_file).unwrap();
-
-    let trace_path = &private_input.trace_path;
-    let trace_file = File::open(trace_path).expect("could not open trace file");
-    let register_states = RegisterStates::from_reader(trace_file);
-
-    let memory_path = &private_input.memory_path;
-    let memory_file = File::open(memory_path).expect("could not open memory file");
-    let memory = Memory::from_reader(memory_file);
-
-    let witness = CairoWitness::new(private_input, register_states, memory);
+) where
+    Claim::AirConfig: AirConfig<PublicInputs = AirPublicInput<Fp>>,
+    Claim::Trace: CairoTrace,
+{
+    let (private_input, register_states, memory) = read_execution(execution, &claim);
+    let witness = CairoWitness::new(private_input, RegisterStates::from(register_states), memory);
 
     let now = Instant::now();
     let proof = pollster::block_on(claim.prove(options, witness)).unwrap();
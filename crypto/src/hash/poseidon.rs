@@ -0,0 +1,91 @@
+use std::fmt::Display;
+use std::ops::Deref;
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+use builtins::poseidon::permute;
+use ministark::hash::Digest;
+use ministark::hash::ElementHashFn;
+use ark_ff::Field;
+use ministark::hash::HashFn;
+use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp;
+use num_bigint::BigUint;
+use ruint::aliases::U256;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, CanonicalDeserialize, CanonicalSerialize)]
+pub struct PoseidonDigest(pub Fp);
+
+impl Display for PoseidonDigest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Digest for PoseidonDigest {
+    fn as_bytes(&self) -> [u8; 32] {
+        let num = U256::from(BigUint::from(self.0));
+        num.to_be_bytes::<32>()
+    }
+}
+
+impl Deref for PoseidonDigest {
+    type Target = Fp;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<Fp> for PoseidonDigest {
+    fn from(value: Fp) -> Self {
+        PoseidonDigest(value)
+    }
+}
+
+/// Computes the Poseidon hash of a and b (the same as Starknet's `poseidon_hash`)
+pub fn poseidon_hash(a: Fp, b: Fp) -> Fp {
+    permute([a, b, Fp::from(2u8)])[0]
+}
+
+pub struct PoseidonHashFn;
+
+impl HashFn for PoseidonHashFn {
+    type Digest = PoseidonDigest;
+    const COLLISION_RESISTANCE: u32 = 125;
+
+    fn hash(_bytes: impl IntoIterator<Item = u8>) -> PoseidonDigest {
+        unreachable!()
+    }
+
+    fn hash_chunks<'a>(_chunks: impl IntoIterator<Item = &'a [u8]>) -> Self::Digest {
+        unreachable!()
+    }
+
+    fn merge(v0: &PoseidonDigest, v1: &PoseidonDigest) -> PoseidonDigest {
+        PoseidonDigest(poseidon_hash(**v0, **v1))
+    }
+
+    fn merge_with_int(seed: &PoseidonDigest, value: u64) -> PoseidonDigest {
+        PoseidonDigest(poseidon_hash(**seed, value.into()))
+    }
+}
+
+impl ElementHashFn<Fp> for PoseidonHashFn {
+    /// Sponge construction with a rate of 2 (the same as Starknet's
+    /// `poseidon_hash_many`). Elements are padded with a one and then with
+    /// zeros to a multiple of the rate.
+    fn hash_elements(elements: impl IntoIterator<Item = Fp>) -> PoseidonDigest {
+        let mut elements = elements.into_iter().collect::<Vec<Fp>>();
+        elements.push(Fp::ONE);
+        if elements.len() % 2 != 0 {
+            elements.push(Fp::ZERO);
+        }
+
+        let mut state = [Fp::ZERO; 3];
+        for chunk in elements.chunks_exact(2) {
+            state[0] += chunk[0];
+            state[1] += chunk[1];
+            state = permute(state);
+        }
+        PoseidonDigest(state[0])
+    }
+}
@@ -0,0 +1,108 @@
+//! Hashes of compiled programs used by SHARP facts. Source:
+//! <https://github.com/starkware-libs/cairo-lang/blob/master/src/starkware/cairo/bootloaders/hash_program.py>
+
+use crate::hash::pedersen::PedersenDigest;
+use crate::hash::pedersen::PedersenHashFn;
+use crate::hash::poseidon::PoseidonDigest;
+use crate::hash::poseidon::PoseidonHashFn;
+use binary::CompiledProgram;
+use ministark::hash::ElementHashFn;
+use ministark::hash::HashFn;
+use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp;
+use num_bigint::BigUint;
+
+/// Version of the bootloader that's part of the program header
+const BOOTLOADER_VERSION: u64 = 0;
+
+pub trait ProgramHash {
+    /// Pedersen hash chain of the program header and data (the same as
+    /// `cairo-hash-program`). Panics if the program has no main function.
+    fn hash_pedersen(&self) -> PedersenDigest;
+
+    /// Poseidon hash of the program header and data (the same as
+    /// `cairo-hash-program --use_poseidon`). Panics if the program has no main
+    /// function.
+    fn hash_poseidon(&self) -> PoseidonDigest;
+}
+
+impl ProgramHash for CompiledProgram<Fp> {
+    fn hash_pedersen(&self) -> PedersenDigest {
+        let elements = program_hash_elements(self);
+        // hash chain of the length followed by the elements:
+        // `h(len, h(e_0, h(e_1, ..., h(e_{n-2}, e_{n-1}))))`
+        let len = Fp::from(elements.len() as u64);
+        let mut elements = std::iter::once(len).chain(elements).rev();
+        let last = PedersenDigest(elements.next().unwrap());
+        elements.fold(last, |acc, element| {
+            PedersenHashFn::merge(&PedersenDigest(element), &acc)
+        })
+    }
+
+    fn hash_poseidon(&self) -> PoseidonDigest {
+        PoseidonHashFn::hash_elements(program_hash_elements(self))
+    }
+}
+
+/// Returns the program header followed by the program's data. The header is
+/// `[bootloader_version, main, num_builtins, ...builtins]` where each builtin
+/// name is encoded as a big-endian ASCII integer.
+fn program_hash_elements(program: &CompiledProgram<Fp>) -> Vec<Fp> {
+    let main = program.main().expect("program has no main function");
+    let mut elements = vec![
+        Fp::from(BOOTLOADER_VERSION),
+        Fp::from(main as u64),
+        Fp::from(program.builtins.len() as u64),
+    ];
+    elements.extend(
+        (program.builtins.iter())
+            .map(|builtin| Fp::from(BigUint::from_bytes_be(builtin.as_bytes()))),
+    );
+    elements.extend(&program.data);
+    elements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ProgramHash;
+    use binary::CompiledProgram;
+    use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp;
+    use num_bigint::BigUint;
+    use std::fs::File;
+
+    fn read_program(path: &str) -> CompiledProgram<Fp> {
+        let path = format!("{}/../{path}", env!("CARGO_MANIFEST_DIR"));
+        serde_json::from_reader(File::open(path).unwrap()).unwrap()
+    }
+
+    fn to_hex(value: Fp) -> String {
+        format!("{:#x}", BigUint::from(value))
+    }
+
+    #[test]
+    fn array_sum_program_hash() {
+        let program = read_program("example/array-sum.json");
+
+        assert_eq!(
+            "0x5050f3abed24285e9cc3ed4272db4a34a437e358e490f38f6e8bace5cdb249d",
+            to_hex(*program.hash_pedersen())
+        );
+        assert_eq!(
+            "0xe95ec11d9e901ec49d27813615ab9481adfca4abe57021a4827a244608818c",
+            to_hex(*program.hash_poseidon())
+        );
+    }
+
+    #[test]
+    fn bootloader_program_hash() {
+        let program = read_program("example/bootloader/bootloader_compiled.json");
+
+        assert_eq!(
+            "0x42ff0eb089e7ae1fea8caa7f4b4f0e8f614a42864cfa4191fcc1ae2c389cc79",
+            to_hex(*program.hash_pedersen())
+        );
+        assert_eq!(
+            "0x34b8461794b3a72c5097066cd614917ba8074a8214b12e4e85b29355f18fe8a",
+            to_hex(*program.hash_poseidon())
+        );
+    }
+}
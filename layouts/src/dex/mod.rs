@@ -0,0 +1,44 @@
+//! Matches the builtins and ratios of the `dex` layout from StarkWare's open
+//! source verifier
+//! <https://github.com/starkware-libs/cairo-lang/blob/361fe32d5930db340ea78fe05aedfe706f6c9405/src/starkware/cairo/lang/instances.py>
+//!
+//! The AIR and trace are built by [crate::dynamic] so cells aren't in the same
+//! place as StarkWare's `dex` layout and proofs can only be verified by
+//! sandstorm.
+
+use crate::dynamic;
+use crate::dynamic::DynamicLayout;
+use crate::dynamic::LayoutParams;
+use binary::Layout;
+
+// must be a power-of-two
+pub const CYCLE_HEIGHT: usize = 16;
+/// 8 memory units per cycle and a public memory fraction of 4
+pub const PUBLIC_MEMORY_STEP: usize = 8;
+pub const MEMORY_STEP: usize = 2;
+/// 4 range check units per cycle
+pub const RANGE_CHECK_STEP: usize = 4;
+
+/// How many cycles per pedersen hash
+pub const PEDERSEN_BUILTIN_RATIO: usize = 8;
+
+/// How many cycles per 128 bit range check
+pub const RANGE_CHECK_BUILTIN_RATIO: usize = 8;
+
+/// How many cycles per ECDSA signature verification
+pub const ECDSA_BUILTIN_RATIO: usize = 512;
+
+/// CPU columns, two columns for each of the 8 pedersen lanes and two ECDSA
+/// columns
+pub const NUM_BASE_COLUMNS: usize = 25;
+pub const NUM_EXTENSION_COLUMNS: usize = 1;
+
+pub struct Dex;
+
+impl DynamicLayout for Dex {
+    const LAYOUT: Layout = Layout::Dex;
+    const PARAMS: LayoutParams = LayoutParams::dex();
+}
+
+pub type AirConfig = dynamic::AirConfig<Dex>;
+pub type ExecutionTrace = dynamic::ExecutionTrace<Dex>;
@@ -0,0 +1,1600 @@
+use super::DynamicLayout;
+use super::LayoutParams;
+use super::LayoutParamsError;
+use super::DYNAMIC_PUBLIC_MEMORY_STEP;
+use crate::recursive;
+use crate::recursive::air::cpu_constraints;
+use crate::recursive::air::cpu_hints;
+use crate::recursive::air::diluted_check_constraints;
+use crate::recursive::air::diluted_check_hints;
+use crate::recursive::air::DilutedCheck;
+use crate::recursive::air::MemoryPermutation;
+use crate::recursive::air::Permutation;
+use crate::recursive::CYCLE_HEIGHT;
+use crate::recursive::DILUTED_CHECK_STEP;
+use crate::recursive::MEMORY_STEP;
+use crate::recursive::PUBLIC_MEMORY_STEP;
+use crate::recursive::RANGE_CHECK_BUILTIN_PARTS;
+use crate::recursive::RANGE_CHECK_STEP;
+use crate::utils::map_into_fp_array;
+use crate::CairoAirConfig;
+use alloc::vec;
+use alloc::vec::Vec;
+use ark_ec::short_weierstrass::SWCurveConfig;
+use ark_ff::Field;
+use ark_ff::MontFp;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use binary::AirPublicInput;
+use binary::Layout;
+use binary::Segment;
+use builtins::ecdsa;
+use builtins::pedersen;
+use builtins::poseidon;
+use core::iter::zip;
+use core::marker::PhantomData;
+use ministark::challenges::Challenges;
+use ministark::constraints::AlgebraicItem;
+use ministark::constraints::CompositionConstraint;
+use ministark::constraints::CompositionItem;
+use ministark::constraints::Constraint;
+use ministark::constraints::ExecutionTraceColumn;
+use ministark::constraints::Hint;
+use ministark::constraints::PeriodicColumn;
+use ministark::expression::Expr;
+use ministark::hints::Hints;
+use ministark::utils::FieldVariant;
+use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp;
+use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::StarkwareCurve;
+use num_bigint::BigUint;
+use num_traits::Pow;
+
+/// Column of the dilutions used by the bitwise builtin
+pub const DILUTED_CHECK_UNORDERED_COLUMN: usize = DilutedCheck::Unordered.col_and_shift().0;
+
+/// Column of the memory accesses made by the CPU and builtins
+pub const NPC_COLUMN: usize = 3;
+
+/// Column of the 16-bit range checked values
+pub const RANGE_CHECK_COLUMN: usize = 5;
+
+/// Column of the CPU registers and intermediate values. Only odd rows are used
+/// by the CPU.
+pub const AUXILIARY_COLUMN: usize = 6;
+
+/// Number of base columns used by the CPU, memory, range check and diluted
+/// check components. Builtin columns come after these.
+pub const NUM_CPU_BASE_COLUMNS: usize = recursive::NUM_BASE_COLUMNS;
+
+/// Rows (in each cycle) of the npc column that aren't used by the CPU for
+/// each public memory step. Builtins store their memory cells in these rows.
+const BUILTIN_MEMORY_SLOTS_8: [usize; 1] = [6];
+const BUILTIN_MEMORY_SLOTS_16: [usize; 2] = [6, 10];
+
+/// Sizes of the groups of consecutive memory cells in each builtin instance.
+/// Cells in a group are spaced evenly across the instance.
+const PEDERSEN_MEMORY_GROUPS: [usize; 2] = [2, 1];
+const RANGE_CHECK_MEMORY_GROUPS: [usize; 1] = [1];
+const ECDSA_MEMORY_GROUPS: [usize; 1] = [2];
+const BITWISE_MEMORY_GROUPS: [usize; 2] = [4, 1];
+const POSEIDON_MEMORY_GROUPS: [usize; 2] = [4, 2];
+
+/// Smallest ratio each builtin's trace cells fit in
+pub const MIN_PEDERSEN_RATIO: usize = 2;
+pub const MIN_RANGE_CHECK_RATIO: usize = 8;
+pub const MIN_ECDSA_RATIO: usize = 256;
+pub const MIN_BITWISE_RATIO: usize = 8;
+pub const MIN_POSEIDON_RATIO: usize = 8;
+
+/// Number of steps in a pedersen hash
+const PEDERSEN_HASH_STEPS: usize = 512;
+
+static PEDERSEN_POINT_X_COEFFS: [FieldVariant<Fp, Fp>; 512] =
+    map_into_fp_array(pedersen::periodic::HASH_POINTS_X_COEFFS);
+static PEDERSEN_POINT_Y_COEFFS: [FieldVariant<Fp, Fp>; 512] =
+    map_into_fp_array(pedersen::periodic::HASH_POINTS_Y_COEFFS);
+static ECDSA_GENERATOR_POINT_X_COEFFS: [FieldVariant<Fp, Fp>; 256] =
+    map_into_fp_array(ecdsa::periodic::GENERATOR_POINTS_X_COEFFS);
+static ECDSA_GENERATOR_POINT_Y_COEFFS: [FieldVariant<Fp, Fp>; 256] =
+    map_into_fp_array(ecdsa::periodic::GENERATOR_POINTS_Y_COEFFS);
+static POSEIDON_FULL_ROUND_KEY0_COEFFS: [FieldVariant<Fp, Fp>; 8] =
+    map_into_fp_array(poseidon::periodic::FULL_ROUND_KEY_0_COEFFS);
+static POSEIDON_FULL_ROUND_KEY1_COEFFS: [FieldVariant<Fp, Fp>; 8] =
+    map_into_fp_array(poseidon::periodic::FULL_ROUND_KEY_1_COEFFS);
+static POSEIDON_FULL_ROUND_KEY2_COEFFS: [FieldVariant<Fp, Fp>; 8] =
+    map_into_fp_array(poseidon::periodic::FULL_ROUND_KEY_2_COEFFS);
+static POSEIDON_PARTIAL_ROUND_KEY0_COEFFS: [FieldVariant<Fp, Fp>; 64] =
+    map_into_fp_array(poseidon::periodic::PARTIAL_ROUND_KEY_0_COEFFS);
+static POSEIDON_PARTIAL_ROUND_KEY1_COEFFS: [FieldVariant<Fp, Fp>; 32] =
+    map_into_fp_array(poseidon::periodic::PARTIAL_ROUND_KEY_1_COEFFS);
+
+/// AIR of a layout described by [LayoutParams]. The CPU uses the same cells as
+/// [crate::recursive] and each builtin is placed in its own columns (or unused
+/// cells of the CPU columns) according to the builtin ratios.
+pub struct AirConfig<L: DynamicLayout>(PhantomData<L>);
+
+impl<L: DynamicLayout> ministark::air::AirConfig for AirConfig<L> {
+    const NUM_BASE_COLUMNS: usize = L::PARAMS.num_base_columns;
+    const NUM_EXTENSION_COLUMNS: usize = L::PARAMS.num_extension_columns;
+    type Fp = Fp;
+    type Fq = Fp;
+    type PublicInputs = AirPublicInput<Fp>;
+
+    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+        use AlgebraicItem::*;
+        use PublicInputHint::*;
+        let n = trace_len;
+        let layout =
+            AirLayout::new(L::PARAMS).unwrap_or_else(|err| panic!("invalid layout params: {err}"));
+        assert!(n >= CYCLE_HEIGHT, "must be a multiple of cycle height");
+        assert!(
+            n >= layout.max_instance_height(),
+            "trace must fit an instance of each builtin"
+        );
+
+        let uses_diluted_pool = L::PARAMS.diluted_check_step.is_some();
+        let mut cpu_constraints = match L::PARAMS.public_memory_step {
+            DYNAMIC_PUBLIC_MEMORY_STEP => cpu_constraints::<DYNAMIC_PUBLIC_MEMORY_STEP, Fp, Fp>(n),
+            PUBLIC_MEMORY_STEP => cpu_constraints::<PUBLIC_MEMORY_STEP, Fp, Fp>(n),
+            step => unreachable!("unsupported public memory step {step}"),
+        };
+        if uses_diluted_pool {
+            cpu_constraints.extend(diluted_check_constraints::<Fp, Fp>(n));
+        }
+
+        // The CPU constraints are shared with the recursive layout which has its
+        // extension columns straight after its 7 base columns. Move them after this
+        // layout's base columns. Only the memory and range check permutation column
+        // is needed without a diluted pool.
+        let first_extension_column = if uses_diluted_pool {
+            DilutedCheck::Aggregate.col_and_shift().0
+        } else {
+            Permutation::Memory.col_and_shift().0
+        };
+        let cpu_constraints = cpu_constraints.into_iter().map(|constraint| {
+            constraint.map_leaves(&mut |&leaf| match leaf {
+                Trace(column, offset) if column >= NUM_CPU_BASE_COLUMNS => Trace(
+                    column - first_extension_column + Self::NUM_BASE_COLUMNS,
+                    offset,
+                ),
+                leaf => leaf,
+            })
+        });
+
+        let mut builtin_constraints = Vec::new();
+        if let Some(memory) = &layout.pedersen {
+            builtin_constraints.extend(builtin_memory_constraints(n, memory, InitialPedersenAddr));
+            for lane in 0..layout.pedersen_lanes() {
+                builtin_constraints.extend(pedersen_constraints(n, &layout, lane));
+            }
+        }
+        if let Some(memory) = &layout.range_check {
+            builtin_constraints.extend(builtin_memory_constraints(n, memory, InitialRcAddr));
+            builtin_constraints.extend(range_check_constraints(n, &layout));
+        }
+        if let Some(memory) = &layout.ecdsa {
+            builtin_constraints.extend(builtin_memory_constraints(n, memory, InitialEcdsaAddr));
+            builtin_constraints.extend(ecdsa_constraints(n, &layout));
+        }
+        if let Some(memory) = &layout.bitwise {
+            builtin_constraints.extend(builtin_memory_constraints(n, memory, InitialBitwiseAddr));
+            builtin_constraints.extend(bitwise_constraints(n, &layout));
+        }
+        if let Some(memory) = &layout.poseidon {
+            builtin_constraints.extend(builtin_memory_constraints(n, memory, InitialPoseidonAddr));
+            builtin_constraints.extend(poseidon_constraints(n, &layout));
+        }
+
+        cpu_constraints
+            .chain(builtin_constraints)
+            .map(Constraint::new)
+            .collect()
+    }
+
+    fn composition_constraint(
+        _trace_len: usize,
+        constraints: &[Constraint<FieldVariant<Self::Fp, Self::Fq>>],
+    ) -> CompositionConstraint<FieldVariant<Self::Fp, Self::Fq>> {
+        use CompositionItem::*;
+        let alpha = Expr::Leaf(CompositionCoeff(0));
+        let expr = constraints
+            .iter()
+            .enumerate()
+            .map(|(i, constraint)| {
+                let constraint = constraint.map_leaves(&mut |&leaf| Item(leaf));
+                constraint * (&alpha).pow(i)
+            })
+            .sum::<Expr<CompositionItem<FieldVariant<Self::Fp, Self::Fq>>>>()
+            .reuse_shared_nodes();
+        CompositionConstraint::new(expr)
+    }
+
+    fn gen_hints(
+        trace_len: usize,
+        execution_info: &AirPublicInput<Self::Fp>,
+        challenges: &Challenges<Self::Fq>,
+    ) -> Hints<Self::Fq> {
+        use recursive::air::PublicInputHint::*;
+        use PublicInputHint::*;
+        assert_public_input_layout::<L>(execution_info);
+
+        let mut hints = match L::PARAMS.public_memory_step {
+            DYNAMIC_PUBLIC_MEMORY_STEP => cpu_hints::<DYNAMIC_PUBLIC_MEMORY_STEP, Fp, Fp>(
+                trace_len,
+                execution_info,
+                challenges,
+            ),
+            PUBLIC_MEMORY_STEP => {
+                cpu_hints::<PUBLIC_MEMORY_STEP, Fp, Fp>(trace_len, execution_info, challenges)
+            }
+            step => unreachable!("unsupported public memory step {step}"),
+        };
+        if L::PARAMS.diluted_check_step.is_some() {
+            hints.extend(diluted_check_hints::<Fp, Fp>(challenges));
+        } else {
+            // not referenced by any constraint without a diluted pool
+            hints.extend([
+                (DilutedCheckProduct.index(), Fp::ZERO),
+                (DilutedCheckFirst.index(), Fp::ZERO),
+                (DilutedCheckCumulativeValue.index(), Fp::ZERO),
+            ]);
+        }
+
+        // builtins that aren't in the layout have no segment
+        let segments = execution_info.memory_segments;
+        let initial_address =
+            |segment: Option<Segment>| segment.map_or(Fp::ZERO, |s| s.begin_addr.into());
+        hints.extend([
+            (
+                InitialPedersenAddr.index(),
+                initial_address(segments.pedersen),
+            ),
+            (InitialRcAddr.index(), initial_address(segments.range_check)),
+            (InitialEcdsaAddr.index(), initial_address(segments.ecdsa)),
+            (
+                InitialBitwiseAddr.index(),
+                initial_address(segments.bitwise),
+            ),
+            (
+                InitialPoseidonAddr.index(),
+                initial_address(segments.poseidon),
+            ),
+        ]);
+        Hints::new(hints)
+    }
+}
+
+impl<L: DynamicLayout> CairoAirConfig for AirConfig<L> {
+    fn public_memory_challenges(challenges: &Challenges<Self::Fq>) -> (Self::Fq, Self::Fq) {
+        (
+            challenges[MemoryPermutation::Z],
+            challenges[MemoryPermutation::A],
+        )
+    }
+
+    fn public_memory_quotient(hints: &Hints<Self::Fq>) -> Self::Fq {
+        hints[recursive::air::PublicInputHint::MemoryQuotient]
+    }
+}
+
+/// Panics if a public input wasn't generated for the layout
+pub(crate) fn assert_public_input_layout<L: DynamicLayout>(public_input: &AirPublicInput<Fp>) {
+    assert_eq!(
+        L::LAYOUT,
+        public_input.layout,
+        "public input has a different layout"
+    );
+    if public_input.layout == Layout::Dynamic {
+        let dynamic_params = public_input
+            .dynamic_params
+            .as_ref()
+            .expect("dynamic layout requires dynamic params");
+        let params = LayoutParams::from_dynamic_params(dynamic_params)
+            .unwrap_or_else(|err| panic!("invalid dynamic params: {err}"));
+        assert_eq!(
+            L::PARAMS,
+            params,
+            "public input has different layout params"
+        );
+    }
+}
+
+/// Evenly spaced cells of a trace column. The `i`th cell is in row
+/// `shift + i * step`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cells {
+    pub column: usize,
+    pub shift: usize,
+    pub step: usize,
+}
+
+impl Cells {
+    pub const fn new(column: usize, shift: usize, step: usize) -> Self {
+        Self {
+            column,
+            shift,
+            step,
+        }
+    }
+
+    /// Returns the row of the `i`th cell
+    pub const fn row(&self, i: usize) -> usize {
+        self.shift + i * self.step
+    }
+}
+
+impl ExecutionTraceColumn for Cells {
+    fn index(&self) -> usize {
+        self.column
+    }
+
+    fn offset<T>(&self, offset: isize) -> Expr<AlgebraicItem<T>> {
+        let trace_offset = self.step as isize * offset + self.shift as isize;
+        AlgebraicItem::Trace(self.column, trace_offset).into()
+    }
+}
+
+/// A group of consecutive memory cells of a builtin instance. Cells are spaced
+/// evenly across the instance starting at `row` of the npc column.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryGroup {
+    pub row: usize,
+    pub size: usize,
+}
+
+/// Memory cells of each instance of a builtin
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BuiltinMemory {
+    pub instance_height: usize,
+    pub groups: Vec<MemoryGroup>,
+}
+
+impl BuiltinMemory {
+    /// Number of memory cells in each instance
+    pub fn num_cells(&self) -> usize {
+        self.groups.iter().map(|group| group.size).sum()
+    }
+
+    /// Returns the row (relative to the start of an instance) of the address of
+    /// the `i`th memory cell. The value is in the row after.
+    pub fn row(&self, i: usize) -> usize {
+        let mut i = i;
+        for group in &self.groups {
+            if i < group.size {
+                return group.row + i * self.instance_height / group.size;
+            }
+            i -= group.size;
+        }
+        panic!(
+            "builtin instances only have {} memory cells",
+            self.num_cells()
+        )
+    }
+
+    /// Returns the `i`th memory address of every instance
+    pub fn addr(&self, i: usize) -> Cells {
+        Cells::new(NPC_COLUMN, self.row(i), self.instance_height)
+    }
+
+    /// Returns the `i`th memory value of every instance
+    pub fn value(&self, i: usize) -> Cells {
+        Cells::new(NPC_COLUMN, self.row(i) + 1, self.instance_height)
+    }
+
+    /// Returns the memory values of every cell in a group
+    pub fn group_values(&self, group: usize) -> Cells {
+        let MemoryGroup { row, size } = self.groups[group];
+        Cells::new(NPC_COLUMN, row + 1, self.instance_height / size)
+    }
+}
+
+/// Pedersen cells of a lane. Each lane computes every `L`th hash (for `L`
+/// lanes) in its own pair of columns.
+#[derive(Clone, Copy, Debug)]
+pub struct PedersenCells {
+    pub suffix: Cells,
+    pub slope: Cells,
+    pub partial_sum_x: Cells,
+    pub partial_sum_y: Cells,
+    pub bit251_and_bit196: Cells,
+    pub bit251_and_bit196_and_bit192: Cells,
+}
+
+/// Bitwise cells. Dilutions share the diluted check column with padding.
+#[derive(Clone, Copy, Debug)]
+pub struct BitwiseCells {
+    /// Rows between consecutive chunk offsets
+    pub spacing: usize,
+    pub instance_height: usize,
+}
+
+impl BitwiseCells {
+    /// Returns the cells for offset `offset` of 64-bit chunk `chunk` of the
+    /// partitions of `x`, `y`, `x&y` and `x^y` (in that order)
+    pub const fn chunk(&self, chunk: usize, offset: usize) -> Cells {
+        Cells::new(
+            DILUTED_CHECK_UNORDERED_COLUMN,
+            self.spacing * (8 * chunk + 2 * offset),
+            self.instance_height / 4,
+        )
+    }
+
+    /// Returns the cell holding `(x&y + x^y) << 4` (or `<< 8` for offset 3) of
+    /// the `offset`th offset of the last 64-bit chunk. Ensures an unique
+    /// unpacking.
+    pub const fn res_shifted(&self, offset: usize) -> Cells {
+        const SHIFTS: [usize; 4] = [1, 65, 33, 97];
+        Cells::new(
+            DILUTED_CHECK_UNORDERED_COLUMN,
+            self.spacing * SHIFTS[offset],
+            self.instance_height,
+        )
+    }
+}
+
+/// ECDSA cells. The public key and `B` scalar multiplications are in the
+/// first column and the generator scalar multiplication in the second.
+#[derive(Clone, Copy, Debug)]
+pub struct EcdsaCells {
+    pub pubkey_doubling_x: Cells,
+    pub pubkey_doubling_y: Cells,
+    pub pubkey_doubling_slope: Cells,
+    pub pubkey_partial_sum_x: Cells,
+    pub pubkey_partial_sum_y: Cells,
+    pub pubkey_partial_sum_x_diff_inv: Cells,
+    pub pubkey_partial_sum_slope: Cells,
+    pub r_suffix: Cells,
+    pub message_suffix: Cells,
+    pub generator_partial_sum_x: Cells,
+    pub generator_partial_sum_y: Cells,
+    pub generator_partial_sum_x_diff_inv: Cells,
+    pub generator_partial_sum_slope: Cells,
+    pub r_point_slope: Cells,
+    pub r_point_x_diff_inv: Cells,
+    pub message_inv: Cells,
+    pub pubkey_x_squared: Cells,
+    pub b_slope: Cells,
+    pub b_x_diff_inv: Cells,
+}
+
+/// Poseidon cells. Partial rounds `0..64` are in the first column and partial
+/// rounds `61..83` and the full rounds in the second.
+#[derive(Clone, Copy, Debug)]
+pub struct PoseidonCells {
+    pub full_rounds_state0: Cells,
+    pub full_rounds_state0_squared: Cells,
+    pub full_rounds_state1: Cells,
+    pub full_rounds_state1_squared: Cells,
+    pub full_rounds_state2: Cells,
+    pub full_rounds_state2_squared: Cells,
+    pub partial_rounds_state0: Cells,
+    pub partial_rounds_state0_squared: Cells,
+    pub partial_rounds_state1: Cells,
+    pub partial_rounds_state1_squared: Cells,
+}
+
+/// Placement of the builtin cells of a layout
+///
+/// ```text
+/// column   | cells
+/// ---------|---------------------------------------------------------------
+/// 0..7     | CPU (as in the recursive layout), bitwise dilutions in column 1
+/// 7..7+2L  | pedersen hashes (two columns per lane)
+/// next 2   | ECDSA (if used)
+/// next 2   | poseidon (if used)
+/// ```
+///
+/// 128-bit range check parts use the unused range check cells (row 12 of each
+/// cycle) and builtin memory cells use unused npc cells (row 6 of each cycle
+/// and row 10 with a public memory step of 16).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AirLayout {
+    pub params: LayoutParams,
+    pub pedersen: Option<BuiltinMemory>,
+    pub range_check: Option<BuiltinMemory>,
+    pub ecdsa: Option<BuiltinMemory>,
+    pub bitwise: Option<BuiltinMemory>,
+    pub poseidon: Option<BuiltinMemory>,
+}
+
+impl AirLayout {
+    /// Places the builtins of a layout. Errors if the params use steps or
+    /// builtins that aren't supported or the column counts don't match the
+    /// columns the builtins need.
+    pub fn new(params: LayoutParams) -> Result<Self, LayoutParamsError> {
+        use LayoutParamsError::*;
+        params.validate()?;
+
+        let steps = [
+            ("cycle_height", params.cycle_height, CYCLE_HEIGHT),
+            ("memory_step", params.memory_step, MEMORY_STEP),
+            (
+                "range_check_step",
+                params.range_check_step,
+                RANGE_CHECK_STEP,
+            ),
+        ];
+        for (name, value, supported) in steps {
+            if value != supported {
+                return Err(UnsupportedParam(name, value));
+            }
+        }
+        let memory_slots: &[usize] = match params.public_memory_step {
+            DYNAMIC_PUBLIC_MEMORY_STEP => &BUILTIN_MEMORY_SLOTS_8,
+            PUBLIC_MEMORY_STEP => &BUILTIN_MEMORY_SLOTS_16,
+            step => return Err(UnsupportedParam("public_memory_step", step)),
+        };
+        let uses_diluted_pool = params.bitwise_ratio.is_some();
+        match (params.diluted_check_step, uses_diluted_pool) {
+            (None, false) | (Some(DILUTED_CHECK_STEP), true) => {}
+            (None, true) => return Err(MissingDilutedPool),
+            (Some(step), _) => return Err(UnsupportedParam("diluted_check_step", step)),
+        }
+        if params.ec_op_ratio.is_some() {
+            return Err(UnsupportedBuiltin("ec_op"));
+        }
+        if params.keccak_ratio.is_some() {
+            return Err(UnsupportedBuiltin("keccak"));
+        }
+
+        let min_ratios = [
+            ("pedersen_ratio", params.pedersen_ratio, MIN_PEDERSEN_RATIO),
+            (
+                "range_check_ratio",
+                params.range_check_ratio,
+                MIN_RANGE_CHECK_RATIO,
+            ),
+            ("ecdsa_ratio", params.ecdsa_ratio, MIN_ECDSA_RATIO),
+            ("bitwise_ratio", params.bitwise_ratio, MIN_BITWISE_RATIO),
+            ("poseidon_ratio", params.poseidon_ratio, MIN_POSEIDON_RATIO),
+        ];
+        for (name, ratio, min_ratio) in min_ratios {
+            match ratio {
+                Some(ratio) if ratio < min_ratio => {
+                    return Err(RatioTooSmall(name, ratio, min_ratio))
+                }
+                _ => {}
+            }
+        }
+
+        let num_base_columns = NUM_CPU_BASE_COLUMNS
+            + 2 * params.pedersen_ratio.map_or(0, pedersen_lanes)
+            + 2 * params.ecdsa_ratio.map_or(0, |_| 1)
+            + 2 * params.poseidon_ratio.map_or(0, |_| 1);
+        if params.num_base_columns != num_base_columns {
+            return Err(ColumnCount(
+                "num_base_columns",
+                params.num_base_columns,
+                num_base_columns,
+            ));
+        }
+        let num_extension_columns = if uses_diluted_pool { 3 } else { 1 };
+        if params.num_extension_columns != num_extension_columns {
+            return Err(ColumnCount(
+                "num_extension_columns",
+                params.num_extension_columns,
+                num_extension_columns,
+            ));
+        }
+
+        let [pedersen, range_check, ecdsa, bitwise, poseidon] =
+            allocate_builtin_memory(&params, memory_slots)?;
+        Ok(Self {
+            params,
+            pedersen,
+            range_check,
+            ecdsa,
+            bitwise,
+            poseidon,
+        })
+    }
+
+    /// Height of the tallest builtin instance (or pedersen lane)
+    pub fn max_instance_height(&self) -> usize {
+        let pedersen_height = self
+            .params
+            .pedersen_ratio
+            .map(|_| self.pedersen_hash_height());
+        [
+            pedersen_height,
+            self.range_check
+                .as_ref()
+                .map(|memory| memory.instance_height),
+            self.ecdsa.as_ref().map(|memory| memory.instance_height),
+            self.bitwise.as_ref().map(|memory| memory.instance_height),
+            self.poseidon.as_ref().map(|memory| memory.instance_height),
+        ]
+        .into_iter()
+        .flatten()
+        .fold(CYCLE_HEIGHT, usize::max)
+    }
+
+    /// Number of pedersen hashes computed side by side
+    pub fn pedersen_lanes(&self) -> usize {
+        self.params.pedersen_ratio.map_or(0, pedersen_lanes)
+    }
+
+    /// Number of rows between the steps of a pedersen hash
+    pub fn pedersen_step(&self) -> usize {
+        pedersen_step(
+            self.params
+                .pedersen_ratio
+                .expect("layout has no pedersen builtin"),
+        )
+    }
+
+    /// Number of rows used by each pedersen hash (in each lane)
+    pub fn pedersen_hash_height(&self) -> usize {
+        PEDERSEN_HASH_STEPS * self.pedersen_step()
+    }
+
+    pub fn pedersen_cells(&self, lane: usize) -> PedersenCells {
+        assert!(lane < self.pedersen_lanes());
+        let step = self.pedersen_step();
+        let hash_height = self.pedersen_hash_height();
+        let column = NUM_CPU_BASE_COLUMNS + 2 * lane;
+        PedersenCells {
+            suffix: Cells::new(column, 0, step),
+            partial_sum_x: Cells::new(column, step / 2, step),
+            slope: Cells::new(column + 1, 0, step),
+            partial_sum_y: Cells::new(column + 1, step / 2, step),
+            // slope of the last step in each half of the hash is unused
+            bit251_and_bit196: Cells::new(column + 1, 255 * step, hash_height / 2),
+            bit251_and_bit196_and_bit192: Cells::new(AUXILIARY_COLUMN, 2 * lane, hash_height / 2),
+        }
+    }
+
+    /// Returns the cells holding the 16-bit parts of 128-bit range checks
+    pub fn range_check_parts(&self) -> Cells {
+        let memory = self
+            .range_check
+            .as_ref()
+            .expect("layout has no range check builtin");
+        // unused range check cells are in row 12 of each cycle
+        Cells::new(
+            RANGE_CHECK_COLUMN,
+            12,
+            memory.instance_height / RANGE_CHECK_BUILTIN_PARTS,
+        )
+    }
+
+    pub fn bitwise_cells(&self) -> BitwiseCells {
+        let memory = self
+            .bitwise
+            .as_ref()
+            .expect("layout has no bitwise builtin");
+        BitwiseCells {
+            spacing: memory.instance_height / 128,
+            instance_height: memory.instance_height,
+        }
+    }
+
+    pub fn ecdsa_cells(&self) -> EcdsaCells {
+        let memory = self.ecdsa.as_ref().expect("layout has no ECDSA builtin");
+        let instance_height = memory.instance_height;
+        let key_step = instance_height / 512;
+        let generator_step = instance_height / 256;
+        let key_column = NUM_CPU_BASE_COLUMNS + 2 * self.pedersen_lanes();
+        let generator_column = key_column + 1;
+        let key_cells = |shift| Cells::new(key_column, shift, key_step);
+        let generator_cells = |shift| Cells::new(generator_column, shift, generator_step);
+        let instance_cells = |shift| Cells::new(generator_column, shift, instance_height);
+        EcdsaCells {
+            pubkey_doubling_x: key_cells(0),
+            pubkey_doubling_y: key_cells(1),
+            pubkey_doubling_slope: key_cells(2),
+            pubkey_partial_sum_x: key_cells(3),
+            pubkey_partial_sum_y: key_cells(4),
+            pubkey_partial_sum_x_diff_inv: key_cells(5),
+            pubkey_partial_sum_slope: key_cells(6),
+            r_suffix: key_cells(7),
+            message_suffix: generator_cells(0),
+            generator_partial_sum_x: generator_cells(1),
+            generator_partial_sum_y: generator_cells(2),
+            generator_partial_sum_x_diff_inv: generator_cells(3),
+            generator_partial_sum_slope: generator_cells(4),
+            r_point_slope: instance_cells(5),
+            r_point_x_diff_inv: instance_cells(6),
+            message_inv: instance_cells(7),
+            pubkey_x_squared: instance_cells(generator_step + 5),
+            b_slope: instance_cells(generator_step + 6),
+            b_x_diff_inv: instance_cells(generator_step + 7),
+        }
+    }
+
+    pub fn poseidon_cells(&self) -> PoseidonCells {
+        let memory = self
+            .poseidon
+            .as_ref()
+            .expect("layout has no poseidon builtin");
+        let instance_height = memory.instance_height;
+        let full_round_step = instance_height / poseidon::params::NUM_FULL_ROUNDS;
+        let partial_round0_step = instance_height / 64;
+        let partial_round1_step = instance_height / 32;
+        let column = NUM_CPU_BASE_COLUMNS
+            + 2 * self.pedersen_lanes()
+            + 2 * self.params.ecdsa_ratio.map_or(0, |_| 1);
+        let full_round_cells = |shift| Cells::new(column + 1, shift, full_round_step);
+        PoseidonCells {
+            partial_rounds_state0: Cells::new(column, 0, partial_round0_step),
+            partial_rounds_state0_squared: Cells::new(
+                column,
+                partial_round0_step / 2,
+                partial_round0_step,
+            ),
+            partial_rounds_state1: Cells::new(column + 1, 0, partial_round1_step),
+            partial_rounds_state1_squared: Cells::new(
+                column + 1,
+                partial_round1_step / 2,
+                partial_round1_step,
+            ),
+            // partial round cells are in even rows
+            full_rounds_state0: full_round_cells(1),
+            full_rounds_state0_squared: full_round_cells(3),
+            full_rounds_state1: full_round_cells(5),
+            full_rounds_state1_squared: full_round_cells(7),
+            full_rounds_state2: full_round_cells(9),
+            full_rounds_state2_squared: full_round_cells(11),
+        }
+    }
+}
+
+const fn pedersen_step(pedersen_ratio: usize) -> usize {
+    let instance_height = pedersen_ratio * CYCLE_HEIGHT;
+    if instance_height / PEDERSEN_HASH_STEPS > 2 {
+        instance_height / PEDERSEN_HASH_STEPS
+    } else {
+        2
+    }
+}
+
+const fn pedersen_lanes(pedersen_ratio: usize) -> usize {
+    PEDERSEN_HASH_STEPS * pedersen_step(pedersen_ratio) / (pedersen_ratio * CYCLE_HEIGHT)
+}
+
+/// Places the memory cells of each builtin in unused npc cells. Each group of
+/// memory cells repeats every `ratio / group_size` cycles. Groups are placed
+/// (most frequent first) in the first free cycle of a memory slot.
+fn allocate_builtin_memory(
+    params: &LayoutParams,
+    memory_slots: &[usize],
+) -> Result<[Option<BuiltinMemory>; 5], LayoutParamsError> {
+    let builtins: [(Option<usize>, &[usize]); 5] = [
+        (params.pedersen_ratio, &PEDERSEN_MEMORY_GROUPS),
+        (params.range_check_ratio, &RANGE_CHECK_MEMORY_GROUPS),
+        (params.ecdsa_ratio, &ECDSA_MEMORY_GROUPS),
+        (params.bitwise_ratio, &BITWISE_MEMORY_GROUPS),
+        (params.poseidon_ratio, &POSEIDON_MEMORY_GROUPS),
+    ];
+
+    // (builtin, group, period in cycles)
+    let mut groups = Vec::new();
+    for (builtin, (ratio, group_sizes)) in builtins.iter().enumerate() {
+        if let Some(ratio) = ratio {
+            for (group, group_size) in group_sizes.iter().enumerate() {
+                groups.push((builtin, group, ratio / group_size));
+            }
+        }
+    }
+    groups.sort_by_key(|&(_, _, period)| period);
+
+    // Periods are powers of two and placed in increasing order so a group
+    // overlaps an earlier group if they share a cycle modulo the earlier period
+    let mut placed: Vec<(usize, usize, usize)> = Vec::new();
+    let mut rows = builtins.map(|(_, group_sizes)| vec![0; group_sizes.len()]);
+    for (builtin, group, period) in groups {
+        let (slot, cycle) = memory_slots
+            .iter()
+            .flat_map(|&slot| (0..period).map(move |cycle| (slot, cycle)))
+            .find(|&(slot, cycle)| {
+                placed
+                    .iter()
+                    .all(|&(placed_slot, placed_cycle, placed_period)| {
+                        placed_slot != slot || cycle % placed_period != placed_cycle
+                    })
+            })
+            .ok_or(LayoutParamsError::InsufficientMemoryCells)?;
+        placed.push((slot, cycle, period));
+        rows[builtin][group] = cycle * CYCLE_HEIGHT + slot;
+    }
+
+    let mut rows = rows.into_iter();
+    Ok(builtins.map(|(ratio, group_sizes)| {
+        let rows = rows.next().unwrap();
+        ratio.map(|ratio| BuiltinMemory {
+            instance_height: ratio * CYCLE_HEIGHT,
+            groups: zip(rows, group_sizes)
+                .map(|(row, &size)| MemoryGroup { row, size })
+                .collect(),
+        })
+    }))
+}
+
+/// Constraints that the memory cells of a builtin's instances have continuous
+/// addresses starting at the builtin segment's first address
+fn builtin_memory_constraints(
+    trace_len: usize,
+    memory: &BuiltinMemory,
+    initial_address: PublicInputHint,
+) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fp>>>> {
+    use AlgebraicItem::*;
+    let n = trace_len;
+    let trace_domain = Radix2EvaluationDomain::<Fp>::new(n).unwrap();
+    let g = trace_domain.group_gen();
+    let x = Expr::from(X);
+    let one = Expr::from(Constant(FieldVariant::Fp(Fp::ONE)));
+    let instance_height = memory.instance_height;
+
+    let first_row_zerofier_inv = &one / (&x - &one);
+    let all_instances_zerofier_inv = &one / (X.pow(n / instance_height) - &one);
+    let last_instance_zerofier =
+        X - Constant(FieldVariant::Fp(g.pow([(n - instance_height) as u64])));
+    let all_instances_except_last_zerofier_inv =
+        &last_instance_zerofier * &all_instances_zerofier_inv;
+
+    let mut constraints =
+        vec![(memory.addr(0).curr() - initial_address.hint()) * &first_row_zerofier_inv];
+
+    let mut first_cell = 0;
+    for group in &memory.groups {
+        if first_cell != 0 {
+            // the group starts directly after the previous group
+            constraints.push(
+                (memory.addr(first_cell).curr() - (memory.addr(first_cell - 1).curr() + &one))
+                    * &all_instances_zerofier_inv,
+            );
+        }
+        if group.size > 1 {
+            // vanishes on every cell of the group except the last
+            let step = instance_height / group.size;
+            let last_cell_offset = (group.size - 1) * n / group.size;
+            let group_transition_zerofier_inv = (X.pow(n / instance_height)
+                - Constant(FieldVariant::Fp(g.pow([last_cell_offset as u64]))))
+                / (X.pow(n / step) - &one);
+            let addrs = Cells::new(NPC_COLUMN, group.row, step);
+            constraints
+                .push((addrs.next() - (addrs.curr() + &one)) * &group_transition_zerofier_inv);
+        }
+        first_cell += group.size;
+    }
+
+    // the next instance starts directly after the last cell
+    constraints.push(
+        (memory.addr(0).next() - (memory.addr(first_cell - 1).curr() + &one))
+            * &all_instances_except_last_zerofier_inv,
+    );
+
+    constraints
+}
+
+/// Pedersen constraints of a lane. These are the pedersen constraints of
+/// [crate::recursive] (see there for a description of each) with the steps of
+/// a hash [AirLayout::pedersen_step] rows apart.
+fn pedersen_constraints(
+    trace_len: usize,
+    layout: &AirLayout,
+    lane: usize,
+) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fp>>>> {
+    use AlgebraicItem::*;
+    let n = trace_len;
+    let trace_domain = Radix2EvaluationDomain::<Fp>::new(n).unwrap();
+    let g = trace_domain.group_gen();
+    let one = Expr::from(Constant(FieldVariant::Fp(Fp::ONE)));
+    let PedersenCells {
+        suffix,
+        slope,
+        partial_sum_x,
+        partial_sum_y,
+        bit251_and_bit196,
+        bit251_and_bit196_and_bit192,
+    } = layout.pedersen_cells(lane);
+    let memory = layout.pedersen.as_ref().unwrap();
+    let step = layout.pedersen_step();
+    let hash_height = layout.pedersen_hash_height();
+    let half_hash_height = hash_height / 2;
+
+    let b0 = suffix.curr() - (suffix.next() + suffix.next());
+    let b0_negate = &one - &b0;
+
+    let every_step_zerofier_inv = &one / (X.pow(n / step) - &one);
+    let every_half_hash_zerofier_inv = &one / (X.pow(n / half_hash_height) - &one);
+
+    let shift191 = Constant(FieldVariant::Fp(Fp::from(BigUint::from(2u32).pow(191u32))));
+    let shift3 = Constant(FieldVariant::Fp(Fp::from(BigUint::from(2u32).pow(3u32))));
+    let shift54 = Constant(FieldVariant::Fp(Fp::from(BigUint::from(2u32).pow(54u32))));
+    let bit_unpacking_last_one_is_zero =
+        (bit251_and_bit196_and_bit192.curr() * &b0) * &every_half_hash_zerofier_inv;
+    let bit_unpacking_zeros_between_ones = (bit251_and_bit196_and_bit192.curr()
+        * (suffix.offset(1) - suffix.offset(192) * shift191))
+        * &every_half_hash_zerofier_inv;
+    let bit_unpacking_cumulative_bit192 = (bit251_and_bit196_and_bit192.curr()
+        - bit251_and_bit196.curr()
+            * (suffix.offset(192) - (suffix.offset(193) + suffix.offset(193))))
+        * &every_half_hash_zerofier_inv;
+    let bit_unpacking_zeroes_between_ones192 = (bit251_and_bit196.curr()
+        * (suffix.offset(193) - suffix.offset(196) * shift3))
+        * &every_half_hash_zerofier_inv;
+    let bit_unpacking_cumulative_bit196 = (bit251_and_bit196.curr()
+        - (suffix.offset(251) - (suffix.offset(252) + suffix.offset(252)))
+            * (suffix.offset(196) - (suffix.offset(197) + suffix.offset(197))))
+        * &every_half_hash_zerofier_inv;
+    let bit_unpacking_zeroes_between_ones196 = ((suffix.offset(251)
+        - (suffix.offset(252) + suffix.offset(252)))
+        * (suffix.offset(197) - suffix.offset(251) * shift54))
+        * &every_half_hash_zerofier_inv;
+
+    // vanishes on every step except the last step in each half of a hash
+    let transition_zerofier_inv = (X.pow(n / half_hash_height)
+        - Constant(FieldVariant::Fp(g.pow([(255 * n / 256) as u64]))))
+        * &every_step_zerofier_inv;
+    let booleanity_test = (&b0 * (&b0 - &one)) * &transition_zerofier_inv;
+    let bit_extraction_end = suffix.curr()
+        / (X.pow(n / half_hash_height) - Constant(FieldVariant::Fp(g.pow([(63 * n / 64) as u64]))));
+    let zeros_tail = suffix.curr()
+        / (X.pow(n / half_hash_height)
+            - Constant(FieldVariant::Fp(g.pow([(255 * n / 256) as u64]))));
+
+    let point_x = Expr::from(Periodic(PeriodicColumn::new(
+        &PEDERSEN_POINT_X_COEFFS,
+        hash_height,
+    )));
+    let point_y = Expr::from(Periodic(PeriodicColumn::new(
+        &PEDERSEN_POINT_Y_COEFFS,
+        hash_height,
+    )));
+    let add_points_slope = (&b0 * (partial_sum_y.curr() - &point_y)
+        - slope.curr() * (partial_sum_x.curr() - &point_x))
+        * &transition_zerofier_inv;
+    let add_points_x = (slope.curr() * slope.curr()
+        - &b0 * (partial_sum_x.curr() + &point_x + partial_sum_x.next()))
+        * &transition_zerofier_inv;
+    let add_points_y = (&b0 * (partial_sum_y.curr() + partial_sum_y.next())
+        - slope.curr() * (partial_sum_x.curr() - partial_sum_x.next()))
+        * &transition_zerofier_inv;
+    let copy_point_x =
+        (&b0_negate * (partial_sum_x.next() - partial_sum_x.curr())) * &transition_zerofier_inv;
+    let copy_point_y =
+        (&b0_negate * (partial_sum_y.next() - partial_sum_y.curr())) * &transition_zerofier_inv;
+
+    // the second half of a hash continues from the first half's partial sum
+    let every_hash_zerofier_inv = &one / (X.pow(n / hash_height) - &one);
+    let hash_copy_point_x =
+        (partial_sum_x.offset(256) - partial_sum_x.offset(255)) * &every_hash_zerofier_inv;
+    let hash_copy_point_y =
+        (partial_sum_y.offset(256) - partial_sum_y.offset(255)) * &every_hash_zerofier_inv;
+    let shift_point = pedersen::constants::P0;
+    let hash_init_x = (partial_sum_x.curr() - Constant(FieldVariant::Fp(shift_point.x)))
+        * &every_hash_zerofier_inv;
+    let hash_init_y = (partial_sum_y.curr() - Constant(FieldVariant::Fp(shift_point.y)))
+        * &every_hash_zerofier_inv;
+
+    // the hash of a lane belongs to every `lanes`th instance
+    let lane = lane as isize;
+    let input0_value = (memory.value(0).offset(lane) - suffix.curr()) * &every_hash_zerofier_inv;
+    let input1_value =
+        (memory.value(1).offset(lane) - suffix.offset(256)) * &every_hash_zerofier_inv;
+    let output_value =
+        (memory.value(2).offset(lane) - partial_sum_x.offset(511)) * &every_hash_zerofier_inv;
+
+    vec![
+        bit_unpacking_last_one_is_zero,
+        bit_unpacking_zeros_between_ones,
+        bit_unpacking_cumulative_bit192,
+        bit_unpacking_zeroes_between_ones192,
+        bit_unpacking_cumulative_bit196,
+        bit_unpacking_zeroes_between_ones196,
+        booleanity_test,
+        bit_extraction_end,
+        zeros_tail,
+        add_points_slope,
+        add_points_x,
+        add_points_y,
+        copy_point_x,
+        copy_point_y,
+        hash_copy_point_x,
+        hash_copy_point_y,
+        hash_init_x,
+        hash_init_y,
+        input0_value,
+        input1_value,
+        output_value,
+    ]
+}
+
+/// Constraints that the 16-bit parts of each 128-bit range check make up the
+/// value in memory
+fn range_check_constraints(
+    trace_len: usize,
+    layout: &AirLayout,
+) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fp>>>> {
+    use AlgebraicItem::*;
+    let n = trace_len;
+    let one = Expr::from(Constant(FieldVariant::Fp(Fp::ONE)));
+    let offset_size = Expr::from(Constant(FieldVariant::Fp(Fp::from(2u32.pow(16)))));
+    let memory = layout.range_check.as_ref().unwrap();
+    let parts = layout.range_check_parts();
+
+    let all_instances_zerofier_inv = &one / (X.pow(n / memory.instance_height) - &one);
+    let value = (1..RANGE_CHECK_BUILTIN_PARTS as isize).fold(parts.offset(0), |value, i| {
+        value * &offset_size + parts.offset(i)
+    });
+    vec![(value - memory.value(0).curr()) * &all_instances_zerofier_inv]
+}
+
+/// Bitwise constraints. These are the bitwise constraints of
+/// [crate::recursive] (see there for a description of each) with the cells
+/// of each instance spread over the instance's rows.
+fn bitwise_constraints(
+    trace_len: usize,
+    layout: &AirLayout,
+) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fp>>>> {
+    use AlgebraicItem::*;
+    let n = trace_len;
+    let trace_domain = Radix2EvaluationDomain::<Fp>::new(n).unwrap();
+    let g = trace_domain.group_gen();
+    let one = Expr::from(Constant(FieldVariant::Fp(Fp::ONE)));
+    let two = Expr::from(Constant(FieldVariant::Fp(Fp::from(2u32))));
+    let memory = layout.bitwise.as_ref().unwrap();
+    let cells = layout.bitwise_cells();
+    let instance_height = memory.instance_height;
+
+    // chunks are 64 bits and each offset is a 16-bit striding of a chunk
+    let partition_sum = (0..4)
+        .flat_map(|chunk| (0..4).map(move |offset| (chunk, offset)))
+        .map(|(chunk, offset)| cells.chunk(chunk, offset).curr() * (&two).pow(64 * chunk + offset))
+        .sum::<Expr<AlgebraicItem<FieldVariant<Fp, Fp>>>>();
+    let every_value_zerofier_inv = &one / (X.pow(n / (instance_height / 4)) - &one);
+    let partition = (partition_sum - memory.group_values(0).curr()) * &every_value_zerofier_inv;
+
+    // NOTE: `x | y = (x & y) + (x ^ y)`
+    let all_bitwise_zerofier = X.pow(n / instance_height) - &one;
+    let all_bitwise_zerofier_inv = &one / &all_bitwise_zerofier;
+    let or_is_and_plus_xor = (memory.value(4).curr()
+        - (memory.value(2).curr() + memory.value(3).curr()))
+        * &all_bitwise_zerofier_inv;
+
+    // vanishes on the 16 segments of the first chunk offset of each value
+    let every_16_bit_segment_zerofier = (1..16).fold(all_bitwise_zerofier, |zerofier, i| {
+        zerofier
+            * (X.pow(n / instance_height)
+                - Constant(FieldVariant::Fp(g.pow([(i * n / 64) as u64]))))
+    });
+    let every_16_bit_segment_zerofier_inv = &one / every_16_bit_segment_zerofier;
+
+    // NOTE: `x+y = (x^y) + (x&y) + (x&y)`
+    let segment = cells.chunk(0, 0);
+    let addition_is_xor_with_and = (segment.offset(0) + segment.offset(1)
+        - (segment.offset(3) + segment.offset(2) + segment.offset(2)))
+        * &every_16_bit_segment_zerofier_inv;
+
+    let mut constraints = vec![partition, or_is_and_plus_xor, addition_is_xor_with_and];
+    for offset in 0..4 {
+        let chunk = cells.chunk(3, offset);
+        let shift = if offset == 3 { 8 } else { 4 };
+        constraints.push(
+            ((chunk.offset(2) + chunk.offset(3)) * (&two).pow(shift)
+                - cells.res_shifted(offset).curr())
+                * &all_bitwise_zerofier_inv,
+        );
+    }
+    constraints
+}
+
+/// ECDSA constraints. These are the ECDSA constraints of [crate::starknet]
+/// (see there for a description of each) with the steps of each scalar
+/// multiplication spread over the instance's rows.
+fn ecdsa_constraints(
+    trace_len: usize,
+    layout: &AirLayout,
+) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fp>>>> {
+    use AlgebraicItem::*;
+    let n = trace_len;
+    let trace_domain = Radix2EvaluationDomain::<Fp>::new(n).unwrap();
+    let g = trace_domain.group_gen();
+    let one = Expr::from(Constant(FieldVariant::Fp(Fp::ONE)));
+    let memory = layout.ecdsa.as_ref().unwrap();
+    let EcdsaCells {
+        pubkey_doubling_x,
+        pubkey_doubling_y,
+        pubkey_doubling_slope,
+        pubkey_partial_sum_x,
+        pubkey_partial_sum_y,
+        pubkey_partial_sum_x_diff_inv,
+        pubkey_partial_sum_slope,
+        r_suffix,
+        message_suffix,
+        generator_partial_sum_x,
+        generator_partial_sum_y,
+        generator_partial_sum_x_diff_inv,
+        generator_partial_sum_slope,
+        r_point_slope,
+        r_point_x_diff_inv,
+        message_inv,
+        pubkey_x_squared,
+        b_slope,
+        b_x_diff_inv,
+    } = layout.ecdsa_cells();
+    let instance_height = memory.instance_height;
+    // each signature has two scalar multiplications with the key cells
+    let key_height = instance_height / 2;
+    let key_step = pubkey_doubling_x.step;
+    let generator_step = message_suffix.step;
+
+    let alpha = Constant(FieldVariant::Fp(StarkwareCurve::COEFF_A));
+    let beta = Constant(FieldVariant::Fp(StarkwareCurve::COEFF_B));
+    let shift_point_x = Constant(FieldVariant::Fp(ecdsa::SHIFT_POINT.x));
+    let shift_point_y = Constant(FieldVariant::Fp(ecdsa::SHIFT_POINT.y));
+
+    // vanishes on every key step except the last of each scalar multiplication
+    let key_transition_zerofier_inv = (X.pow(n / key_height)
+        - Constant(FieldVariant::Fp(g.pow([(255 * n / 256) as u64]))))
+        / (X.pow(n / key_step) - &one);
+    let doubling_key_x_squared = pubkey_doubling_x.curr() * pubkey_doubling_x.curr();
+    let doubling_key_slope =
+        (&doubling_key_x_squared + &doubling_key_x_squared + &doubling_key_x_squared + alpha
+            - (pubkey_doubling_y.curr() + pubkey_doubling_y.curr()) * pubkey_doubling_slope.curr())
+            * &key_transition_zerofier_inv;
+    let doubling_key_x = (pubkey_doubling_slope.curr() * pubkey_doubling_slope.curr()
+        - (pubkey_doubling_x.curr() + pubkey_doubling_x.curr() + pubkey_doubling_x.next()))
+        * &key_transition_zerofier_inv;
+    let doubling_key_y = (pubkey_doubling_y.curr() + pubkey_doubling_y.next()
+        - pubkey_doubling_slope.curr() * (pubkey_doubling_x.curr() - pubkey_doubling_x.next()))
+        * &key_transition_zerofier_inv;
+
+    // vanishes on every generator step except the last
+    let generator_transition_zerofier_inv = (X.pow(n / instance_height)
+        - Constant(FieldVariant::Fp(g.pow([(255 * n / 256) as u64]))))
+        / (X.pow(n / generator_step) - &one);
+    let generator_b0 = message_suffix.curr() - (message_suffix.next() + message_suffix.next());
+    let generator_b0_neg = &one - &generator_b0;
+    let generator_booleanity_test =
+        (&generator_b0 * (&generator_b0 - &one)) * &generator_transition_zerofier_inv;
+    let generator_bit_extraction_end = message_suffix.curr()
+        / (X.pow(n / instance_height)
+            - Constant(FieldVariant::Fp(g.pow([(251 * n / 256) as u64]))));
+    let generator_zeros_tail = message_suffix.curr()
+        / (X.pow(n / instance_height)
+            - Constant(FieldVariant::Fp(g.pow([(255 * n / 256) as u64]))));
+    let generator_point_x = Expr::from(Periodic(PeriodicColumn::new(
+        &ECDSA_GENERATOR_POINT_X_COEFFS,
+        instance_height,
+    )));
+    let generator_point_y = Expr::from(Periodic(PeriodicColumn::new(
+        &ECDSA_GENERATOR_POINT_Y_COEFFS,
+        instance_height,
+    )));
+    let generator_add_points_slope = (&generator_b0
+        * (generator_partial_sum_y.curr() - &generator_point_y)
+        - generator_partial_sum_slope.curr()
+            * (generator_partial_sum_x.curr() - &generator_point_x))
+        * &generator_transition_zerofier_inv;
+    let generator_add_points_x = (generator_partial_sum_slope.curr()
+        * generator_partial_sum_slope.curr()
+        - &generator_b0
+            * (generator_partial_sum_x.curr()
+                + &generator_point_x
+                + generator_partial_sum_x.next()))
+        * &generator_transition_zerofier_inv;
+    let generator_add_points_y = (&generator_b0
+        * (generator_partial_sum_y.curr() + generator_partial_sum_y.next())
+        - generator_partial_sum_slope.curr()
+            * (generator_partial_sum_x.curr() - generator_partial_sum_x.next()))
+        * &generator_transition_zerofier_inv;
+    let generator_add_points_x_diff_inv = (generator_partial_sum_x_diff_inv.curr()
+        * (generator_partial_sum_x.curr() - &generator_point_x)
+        - &one)
+        * &generator_transition_zerofier_inv;
+    let generator_copy_point_x = (&generator_b0_neg
+        * (generator_partial_sum_x.next() - generator_partial_sum_x.curr()))
+        * &generator_transition_zerofier_inv;
+    let generator_copy_point_y = (&generator_b0_neg
+        * (generator_partial_sum_y.next() - generator_partial_sum_y.curr()))
+        * &generator_transition_zerofier_inv;
+
+    let key_b0 = r_suffix.curr() - (r_suffix.next() + r_suffix.next());
+    let key_b0_neg = &one - &key_b0;
+    let key_booleanity_test = (&key_b0 * (&key_b0 - &one)) * &key_transition_zerofier_inv;
+    let key_bit_extraction_end = r_suffix.curr()
+        / (X.pow(n / key_height) - Constant(FieldVariant::Fp(g.pow([(251 * n / 256) as u64]))));
+    let key_zeros_tail = r_suffix.curr()
+        / (X.pow(n / key_height) - Constant(FieldVariant::Fp(g.pow([(255 * n / 256) as u64]))));
+    let key_add_points_slope = (&key_b0 * (pubkey_partial_sum_y.curr() - pubkey_doubling_y.curr())
+        - pubkey_partial_sum_slope.curr()
+            * (pubkey_partial_sum_x.curr() - pubkey_doubling_x.curr()))
+        * &key_transition_zerofier_inv;
+    let key_add_points_x = (pubkey_partial_sum_slope.curr() * pubkey_partial_sum_slope.curr()
+        - &key_b0
+            * (pubkey_partial_sum_x.curr()
+                + pubkey_doubling_x.curr()
+                + pubkey_partial_sum_x.next()))
+        * &key_transition_zerofier_inv;
+    let key_add_points_y = (&key_b0 * (pubkey_partial_sum_y.curr() + pubkey_partial_sum_y.next())
+        - pubkey_partial_sum_slope.curr()
+            * (pubkey_partial_sum_x.curr() - pubkey_partial_sum_x.next()))
+        * &key_transition_zerofier_inv;
+    let key_add_points_x_diff_inv = (pubkey_partial_sum_x_diff_inv.curr()
+        * (pubkey_partial_sum_x.curr() - pubkey_doubling_x.curr())
+        - &one)
+        * &key_transition_zerofier_inv;
+    let key_copy_point_x = (&key_b0_neg
+        * (pubkey_partial_sum_x.next() - pubkey_partial_sum_x.curr()))
+        * &key_transition_zerofier_inv;
+    let key_copy_point_y = (&key_b0_neg
+        * (pubkey_partial_sum_y.next() - pubkey_partial_sum_y.curr()))
+        * &key_transition_zerofier_inv;
+
+    let all_ecdsa_zerofier_inv = &one / (X.pow(n / instance_height) - &one);
+    let all_key_zerofier_inv = &one / (X.pow(n / key_height) - &one);
+    let init_gen_x = (generator_partial_sum_x.curr() - shift_point_x) * &all_ecdsa_zerofier_inv;
+    let init_gen_y = (generator_partial_sum_y.curr() + shift_point_y) * &all_ecdsa_zerofier_inv;
+    let init_key_x = (pubkey_partial_sum_x.curr() - shift_point_x) * &all_key_zerofier_inv;
+    let init_key_y = (pubkey_partial_sum_y.curr() - shift_point_y) * &all_key_zerofier_inv;
+
+    let add_results_slope = (generator_partial_sum_y.offset(255)
+        - (pubkey_partial_sum_y.offset(255)
+            + b_slope.curr()
+                * (generator_partial_sum_x.offset(255) - pubkey_partial_sum_x.offset(255))))
+        * &all_ecdsa_zerofier_inv;
+    let add_results_x = (b_slope.curr() * b_slope.curr()
+        - (generator_partial_sum_x.offset(255)
+            + pubkey_partial_sum_x.offset(255)
+            + pubkey_doubling_x.offset(256)))
+        * &all_ecdsa_zerofier_inv;
+    let add_results_y = (generator_partial_sum_y.offset(255) + pubkey_doubling_y.offset(256)
+        - b_slope.curr() * (generator_partial_sum_x.offset(255) - pubkey_doubling_x.offset(256)))
+        * &all_ecdsa_zerofier_inv;
+    let add_results_x_diff_inv = (b_x_diff_inv.curr()
+        * (generator_partial_sum_x.offset(255) - pubkey_partial_sum_x.offset(255))
+        - &one)
+        * &all_ecdsa_zerofier_inv;
+
+    let extract_r_slope = (pubkey_partial_sum_y.offset(256 + 255) + shift_point_y
+        - r_point_slope.curr() * (pubkey_partial_sum_x.offset(256 + 255) - shift_point_x))
+        * &all_ecdsa_zerofier_inv;
+    let extract_r_x = (r_point_slope.curr() * r_point_slope.curr()
+        - (pubkey_partial_sum_x.offset(256 + 255) + shift_point_x + r_suffix.curr()))
+        * &all_ecdsa_zerofier_inv;
+    let extract_r_x_diff_inv = (r_point_x_diff_inv.curr()
+        * (pubkey_partial_sum_x.offset(256 + 255) - shift_point_x)
+        - &one)
+        * &all_ecdsa_zerofier_inv;
+
+    let z_nonzero = (message_suffix.curr() * message_inv.curr() - &one) * &all_ecdsa_zerofier_inv;
+    // the slope of the last doubling holds the inverse of `r` (and `w`)
+    let r_and_w_nonzero =
+        (r_suffix.curr() * pubkey_doubling_slope.offset(255) - &one) * &all_key_zerofier_inv;
+
+    let q_on_curve_x_squared = (pubkey_x_squared.curr()
+        - pubkey_doubling_x.curr() * pubkey_doubling_x.curr())
+        * &all_ecdsa_zerofier_inv;
+    let q_on_curve_on_curve = (pubkey_doubling_y.curr() * pubkey_doubling_y.curr()
+        - (pubkey_doubling_x.curr() * pubkey_x_squared.curr()
+            + pubkey_doubling_x.curr() * alpha
+            + beta))
+        * &all_ecdsa_zerofier_inv;
+
+    let pubkey_value =
+        (memory.value(0).curr() - pubkey_doubling_x.curr()) * &all_ecdsa_zerofier_inv;
+    let message_value = (memory.value(1).curr() - message_suffix.curr()) * &all_ecdsa_zerofier_inv;
+
+    vec![
+        doubling_key_slope,
+        doubling_key_x,
+        doubling_key_y,
+        generator_booleanity_test,
+        generator_bit_extraction_end,
+        generator_zeros_tail,
+        generator_add_points_slope,
+        generator_add_points_x,
+        generator_add_points_y,
+        generator_add_points_x_diff_inv,
+        generator_copy_point_x,
+        generator_copy_point_y,
+        key_booleanity_test,
+        key_bit_extraction_end,
+        key_zeros_tail,
+        key_add_points_slope,
+        key_add_points_x,
+        key_add_points_y,
+        key_add_points_x_diff_inv,
+        key_copy_point_x,
+        key_copy_point_y,
+        init_gen_x,
+        init_gen_y,
+        init_key_x,
+        init_key_y,
+        add_results_slope,
+        add_results_x,
+        add_results_y,
+        add_results_x_diff_inv,
+        extract_r_slope,
+        extract_r_x,
+        extract_r_x_diff_inv,
+        z_nonzero,
+        r_and_w_nonzero,
+        q_on_curve_x_squared,
+        q_on_curve_on_curve,
+        pubkey_value,
+        message_value,
+    ]
+}
+
+/// Poseidon constraints. These are the poseidon constraints of
+/// [crate::starknet] (see there for a description of each) with the rounds
+/// spread over the instance's rows.
+fn poseidon_constraints(
+    trace_len: usize,
+    layout: &AirLayout,
+) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fp>>>> {
+    use AlgebraicItem::*;
+    let n = trace_len;
+    let trace_domain = Radix2EvaluationDomain::<Fp>::new(n).unwrap();
+    let g = trace_domain.group_gen();
+    let one = Expr::from(Constant(FieldVariant::Fp(Fp::ONE)));
+    let memory = layout.poseidon.as_ref().unwrap();
+    let PoseidonCells {
+        full_rounds_state0,
+        full_rounds_state0_squared,
+        full_rounds_state1,
+        full_rounds_state1_squared,
+        full_rounds_state2,
+        full_rounds_state2_squared,
+        partial_rounds_state0,
+        partial_rounds_state0_squared,
+        partial_rounds_state1,
+        partial_rounds_state1_squared,
+    } = layout.poseidon_cells();
+    let instance_height = memory.instance_height;
+    let constant = |value: Fp| Constant(FieldVariant::Fp(value));
+    // vanishes on the row `numerator/denominator` of the way through each instance
+    let instance_row = |numerator: usize, denominator: usize| {
+        X.pow(n / instance_height) - constant(g.pow([(numerator * n / denominator) as u64]))
+    };
+
+    let full_cubed = |state: Cells, state_squared: Cells, round: isize| {
+        state.offset(round) * state_squared.offset(round)
+    };
+    let state0_cubed_0 = full_cubed(full_rounds_state0, full_rounds_state0_squared, 0);
+    let state1_cubed_0 = full_cubed(full_rounds_state1, full_rounds_state1_squared, 0);
+    let state2_cubed_0 = full_cubed(full_rounds_state2, full_rounds_state2_squared, 0);
+    let state0_cubed_3 = full_cubed(full_rounds_state0, full_rounds_state0_squared, 3);
+    let state1_cubed_3 = full_cubed(full_rounds_state1, full_rounds_state1_squared, 3);
+    let state2_cubed_3 = full_cubed(full_rounds_state2, full_rounds_state2_squared, 3);
+    let state0_cubed_7 = full_cubed(full_rounds_state0, full_rounds_state0_squared, 7);
+    let state1_cubed_7 = full_cubed(full_rounds_state1, full_rounds_state1_squared, 7);
+    let state2_cubed_7 = full_cubed(full_rounds_state2, full_rounds_state2_squared, 7);
+    let partial0_cubed = |round: isize| {
+        partial_rounds_state0.offset(round) * partial_rounds_state0_squared.offset(round)
+    };
+    let partial1_cubed = |round: isize| {
+        partial_rounds_state1.offset(round) * partial_rounds_state1_squared.offset(round)
+    };
+
+    let full_round_step = full_rounds_state0.step;
+    let partial_round0_step = partial_rounds_state0.step;
+    let partial_round1_step = partial_rounds_state1.step;
+    let every_full_round_zerofier_inv = &one / (X.pow(n / full_round_step) - &one);
+    let every_partial_round0_zerofier_inv = &one / (X.pow(n / partial_round0_step) - &one);
+    let every_partial_round1_zerofier_inv = &one / (X.pow(n / partial_round1_step) - &one);
+    let all_poseidon_zerofier_inv = &one / (X.pow(n / instance_height) - &one);
+
+    let domain14 = instance_row(3, 4) * instance_row(7, 8);
+    let domain15 = instance_row(5, 8) * &domain14;
+    let domain16 = instance_row(31, 32);
+    let domain17 = instance_row(11, 16)
+        * instance_row(23, 32)
+        * instance_row(25, 32)
+        * instance_row(13, 16)
+        * instance_row(27, 32)
+        * instance_row(29, 32)
+        * instance_row(15, 16)
+        * &domain16;
+    let domain19 = instance_row(61, 64) * instance_row(63, 64) * &domain16;
+    let domain20 = instance_row(19, 32) * instance_row(21, 32) * &domain15 * &domain17;
+
+    let full_rounds_state0_squaring = (full_rounds_state0.curr() * full_rounds_state0.curr()
+        - full_rounds_state0_squared.curr())
+        * &every_full_round_zerofier_inv;
+    let full_rounds_state1_squaring = (full_rounds_state1.curr() * full_rounds_state1.curr()
+        - full_rounds_state1_squared.curr())
+        * &every_full_round_zerofier_inv;
+    let full_rounds_state2_squaring = (full_rounds_state2.curr() * full_rounds_state2.curr()
+        - full_rounds_state2_squared.curr())
+        * &every_full_round_zerofier_inv;
+    let partial_rounds_state0_squaring = (partial_rounds_state0.curr()
+        * partial_rounds_state0.curr()
+        - partial_rounds_state0_squared.curr())
+        * &every_partial_round0_zerofier_inv;
+    let partial_rounds_state1_squaring = (partial_rounds_state1.curr()
+        * partial_rounds_state1.curr()
+        - partial_rounds_state1_squared.curr())
+        * &domain14
+        * &domain17
+        * &every_partial_round1_zerofier_inv;
+
+    let round_keys = poseidon::params::ROUND_KEYS[0];
+    let add_first_round_key0 = (memory.value(0).curr() + constant(round_keys[0])
+        - full_rounds_state0.curr())
+        * &all_poseidon_zerofier_inv;
+    let add_first_round_key1 = (memory.value(1).curr() + constant(round_keys[1])
+        - full_rounds_state1.curr())
+        * &all_poseidon_zerofier_inv;
+    let add_first_round_key2 = (memory.value(2).curr() + constant(round_keys[2])
+        - full_rounds_state2.curr())
+        * &all_poseidon_zerofier_inv;
+
+    let periodic = |coeffs: &'static [FieldVariant<Fp, Fp>]| {
+        Expr::from(Periodic(PeriodicColumn::new(coeffs, instance_height)))
+    };
+    let full_round_key0 = periodic(&POSEIDON_FULL_ROUND_KEY0_COEFFS);
+    let full_round_key1 = periodic(&POSEIDON_FULL_ROUND_KEY1_COEFFS);
+    let full_round_key2 = periodic(&POSEIDON_FULL_ROUND_KEY2_COEFFS);
+    let partial_round_key0 = periodic(&POSEIDON_PARTIAL_ROUND_KEY0_COEFFS);
+    let partial_round_key1 = periodic(&POSEIDON_PARTIAL_ROUND_KEY1_COEFFS);
+
+    // vanishes on every full round except the last of each half
+    let half_full_round_transition_zerofier_inv = (X.pow(n / (instance_height / 2))
+        - constant(g.pow([(3 * n / 4) as u64])))
+        * &every_full_round_zerofier_inv;
+    let full_round0 = (full_rounds_state0.next()
+        - (&state0_cubed_0
+            + &state0_cubed_0
+            + &state0_cubed_0
+            + &state1_cubed_0
+            + &state2_cubed_0
+            + &full_round_key0))
+        * &half_full_round_transition_zerofier_inv;
+    let full_round1 = (full_rounds_state1.next() + &state1_cubed_0
+        - (&state0_cubed_0 + &state2_cubed_0 + &full_round_key1))
+        * &half_full_round_transition_zerofier_inv;
+    let full_round2 = (full_rounds_state2.next() + &state2_cubed_0 + &state2_cubed_0
+        - (&state0_cubed_0 + &state1_cubed_0 + &full_round_key2))
+        * &half_full_round_transition_zerofier_inv;
+
+    let last_full_round0 = (memory.value(3).curr()
+        - (&state0_cubed_7
+            + &state0_cubed_7
+            + &state0_cubed_7
+            + &state1_cubed_7
+            + &state2_cubed_7))
+        * &all_poseidon_zerofier_inv;
+    let last_full_round1 = (memory.value(4).curr() + &state1_cubed_7
+        - (&state0_cubed_7 + &state2_cubed_7))
+        * &all_poseidon_zerofier_inv;
+    let last_full_round2 = (memory.value(5).curr() + &state2_cubed_7 + &state2_cubed_7
+        - (&state0_cubed_7 + &state1_cubed_7))
+        * &all_poseidon_zerofier_inv;
+
+    let copy_partial_rounds0_i0 = (partial_rounds_state0.offset(61)
+        - partial_rounds_state1.offset(0))
+        * &all_poseidon_zerofier_inv;
+    let copy_partial_rounds0_i1 = (partial_rounds_state0.offset(62)
+        - partial_rounds_state1.offset(1))
+        * &all_poseidon_zerofier_inv;
+    let copy_partial_rounds0_i2 = (partial_rounds_state0.offset(63)
+        - partial_rounds_state1.offset(2))
+        * &all_poseidon_zerofier_inv;
+
+    let margin_full_to_partial_round_keys = poseidon::params::PARTIAL_ROUND_KEYS[0];
+    let margin_full_to_partial0 =
+        (partial_rounds_state0.offset(0) + &state2_cubed_3 + &state2_cubed_3
+            - (&state0_cubed_3 + &state1_cubed_3 + constant(margin_full_to_partial_round_keys[2])))
+            * &all_poseidon_zerofier_inv;
+    let margin_full_to_partial1 = (partial_rounds_state0.offset(1)
+        - (&state1_cubed_3 * constant(-Fp::from(4u8))
+            + &state2_cubed_3 * constant(Fp::from(10u8))
+            + partial_rounds_state0.offset(0) * constant(Fp::from(4u8))
+            + partial0_cubed(0) * constant(-Fp::from(2u8))
+            + constant(MontFp!(
+                "2006642341318481906727563724340978325665491359415674592697055778067937914672"
+            ))))
+        * &all_poseidon_zerofier_inv;
+    let margin_full_to_partial2 = (partial_rounds_state0.offset(2)
+        - (&state2_cubed_3 * constant(Fp::from(8u8))
+            + partial_rounds_state0.offset(0) * constant(Fp::from(4u8))
+            + partial0_cubed(0) * constant(Fp::from(6u8))
+            + partial_rounds_state0.offset(1)
+            + partial_rounds_state0.offset(1)
+            + partial0_cubed(1) * constant(-Fp::from(2u8))
+            + constant(MontFp!(
+                "427751140904099001132521606468025610873158555767197326325930641757709538586"
+            ))))
+        * &all_poseidon_zerofier_inv;
+
+    let partial_round0 = (partial_rounds_state0.offset(3)
+        - (partial0_cubed(0) * constant(Fp::from(8u8))
+            + partial_rounds_state0.offset(1) * constant(Fp::from(4u8))
+            + partial0_cubed(1) * constant(Fp::from(6u8))
+            + partial_rounds_state0.offset(2)
+            + partial_rounds_state0.offset(2)
+            + partial0_cubed(2) * constant(-Fp::from(2u8))
+            + &partial_round_key0))
+        * &domain19
+        * &every_partial_round0_zerofier_inv;
+    let partial_round1 = (partial_rounds_state1.offset(3)
+        - (partial1_cubed(0) * constant(Fp::from(8u8))
+            + partial_rounds_state1.offset(1) * constant(Fp::from(4u8))
+            + partial1_cubed(1) * constant(Fp::from(6u8))
+            + partial_rounds_state1.offset(2)
+            + partial_rounds_state1.offset(2)
+            + partial1_cubed(2) * constant(-Fp::from(2u8))
+            + &partial_round_key1))
+        * &domain20
+        * &every_partial_round1_zerofier_inv;
+
+    let margin_partial_to_full0 = (full_rounds_state0.offset(4)
+        - (partial1_cubed(19) * constant(Fp::from(16u8))
+            + partial_rounds_state1.offset(20) * constant(Fp::from(8u8))
+            + partial1_cubed(20) * constant(Fp::from(16u8))
+            + partial_rounds_state1.offset(21) * constant(Fp::from(6u8))
+            + partial1_cubed(21)
+            + constant(MontFp!(
+                "560279373700919169769089400651532183647886248799764942664266404650165812023"
+            ))))
+        * &all_poseidon_zerofier_inv;
+    let margin_partial_to_full1 = (full_rounds_state1.offset(4)
+        - (partial1_cubed(20) * constant(Fp::from(4u8))
+            + partial_rounds_state1.offset(21)
+            + partial_rounds_state1.offset(21)
+            + partial1_cubed(21)
+            + constant(MontFp!(
+                "1401754474293352309994371631695783042590401941592571735921592823982231996415"
+            ))))
+        * &all_poseidon_zerofier_inv;
+    let margin_partial_to_full2 = (full_rounds_state2.offset(4)
+        - (partial1_cubed(19) * constant(Fp::from(8u8))
+            + partial_rounds_state1.offset(20) * constant(Fp::from(4u8))
+            + partial1_cubed(20) * constant(Fp::from(6u8))
+            + partial_rounds_state1.offset(21)
+            + partial_rounds_state1.offset(21)
+            + partial1_cubed(21) * constant(-Fp::from(2u8))
+            + constant(MontFp!(
+                "1246177936547655338400308396717835700699368047388302793172818304164989556526"
+            ))))
+        * &all_poseidon_zerofier_inv;
+
+    vec![
+        full_rounds_state0_squaring,
+        full_rounds_state1_squaring,
+        full_rounds_state2_squaring,
+        partial_rounds_state0_squaring,
+        partial_rounds_state1_squaring,
+        add_first_round_key0,
+        add_first_round_key1,
+        add_first_round_key2,
+        full_round0,
+        full_round1,
+        full_round2,
+        last_full_round0,
+        last_full_round1,
+        last_full_round2,
+        copy_partial_rounds0_i0,
+        copy_partial_rounds0_i1,
+        copy_partial_rounds0_i2,
+        margin_full_to_partial0,
+        margin_full_to_partial1,
+        margin_full_to_partial2,
+        partial_round0,
+        partial_round1,
+        margin_partial_to_full0,
+        margin_partial_to_full1,
+        margin_partial_to_full2,
+    ]
+}
+
+/// Hints for the first address of each builtin segment. The CPU and diluted
+/// check hints come first (see [crate::recursive::air::PublicInputHint]).
+#[derive(Clone, Copy)]
+pub enum PublicInputHint {
+    InitialPedersenAddr = 11,
+    InitialRcAddr = 12,
+    InitialEcdsaAddr = 13,
+    InitialBitwiseAddr = 14,
+    InitialPoseidonAddr = 15,
+}
+
+impl Hint for PublicInputHint {
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
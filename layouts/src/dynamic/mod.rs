@@ -0,0 +1,774 @@
+//! Layout parameters supplied at runtime rather than hard-coded in a layout
+//! module. Similar to the `dynamic` layout from StarkWare's cairo-lang where
+//! builtin ratios are provided alongside the program instead of being fixed by
+//! the layout.
+//!
+//! [AirConfig] and [ExecutionTrace] build the AIR and trace of a layout from
+//! its params. ministark's `AirConfig` fixes the number of columns with
+//! associated constants so the params are supplied as a constant of a
+//! [DynamicLayout]. The CPU uses the same cells as the recursive layout and
+//! builtins are placed in the columns following it (see [air::AirLayout]).
+//! This placement is specific to sandstorm so proofs of the dynamic layout can
+//! only be verified by sandstorm and `num_columns_first` and
+//! `num_columns_second` must match the columns used by sandstorm. The
+//! `ec_op` and `keccak` builtins aren't supported.
+
+use binary::DynamicParams;
+use binary::Layout;
+use serde::Deserialize;
+use serde::Serialize;
+use std::error::Error;
+use std::fmt::Display;
+use std::io::Read;
+
+pub mod air;
+pub mod trace;
+
+pub use air::AirConfig;
+pub use trace::ExecutionTrace;
+
+/// Number of trace rows used by the CPU component for each Cairo step
+const CPU_COMPONENT_HEIGHT: usize = 16;
+
+/// Number of trace rows between public memory cells in cairo-lang's dynamic
+/// layout
+const DYNAMIC_PUBLIC_MEMORY_STEP: usize = 8;
+
+/// A layout with params known at compile time. `LAYOUT` is the layout of
+/// public inputs the layout proves. Params of public inputs with the dynamic
+/// layout must match `PARAMS`.
+pub trait DynamicLayout: Send + Sync + 'static {
+    const LAYOUT: Layout;
+    const PARAMS: LayoutParams;
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LayoutParams {
+    /// Number of trace rows per Cairo VM cycle. Must be a power-of-two
+    pub cycle_height: usize,
+    pub num_base_columns: usize,
+    pub num_extension_columns: usize,
+    /// Number of trace rows between public memory cells
+    pub public_memory_step: usize,
+    /// Number of trace rows between memory cells
+    pub memory_step: usize,
+    /// Number of trace rows between 16-bit range check units
+    pub range_check_step: usize,
+    /// Number of trace rows between diluted check units. Only required by
+    /// layouts with builtins that use the diluted pool (bitwise and keccak)
+    #[serde(default)]
+    pub diluted_check_step: Option<usize>,
+    /// How many cycles per pedersen hash
+    #[serde(default)]
+    pub pedersen_ratio: Option<usize>,
+    /// How many cycles per 128 bit range check
+    #[serde(default)]
+    pub range_check_ratio: Option<usize>,
+    /// How many cycles per ECDSA signature verification
+    #[serde(default)]
+    pub ecdsa_ratio: Option<usize>,
+    /// How many cycles per bitwise operation
+    #[serde(default)]
+    pub bitwise_ratio: Option<usize>,
+    /// How many cycles per EC operation
+    #[serde(default)]
+    pub ec_op_ratio: Option<usize>,
+    /// How many cycles per Keccak permutation
+    #[serde(default)]
+    pub keccak_ratio: Option<usize>,
+    /// How many cycles per Poseidon permutation
+    #[serde(default)]
+    pub poseidon_ratio: Option<usize>,
+}
+
+impl LayoutParams {
+    /// Parses and validates layout parameters from JSON
+    pub fn from_reader(r: impl Read) -> Result<Self, LayoutParamsError> {
+        let params: Self = serde_json::from_reader(r).map_err(LayoutParamsError::Json)?;
+        params.validate()?;
+        Ok(params)
+    }
+
+    /// Builds and validates layout parameters from the `dynamic_params` of a
+    /// public input produced with `cairo-run --layout dynamic`. Builtin row
+    /// ratios (trace rows per instance) are converted to cycles per instance.
+    pub fn from_dynamic_params(params: &DynamicParams) -> Result<Self, LayoutParamsError> {
+        let cycle_height = CPU_COMPONENT_HEIGHT * dynamic_param(params, "cpu_component_step")?;
+        let builtin_ratio = |builtin, row_ratio_name| {
+            dynamic_builtin_ratio(params, builtin, row_ratio_name, cycle_height)
+        };
+        let uses_diluted_pool =
+            uses_dynamic_builtin(params, "bitwise") || uses_dynamic_builtin(params, "keccak");
+        let layout_params = Self {
+            cycle_height,
+            num_base_columns: dynamic_param(params, "num_columns_first")?,
+            num_extension_columns: dynamic_param(params, "num_columns_second")?,
+            public_memory_step: DYNAMIC_PUBLIC_MEMORY_STEP,
+            memory_step: dynamic_param(params, "memory_units_row_ratio")?,
+            range_check_step: dynamic_param(params, "range_check_units_row_ratio")?,
+            diluted_check_step: if uses_diluted_pool {
+                Some(dynamic_param(params, "diluted_units_row_ratio")?)
+            } else {
+                None
+            },
+            pedersen_ratio: builtin_ratio("pedersen", "pedersen_builtin_row_ratio")?,
+            range_check_ratio: builtin_ratio("range_check", "range_check_builtin_row_ratio")?,
+            ecdsa_ratio: builtin_ratio("ecdsa", "ecdsa_builtin_row_ratio")?,
+            bitwise_ratio: builtin_ratio("bitwise", "bitwise_row_ratio")?,
+            ec_op_ratio: builtin_ratio("ec_op", "ec_op_builtin_row_ratio")?,
+            keccak_ratio: builtin_ratio("keccak", "keccak_row_ratio")?,
+            poseidon_ratio: builtin_ratio("poseidon", "poseidon_row_ratio")?,
+        };
+        layout_params.validate()?;
+        Ok(layout_params)
+    }
+
+    /// Parameters of the `dex` layout
+    pub const fn dex() -> Self {
+        use crate::dex::*;
+        Self {
+            cycle_height: CYCLE_HEIGHT,
+            num_base_columns: NUM_BASE_COLUMNS,
+            num_extension_columns: NUM_EXTENSION_COLUMNS,
+            public_memory_step: PUBLIC_MEMORY_STEP,
+            memory_step: MEMORY_STEP,
+            range_check_step: RANGE_CHECK_STEP,
+            diluted_check_step: None,
+            pedersen_ratio: Some(PEDERSEN_BUILTIN_RATIO),
+            range_check_ratio: Some(RANGE_CHECK_BUILTIN_RATIO),
+            ecdsa_ratio: Some(ECDSA_BUILTIN_RATIO),
+            bitwise_ratio: None,
+            ec_op_ratio: None,
+            keccak_ratio: None,
+            poseidon_ratio: None,
+        }
+    }
+
+    /// Parameters of the `plain` layout
+    pub const fn plain() -> Self {
+        use crate::plain::*;
+        Self {
+            cycle_height: CYCLE_HEIGHT,
+            num_base_columns: NUM_BASE_COLUMNS,
+            num_extension_columns: NUM_EXTENSION_COLUMNS,
+            public_memory_step: PUBLIC_MEMORY_STEP,
+            memory_step: MEMORY_STEP,
+            range_check_step: RANGE_CHECK_STEP,
+            diluted_check_step: None,
+            pedersen_ratio: None,
+            range_check_ratio: None,
+            ecdsa_ratio: None,
+            bitwise_ratio: None,
+            ec_op_ratio: None,
+            keccak_ratio: None,
+            poseidon_ratio: None,
+        }
+    }
+
+    /// Parameters of the `recursive` layout
+    pub const fn recursive() -> Self {
+        use crate::recursive::*;
+        Self {
+            cycle_height: CYCLE_HEIGHT,
+            num_base_columns: NUM_BASE_COLUMNS,
+            num_extension_columns: NUM_EXTENSION_COLUMNS,
+            public_memory_step: PUBLIC_MEMORY_STEP,
+            memory_step: MEMORY_STEP,
+            range_check_step: RANGE_CHECK_STEP,
+            diluted_check_step: Some(DILUTED_CHECK_STEP),
+            pedersen_ratio: Some(PEDERSEN_BUILTIN_RATIO),
+            range_check_ratio: Some(RANGE_CHECK_BUILTIN_RATIO),
+            ecdsa_ratio: None,
+            bitwise_ratio: Some(BITWISE_RATIO),
+            ec_op_ratio: None,
+            keccak_ratio: None,
+            poseidon_ratio: None,
+        }
+    }
+
+    /// Parameters of the field agnostic `recursive` layout (no pedersen)
+    pub const fn recursive_generic() -> Self {
+        use crate::recursive_generic::*;
+        Self {
+            cycle_height: CYCLE_HEIGHT,
+            num_base_columns: NUM_BASE_COLUMNS,
+            num_extension_columns: NUM_EXTENSION_COLUMNS,
+            public_memory_step: PUBLIC_MEMORY_STEP,
+            memory_step: MEMORY_STEP,
+            range_check_step: RANGE_CHECK_STEP,
+            diluted_check_step: Some(DILUTED_CHECK_STEP),
+            pedersen_ratio: None,
+            range_check_ratio: Some(RANGE_CHECK_BUILTIN_RATIO),
+            ecdsa_ratio: None,
+            bitwise_ratio: Some(BITWISE_RATIO),
+            ec_op_ratio: None,
+            keccak_ratio: None,
+            poseidon_ratio: None,
+        }
+    }
+
+    /// Parameters of the `recursive_large_output` layout
+    pub const fn recursive_large_output() -> Self {
+        use crate::recursive_large_output::*;
+        Self {
+            cycle_height: CYCLE_HEIGHT,
+            num_base_columns: NUM_BASE_COLUMNS,
+            num_extension_columns: NUM_EXTENSION_COLUMNS,
+            public_memory_step: PUBLIC_MEMORY_STEP,
+            memory_step: MEMORY_STEP,
+            range_check_step: RANGE_CHECK_STEP,
+            diluted_check_step: Some(DILUTED_CHECK_STEP),
+            pedersen_ratio: Some(PEDERSEN_BUILTIN_RATIO),
+            range_check_ratio: Some(RANGE_CHECK_BUILTIN_RATIO),
+            ecdsa_ratio: None,
+            bitwise_ratio: Some(BITWISE_RATIO),
+            ec_op_ratio: None,
+            keccak_ratio: None,
+            poseidon_ratio: Some(POSEIDON_RATIO),
+        }
+    }
+
+    /// Parameters of the `small` layout
+    pub const fn small() -> Self {
+        use crate::small::*;
+        Self {
+            cycle_height: CYCLE_HEIGHT,
+            num_base_columns: NUM_BASE_COLUMNS,
+            num_extension_columns: NUM_EXTENSION_COLUMNS,
+            public_memory_step: PUBLIC_MEMORY_STEP,
+            memory_step: MEMORY_STEP,
+            range_check_step: RANGE_CHECK_STEP,
+            diluted_check_step: None,
+            pedersen_ratio: Some(PEDERSEN_BUILTIN_RATIO),
+            range_check_ratio: Some(RANGE_CHECK_BUILTIN_RATIO),
+            ecdsa_ratio: Some(ECDSA_BUILTIN_RATIO),
+            bitwise_ratio: None,
+            ec_op_ratio: None,
+            keccak_ratio: None,
+            poseidon_ratio: None,
+        }
+    }
+
+    /// Parameters of the `starknet` layout
+    pub const fn starknet() -> Self {
+        use crate::starknet::*;
+        Self {
+            cycle_height: CYCLE_HEIGHT,
+            num_base_columns: NUM_BASE_COLUMNS,
+            num_extension_columns: NUM_EXTENSION_COLUMNS,
+            public_memory_step: PUBLIC_MEMORY_STEP,
+            memory_step: MEMORY_STEP,
+            range_check_step: RANGE_CHECK_STEP,
+            diluted_check_step: Some(DILUTED_CHECK_STEP),
+            pedersen_ratio: Some(PEDERSEN_BUILTIN_RATIO),
+            range_check_ratio: Some(RANGE_CHECK_BUILTIN_RATIO),
+            ecdsa_ratio: Some(ECDSA_BUILTIN_RATIO),
+            bitwise_ratio: Some(BITWISE_RATIO),
+            ec_op_ratio: Some(EC_OP_BUILTIN_RATIO),
+            keccak_ratio: None,
+            poseidon_ratio: Some(POSEIDON_RATIO),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), LayoutParamsError> {
+        use LayoutParamsError::*;
+        if self.num_base_columns == 0 {
+            return Err(NoBaseColumns);
+        }
+
+        let steps = [
+            ("cycle_height", Some(self.cycle_height)),
+            ("public_memory_step", Some(self.public_memory_step)),
+            ("memory_step", Some(self.memory_step)),
+            ("range_check_step", Some(self.range_check_step)),
+            ("diluted_check_step", self.diluted_check_step),
+        ];
+        for (name, value) in steps.into_iter().chain(self.builtin_ratios()) {
+            match value {
+                Some(value) if !value.is_power_of_two() => return Err(NotPowerOfTwo(name, value)),
+                _ => {}
+            }
+        }
+
+        let uses_diluted_pool = self.bitwise_ratio.is_some() || self.keccak_ratio.is_some();
+        if uses_diluted_pool && self.diluted_check_step.is_none() {
+            return Err(MissingDilutedPool);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the ratio of each builtin (if the builtin is used)
+    pub fn builtin_ratios(&self) -> [(&'static str, Option<usize>); 7] {
+        [
+            ("pedersen_ratio", self.pedersen_ratio),
+            ("range_check_ratio", self.range_check_ratio),
+            ("ecdsa_ratio", self.ecdsa_ratio),
+            ("bitwise_ratio", self.bitwise_ratio),
+            ("ec_op_ratio", self.ec_op_ratio),
+            ("keccak_ratio", self.keccak_ratio),
+            ("poseidon_ratio", self.poseidon_ratio),
+        ]
+    }
+
+    /// Returns the number of trace rows used by each instance of a builtin
+    pub const fn instance_height(&self, ratio: usize) -> usize {
+        ratio * self.cycle_height
+    }
+
+    /// Returns the number of builtin instances that fit in a trace
+    pub const fn num_instances(&self, ratio: usize, trace_len: usize) -> usize {
+        trace_len / self.instance_height(ratio)
+    }
+
+    /// Returns the length of the trace required for `n_steps` VM steps
+    pub const fn trace_len(&self, n_steps: usize) -> usize {
+        n_steps * self.cycle_height
+    }
+}
+
+fn dynamic_param(params: &DynamicParams, name: &'static str) -> Result<usize, LayoutParamsError> {
+    match params.get(name) {
+        Some(&value) => Ok(value as usize),
+        None => Err(LayoutParamsError::MissingParam(name)),
+    }
+}
+
+fn uses_dynamic_builtin(params: &DynamicParams, builtin: &str) -> bool {
+    params
+        .get(&format!("uses_{builtin}_builtin"))
+        .is_some_and(|&uses_builtin| uses_builtin != 0)
+}
+
+/// Returns the number of cycles per instance of a builtin in the dynamic layout
+/// or None if the builtin isn't used
+fn dynamic_builtin_ratio(
+    params: &DynamicParams,
+    builtin: &str,
+    row_ratio_name: &'static str,
+    cycle_height: usize,
+) -> Result<Option<usize>, LayoutParamsError> {
+    if !uses_dynamic_builtin(params, builtin) {
+        return Ok(None);
+    }
+    let row_ratio = dynamic_param(params, row_ratio_name)?;
+    if !row_ratio.is_power_of_two() || row_ratio < cycle_height {
+        return Err(LayoutParamsError::InvalidRowRatio(
+            row_ratio_name,
+            row_ratio,
+        ));
+    }
+    Ok(Some(row_ratio / cycle_height))
+}
+
+#[derive(Debug)]
+pub enum LayoutParamsError {
+    Json(serde_json::Error),
+    NoBaseColumns,
+    NotPowerOfTwo(&'static str, usize),
+    MissingDilutedPool,
+    /// A parameter is missing from a public input's dynamic params
+    MissingParam(&'static str),
+    /// A builtin row ratio that isn't a power-of-two multiple of the cycle
+    /// height
+    InvalidRowRatio(&'static str, usize),
+    /// A step the dynamic AIR doesn't support
+    UnsupportedParam(&'static str, usize),
+    /// A builtin the dynamic AIR doesn't support
+    UnsupportedBuiltin(&'static str),
+    /// A builtin ratio too small to fit an instance in the dynamic AIR
+    RatioTooSmall(&'static str, usize, usize),
+    /// A column count that doesn't match the columns used by the dynamic AIR
+    ColumnCount(&'static str, usize, usize),
+    /// The builtins have more memory cells than there are unused npc cells
+    InsufficientMemoryCells,
+}
+
+impl Display for LayoutParamsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Json(err) => write!(f, "Invalid layout params: {err}"),
+            Self::NoBaseColumns => write!(f, "Layout must have at least one base column"),
+            Self::NotPowerOfTwo(name, value) => {
+                write!(f, "Invalid {name}: {value}, must be a power-of-two")
+            }
+            Self::MissingDilutedPool => write!(
+                f,
+                "Bitwise and keccak builtins require a diluted_check_step"
+            ),
+            Self::MissingParam(name) => write!(f, "Dynamic params are missing {name}"),
+            Self::InvalidRowRatio(name, value) => write!(
+                f,
+                "Invalid {name}: {value}, must be a power-of-two multiple of the cycle height"
+            ),
+            Self::UnsupportedParam(name, value) => write!(
+                f,
+                "Invalid {name}: {value}, not supported by the dynamic layout"
+            ),
+            Self::UnsupportedBuiltin(name) => write!(
+                f,
+                "The {name} builtin isn't supported by the dynamic layout"
+            ),
+            Self::RatioTooSmall(name, value, min) => {
+                write!(f, "Invalid {name}: {value}, must be at least {min}")
+            }
+            Self::ColumnCount(name, value, expected) => {
+                write!(f, "Invalid {name}: {value}, the layout uses {expected}")
+            }
+            Self::InsufficientMemoryCells => {
+                write!(f, "Builtins use more memory cells than the layout has")
+            }
+        }
+    }
+}
+
+impl Error for LayoutParamsError {}
+
+#[cfg(test)]
+mod tests {
+    use super::air::AirLayout;
+    use super::air::BuiltinMemory;
+    use super::LayoutParams;
+    use super::LayoutParamsError;
+    use binary::AirPublicInput;
+    use binary::DynamicParams;
+    use binary::Layout;
+    use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp;
+    use serde_json::json;
+    use serde_json::Value;
+    use std::collections::BTreeSet;
+    use std::fs::File;
+
+    /// Params of a dynamic layout with the builtins of the recursive layout
+    const TEST_PARAMS: LayoutParams = LayoutParams {
+        cycle_height: 16,
+        num_base_columns: 9,
+        num_extension_columns: 3,
+        public_memory_step: 8,
+        memory_step: 2,
+        range_check_step: 4,
+        diluted_check_step: Some(1),
+        pedersen_ratio: Some(128),
+        range_check_ratio: Some(8),
+        ecdsa_ratio: None,
+        bitwise_ratio: Some(8),
+        ec_op_ratio: None,
+        keccak_ratio: None,
+        poseidon_ratio: None,
+    };
+
+    /// Returns the bootloader's public input with the starknet layout replaced
+    /// by the equivalent dynamic layout params (under the `dynamic_params` key
+    /// used by cairo-run instead of the bootloader's `layout_params`)
+    fn dynamic_bootloader_public_input() -> Value {
+        let path = format!(
+            "{}/../example/bootloader/air-public-input.json",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        let mut public_input: Value = serde_json::from_reader(File::open(path).unwrap()).unwrap();
+        public_input["layout"] = json!("dynamic");
+        public_input
+            .as_object_mut()
+            .unwrap()
+            .remove("layout_params");
+        public_input["dynamic_params"] = json!({
+            "cpu_component_step": 1,
+            "num_columns_first": 9,
+            "num_columns_second": 1,
+            "memory_units_row_ratio": 2,
+            "range_check_units_row_ratio": 4,
+            "diluted_units_row_ratio": 8,
+            "uses_output_builtin": 1,
+            "uses_pedersen_builtin": 1,
+            "pedersen_builtin_row_ratio": 512,
+            "uses_range_check_builtin": 1,
+            "range_check_builtin_row_ratio": 256,
+            "uses_ecdsa_builtin": 1,
+            "ecdsa_builtin_row_ratio": 32768,
+            "uses_bitwise_builtin": 1,
+            "bitwise_row_ratio": 1024,
+            "uses_ec_op_builtin": 1,
+            "ec_op_builtin_row_ratio": 16384,
+            "uses_keccak_builtin": 0,
+            "uses_poseidon_builtin": 1,
+            "poseidon_row_ratio": 512,
+        });
+        public_input
+    }
+
+    #[test]
+    fn dynamic_public_input_round_trips() {
+        let expected = dynamic_bootloader_public_input();
+        let public_input: AirPublicInput<Fp> = serde_json::from_value(expected.clone()).unwrap();
+        let actual = serde_json::to_value(public_input).unwrap();
+
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn dynamic_public_input_params_match_starknet() {
+        let public_input: AirPublicInput<Fp> =
+            serde_json::from_value(dynamic_bootloader_public_input()).unwrap();
+        public_input.validate().unwrap();
+        assert_eq!(Layout::Dynamic, public_input.layout);
+
+        let params = LayoutParams::from_dynamic_params(&public_input.dynamic_params.unwrap());
+
+        assert_eq!(LayoutParams::starknet(), params.unwrap());
+    }
+
+    #[test]
+    fn missing_dynamic_param_is_rejected() {
+        let mut public_input = dynamic_bootloader_public_input();
+        let dynamic_params = public_input["dynamic_params"].as_object_mut().unwrap();
+        dynamic_params.remove("bitwise_row_ratio");
+        let public_input: AirPublicInput<Fp> = serde_json::from_value(public_input).unwrap();
+
+        let err = LayoutParams::from_dynamic_params(&public_input.dynamic_params.unwrap());
+
+        assert!(matches!(
+            err,
+            Err(LayoutParamsError::MissingParam("bitwise_row_ratio"))
+        ));
+    }
+
+    #[test]
+    fn row_ratio_shorter_than_a_cycle_is_rejected() {
+        let mut public_input = dynamic_bootloader_public_input();
+        public_input["dynamic_params"]["poseidon_row_ratio"] = json!(8);
+        let public_input: AirPublicInput<Fp> = serde_json::from_value(public_input).unwrap();
+
+        let err = LayoutParams::from_dynamic_params(&public_input.dynamic_params.unwrap());
+
+        assert!(matches!(
+            err,
+            Err(LayoutParamsError::InvalidRowRatio("poseidon_row_ratio", 8))
+        ));
+    }
+
+    #[test]
+    fn builtin_layout_params_are_valid() {
+        for params in [
+            LayoutParams::dex(),
+            LayoutParams::plain(),
+            LayoutParams::recursive(),
+            LayoutParams::recursive_generic(),
+            LayoutParams::recursive_large_output(),
+            LayoutParams::small(),
+            LayoutParams::starknet(),
+        ] {
+            params.validate().unwrap();
+        }
+    }
+
+    #[test]
+    fn builtin_layout_ratios_match_public_input_ratios() {
+        for (layout, params) in [
+            (Layout::Dex, LayoutParams::dex()),
+            (Layout::Plain, LayoutParams::plain()),
+            (Layout::Recursive, LayoutParams::recursive()),
+            (
+                Layout::RecursiveLargeOutput,
+                LayoutParams::recursive_large_output(),
+            ),
+            (Layout::Small, LayoutParams::small()),
+            (Layout::Starknet, LayoutParams::starknet()),
+        ] {
+            for (name, ratio) in params.builtin_ratios() {
+                let builtin = name.strip_suffix("_ratio").unwrap();
+                let expected = layout.builtin_ratio(builtin).map(|ratio| ratio as usize);
+                assert_eq!(expected, ratio, "{layout} {builtin}");
+            }
+        }
+    }
+
+    #[test]
+    fn layout_without_base_columns_is_rejected() {
+        let params = LayoutParams {
+            num_base_columns: 0,
+            ..LayoutParams::plain()
+        };
+
+        assert!(matches!(
+            params.validate(),
+            Err(LayoutParamsError::NoBaseColumns)
+        ));
+    }
+
+    #[test]
+    fn ratio_that_is_not_a_power_of_two_is_rejected() {
+        let params = LayoutParams {
+            bitwise_ratio: Some(48),
+            ..LayoutParams::starknet()
+        };
+
+        let err = params.validate();
+
+        assert!(matches!(
+            err,
+            Err(LayoutParamsError::NotPowerOfTwo("bitwise_ratio", 48))
+        ));
+    }
+
+    #[test]
+    fn bitwise_without_diluted_pool_is_rejected() {
+        let params = LayoutParams {
+            diluted_check_step: None,
+            ..LayoutParams::recursive()
+        };
+
+        assert!(matches!(
+            params.validate(),
+            Err(LayoutParamsError::MissingDilutedPool)
+        ));
+    }
+
+    #[test]
+    fn invalid_json_is_rejected() {
+        let err = LayoutParams::from_reader(&b"{\"cycle_height\": 16}"[..]);
+
+        assert!(matches!(err, Err(LayoutParamsError::Json(_))));
+    }
+
+    #[test]
+    fn params_round_trip_through_json() {
+        let json = serde_json::to_vec(&LayoutParams::starknet()).unwrap();
+
+        let params = LayoutParams::from_reader(&*json).unwrap();
+
+        assert_eq!(LayoutParams::starknet(), params);
+    }
+
+    #[test]
+    fn dynamic_params_drive_air_layout() {
+        let dynamic_params: DynamicParams = serde_json::from_value(json!({
+            "cpu_component_step": 1,
+            "num_columns_first": 9,
+            "num_columns_second": 3,
+            "memory_units_row_ratio": 2,
+            "range_check_units_row_ratio": 4,
+            "diluted_units_row_ratio": 1,
+            "uses_output_builtin": 1,
+            "uses_pedersen_builtin": 1,
+            "pedersen_builtin_row_ratio": 2048,
+            "uses_range_check_builtin": 1,
+            "range_check_builtin_row_ratio": 128,
+            "uses_ecdsa_builtin": 0,
+            "uses_bitwise_builtin": 1,
+            "bitwise_row_ratio": 128,
+            "uses_ec_op_builtin": 0,
+            "uses_keccak_builtin": 0,
+            "uses_poseidon_builtin": 0,
+        }))
+        .unwrap();
+        let params = LayoutParams::from_dynamic_params(&dynamic_params).unwrap();
+        assert_eq!(TEST_PARAMS, params);
+
+        let layout = AirLayout::new(params).unwrap();
+
+        // groups are placed in row 6 of the first free cycle (most frequent first)
+        let group_rows = |memory: Option<BuiltinMemory>| {
+            let groups = memory.unwrap().groups;
+            groups.iter().map(|group| group.row).collect::<Vec<usize>>()
+        };
+        assert_eq!(1, layout.pedersen_lanes());
+        assert_eq!(vec![86, 118], group_rows(layout.pedersen));
+        assert_eq!(vec![22], group_rows(layout.range_check));
+        assert_eq!(vec![6, 54], group_rows(layout.bitwise));
+        assert_eq!(None, layout.ecdsa);
+        assert_eq!(None, layout.poseidon);
+    }
+
+    #[test]
+    fn builtin_memory_cells_use_distinct_unused_npc_cells() {
+        for params in [
+            TEST_PARAMS,
+            LayoutParams::small(),
+            LayoutParams::recursive_large_output(),
+        ] {
+            let layout = AirLayout::new(params).unwrap();
+            let free_rows: &[usize] = match params.public_memory_step {
+                8 => &[6],
+                _ => &[6, 10],
+            };
+            let builtins = [
+                &layout.pedersen,
+                &layout.range_check,
+                &layout.ecdsa,
+                &layout.bitwise,
+                &layout.poseidon,
+            ];
+            let trace_len = layout.max_instance_height();
+            let mut rows = BTreeSet::new();
+            for memory in builtins.into_iter().flatten() {
+                for instance in 0..trace_len / memory.instance_height {
+                    for i in 0..memory.num_cells() {
+                        let row = instance * memory.instance_height + memory.row(i);
+                        assert!(free_rows.contains(&(row % params.cycle_height)));
+                        assert!(rows.insert(row), "row {row} is used twice");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unsupported_builtin_is_rejected_by_air_layout() {
+        let params = LayoutParams {
+            ec_op_ratio: Some(1024),
+            ..TEST_PARAMS
+        };
+
+        assert!(matches!(
+            AirLayout::new(params),
+            Err(LayoutParamsError::UnsupportedBuiltin("ec_op"))
+        ));
+    }
+
+    #[test]
+    fn unsupported_step_is_rejected_by_air_layout() {
+        assert!(matches!(
+            AirLayout::new(LayoutParams::starknet()),
+            Err(LayoutParamsError::UnsupportedParam("diluted_check_step", 8))
+        ));
+    }
+
+    #[test]
+    fn ratio_too_small_for_an_instance_is_rejected() {
+        let params = LayoutParams {
+            ecdsa_ratio: Some(128),
+            ..TEST_PARAMS
+        };
+
+        assert!(matches!(
+            AirLayout::new(params),
+            Err(LayoutParamsError::RatioTooSmall("ecdsa_ratio", 128, 256))
+        ));
+    }
+
+    #[test]
+    fn column_count_must_match_air_layout() {
+        // the recursive layout's pedersen columns are shared with its CPU
+        assert!(matches!(
+            AirLayout::new(LayoutParams::recursive()),
+            Err(LayoutParamsError::ColumnCount("num_base_columns", 7, 9))
+        ));
+    }
+
+    #[test]
+    fn builtins_without_enough_memory_cells_are_rejected() {
+        let params = LayoutParams {
+            num_base_columns: 71,
+            num_extension_columns: 1,
+            diluted_check_step: None,
+            pedersen_ratio: Some(2),
+            bitwise_ratio: None,
+            ..TEST_PARAMS
+        };
+
+        assert!(matches!(
+            AirLayout::new(params),
+            Err(LayoutParamsError::InsufficientMemoryCells)
+        ));
+    }
+}
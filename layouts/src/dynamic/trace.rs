@@ -0,0 +1,796 @@
+use super::air::assert_public_input_layout;
+use super::air::AirLayout;
+use super::air::BuiltinMemory;
+use super::air::Cells;
+use super::air::NUM_CPU_BASE_COLUMNS;
+use super::DynamicLayout;
+use super::DYNAMIC_PUBLIC_MEMORY_STEP;
+use crate::recursive::air::Npc;
+use crate::recursive::air::RangeCheck;
+use crate::recursive::trace::cpu_extension_columns;
+use crate::recursive::trace::fill_cpu_cycles;
+use crate::recursive::trace::generate_memory_column;
+use crate::recursive::trace::memory_and_range_check_permutation_column;
+use crate::recursive::CYCLE_HEIGHT;
+use crate::recursive::DILUTED_CHECK_N_BITS;
+use crate::recursive::DILUTED_CHECK_SPACING;
+use crate::recursive::PUBLIC_MEMORY_STEP;
+use crate::recursive::RANGE_CHECK_BUILTIN_PARTS;
+use crate::recursive::RANGE_CHECK_STEP;
+use crate::utils::DilutedCheckPool;
+use crate::utils::RangeCheckPool;
+use crate::CairoTrace;
+use crate::CairoWitness;
+use crate::UnsupportedInstanceError;
+use crate::WitnessMemory;
+use alloc::vec;
+use alloc::vec::Vec;
+use ark_ff::BigInt;
+use ark_ff::Field;
+use binary::AirPrivateInput;
+use binary::AirPublicInput;
+use binary::BitwiseInstance;
+use binary::CompiledProgram;
+use binary::MemoryEntry;
+use binary::PedersenInstance;
+use binary::PoseidonInstance;
+use binary::RangeCheckInstance;
+use binary::RegisterState;
+use binary::RegisterStates;
+use binary::Segment;
+use builtins::bitwise;
+use builtins::bitwise::dilute;
+use builtins::ecdsa;
+use builtins::pedersen;
+use builtins::poseidon;
+use builtins::range_check;
+use core::iter::zip;
+use core::marker::PhantomData;
+use ministark::challenges::Challenges;
+use ministark::utils::GpuAllocator;
+use ministark::utils::GpuVec;
+use ministark::Matrix;
+use ministark::Trace;
+use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp;
+use num_bigint::BigUint;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use ruint::aliases::U256;
+
+pub struct ExecutionTrace<L: DynamicLayout> {
+    pub air_public_input: AirPublicInput<Fp>,
+    pub public_memory: Vec<MemoryEntry<Fp>>,
+    pub padding_entry: MemoryEntry<Fp>,
+    pub range_check_min: u16,
+    pub range_check_max: u16,
+    pub initial_registers: RegisterState,
+    pub final_registers: RegisterState,
+    pub program: CompiledProgram<Fp>,
+    npc_column: GpuVec<Fp>,
+    memory_column: GpuVec<Fp>,
+    diluted_check_ordered_column: GpuVec<Fp>,
+    diluted_check_unordered_column: GpuVec<Fp>,
+    range_check_column: GpuVec<Fp>,
+    base_trace: Matrix<Fp>,
+    _register_states: RegisterStates,
+    _memory: WitnessMemory<Fp>,
+    _flags_column: GpuVec<Fp>,
+    _auxiliary_column: GpuVec<Fp>,
+    _layout: PhantomData<L>,
+}
+
+impl<L: DynamicLayout> CairoTrace for ExecutionTrace<L> {
+    fn new(
+        program: CompiledProgram<Fp>,
+        air_public_input: AirPublicInput<Fp>,
+        witness: CairoWitness<Fp>,
+    ) -> Self {
+        let CairoWitness {
+            air_private_input,
+            register_states,
+            memory,
+        } = witness;
+
+        let layout =
+            AirLayout::new(L::PARAMS).unwrap_or_else(|err| panic!("invalid layout params: {err}"));
+        assert_public_input_layout::<L>(&air_public_input);
+
+        let num_cycles = register_states.len();
+        assert!(num_cycles.is_power_of_two());
+        let trace_len = num_cycles * CYCLE_HEIGHT;
+        assert!(
+            trace_len >= layout.max_instance_height(),
+            "trace must fit an instance of each builtin"
+        );
+        let public_memory = air_public_input
+            .public_memory
+            .iter()
+            .map(|e| MemoryEntry {
+                address: e.address,
+                value: Fp::from(BigUint::from(e.value)),
+            })
+            .collect::<Vec<MemoryEntry<Fp>>>();
+
+        println!("Num cycles: {}", num_cycles);
+        println!("Trace len: {}", trace_len);
+
+        let mut flags_column = Vec::new_in(GpuAllocator);
+        flags_column.resize(trace_len, Fp::ZERO);
+
+        let padding_entry = air_public_input
+            .public_memory_padding()
+            .unwrap_or_else(|err| panic!("invalid public input: {err}"));
+        let mut npc_column = Vec::new_in(GpuAllocator);
+        npc_column.resize(trace_len, Fp::ZERO);
+        {
+            // default all memory items to our padding entry
+            let padding_address = padding_entry.address.into();
+            let padding_value = padding_entry.value;
+            for [address, value] in npc_column.array_chunks_mut() {
+                *address = padding_address;
+                *value = padding_value;
+            }
+        }
+
+        // Keep trace of all 16-bit range check values
+        let mut rc_pool = RangeCheckPool::new();
+
+        // add offsets to the range check pool
+        for &RegisterState { pc, .. } in register_states.iter() {
+            let word = memory.get(pc).unwrap();
+            rc_pool.push(word.get_off_dst());
+            rc_pool.push(word.get_off_op0());
+            rc_pool.push(word.get_off_op1());
+        }
+
+        // add 128-bit range check builtin parts to the range check pool
+        let rc128_traces = air_private_input
+            .range_check
+            .into_iter()
+            .map(range_check::InstanceTrace::<RANGE_CHECK_BUILTIN_PARTS>::new)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap_or_else(|err| panic!("unchecked private input: {err}"));
+        for rc128_trace in &rc128_traces {
+            for part in rc128_trace.parts {
+                rc_pool.push(part);
+            }
+        }
+
+        let (ordered_rc_vals, ordered_rc_padding_vals) = rc_pool.get_ordered_values_with_padding();
+        let range_check_min = rc_pool.min().unwrap();
+        let range_check_max = rc_pool.max().unwrap();
+        let range_check_padding_value = Fp::from(range_check_max);
+        let mut ordered_rc_padding_vals = ordered_rc_padding_vals.into_iter();
+        let mut ordered_rc_vals = ordered_rc_vals.into_iter();
+        let mut range_check_column = Vec::new_in(GpuAllocator);
+        range_check_column.resize(trace_len, range_check_padding_value);
+
+        let mut auxiliary_column = Vec::new_in(GpuAllocator);
+        auxiliary_column.resize(trace_len, Fp::ZERO);
+
+        fill_cpu_cycles(
+            &register_states,
+            &memory,
+            &mut flags_column,
+            &mut npc_column,
+            &mut range_check_column,
+            &mut auxiliary_column,
+        );
+
+        // public memory cells are every 8 rows so there are two in each cycle
+        if L::PARAMS.public_memory_step == DYNAMIC_PUBLIC_MEMORY_STEP {
+            for npc_cycle in npc_column.array_chunks_mut::<CYCLE_HEIGHT>() {
+                npc_cycle[Npc::PubMemAddr as usize + DYNAMIC_PUBLIC_MEMORY_STEP] = Fp::ZERO;
+                npc_cycle[Npc::PubMemVal as usize + DYNAMIC_PUBLIC_MEMORY_STEP] = Fp::ZERO;
+            }
+        } else {
+            assert_eq!(L::PARAMS.public_memory_step, PUBLIC_MEMORY_STEP);
+        }
+
+        let mut builtin_columns = (NUM_CPU_BASE_COLUMNS..L::PARAMS.num_base_columns)
+            .map(|_| {
+                let mut column = Vec::new_in(GpuAllocator);
+                column.resize(trace_len, Fp::ZERO);
+                column
+            })
+            .collect::<Vec<GpuVec<Fp>>>();
+
+        // Range check builtin
+        // ===================
+        if let Some(rc_memory) = &layout.range_check {
+            let initial_rc_address = segment_address(air_public_input.memory_segments.range_check);
+            let num_instances = trace_len / rc_memory.instance_height;
+            assert!(
+                rc128_traces.len() <= num_instances,
+                "too many range check instances"
+            );
+
+            // create dummy 128-bit range check values that are filled with 16-bit range
+            // check padding values
+            let rc128_dummy_traces = (rc128_traces.len()..num_instances).map(|index| {
+                let mut value = U256::ZERO;
+                for _ in 0..RANGE_CHECK_BUILTIN_PARTS {
+                    let part = ordered_rc_padding_vals.next().unwrap_or(range_check_max);
+                    value = (value << 16) + U256::from(part)
+                }
+
+                // built from 16-bit parts so the value always fits
+                range_check::InstanceTrace::<RANGE_CHECK_BUILTIN_PARTS>::new(RangeCheckInstance {
+                    index: index as u32,
+                    value,
+                })
+                .unwrap()
+            });
+
+            let parts = layout.range_check_parts();
+            for (i, rc_trace) in rc128_traces
+                .into_iter()
+                .chain(rc128_dummy_traces)
+                .enumerate()
+            {
+                let instance_offset = i * rc_memory.instance_height;
+                for (j, part) in rc_trace.parts.into_iter().enumerate() {
+                    range_check_column[instance_offset + parts.row(j)] = part.into();
+                }
+                let value = Fp::from(BigUint::from(rc_trace.instance.value));
+                write_memory(&mut npc_column, rc_memory, initial_rc_address, i, 0, value);
+            }
+        }
+
+        let range_check_parts = layout
+            .range_check
+            .as_ref()
+            .map(|_| layout.range_check_parts());
+        for cycle in 0..num_cycles {
+            let cycle_offset = CYCLE_HEIGHT * cycle;
+            let rc_virtual_row = &mut range_check_column[cycle_offset..cycle_offset + CYCLE_HEIGHT];
+
+            // overwrite range check cells not used by the builtin with remaining padding
+            let unused_row = cycle_offset + RangeCheck::Unused as usize;
+            let is_builtin_cell = range_check_parts
+                .is_some_and(|parts| unused_row % parts.step == parts.shift % parts.step);
+            if !is_builtin_cell {
+                rc_virtual_row[RangeCheck::Unused as usize] =
+                    if let Some(val) = ordered_rc_padding_vals.next() {
+                        val.into()
+                    } else {
+                        range_check_padding_value
+                    };
+            }
+
+            // add ordered range check values
+            for offset in (0..CYCLE_HEIGHT).step_by(RANGE_CHECK_STEP) {
+                rc_virtual_row[offset + RangeCheck::Ordered as usize] =
+                    if let Some(val) = ordered_rc_vals.next() {
+                        (val as u64).into()
+                    } else {
+                        range_check_padding_value
+                    };
+            }
+        }
+
+        // ensure range check values have been fully consumed
+        assert!(ordered_rc_padding_vals.next().is_none());
+        assert!(ordered_rc_vals.next().is_none());
+
+        // Pedersen builtin
+        // ================
+        if let Some(pedersen_memory) = &layout.pedersen {
+            let initial_pedersen_address =
+                segment_address(air_public_input.memory_segments.pedersen);
+            let num_instances = trace_len / pedersen_memory.instance_height;
+            let pedersen_instances = air_private_input.pedersen;
+            assert!(
+                pedersen_instances.len() <= num_instances,
+                "too many pedersen instances"
+            );
+
+            // create dummy instances if there are cells that need to be filled
+            let empty_pedersen_instances =
+                ark_std::cfg_into_iter!(pedersen_instances.len() as u32..num_instances as u32)
+                    .map(PedersenInstance::new_empty);
+            let pedersen_traces = ark_std::cfg_into_iter!(pedersen_instances)
+                .chain(empty_pedersen_instances)
+                .map(pedersen::InstanceTrace::new)
+                .collect::<Vec<_>>();
+
+            // consecutive hashes are computed side by side in different lanes
+            let lanes = layout.pedersen_lanes();
+            let hash_height = layout.pedersen_hash_height();
+            for (i, pedersen_trace) in pedersen_traces.into_iter().enumerate() {
+                let cells = layout.pedersen_cells(i % lanes);
+                let hash_offset = i / lanes * hash_height;
+
+                let partial_steps = [pedersen_trace.a_steps, pedersen_trace.b_steps].concat();
+                for (j, step) in partial_steps.into_iter().enumerate() {
+                    let suffix_column = builtin_column(&mut builtin_columns, cells.suffix);
+                    suffix_column[hash_offset + cells.suffix.row(j)] = step.suffix;
+                    suffix_column[hash_offset + cells.partial_sum_x.row(j)] = step.point.x;
+                    let slope_column = builtin_column(&mut builtin_columns, cells.slope);
+                    slope_column[hash_offset + cells.slope.row(j)] = step.slope;
+                    slope_column[hash_offset + cells.partial_sum_y.row(j)] = step.point.y;
+                }
+
+                // load fields for unique bit decomposition checks into the trace
+                // NOTE: overwrites the unused slope of the last step of each half
+                let slope_column = builtin_column(&mut builtin_columns, cells.slope);
+                let bit251_and_bit196 = cells.bit251_and_bit196;
+                slope_column[hash_offset + bit251_and_bit196.row(0)] =
+                    pedersen_trace.a_bit251_and_bit196.into();
+                slope_column[hash_offset + bit251_and_bit196.row(1)] =
+                    pedersen_trace.b_bit251_and_bit196.into();
+                let bit251_and_bit196_and_bit192 = cells.bit251_and_bit196_and_bit192;
+                auxiliary_column[hash_offset + bit251_and_bit196_and_bit192.row(0)] =
+                    pedersen_trace.a_bit251_and_bit196_and_bit192.into();
+                auxiliary_column[hash_offset + bit251_and_bit196_and_bit192.row(1)] =
+                    pedersen_trace.b_bit251_and_bit196_and_bit192.into();
+
+                // add the hash to the memory pool
+                let instance = pedersen_trace.instance;
+                let values = [
+                    Fp::from(BigUint::from(instance.a)),
+                    Fp::from(BigUint::from(instance.b)),
+                    pedersen_trace.output,
+                ];
+                for (j, value) in values.into_iter().enumerate() {
+                    let address = initial_pedersen_address;
+                    write_memory(&mut npc_column, pedersen_memory, address, i, j, value);
+                }
+            }
+        }
+
+        // ECDSA builtin
+        // =============
+        if let Some(ecdsa_memory) = &layout.ecdsa {
+            let initial_ecdsa_address = segment_address(air_public_input.memory_segments.ecdsa);
+            let num_instances = trace_len / ecdsa_memory.instance_height;
+            let ecdsa_instances = air_private_input.ecdsa;
+            assert!(
+                ecdsa_instances.len() <= num_instances,
+                "too many ECDSA instances"
+            );
+
+            // create dummy instances if there are cells that need to be filled
+            let ecdsa_dummy_traces =
+                ark_std::cfg_into_iter!(ecdsa_instances.len() as u32..num_instances as u32)
+                    .map(ecdsa::InstanceTrace::new_dummy);
+            let ecdsa_traces = ark_std::cfg_into_iter!(ecdsa_instances)
+                .map(ecdsa::InstanceTrace::new)
+                .chain(ecdsa_dummy_traces)
+                .collect::<Vec<_>>();
+
+            let cells = layout.ecdsa_cells();
+            for (i, ecdsa_trace) in ecdsa_traces.into_iter().enumerate() {
+                let instance_offset = i * ecdsa_memory.instance_height;
+
+                // there are two EC ops per ECDSA instance
+                // 1st is for public key scalar multiplication `r * Q`
+                // 2nd is for `B` scalar multiplication `w * B` where `B = z * G + r * Q`
+                let key_steps = zip(ecdsa_trace.rq_steps, ecdsa_trace.pubkey_doubling_steps)
+                    .chain(zip(ecdsa_trace.wb_steps, ecdsa_trace.b_doubling_steps));
+                let key_column = builtin_column(&mut builtin_columns, cells.pubkey_doubling_x);
+                for (j, (partial_step, doubling_step)) in key_steps.enumerate() {
+                    let mut set = |cells: Cells, value| {
+                        key_column[instance_offset + cells.row(j)] = value;
+                    };
+                    set(cells.pubkey_doubling_x, doubling_step.point.x);
+                    set(cells.pubkey_doubling_y, doubling_step.point.y);
+                    set(cells.pubkey_doubling_slope, doubling_step.slope);
+                    set(cells.pubkey_partial_sum_x, partial_step.partial_sum.x);
+                    set(cells.pubkey_partial_sum_y, partial_step.partial_sum.y);
+                    set(cells.pubkey_partial_sum_slope, partial_step.slope);
+                    set(cells.pubkey_partial_sum_x_diff_inv, partial_step.x_diff_inv);
+                    set(cells.r_suffix, partial_step.suffix);
+                }
+
+                // the slope of the last doubling of each scalar multiplication is unused
+                let doubling_slope = cells.pubkey_doubling_slope;
+                key_column[instance_offset + doubling_slope.row(255)] = ecdsa_trace.r_inv;
+                key_column[instance_offset + doubling_slope.row(511)] = ecdsa_trace.w_inv;
+
+                // load the scalar multiplication `z * G` into the trace
+                // where `z` is the message hash and `G` is the curve generator point
+                let generator_column = builtin_column(&mut builtin_columns, cells.message_suffix);
+                for (j, zg_step) in ecdsa_trace.zg_steps.into_iter().enumerate() {
+                    let mut set = |cells: Cells, value| {
+                        generator_column[instance_offset + cells.row(j)] = value;
+                    };
+                    set(cells.generator_partial_sum_x, zg_step.partial_sum.x);
+                    set(cells.generator_partial_sum_y, zg_step.partial_sum.y);
+                    set(cells.generator_partial_sum_slope, zg_step.slope);
+                    set(cells.generator_partial_sum_x_diff_inv, zg_step.x_diff_inv);
+                    set(cells.message_suffix, zg_step.suffix);
+                }
+
+                let pubkey = ecdsa_trace.pubkey;
+                let mut set = |cells: Cells, value| {
+                    generator_column[instance_offset + cells.row(0)] = value;
+                };
+                set(cells.b_slope, ecdsa_trace.b_slope);
+                set(cells.b_x_diff_inv, ecdsa_trace.b_x_diff_inv);
+                set(cells.r_point_slope, ecdsa_trace.r_point_slope);
+                set(cells.r_point_x_diff_inv, ecdsa_trace.r_point_x_diff_inv);
+                set(cells.message_inv, ecdsa_trace.message_inv);
+                set(cells.pubkey_x_squared, pubkey.x.square());
+
+                // add the instance to the memory pool
+                let address = initial_ecdsa_address;
+                write_memory(&mut npc_column, ecdsa_memory, address, i, 0, pubkey.x);
+                let message = ecdsa_trace.message;
+                write_memory(&mut npc_column, ecdsa_memory, address, i, 1, message);
+            }
+        }
+
+        // Poseidon builtin
+        // ================
+        if let Some(poseidon_memory) = &layout.poseidon {
+            let initial_poseidon_address =
+                segment_address(air_public_input.memory_segments.poseidon);
+            let num_instances = trace_len / poseidon_memory.instance_height;
+            let poseidon_instances = air_private_input.poseidon;
+            assert!(
+                poseidon_instances.len() <= num_instances,
+                "too many poseidon instances"
+            );
+
+            // create dummy instances if there are cells that need to be filled
+            let poseidon_dummy_instances =
+                ark_std::cfg_into_iter!(poseidon_instances.len() as u32..num_instances as u32)
+                    .map(PoseidonInstance::new_empty);
+            let poseidon_traces = ark_std::cfg_into_iter!(poseidon_instances)
+                .chain(poseidon_dummy_instances)
+                .map(poseidon::InstanceTrace::new)
+                .collect::<Vec<_>>();
+
+            let cells = layout.poseidon_cells();
+            for (i, poseidon_trace) in poseidon_traces.into_iter().enumerate() {
+                let instance_offset = i * poseidon_memory.instance_height;
+
+                // load in full rounds
+                let full_round_states = [
+                    poseidon_trace.full_round_states_1st_half,
+                    poseidon_trace.full_round_states_2nd_half,
+                ]
+                .concat();
+                let column = builtin_column(&mut builtin_columns, cells.full_rounds_state0);
+                for (j, round_state) in full_round_states.into_iter().enumerate() {
+                    let [state0, state1, state2] = round_state.after_add_round_keys;
+                    let mut set = |cells: Cells, value| {
+                        column[instance_offset + cells.row(j)] = value;
+                    };
+                    set(cells.full_rounds_state0, state0);
+                    set(cells.full_rounds_state1, state1);
+                    set(cells.full_rounds_state2, state2);
+                    set(cells.full_rounds_state0_squared, state0.square());
+                    set(cells.full_rounds_state1_squared, state1.square());
+                    set(cells.full_rounds_state2_squared, state2.square());
+                }
+
+                // load in the last 22 partial rounds
+                // NOTE: the second column starts from the 61st partial round
+                let partial_round_states = poseidon_trace.partial_round_states;
+                for (j, state) in partial_round_states[64 - 3..].iter().enumerate() {
+                    let value = state.after_add_round_key;
+                    column[instance_offset + cells.partial_rounds_state1.row(j)] = value;
+                    column[instance_offset + cells.partial_rounds_state1_squared.row(j)] =
+                        value.square();
+                }
+
+                // load in the first 64 partial rounds
+                let column = builtin_column(&mut builtin_columns, cells.partial_rounds_state0);
+                for (j, state) in partial_round_states[..64].iter().enumerate() {
+                    let value = state.after_add_round_key;
+                    column[instance_offset + cells.partial_rounds_state0.row(j)] = value;
+                    column[instance_offset + cells.partial_rounds_state0_squared.row(j)] =
+                        value.square();
+                }
+
+                // add the inputs and outputs to the memory pool
+                let values = [
+                    poseidon_trace.input0,
+                    poseidon_trace.input1,
+                    poseidon_trace.input2,
+                    poseidon_trace.output0,
+                    poseidon_trace.output1,
+                    poseidon_trace.output2,
+                ];
+                for (j, value) in values.into_iter().enumerate() {
+                    let address = initial_poseidon_address;
+                    write_memory(&mut npc_column, poseidon_memory, address, i, j, value);
+                }
+            }
+        }
+
+        // Bitwise builtin
+        // ===============
+        let mut diluted_check_ordered_column = Vec::new_in(GpuAllocator);
+        diluted_check_ordered_column.resize(trace_len, Fp::ZERO);
+        let mut diluted_check_unordered_column = Vec::new_in(GpuAllocator);
+        diluted_check_unordered_column.resize(trace_len, Fp::ZERO);
+
+        if let Some(bitwise_memory) = &layout.bitwise {
+            let initial_bitwise_address = segment_address(air_public_input.memory_segments.bitwise);
+            let bitwise_height = bitwise_memory.instance_height;
+            let num_instances = trace_len / bitwise_height;
+            let bitwise_instances = air_private_input.bitwise;
+            assert!(
+                bitwise_instances.len() <= num_instances,
+                "too many bitwise instances"
+            );
+
+            // create dummy instances if there are cells that need to be filled
+            let bitwise_dummy_instances =
+                ark_std::cfg_into_iter!(bitwise_instances.len() as u32..num_instances as u32)
+                    .map(BitwiseInstance::new_empty);
+            let bitwise_traces = ark_std::cfg_into_iter!(bitwise_instances)
+                .chain(bitwise_dummy_instances)
+                .map(bitwise::InstanceTrace::<DILUTED_CHECK_SPACING>::new)
+                .collect::<Vec<_>>();
+
+            // rows of each instance with bitwise dilutions
+            let mut is_bitwise_row = vec![false; bitwise_height];
+
+            let cells = layout.bitwise_cells();
+            let mut diluted_check_pool =
+                DilutedCheckPool::<DILUTED_CHECK_N_BITS, DILUTED_CHECK_SPACING>::new();
+            for (i, bitwise_trace) in bitwise_traces.into_iter().enumerate() {
+                let instance_offset = i * bitwise_height;
+
+                {
+                    // add shifts to ensure a unique unpacking
+                    let x_and_y = bitwise_trace.x_and_y_partition.high.high;
+                    let x_xor_y = bitwise_trace.x_xor_y_partition.high.high;
+                    for offset in 0..4 {
+                        let v = x_and_y[offset] + x_xor_y[offset];
+                        let shift = if offset == 3 { 8 } else { 4 };
+                        // only fails if the AIR will error
+                        assert_eq!(v, (v << shift) >> shift);
+                        let s = v << shift;
+                        diluted_check_pool.push_diluted(U256::from(s));
+                        let row = cells.res_shifted(offset).row(0);
+                        diluted_check_unordered_column[instance_offset + row] = s.into();
+                        is_bitwise_row[row] = true;
+                    }
+                }
+
+                // NOTE: the order of these partitions matters
+                let partitions = [
+                    bitwise_trace.x_partition,
+                    bitwise_trace.y_partition,
+                    bitwise_trace.x_and_y_partition,
+                    bitwise_trace.x_xor_y_partition,
+                ];
+
+                // load diluted partitions into the execution trace
+                for (j, partition) in partitions.into_iter().enumerate() {
+                    let chunks = [
+                        partition.low.low,
+                        partition.low.high,
+                        partition.high.low,
+                        partition.high.high,
+                    ];
+                    for (chunk, values) in chunks.into_iter().enumerate() {
+                        for (offset, &v) in values.iter().enumerate() {
+                            let row = cells.chunk(chunk, offset).row(j);
+                            diluted_check_unordered_column[instance_offset + row] = v.into();
+                            diluted_check_pool.push_diluted(U256::from(v));
+                            is_bitwise_row[row] = true;
+                        }
+                    }
+                }
+
+                // load bitwise values into memory
+                let values = [
+                    bitwise_trace.x,
+                    bitwise_trace.y,
+                    bitwise_trace.x_and_y,
+                    bitwise_trace.x_xor_y,
+                    bitwise_trace.x_or_y,
+                ];
+                for (j, value) in values.into_iter().enumerate() {
+                    let address = initial_bitwise_address;
+                    write_memory(&mut npc_column, bitwise_memory, address, i, j, value);
+                }
+            }
+
+            // make sure all diluted check values are encountered for
+            const DILUTED_MIN: u128 = 0;
+            const DILUTED_MAX: u128 = (1 << DILUTED_CHECK_N_BITS) - 1;
+            let (ordered_diluted_vals, ordered_diluted_padding_vals) =
+                diluted_check_pool.get_ordered_values_with_padding(DILUTED_MIN, DILUTED_MAX);
+            let mut ordered_diluted_vals = ark_std::cfg_into_iter!(ordered_diluted_vals)
+                .map(|v| BigInt(dilute::<DILUTED_CHECK_SPACING>(U256::from(v)).into_limbs()).into())
+                .collect::<Vec<Fp>>()
+                .into_iter();
+            let mut ordered_diluted_padding_vals =
+                ark_std::cfg_into_iter!(ordered_diluted_padding_vals)
+                    .map(|v| {
+                        BigInt(dilute::<DILUTED_CHECK_SPACING>(U256::from(v)).into_limbs()).into()
+                    })
+                    .collect::<Vec<Fp>>()
+                    .into_iter();
+
+            // add diluted padding values to the cells not used by the bitwise builtin
+            let unused_rows = (0..trace_len).filter(|row| !is_bitwise_row[row % bitwise_height]);
+            for (row, padding_val) in zip(unused_rows, &mut ordered_diluted_padding_vals) {
+                diluted_check_unordered_column[row] = padding_val;
+            }
+
+            // add ordered diluted check values
+            let padding_offset = diluted_check_ordered_column.len() - ordered_diluted_vals.len();
+            for diluted_val in &mut diluted_check_ordered_column[padding_offset..] {
+                *diluted_val = ordered_diluted_vals.next().unwrap();
+            }
+
+            // ensure dilution check values have been fully consumed
+            assert!(ordered_diluted_padding_vals.next().is_none());
+            assert!(ordered_diluted_vals.next().is_none());
+        }
+
+        // VM Memory
+        // =========
+        // generate the memory column by ordering memory accesses
+        let memory_column = match L::PARAMS.public_memory_step {
+            DYNAMIC_PUBLIC_MEMORY_STEP => generate_memory_column::<DYNAMIC_PUBLIC_MEMORY_STEP, Fp>(
+                &mut npc_column,
+                &public_memory,
+                padding_entry,
+            ),
+            PUBLIC_MEMORY_STEP => generate_memory_column::<PUBLIC_MEMORY_STEP, Fp>(
+                &mut npc_column,
+                &public_memory,
+                padding_entry,
+            ),
+            step => unreachable!("unsupported public memory step {step}"),
+        };
+
+        let base_trace = Matrix::new(
+            [
+                flags_column.to_vec_in(GpuAllocator),
+                diluted_check_unordered_column.to_vec_in(GpuAllocator),
+                diluted_check_ordered_column.to_vec_in(GpuAllocator),
+                npc_column.to_vec_in(GpuAllocator),
+                memory_column.to_vec_in(GpuAllocator),
+                range_check_column.to_vec_in(GpuAllocator),
+                auxiliary_column.to_vec_in(GpuAllocator),
+            ]
+            .into_iter()
+            .chain(builtin_columns)
+            .collect(),
+        );
+
+        let initial_registers = *register_states.first().unwrap();
+        let final_registers = *register_states.last().unwrap();
+
+        ExecutionTrace {
+            air_public_input,
+            public_memory,
+            padding_entry,
+            range_check_min,
+            range_check_max,
+            initial_registers,
+            final_registers,
+            diluted_check_ordered_column,
+            diluted_check_unordered_column,
+            npc_column,
+            memory_column,
+            range_check_column,
+            base_trace,
+            program,
+            _flags_column: flags_column,
+            _auxiliary_column: auxiliary_column,
+            _memory: memory,
+            _register_states: register_states,
+            _layout: PhantomData,
+        }
+    }
+
+    fn check_private_input(
+        air_private_input: &AirPrivateInput,
+    ) -> Result<(), UnsupportedInstanceError> {
+        let params = L::PARAMS;
+        let builtins = [
+            (
+                "pedersen",
+                params.pedersen_ratio,
+                air_private_input.pedersen.is_empty(),
+            ),
+            (
+                "range_check",
+                params.range_check_ratio,
+                air_private_input.range_check.is_empty(),
+            ),
+            (
+                "ecdsa",
+                params.ecdsa_ratio,
+                air_private_input.ecdsa.is_empty(),
+            ),
+            (
+                "bitwise",
+                params.bitwise_ratio,
+                air_private_input.bitwise.is_empty(),
+            ),
+            (
+                "ec_op",
+                params.ec_op_ratio,
+                air_private_input.ec_op.is_empty(),
+            ),
+            (
+                "keccak",
+                params.keccak_ratio,
+                air_private_input.keccak.is_empty(),
+            ),
+            (
+                "poseidon",
+                params.poseidon_ratio,
+                air_private_input.poseidon.is_empty(),
+            ),
+        ];
+        for (builtin, ratio, has_no_instances) in builtins {
+            if ratio.is_none() && !has_no_instances {
+                return Err(UnsupportedInstanceError::UnsupportedBuiltin(builtin));
+            }
+        }
+        for &instance in &air_private_input.range_check {
+            range_check::InstanceTrace::<RANGE_CHECK_BUILTIN_PARTS>::new(instance)?;
+        }
+        Ok(())
+    }
+}
+
+impl<L: DynamicLayout> Trace for ExecutionTrace<L> {
+    type Fp = Fp;
+    type Fq = Fp;
+
+    fn base_columns(&self) -> &Matrix<Self::Fp> {
+        &self.base_trace
+    }
+
+    fn build_extension_columns(&self, challenges: &Challenges<Fp>) -> Option<Matrix<Fp>> {
+        Some(if L::PARAMS.diluted_check_step.is_some() {
+            cpu_extension_columns(
+                challenges,
+                &self.npc_column,
+                &self.memory_column,
+                &self.range_check_column,
+                &self.diluted_check_unordered_column,
+                &self.diluted_check_ordered_column,
+            )
+        } else {
+            Matrix::new(vec![memory_and_range_check_permutation_column(
+                challenges,
+                &self.npc_column,
+                &self.memory_column,
+                &self.range_check_column,
+            )])
+        })
+    }
+}
+
+/// Returns the first address of a builtin's memory segment
+fn segment_address(segment: Option<Segment>) -> u32 {
+    segment
+        .expect("layout requires a memory segment for each builtin")
+        .begin_addr
+}
+
+/// Returns the builtin column (after the CPU columns) containing `cells`
+fn builtin_column(builtin_columns: &mut [GpuVec<Fp>], cells: Cells) -> &mut [Fp] {
+    &mut builtin_columns[cells.column - NUM_CPU_BASE_COLUMNS]
+}
+
+/// Adds the `i`th memory cell of a builtin instance to the memory pool.
+/// Instances have continuous addresses starting at the segment's first address.
+fn write_memory(
+    npc_column: &mut [Fp],
+    memory: &BuiltinMemory,
+    initial_address: u32,
+    instance: usize,
+    i: usize,
+    value: Fp,
+) {
+    let row = instance * memory.instance_height + memory.row(i);
+    let address = initial_address as usize + instance * memory.num_cells() + i;
+    npc_column[row] = (address as u64).into();
+    npc_column[row + 1] = value;
+}
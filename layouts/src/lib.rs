@@ -12,15 +12,24 @@ use ark_ff::Field;
 use binary::AirPrivateInput;
 use binary::AirPublicInput;
 use binary::CompiledProgram;
-use binary::Memory;
+use binary::ReadMemory;
 use binary::RegisterStates;
+use builtins::bitwise;
+use builtins::range_check;
 use ministark::air::AirConfig;
 use ministark::challenges::Challenges;
 use ministark::hints::Hints;
 use ministark::Trace;
+use std::error::Error;
+use std::fmt::Display;
 
+pub mod dex;
+pub mod dynamic;
 pub mod plain;
 pub mod recursive;
+pub mod recursive_generic;
+pub mod recursive_large_output;
+pub mod small;
 pub mod starknet;
 pub mod utils;
 
@@ -34,23 +43,27 @@ pub trait CairoAirConfig: AirConfig {
     fn public_memory_quotient(hints: &Hints<Self::Fq>) -> Self::Fq;
 }
 
+/// Memory of an execution. Any [ReadMemory] can be used so memory files can be
+/// proven from a memory map without copying them.
+pub type WitnessMemory<F> = Box<dyn ReadMemory<F> + Send + Sync>;
+
 #[derive(Debug)]
 pub struct CairoWitness<F: Field> {
     air_private_input: AirPrivateInput,
     register_states: RegisterStates,
-    memory: Memory<F>,
+    memory: WitnessMemory<F>,
 }
 
 impl<F: Field> CairoWitness<F> {
     pub fn new(
         air_private_input: AirPrivateInput,
         register_states: RegisterStates,
-        memory: Memory<F>,
+        memory: impl ReadMemory<F> + Send + Sync + 'static,
     ) -> Self {
         Self {
             air_private_input,
             register_states,
-            memory,
+            memory: Box::new(memory),
         }
     }
 }
@@ -61,4 +74,48 @@ pub trait CairoTrace: Trace {
         public_input: AirPublicInput<Self::Fp>,
         witness: CairoWitness<Self::Fp>,
     ) -> Self;
+
+    /// Checks every builtin instance can be represented in the trace.
+    /// [CairoTrace::new] panics on instances that can't so call this first to
+    /// get an error instead.
+    fn check_private_input(
+        _air_private_input: &AirPrivateInput,
+    ) -> Result<(), UnsupportedInstanceError> {
+        Ok(())
+    }
+}
+
+/// A builtin instance that can't be represented in a layout's trace
+#[derive(Debug, Clone, Copy)]
+pub enum UnsupportedInstanceError {
+    /// The layout has no cells for instances of this builtin
+    UnsupportedBuiltin(&'static str),
+    RangeCheck(range_check::ValueTooLargeError),
+    Bitwise(bitwise::InputTooLargeError),
+}
+
+impl Display for UnsupportedInstanceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnsupportedBuiltin(builtin) => {
+                write!(f, "layout does not support the {builtin} builtin")
+            }
+            Self::RangeCheck(err) => write!(f, "{err}"),
+            Self::Bitwise(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl Error for UnsupportedInstanceError {}
+
+impl From<range_check::ValueTooLargeError> for UnsupportedInstanceError {
+    fn from(err: range_check::ValueTooLargeError) -> Self {
+        Self::RangeCheck(err)
+    }
+}
+
+impl From<bitwise::InputTooLargeError> for UnsupportedInstanceError {
+    fn from(err: bitwise::InputTooLargeError) -> Self {
+        Self::Bitwise(err)
+    }
 }
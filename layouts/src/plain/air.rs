@@ -544,11 +544,9 @@ impl<Fp: GpuFftField + PrimeField, Fq: StarkExtensionOf<Fp>> ministark::air::Air
                 challenges[MemoryPermutation::Z],
                 challenges[MemoryPermutation::A],
                 trace_len,
-                &public_input.public_memory,
-                public_input.public_memory_padding(),
+                public_input,
             );
 
-        // assert!(range_check_min <= range_check_max);
         let initial_ap = public_input.initial_ap().into();
         let final_ap = public_input.final_ap().into();
         let initial_pc = public_input.initial_pc().into();
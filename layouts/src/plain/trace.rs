@@ -13,6 +13,7 @@ use crate::utils::get_ordered_memory_accesses;
 use crate::utils::RangeCheckPool;
 use crate::CairoTrace;
 use crate::CairoWitness;
+use crate::WitnessMemory;
 use alloc::vec;
 use alloc::vec::Vec;
 use ark_ff::batch_inversion;
@@ -21,8 +22,8 @@ use ark_ff::Field;
 use ark_ff::PrimeField;
 use binary::AirPublicInput;
 use binary::CompiledProgram;
-use binary::Memory;
 use binary::MemoryEntry;
+use binary::ReadMemory;
 use binary::RegisterState;
 use binary::RegisterStates;
 use core::iter::zip;
@@ -45,7 +46,7 @@ pub struct ExecutionTrace<Fp: Field, Fq: Field> {
     pub final_registers: RegisterState,
     pub program: CompiledProgram<Fp>,
     _register_states: RegisterStates,
-    _memory: Memory<Fp>,
+    _memory: WitnessMemory<Fp>,
     _flags_column: GpuVec<Fp>,
     npc_column: GpuVec<Fp>,
     memory_column: GpuVec<Fp>,
@@ -74,7 +75,9 @@ impl<Fp: GpuFftField + PrimeField, Fq: StarkExtensionOf<Fp>> CairoTrace for Exec
         let mut flags_column = Vec::new_in(GpuAllocator);
         flags_column.resize(trace_len, Fp::zero());
 
-        let padding_entry = air_public_input.public_memory_padding();
+        let padding_entry = air_public_input
+            .public_memory_padding()
+            .unwrap_or_else(|err| panic!("invalid public input: {err}"));
         let mut npc_column = Vec::new_in(GpuAllocator);
         npc_column.resize(trace_len, Fp::zero());
         {
@@ -92,8 +95,8 @@ impl<Fp: GpuFftField + PrimeField, Fq: StarkExtensionOf<Fp>> CairoTrace for Exec
         // skip the memory at address 0 - this is a special memory address in Cairo
         // TODO: a little brittle. investigate more.
         let mut npc_gap_iter = npc_column.array_chunks_mut().skip(7).step_by(8);
-        for (a, v) in memory.iter().enumerate().skip(1) {
-            if v.is_none() {
+        for a in 1..memory.end_address() {
+            if memory.get(a).is_none() {
                 *npc_gap_iter.next().unwrap() = [(a as u64).into(), Fp::zero()];
             }
         }
@@ -101,7 +104,7 @@ impl<Fp: GpuFftField + PrimeField, Fq: StarkExtensionOf<Fp>> CairoTrace for Exec
         // add offsets to the range check pool
         let mut rc_pool = RangeCheckPool::new();
         for &RegisterState { pc, .. } in register_states.iter() {
-            let word = memory[pc].unwrap();
+            let word = memory.get(pc).unwrap();
             rc_pool.push(word.get_off_dst());
             rc_pool.push(word.get_off_op0());
             rc_pool.push(word.get_off_op1());
@@ -131,7 +134,7 @@ impl<Fp: GpuFftField + PrimeField, Fq: StarkExtensionOf<Fp>> CairoTrace for Exec
             .for_each(
                 |((((rc_cycle, aux_cycle), npc_cycle), flag_cycle), registers)| {
                     let &RegisterState { pc, ap, fp } = registers;
-                    let word = memory[pc].unwrap();
+                    let word = memory.get(pc).unwrap();
                     debug_assert!(!word.get_flag(Flag::Zero.into()));
 
                     // range check all offset values
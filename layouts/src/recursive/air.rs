@@ -22,6 +22,7 @@ use ministark::constraints::CompositionConstraint;
 use ministark::constraints::CompositionItem;
 use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp;
 use ark_ff::Field;
+use ark_ff::PrimeField;
 use ministark::challenges::Challenges;
 use ministark::constraints::AlgebraicItem;
 use ministark::constraints::Constraint;
@@ -31,6 +32,8 @@ use ministark::constraints::VerifierChallenge;
 use ministark::expression::Expr;
 use ministark::hints::Hints;
 use ministark::utils::FieldVariant;
+use ministark::StarkExtensionOf;
+use ministark_gpu::GpuFftField;
 use num_bigint::BigUint;
 use num_traits::Pow;
 use strum_macros::EnumIter;
@@ -73,30 +76,7 @@ impl ministark::air::AirConfig for AirConfig {
         let x = Expr::from(X);
         let one = Expr::from(Constant(FieldVariant::Fp(Fp::ONE)));
         let two = Expr::from(Constant(FieldVariant::Fp(Fp::from(2u32))));
-        let four = Expr::from(Constant(FieldVariant::Fp(Fp::from(4u32))));
         let offset_size = Expr::from(Constant(FieldVariant::Fp(Fp::from(2u32.pow(16)))));
-        let half_offset_size = Expr::from(Constant(FieldVariant::Fp(Fp::from(2u32.pow(15)))));
-
-        // cpu/decode/flag_op1_base_op0_0
-        let cpu_decode_flag_op1_base_op0_0 =
-            &one - (Flag::Op1Imm.curr() + Flag::Op1Ap.curr() + Flag::Op1Fp.curr());
-        // cpu/decode/flag_res_op1_0
-        let cpu_decode_flag_res_op1_0 =
-            &one - (Flag::ResAdd.curr() + Flag::ResMul.curr() + Flag::PcJnz.curr());
-        // cpu/decode/flag_pc_update_regular_0
-        let cpu_decode_flag_pc_update_regular_0 =
-            &one - (Flag::PcJumpAbs.curr() + Flag::PcJumpRel.curr() + Flag::PcJnz.curr());
-        // cpu/decode/fp_update_regular_0
-        let cpu_decode_fp_update_regular_0 =
-            &one - (Flag::OpcodeCall.curr() + Flag::OpcodeRet.curr());
-
-        // NOTE: npc_reg_0 = pc + instruction_size
-        // NOTE: instruction_size = fOP1_IMM + 1
-        let npc_reg_0 = Npc::Pc.curr() + Flag::Op1Imm.curr() + &one;
-
-        let memory_address_diff_0 = Mem::Address.next() - Mem::Address.curr();
-
-        let rc16_diff_0 = RangeCheck::Ordered.next() - RangeCheck::Ordered.curr();
 
         // TODO: builtins
         let pedersen_hash0_ec_subset_sum_b0 =
@@ -137,468 +117,8 @@ impl ministark::air::AirConfig for AirConfig {
             + Bitwise::Bits16Chunk3Offset2.curr() * (&two).pow(194)
             + Bitwise::Bits16Chunk3Offset3.curr() * (&two).pow(195);
 
-        // example for trace length n=64
-        // =============================
-        // x^(n/16)                 = (x - ω_0)(x - ω_16)(x - ω_32)(x - ω_48)
-        // x^(n/16) - c             = (x - c*ω_0)(x - c*ω_16)(x - c*ω_32)(x - c*ω_48)
-        // x^(n/16) - ω^(n/16)      = (x - ω_1)(x - ω_17)(x - ω_33)(x - )
-        // x^(n/16) - ω^(n/16)^(15) = (x - ω_15)(x - ω_31)(x - ω_47)(x - ω_6ω_493)
-        let flag0_offset =
-            FieldVariant::Fp(g.pow([(Flag::Zero as usize * n / CYCLE_HEIGHT) as u64]));
-        let flag0_zerofier = X.pow(n / CYCLE_HEIGHT) - Constant(flag0_offset);
-        let every_row_zerofier = X.pow(n) - &one;
-        let every_row_zerofier_inv = &one / &every_row_zerofier;
-        let flags_zerofier_inv = &flag0_zerofier * &every_row_zerofier_inv;
-
-        // check decoded flag values are 0 or 1
-        // NOTE: This expression is a bit confusing. The zerofier forces this constraint
-        // to apply in all rows of the trace therefore it applies to all flags (not just
-        // DstReg). Funnily enough any flag here would work (it just wouldn't be SHARP
-        // compatible).
-        let cpu_decode_opcode_rc_b =
-            (Flag::DstReg.curr() * Flag::DstReg.curr() - Flag::DstReg.curr()) * &flags_zerofier_inv;
-
-        // The first word of each instruction:
-        // ┌─────────────────────────────────────────────────────────────────────────┐
-        // │                     off_dst (biased representation)                     │
-        // ├─────────────────────────────────────────────────────────────────────────┤
-        // │                     off_op0 (biased representation)                     │
-        // ├─────────────────────────────────────────────────────────────────────────┤
-        // │                     off_op1 (biased representation)                     │
-        // ├─────┬─────┬───────┬───────┬───────────┬────────┬───────────────────┬────┤
-        // │ dst │ op0 │  op1  │  res  │    pc     │   ap   │      opcode       │ 0  │
-        // │ reg │ reg │  src  │ logic │  update   │ update │                   │    │
-        // ├─────┼─────┼───┬───┼───┬───┼───┬───┬───┼───┬────┼────┬────┬────┬────┼────┤
-        // │  0  │  1  │ 2 │ 3 │ 4 │ 5 │ 6 │ 7 │ 8 │ 9 │ 10 │ 11 │ 12 │ 13 │ 14 │ 15 │
-        // └─────┴─────┴───┴───┴───┴───┴───┴───┴───┴───┴────┴────┴────┴────┴────┴────┘
-        let whole_flag_prefix = Expr::from(Trace(0, 0));
-        // NOTE: Forces the `0` flag prefix to =0 in every cycle.
-        let cpu_decode_opcode_rc_zero = &whole_flag_prefix / flag0_zerofier;
-
-        // force constraint to apply every 16 trace rows (every cairo cycle)
-        // e.g. (x - ω_0)(x - ω_16)(x - ω_32)(x - ω_48) for n=64
-        let all_cycles_zerofier = X.pow(n / CYCLE_HEIGHT) - &one;
-        let all_cycles_zerofier_inv = &one / all_cycles_zerofier;
-        let cpu_decode_opcode_rc_input = (Npc::Instruction.curr()
-            - (((&whole_flag_prefix * &offset_size + RangeCheck::OffOp1.curr()) * &offset_size
-                + RangeCheck::OffOp0.curr())
-                * &offset_size
-                + RangeCheck::OffDst.curr()))
-            * &all_cycles_zerofier_inv;
-
-        // constraint for the Op1Src flag group - forces vals 000, 100, 010 or 001
-        let cpu_decode_flag_op1_base_op0_bit = (&cpu_decode_flag_op1_base_op0_0
-            * &cpu_decode_flag_op1_base_op0_0
-            - &cpu_decode_flag_op1_base_op0_0)
-            * &all_cycles_zerofier_inv;
-
-        // forces only one or none of ResAdd, ResMul or PcJnz to be 1
-        // TODO: Why the F is PcJnz in here? Res flag group is only bit 5 and 6
-        // NOTE: looks like it's a handy optimization to calculate next_fp and next_ap
-        let cpu_decode_flag_res_op1_bit = (&cpu_decode_flag_res_op1_0 * &cpu_decode_flag_res_op1_0
-            - &cpu_decode_flag_res_op1_0)
-            * &all_cycles_zerofier_inv;
-
-        // constraint forces PcUpdate flag to be 000, 100, 010 or 001
-        let cpu_decode_flag_pc_update_regular_bit = (&cpu_decode_flag_pc_update_regular_0
-            * &cpu_decode_flag_pc_update_regular_0
-            - &cpu_decode_flag_pc_update_regular_0)
-            * &all_cycles_zerofier_inv;
-
-        // forces max only OpcodeRet or OpcodeAssertEq to be 1
-        // TODO: why OpcodeCall not included? that would make whole flag group
-        let cpu_decode_fp_update_regular_bit = (&cpu_decode_fp_update_regular_0
-            * &cpu_decode_fp_update_regular_0
-            - &cpu_decode_fp_update_regular_0)
-            * &all_cycles_zerofier_inv;
-
-        // cpu/operands/mem_dst_addr
-        // NOTE: Pseudo code from cairo whitepaper
-        // ```
-        // if dst_reg == 0:
-        //     dst = m(ap + offdst)
-        // else:
-        //     dst = m(fp + offdst)
-        // ```
-        // NOTE: Trace(5, 8) dest mem address
-        let cpu_operands_mem_dst_addr = (Npc::MemDstAddr.curr() + &half_offset_size
-            - (Flag::DstReg.curr() * Auxiliary::Fp.curr()
-                + (&one - Flag::DstReg.curr()) * Auxiliary::Ap.curr()
-                + RangeCheck::OffDst.curr()))
-            * &all_cycles_zerofier_inv;
-
-        // whitepaper pseudocode
-        // ```
-        // # Compute op0.
-        // if op0_reg == 0:
-        //     op0 = m(-->>ap + offop0<<--)
-        // else:
-        //     op0 = m(-->>fp + offop0<<--)
-        // ```
-        // NOTE: StarkEx contracts as: cpu_operands_mem0_addr
-        let cpu_operands_mem_op0_addr = (Npc::MemOp0Addr.curr() + &half_offset_size
-            - (Flag::Op0Reg.curr() * Auxiliary::Fp.curr()
-                + (&one - Flag::Op0Reg.curr()) * Auxiliary::Ap.curr()
-                + RangeCheck::OffOp0.curr()))
-            * &all_cycles_zerofier_inv;
-
-        // NOTE: StarkEx contracts as: cpu_operands_mem1_addr
-        let cpu_operands_mem_op1_addr = (Npc::MemOp1Addr.curr() + &half_offset_size
-            - (Flag::Op1Imm.curr() * Npc::Pc.curr()
-                + Flag::Op1Ap.curr() * Auxiliary::Ap.curr()
-                + Flag::Op1Fp.curr() * Auxiliary::Fp.curr()
-                + &cpu_decode_flag_op1_base_op0_0 * Npc::MemOp0.curr()
-                + RangeCheck::OffOp1.curr()))
-            * &all_cycles_zerofier_inv;
-
-        // op1 * op0
-        // NOTE: starkex cpu/operands/ops_mul
-        let cpu_operands_ops_mul = (Auxiliary::Op0MulOp1.curr()
-            - Npc::MemOp0.curr() * Npc::MemOp1.curr())
-            * &all_cycles_zerofier_inv;
-
-        // From cairo whitepaper
-        // ```
-        // # Compute res.
-        // if pc_update == 4:
-        //     if res_logic == 0 && opcode == 0 && ap_update != 1:
-        //         res = Unused
-        //     else:
-        //         Undefined Behavior
-        // else if pc_update = 0, 1 or 2:
-        //     switch res_logic:
-        //         case 0: res = op1
-        //         case 1: res = op0 + op1
-        //         case 2: res = op0 * op1
-        //         default: Undefined Behavior
-        // else: Undefined Behavior
-        // ```
-        // NOTE: this constraint only handles:
-        // ```
-        // else if pc_update = 0, 1 or 2:
-        //   switch res_logic:
-        //     case 0: res = op1
-        //     case 1: res = op0 + op1
-        //     case 2: res = op0 * op1
-        // ```
-        let cpu_operands_res = ((&one - Flag::PcJnz.curr()) * Auxiliary::Res.curr()
-            - (Flag::ResAdd.curr() * (Npc::MemOp0.curr() + Npc::MemOp1.curr())
-                + Flag::ResMul.curr() * Auxiliary::Op0MulOp1.curr()
-                + &cpu_decode_flag_res_op1_0 * Npc::MemOp1.curr()))
-            * &all_cycles_zerofier_inv;
-
-        // example for trace length n=64
-        // =============================
-        // all_cycles_zerofier              = (x - ω_0)(x - ω_16)(x - ω_32)(x - ω_48)
-        // X - ω^(16*(n/16 - 1))            = x - ω^n/w^16 = x - 1/w_16 = x - w_48
-        // (X - w_48) / all_cycles_zerofier = (x - ω_0)(x - ω_16)(x - ω_32)
-        let last_cycle_zerofier = X - Constant(FieldVariant::Fp(
-            g.pow([(CYCLE_HEIGHT * (n / CYCLE_HEIGHT - 1)) as u64]),
-        ));
-        let last_cycle_zerofier_inv = &one / &last_cycle_zerofier;
-        let all_cycles_except_last_zerofier_inv = &last_cycle_zerofier * &all_cycles_zerofier_inv;
-
-        // Updating the program counter
-        // ============================
-        // This is not as straight forward as the other constraints. Read section 9.5
-        // Updating pc to understand.
-
-        // from whitepaper `t0 = fPC_JNZ * dst`
-        let cpu_update_registers_update_pc_tmp0 = (Auxiliary::Tmp0.curr()
-            - Flag::PcJnz.curr() * Npc::MemDst.curr())
-            * &all_cycles_except_last_zerofier_inv;
-
-        // From the whitepaper "To verify that we make a regular update if dst = 0, we
-        // need an auxiliary variable, v (to fill the trace in the case dst != 0, set v
-        // = dst^(−1)): `fPC_JNZ * (dst * v − 1) * (next_pc − (pc + instruction_size)) =
-        // 0` NOTE: if fPC_JNZ=1 then `res` is "unused" and repurposed as our
-        // temporary variable `v`. The value assigned to v is `dst^(−1)`.
-        // NOTE: `t1 = t0 * v`
-        let cpu_update_registers_update_pc_tmp1 = (Auxiliary::Tmp1.curr()
-            - Auxiliary::Tmp0.curr() * Auxiliary::Res.curr())
-            * &all_cycles_except_last_zerofier_inv;
-
-        // There are two constraints here bundled in one. The first is `t0 * (next_pc −
-        // (pc + op1)) = 0` (ensures if dst != 0 a relative jump is made) and the second
-        // is `(1−fPC_JNZ) * next_pc - (regular_update * (pc + instruction_size) +
-        // fPC_JUMP_ABS * res + fPC_JUMP_REL * (pc + res)) = 0` (handles update except
-        // for jnz). Note that due to the flag group constraints for PcUpdate if jnz=1
-        // then the second constraint is trivially 0=0 and if jnz=0 then the first
-        // constraint is trivially 0=0. For this reason we can bundle these constraints
-        // into one.
-        // TODO: fix padding bug
-        let cpu_update_registers_update_pc_pc_cond_negative = ((&one - Flag::PcJnz.curr())
-            * Npc::Pc.next()
-            + Auxiliary::Tmp0.curr() * (Npc::Pc.next() - (Npc::Pc.curr() + Npc::MemOp1.curr()))
-            - (&cpu_decode_flag_pc_update_regular_0 * &npc_reg_0
-                + Flag::PcJumpAbs.curr() * Auxiliary::Res.curr()
-                + Flag::PcJumpRel.curr() * (Npc::Pc.curr() + Auxiliary::Res.curr())))
-            * &all_cycles_except_last_zerofier_inv;
-
-        // ensure `if dst == 0: pc + instruction_size == next_pc`
-        let cpu_update_registers_update_pc_pc_cond_positive =
-            ((Auxiliary::Tmp1.curr() - Flag::PcJnz.curr()) * (Npc::Pc.next() - npc_reg_0))
-                * &all_cycles_except_last_zerofier_inv;
-
-        // Updating the allocation pointer
-        // ===============================
-        // TODO: seems fishy don't see how `next_ap = ap + fAP_ADD · res + fAP_ADD1 · 1
-        // + fOPCODE_CALL · 2` meets the pseudo code in the whitepaper
-        // Ok, it does kinda make sense. move the `opcode == 1` statement inside and
-        // move the switch to the outside and it's more clear.
-        let cpu_update_registers_update_ap_ap_update = (Auxiliary::Ap.next()
-            - (Auxiliary::Ap.curr()
-                + Flag::ApAdd.curr() * Auxiliary::Res.curr()
-                + Flag::ApAdd1.curr()
-                + Flag::OpcodeCall.curr() * &two))
-            * &all_cycles_except_last_zerofier_inv;
-
-        // Updating the frame pointer
-        // ==========================
-        // This handles all fp update except the `op0 == pc + instruction_size`, `res =
-        // dst` and `dst == fp` assertions.
-        // TODO: fix padding bug
-        let cpu_update_registers_update_fp_fp_update = (Auxiliary::Fp.next()
-            - (&cpu_decode_fp_update_regular_0 * Auxiliary::Fp.curr()
-                + Flag::OpcodeRet.curr() * Npc::MemDst.curr()
-                + Flag::OpcodeCall.curr() * (Auxiliary::Ap.curr() + &two)))
-            * &all_cycles_except_last_zerofier_inv;
-
-        // push registers to memory (see section 8.4 in the whitepaper).
-        // These are essentially the assertions for assert `op0 == pc +
-        // instruction_size` and `assert dst == fp`.
-        let cpu_opcodes_call_push_fp = (Flag::OpcodeCall.curr()
-            * (Npc::MemDst.curr() - Auxiliary::Fp.curr()))
-            * &all_cycles_zerofier_inv;
-        let cpu_opcodes_call_push_pc = (Flag::OpcodeCall.curr()
-            * (Npc::MemOp0.curr() - (Npc::Pc.curr() + Flag::Op1Imm.curr() + &one)))
-            * &all_cycles_zerofier_inv;
-
-        // make sure all offsets are valid for the call opcode
-        // ===================================================
-        // checks `if opcode == OpcodeCall: assert off_dst = 2^15`
-        // this is supplementary to the constraints above because
-        // offsets are in the range [-2^15, 2^15) encoded using
-        // biased representation
-        let cpu_opcodes_call_off0 = (Flag::OpcodeCall.curr()
-            * (RangeCheck::OffDst.curr() - &half_offset_size))
-            * &all_cycles_zerofier_inv;
-        // checks `if opcode == OpcodeCall: assert off_op0 = 2^15 + 1`
-        // TODO: why +1?
-        let cpu_opcodes_call_off1 = (Flag::OpcodeCall.curr()
-            * (RangeCheck::OffOp0.curr() - (&half_offset_size + &one)))
-            * &all_cycles_zerofier_inv;
-        // TODO: I don't understand this one - Flag::OpcodeCall.curr() is 0 or 1. Why
-        // not just replace `Flag::OpcodeCall.curr() + Flag::OpcodeCall.curr() +
-        // &one + &one` with `4`
-        let cpu_opcodes_call_flags = (Flag::OpcodeCall.curr()
-            * (Flag::OpcodeCall.curr() + Flag::OpcodeCall.curr() + &one + &one
-                - (Flag::DstReg.curr() + Flag::Op0Reg.curr() + &four)))
-            * &all_cycles_zerofier_inv;
-        // checks `if opcode == OpcodeRet: assert off_dst = 2^15 - 2`
-        // TODO: why -2 🤯? Instruction size?
-        let cpu_opcodes_ret_off0 = (Flag::OpcodeRet.curr()
-            * (RangeCheck::OffDst.curr() + &two - &half_offset_size))
-            * &all_cycles_zerofier_inv;
-        // checks `if opcode == OpcodeRet: assert off_op1 = 2^15 - 1`
-        // TODO: why -1?
-        let cpu_opcodes_ret_off2 = (Flag::OpcodeRet.curr()
-            * (RangeCheck::OffOp1.curr() + &one - &half_offset_size))
-            * &all_cycles_zerofier_inv;
-        // checks `if OpcodeRet: assert PcJumpAbs=1, DstReg=1, Op1Fp=1, ResLogic=0`
-        let cpu_opcodes_ret_flags = (Flag::OpcodeRet.curr()
-            * (Flag::PcJumpAbs.curr()
-                + Flag::DstReg.curr()
-                + Flag::Op1Fp.curr()
-                + &cpu_decode_flag_res_op1_0
-                - &four))
-            * &all_cycles_zerofier_inv;
-        // handles the "assert equal" instruction. Represents this pseudo code from the
-        // whitepaper `assert res = dst`.
-        let cpu_opcodes_assert_eq_assert_eq = (Flag::OpcodeAssertEq.curr()
-            * (Npc::MemDst.curr() - Auxiliary::Res.curr()))
-            * &all_cycles_zerofier_inv;
-
-        let first_row_zerofier = &x - &one;
-        let first_row_zerofier_inv = &one / first_row_zerofier;
-
-        // boundary constraint expression for initial registers
-        let initial_ap = (Auxiliary::Ap.curr() - InitialAp.hint()) * &first_row_zerofier_inv;
-        let initial_fp = (Auxiliary::Fp.curr() - InitialAp.hint()) * &first_row_zerofier_inv;
-        let initial_pc = (Npc::Pc.curr() - InitialPc.hint()) * &first_row_zerofier_inv;
-
-        // boundary constraint expression for final registers
-        let final_ap = (Auxiliary::Ap.curr() - FinalAp.hint()) * &last_cycle_zerofier_inv;
-        let final_fp = (Auxiliary::Fp.curr() - InitialAp.hint()) * &last_cycle_zerofier_inv;
-        let final_pc = (Npc::Pc.curr() - FinalPc.hint()) * &last_cycle_zerofier_inv;
-
-        // examples for trace length n=8
-        // =============================
-        // x^(n/2) - 1             = (x - ω_0)(x - ω_2)(x - ω_4)(x - ω_6)
-        // x - ω^(2*(n/2 - 1))     = x - ω^n/w^2 = x - 1/w_2 = x - w_6
-        // (x - w_6) / x^(n/2) - 1 = (x - ω_0)(x - ω_2)(x - ω_4)
-        let every_second_row_zerofier = X.pow(n / 2) - &one;
-        let second_last_row_zerofier =
-            X - Constant(FieldVariant::Fp(g.pow([2 * (n as u64 / 2 - 1)])));
-        let every_second_row_except_last_zerofier_inv =
-            &second_last_row_zerofier / &every_second_row_zerofier;
-
-        // Memory access constraints
-        // =========================
-        // All these constraints make more sense once you understand how the permutation
-        // column is calculated (look at get_ordered_memory_accesses()). Sections 9.8
-        // and 9.7 of the Cairo paper justify these constraints.
-        // memory permutation boundary constraint
-        let memory_multi_column_perm_perm_init0 = ((MemoryPermutation::Z.challenge()
-            - (Mem::Address.curr() + MemoryPermutation::A.challenge() * Mem::Value.curr()))
-            * Permutation::Memory.curr()
-            + Npc::Pc.curr()
-            + MemoryPermutation::A.challenge() * Npc::Instruction.curr()
-            - MemoryPermutation::Z.challenge())
-            * &first_row_zerofier_inv;
-        // memory permutation transition constraint
-        // NOTE: memory entries are stacked in the trace like so:
-        // ┌─────┬───────────┬─────┐
-        // │ ... │    ...    │ ... │
-        // ├─────┼───────────┼─────┤
-        // │ ... │ address 0 │ ... │
-        // ├─────┼───────────┼─────┤
-        // │ ... │  value 0  │ ... │
-        // ├─────┼───────────┼─────┤
-        // │ ... │ address 1 │ ... │
-        // ├─────┼───────────┼─────┤
-        // │ ... │  value 1  │ ... │
-        // ├─────┼───────────┼─────┤
-        // │ ... │    ...    │ ... │
-        // └─────┴───────────┴─────┘
-        let memory_multi_column_perm_perm_step0 = ((MemoryPermutation::Z.challenge()
-            - (Mem::Address.next() + MemoryPermutation::A.challenge() * Mem::Value.next()))
-            * Permutation::Memory.next()
-            - (MemoryPermutation::Z.challenge()
-                - (Npc::PubMemAddr.curr()
-                    + MemoryPermutation::A.challenge() * Npc::PubMemVal.curr()))
-                * Permutation::Memory.curr())
-            * &every_second_row_except_last_zerofier_inv;
-        // Check the last permutation value to verify public memory
-        let memory_multi_column_perm_perm_last =
-            (Permutation::Memory.curr() - MemoryQuotient.hint()) / &second_last_row_zerofier;
-        // Constraint expression for memory/diff_is_bit
-        // checks the address doesn't change or increases by 1
-        // "Continuity" constraint in cairo whitepaper 9.7.2
-        let memory_diff_is_bit = (&memory_address_diff_0 * &memory_address_diff_0
-            - &memory_address_diff_0)
-            * &every_second_row_except_last_zerofier_inv;
-        // if the address stays the same then the value stays the same
-        // "Single-valued" constraint in cairo whitepaper 9.7.2.
-        // cairo uses nondeterministic read-only memory so if the address is the same
-        // the value should also stay the same.
-        let memory_is_func = ((&memory_address_diff_0 - &one)
-            * (Mem::Value.curr() - Mem::Value.next()))
-            * &every_second_row_except_last_zerofier_inv;
-        // boundary condition stating the first memory address == 1
-        let memory_initial_addr = (Mem::Address.curr() - &one) * &first_row_zerofier_inv;
-        // applies every 8 rows
-        // Read cairo whitepaper section 9.8 as to why the public memory cells are 0.
-        // The high level is that the way public memory works is that the prover is
-        // forced (with these constraints) to exclude the public memory from one of
-        // the permutation products. This means the running permutation column
-        // terminates with more-or-less the permutation of just the public input. The
-        // verifier can relatively cheaply calculate this terminal. The constraint for
-        // this terminal is `memory_multi_column_perm_perm_last`.
-        let public_memory_addr_zero = Npc::PubMemAddr.curr() * &all_cycles_zerofier_inv;
-        let public_memory_value_zero = Npc::PubMemVal.curr() * &all_cycles_zerofier_inv;
-
-        // examples for trace length n=16
-        // =====================================
-        // x^(n/4) - 1              = (x - ω_0)(x - ω_4)(x - ω_8)(x - ω_12)
-        // x - ω^(4*(n/4 - 1))      = x - ω^n/w^4 = x - 1/w_4 = x - w_12
-        // (x - w_12) / x^(n/4) - 1 = (x - ω_0)(x - ω_4)(x - ω_8)
+        let first_row_zerofier_inv = &one / (&x - &one);
         let every_fourth_row_zerofier_inv = &one / (X.pow(n / 4) - &one);
-        let fourth_last_row_zerofier =
-            X - Constant(FieldVariant::Fp(g.pow([4 * (n as u64 / 4 - 1)])));
-        let fourth_last_row_zerofier_inv = &one / &fourth_last_row_zerofier;
-        let every_fourth_row_except_last_zerofier_inv =
-            &fourth_last_row_zerofier * &every_fourth_row_zerofier_inv;
-
-        // Range check constraints
-        // =======================
-        // Look at memory to understand the general approach to permutation.
-        // More info in section 9.9 of the Cairo paper.
-        let rc16_perm_init0 = ((RangeCheckPermutation::Z.challenge() - RangeCheck::Ordered.curr())
-            * Permutation::RangeCheck.curr()
-            + RangeCheck::OffDst.curr()
-            - RangeCheckPermutation::Z.challenge())
-            * &first_row_zerofier_inv;
-        let rc16_perm_step0 = ((RangeCheckPermutation::Z.challenge() - RangeCheck::Ordered.next())
-            * Permutation::RangeCheck.next()
-            - (RangeCheckPermutation::Z.challenge() - RangeCheck::OffOp1.curr())
-                * Permutation::RangeCheck.curr())
-            * &every_fourth_row_except_last_zerofier_inv;
-        let rc16_perm_last = (Permutation::RangeCheck.curr() - RangeCheckProduct.hint())
-            * &fourth_last_row_zerofier_inv;
-        // Check the value increases by 0 or 1
-        let rc16_diff_is_bit = (&rc16_diff_0 * &rc16_diff_0 - &rc16_diff_0)
-            * &every_fourth_row_except_last_zerofier_inv;
-        // Prover sends the minimim and maximum as a public input.
-        // Verifier checks the RC min and max fall within [0, 2^16).
-        let rc16_minimum =
-            (RangeCheck::Ordered.curr() - RangeCheckMin.hint()) * &first_row_zerofier_inv;
-        let rc16_maximum =
-            (RangeCheck::Ordered.curr() - RangeCheckMax.hint()) * &fourth_last_row_zerofier_inv;
-
-        // Diluted Check constraints
-        // =========================
-        // A "dilution" is spreading out of the bits in a number.
-        // Dilutions have two parameters (1) the number of bits they operate on and
-        // (2) the spread of each bit. For example the the dilution of binary
-        // digit 1111 to 0001000100010001 operates on 4 bits with a spread of 4.
-        let diluted_check_permutation_init0 = ((DilutedCheckPermutation::Z.challenge()
-            - DilutedCheck::Ordered.curr())
-            * Permutation::DilutedCheck.curr()
-            + DilutedCheck::Unordered.curr()
-            - DilutedCheckPermutation::Z.challenge())
-            * &first_row_zerofier_inv;
-
-        // Diluted checks operate every row (16 times per cycle)
-        let last_row_zerofier = X - Constant(FieldVariant::Fp(g.pow([n as u64 - 1])));
-        let last_row_zerofier_inv = &one / &last_row_zerofier;
-        let every_row_except_last_zerofier_inv = &last_row_zerofier * &one / &every_row_zerofier;
-        // we have an out-of-order and in-order list of diluted values for this layout
-        // (starknet). We want to check each list is a permutation of one another
-        let diluted_check_permutation_step0 = ((DilutedCheckPermutation::Z.challenge()
-            - DilutedCheck::Ordered.next())
-            * Permutation::DilutedCheck.next()
-            - (DilutedCheckPermutation::Z.challenge() - DilutedCheck::Unordered.next())
-                * Permutation::DilutedCheck.curr())
-            * &every_row_except_last_zerofier_inv;
-        let diluted_check_permutation_last = (Permutation::DilutedCheck.curr()
-            - DilutedCheckProduct.hint())
-            * &last_row_zerofier_inv;
-
-        // Initial aggregate value should be =1
-        let diluted_check_init = (DilutedCheck::Aggregate.curr() - &one) * &first_row_zerofier_inv;
-
-        // Check first, in-order, diluted value
-        let diluted_check_first_element =
-            (DilutedCheck::Ordered.curr() - DilutedCheckFirst.hint()) * &first_row_zerofier_inv;
-
-        // TODO: add more docs
-        // `diluted_diff` is related to `u` in `compute_diluted_cumulative_value`
-        // Note that if there is no difference between the current and next ordered
-        // diluted values then `diluted_diff == 0` and the previous aggregate value is
-        // copied over
-        let diluted_diff = DilutedCheck::Ordered.next() - DilutedCheck::Ordered.curr();
-        let diluted_check_step = (DilutedCheck::Aggregate.next()
-            - (DilutedCheck::Aggregate.curr()
-                * (&one + DilutedCheckAggregation::Z.challenge() * &diluted_diff)
-                + DilutedCheckAggregation::A.challenge() * &diluted_diff * diluted_diff))
-            * &every_row_except_last_zerofier_inv;
-
-        // Check the last cumulative value.
-        // NOTE: This can be calculated efficiently by the verifier.
-        let diluted_check_last = (DilutedCheck::Aggregate.curr()
-            - DilutedCheckCumulativeValue.hint())
-            * &last_row_zerofier_inv;
 
         // Pedersen builtin
         // ================
@@ -1081,104 +601,52 @@ impl ministark::air::AirConfig for AirConfig {
             * &all_bitwise_zerofier_inv;
 
         // NOTE: for composition OODs only seem to involve one random per constraint
-        vec![
-            cpu_decode_opcode_rc_b, // +1
-            cpu_decode_opcode_rc_zero,
-            cpu_decode_opcode_rc_input,
-            cpu_decode_flag_op1_base_op0_bit,
-            cpu_decode_flag_res_op1_bit,
-            cpu_decode_flag_pc_update_regular_bit,
-            cpu_decode_fp_update_regular_bit,
-            cpu_operands_mem_dst_addr,
-            cpu_operands_mem_op0_addr,
-            cpu_operands_mem_op1_addr,
-            cpu_operands_ops_mul,
-            cpu_operands_res,
-            cpu_update_registers_update_pc_tmp0,
-            cpu_update_registers_update_pc_tmp1,
-            cpu_update_registers_update_pc_pc_cond_negative,
-            cpu_update_registers_update_pc_pc_cond_positive,
-            cpu_update_registers_update_ap_ap_update,
-            cpu_update_registers_update_fp_fp_update,
-            cpu_opcodes_call_push_fp,
-            cpu_opcodes_call_push_pc,
-            cpu_opcodes_call_off0,
-            cpu_opcodes_call_off1,
-            cpu_opcodes_call_flags,
-            cpu_opcodes_ret_off0,
-            cpu_opcodes_ret_off2,
-            cpu_opcodes_ret_flags,
-            cpu_opcodes_assert_eq_assert_eq,
-            initial_ap,
-            initial_fp,
-            initial_pc,
-            final_ap,
-            final_fp,
-            final_pc,
-            memory_multi_column_perm_perm_init0,
-            memory_multi_column_perm_perm_step0,
-            memory_multi_column_perm_perm_last,
-            memory_diff_is_bit,
-            memory_is_func,
-            memory_initial_addr,
-            public_memory_addr_zero,
-            public_memory_value_zero,
-            rc16_perm_init0,
-            rc16_perm_step0,
-            rc16_perm_last,
-            rc16_diff_is_bit,
-            rc16_minimum,
-            rc16_maximum,
-            diluted_check_permutation_init0,
-            diluted_check_permutation_step0,
-            diluted_check_permutation_last,
-            diluted_check_init,
-            diluted_check_first_element,
-            diluted_check_step,
-            diluted_check_last,
-            pedersen_hash0_ec_subset_sub_bit_unpacking_last_one_is_zero,
-            pedersen_hash0_ec_subset_sub_bit_unpacking_zeros_between_ones,
-            pedersen_hash0_ec_subset_sum_bit_unpacking_cumulative_bit192,
-            pedersen_hash0_ec_subset_sum_bit_unpacking_zeroes_between_ones192,
-            pedersen_hash0_ec_subset_sum_bit_unpacking_cumulative_bit196,
-            pedersen_hash0_ec_subset_sum_bit_unpacking_zeroes_between_ones196,
-            pedersen_hash0_ec_subset_sum_booleanity_test,
-            pedersen_hash0_ec_subset_sum_bit_extraction_end,
-            pedersen_hash0_ec_subset_sum_zeros_tail,
-            pedersen_hash0_ec_subset_sum_add_points_slope,
-            pedersen_hash0_ec_subset_sum_add_points_x,
-            pedersen_hash0_ec_subset_sum_add_points_y,
-            pedersen_hash0_ec_subset_sum_copy_point_x,
-            pedersen_hash0_ec_subset_sum_copy_point_y,
-            pedersen_hash0_copy_point_x,
-            pedersen_hash0_copy_point_y,
-            pedersen_hash0_init_x,
-            pedersen_hash0_init_y,
-            pedersen_input0_value0,
-            pedersen_input0_addr,
-            pedersen_init_addr,
-            pedersen_input1_value0,
-            pedersen_input1_addr,
-            pedersen_output_value0,
-            pedersen_output_addr,
-            rc_builtin_value,
-            rc_builtin_addr_step,
-            rc_builtin_init_addr,
-            bitwise_init_var_pool_addr, // +1
-            bitwise_step_var_pool_addr,
-            bitwise_x_or_y_addr,
-            bitwise_next_var_pool_addr,
-            bitwise_partition,
-            bitwise_or_is_and_plus_xor,
-            bitwise_addition_is_xor_with_and,
-            bitwise_unique_unpacking192,
-            bitwise_unique_unpacking193,
-            bitwise_unique_unpacking194,
-            bitwise_unique_unpacking195,
-        ]
-        .into_iter()
-        .map(Constraint::new)
-        .collect()
+        cpu_constraints::<PUBLIC_MEMORY_STEP, Fp, Fp>(n)
+            .into_iter()
+            .chain(diluted_check_constraints::<Fp, Fp>(n))
+            .chain([
+                pedersen_hash0_ec_subset_sub_bit_unpacking_last_one_is_zero,
+                pedersen_hash0_ec_subset_sub_bit_unpacking_zeros_between_ones,
+                pedersen_hash0_ec_subset_sum_bit_unpacking_cumulative_bit192,
+                pedersen_hash0_ec_subset_sum_bit_unpacking_zeroes_between_ones192,
+                pedersen_hash0_ec_subset_sum_bit_unpacking_cumulative_bit196,
+                pedersen_hash0_ec_subset_sum_bit_unpacking_zeroes_between_ones196,
+                pedersen_hash0_ec_subset_sum_booleanity_test,
+                pedersen_hash0_ec_subset_sum_bit_extraction_end,
+                pedersen_hash0_ec_subset_sum_zeros_tail,
+                pedersen_hash0_ec_subset_sum_add_points_slope,
+                pedersen_hash0_ec_subset_sum_add_points_x,
+                pedersen_hash0_ec_subset_sum_add_points_y,
+                pedersen_hash0_ec_subset_sum_copy_point_x,
+                pedersen_hash0_ec_subset_sum_copy_point_y,
+                pedersen_hash0_copy_point_x,
+                pedersen_hash0_copy_point_y,
+                pedersen_hash0_init_x,
+                pedersen_hash0_init_y,
+                pedersen_input0_value0,
+                pedersen_input0_addr,
+                pedersen_init_addr,
+                pedersen_input1_value0,
+                pedersen_input1_addr,
+                pedersen_output_value0,
+                pedersen_output_addr,
+                rc_builtin_value,
+                rc_builtin_addr_step,
+                rc_builtin_init_addr,
+                bitwise_init_var_pool_addr, // +1
+                bitwise_step_var_pool_addr,
+                bitwise_x_or_y_addr,
+                bitwise_next_var_pool_addr,
+                bitwise_partition,
+                bitwise_or_is_and_plus_xor,
+                bitwise_addition_is_xor_with_and,
+                bitwise_unique_unpacking192,
+                bitwise_unique_unpacking193,
+                bitwise_unique_unpacking194,
+                bitwise_unique_unpacking195,
+            ])
+            .map(Constraint::new)
+            .collect()
     }
 
     fn composition_constraint(
@@ -1215,52 +683,657 @@ impl ministark::air::AirConfig for AirConfig {
         let initial_rc_address = rc_segment.begin_addr.into();
         let initial_bitwise_address = bitwise_segment.begin_addr.into();
 
-        let memory_quotient =
-            utils::compute_public_memory_quotient::<PUBLIC_MEMORY_STEP, Self::Fp, Self::Fq>(
-                challenges[MemoryPermutation::Z],
-                challenges[MemoryPermutation::A],
-                trace_len,
-                &execution_info.public_memory,
-                execution_info.public_memory_padding(),
-            );
-
-        let diluted_cumulative_val = compute_diluted_cumulative_value::<
-            Fp,
-            Fp,
-            DILUTED_CHECK_N_BITS,
-            DILUTED_CHECK_SPACING,
-        >(
-            challenges[DilutedCheckAggregation::Z],
-            challenges[DilutedCheckAggregation::A],
-        );
-
-        // TODO: add validation on the AirPublicInput struct
-        // assert!(range_check_min <= range_check_max);
-        let initial_ap = execution_info.initial_ap().into();
-        let final_ap = execution_info.final_ap().into();
-        let initial_pc = execution_info.initial_pc().into();
-        let final_pc = execution_info.final_pc().into();
-
-        Hints::new(vec![
-            (InitialAp.index(), initial_ap),
-            (InitialPc.index(), initial_pc),
-            (FinalAp.index(), final_ap),
-            (FinalPc.index(), final_pc),
-            // TODO: this is a wrong value. Must fix
-            (MemoryQuotient.index(), memory_quotient),
-            (RangeCheckProduct.index(), Fp::ONE),
-            (RangeCheckMin.index(), execution_info.rc_min.into()),
-            (RangeCheckMax.index(), execution_info.rc_max.into()),
-            (DilutedCheckProduct.index(), Fp::ONE),
-            (DilutedCheckFirst.index(), Fp::ZERO),
-            (DilutedCheckCumulativeValue.index(), diluted_cumulative_val),
+        let mut hints =
+            cpu_hints::<PUBLIC_MEMORY_STEP, Fp, Fp>(trace_len, execution_info, challenges);
+        hints.extend(diluted_check_hints::<Fp, Fp>(challenges));
+        hints.extend([
             (InitialPedersenAddr.index(), initial_perdersen_address),
             (InitialRcAddr.index(), initial_rc_address),
             (InitialBitwiseAddr.index(), initial_bitwise_address),
-        ])
+        ]);
+        Hints::new(hints)
     }
 }
 
+/// Constraints for the CPU, memory and 16-bit range check components. These
+/// occupy the same cells in [crate::recursive_generic] and [crate::dynamic] so
+/// the layouts share them and only differ in their builtin constraints. Public
+/// memory cells are every `PUBLIC_MEMORY_STEP` rows.
+pub(crate) fn cpu_constraints<
+    const PUBLIC_MEMORY_STEP: usize,
+    Fp: GpuFftField + PrimeField,
+    Fq: StarkExtensionOf<Fp>,
+>(
+    trace_len: usize,
+) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>> {
+    use AlgebraicItem::*;
+    use PublicInputHint::*;
+    let n = trace_len;
+    let trace_domain = Radix2EvaluationDomain::<Fp>::new(n).unwrap();
+    let g = trace_domain.group_gen();
+    assert!(n >= CYCLE_HEIGHT, "must be a multiple of cycle height");
+    let x = Expr::from(X);
+    let one = Expr::from(Constant(FieldVariant::Fp(Fp::ONE)));
+    let two = Expr::from(Constant(FieldVariant::Fp(Fp::from(2u32))));
+    let four = Expr::from(Constant(FieldVariant::Fp(Fp::from(4u32))));
+    let offset_size = Expr::from(Constant(FieldVariant::Fp(Fp::from(2u32.pow(16)))));
+    let half_offset_size = Expr::from(Constant(FieldVariant::Fp(Fp::from(2u32.pow(15)))));
+
+    // cpu/decode/flag_op1_base_op0_0
+    let cpu_decode_flag_op1_base_op0_0: Expr<AlgebraicItem<FieldVariant<Fp, Fq>>> =
+        &one - (Flag::Op1Imm.curr() + Flag::Op1Ap.curr() + Flag::Op1Fp.curr());
+    // cpu/decode/flag_res_op1_0
+    let cpu_decode_flag_res_op1_0: Expr<AlgebraicItem<FieldVariant<Fp, Fq>>> =
+        &one - (Flag::ResAdd.curr() + Flag::ResMul.curr() + Flag::PcJnz.curr());
+    // cpu/decode/flag_pc_update_regular_0
+    let cpu_decode_flag_pc_update_regular_0: Expr<AlgebraicItem<FieldVariant<Fp, Fq>>> =
+        &one - (Flag::PcJumpAbs.curr() + Flag::PcJumpRel.curr() + Flag::PcJnz.curr());
+    // cpu/decode/fp_update_regular_0
+    let cpu_decode_fp_update_regular_0: Expr<AlgebraicItem<FieldVariant<Fp, Fq>>> =
+        &one - (Flag::OpcodeCall.curr() + Flag::OpcodeRet.curr());
+
+    // NOTE: npc_reg_0 = pc + instruction_size
+    // NOTE: instruction_size = fOP1_IMM + 1
+    let npc_reg_0 = Npc::Pc.curr() + Flag::Op1Imm.curr() + &one;
+
+    let memory_address_diff_0: Expr<AlgebraicItem<FieldVariant<Fp, Fq>>> =
+        Mem::Address.next() - Mem::Address.curr();
+
+    let rc16_diff_0: Expr<AlgebraicItem<FieldVariant<Fp, Fq>>> =
+        RangeCheck::Ordered.next() - RangeCheck::Ordered.curr();
+
+    // example for trace length n=64
+    // =============================
+    // x^(n/16)                 = (x - ω_0)(x - ω_16)(x - ω_32)(x - ω_48)
+    // x^(n/16) - c             = (x - c*ω_0)(x - c*ω_16)(x - c*ω_32)(x - c*ω_48)
+    // x^(n/16) - ω^(n/16)      = (x - ω_1)(x - ω_17)(x - ω_33)(x - )
+    // x^(n/16) - ω^(n/16)^(15) = (x - ω_15)(x - ω_31)(x - ω_47)(x - ω_6ω_493)
+    let flag0_offset = FieldVariant::Fp(g.pow([(Flag::Zero as usize * n / CYCLE_HEIGHT) as u64]));
+    let flag0_zerofier = X.pow(n / CYCLE_HEIGHT) - Constant(flag0_offset);
+    let every_row_zerofier = X.pow(n) - &one;
+    let every_row_zerofier_inv = &one / &every_row_zerofier;
+    let flags_zerofier_inv = &flag0_zerofier * &every_row_zerofier_inv;
+
+    // check decoded flag values are 0 or 1
+    // NOTE: This expression is a bit confusing. The zerofier forces this constraint
+    // to apply in all rows of the trace therefore it applies to all flags (not just
+    // DstReg). Funnily enough any flag here would work (it just wouldn't be SHARP
+    // compatible).
+    let cpu_decode_opcode_rc_b =
+        (Flag::DstReg.curr() * Flag::DstReg.curr() - Flag::DstReg.curr()) * &flags_zerofier_inv;
+
+    // The first word of each instruction:
+    // ┌─────────────────────────────────────────────────────────────────────────┐
+    // │                     off_dst (biased representation)                     │
+    // ├─────────────────────────────────────────────────────────────────────────┤
+    // │                     off_op0 (biased representation)                     │
+    // ├─────────────────────────────────────────────────────────────────────────┤
+    // │                     off_op1 (biased representation)                     │
+    // ├─────┬─────┬───────┬───────┬───────────┬────────┬───────────────────┬────┤
+    // │ dst │ op0 │  op1  │  res  │    pc     │   ap   │      opcode       │ 0  │
+    // │ reg │ reg │  src  │ logic │  update   │ update │                   │    │
+    // ├─────┼─────┼───┬───┼───┬───┼───┬───┬───┼───┬────┼────┬────┬────┬────┼────┤
+    // │  0  │  1  │ 2 │ 3 │ 4 │ 5 │ 6 │ 7 │ 8 │ 9 │ 10 │ 11 │ 12 │ 13 │ 14 │ 15 │
+    // └─────┴─────┴───┴───┴───┴───┴───┴───┴───┴───┴────┴────┴────┴────┴────┴────┘
+    let whole_flag_prefix = Expr::from(Trace(0, 0));
+    // NOTE: Forces the `0` flag prefix to =0 in every cycle.
+    let cpu_decode_opcode_rc_zero = &whole_flag_prefix / flag0_zerofier;
+
+    // force constraint to apply every 16 trace rows (every cairo cycle)
+    // e.g. (x - ω_0)(x - ω_16)(x - ω_32)(x - ω_48) for n=64
+    let all_cycles_zerofier = X.pow(n / CYCLE_HEIGHT) - &one;
+    let all_cycles_zerofier_inv = &one / all_cycles_zerofier;
+    let cpu_decode_opcode_rc_input = (Npc::Instruction.curr()
+        - (((&whole_flag_prefix * &offset_size + RangeCheck::OffOp1.curr()) * &offset_size
+            + RangeCheck::OffOp0.curr())
+            * &offset_size
+            + RangeCheck::OffDst.curr()))
+        * &all_cycles_zerofier_inv;
+
+    // constraint for the Op1Src flag group - forces vals 000, 100, 010 or 001
+    let cpu_decode_flag_op1_base_op0_bit = (&cpu_decode_flag_op1_base_op0_0
+        * &cpu_decode_flag_op1_base_op0_0
+        - &cpu_decode_flag_op1_base_op0_0)
+        * &all_cycles_zerofier_inv;
+
+    // forces only one or none of ResAdd, ResMul or PcJnz to be 1
+    // TODO: Why the F is PcJnz in here? Res flag group is only bit 5 and 6
+    // NOTE: looks like it's a handy optimization to calculate next_fp and next_ap
+    let cpu_decode_flag_res_op1_bit = (&cpu_decode_flag_res_op1_0 * &cpu_decode_flag_res_op1_0
+        - &cpu_decode_flag_res_op1_0)
+        * &all_cycles_zerofier_inv;
+
+    // constraint forces PcUpdate flag to be 000, 100, 010 or 001
+    let cpu_decode_flag_pc_update_regular_bit = (&cpu_decode_flag_pc_update_regular_0
+        * &cpu_decode_flag_pc_update_regular_0
+        - &cpu_decode_flag_pc_update_regular_0)
+        * &all_cycles_zerofier_inv;
+
+    // forces max only OpcodeRet or OpcodeAssertEq to be 1
+    // TODO: why OpcodeCall not included? that would make whole flag group
+    let cpu_decode_fp_update_regular_bit = (&cpu_decode_fp_update_regular_0
+        * &cpu_decode_fp_update_regular_0
+        - &cpu_decode_fp_update_regular_0)
+        * &all_cycles_zerofier_inv;
+
+    // cpu/operands/mem_dst_addr
+    // NOTE: Pseudo code from cairo whitepaper
+    // ```
+    // if dst_reg == 0:
+    //     dst = m(ap + offdst)
+    // else:
+    //     dst = m(fp + offdst)
+    // ```
+    // NOTE: Trace(5, 8) dest mem address
+    let cpu_operands_mem_dst_addr = (Npc::MemDstAddr.curr() + &half_offset_size
+        - (Flag::DstReg.curr() * Auxiliary::Fp.curr()
+            + (&one - Flag::DstReg.curr()) * Auxiliary::Ap.curr()
+            + RangeCheck::OffDst.curr()))
+        * &all_cycles_zerofier_inv;
+
+    // whitepaper pseudocode
+    // ```
+    // # Compute op0.
+    // if op0_reg == 0:
+    //     op0 = m(-->>ap + offop0<<--)
+    // else:
+    //     op0 = m(-->>fp + offop0<<--)
+    // ```
+    // NOTE: StarkEx contracts as: cpu_operands_mem0_addr
+    let cpu_operands_mem_op0_addr = (Npc::MemOp0Addr.curr() + &half_offset_size
+        - (Flag::Op0Reg.curr() * Auxiliary::Fp.curr()
+            + (&one - Flag::Op0Reg.curr()) * Auxiliary::Ap.curr()
+            + RangeCheck::OffOp0.curr()))
+        * &all_cycles_zerofier_inv;
+
+    // NOTE: StarkEx contracts as: cpu_operands_mem1_addr
+    let cpu_operands_mem_op1_addr = (Npc::MemOp1Addr.curr() + &half_offset_size
+        - (Flag::Op1Imm.curr() * Npc::Pc.curr()
+            + Flag::Op1Ap.curr() * Auxiliary::Ap.curr()
+            + Flag::Op1Fp.curr() * Auxiliary::Fp.curr()
+            + &cpu_decode_flag_op1_base_op0_0 * Npc::MemOp0.curr()
+            + RangeCheck::OffOp1.curr()))
+        * &all_cycles_zerofier_inv;
+
+    // op1 * op0
+    // NOTE: starkex cpu/operands/ops_mul
+    let cpu_operands_ops_mul = (Auxiliary::Op0MulOp1.curr()
+        - Npc::MemOp0.curr() * Npc::MemOp1.curr())
+        * &all_cycles_zerofier_inv;
+
+    // From cairo whitepaper
+    // ```
+    // # Compute res.
+    // if pc_update == 4:
+    //     if res_logic == 0 && opcode == 0 && ap_update != 1:
+    //         res = Unused
+    //     else:
+    //         Undefined Behavior
+    // else if pc_update = 0, 1 or 2:
+    //     switch res_logic:
+    //         case 0: res = op1
+    //         case 1: res = op0 + op1
+    //         case 2: res = op0 * op1
+    //         default: Undefined Behavior
+    // else: Undefined Behavior
+    // ```
+    // NOTE: this constraint only handles:
+    // ```
+    // else if pc_update = 0, 1 or 2:
+    //   switch res_logic:
+    //     case 0: res = op1
+    //     case 1: res = op0 + op1
+    //     case 2: res = op0 * op1
+    // ```
+    let cpu_operands_res = ((&one - Flag::PcJnz.curr()) * Auxiliary::Res.curr()
+        - (Flag::ResAdd.curr() * (Npc::MemOp0.curr() + Npc::MemOp1.curr())
+            + Flag::ResMul.curr() * Auxiliary::Op0MulOp1.curr()
+            + &cpu_decode_flag_res_op1_0 * Npc::MemOp1.curr()))
+        * &all_cycles_zerofier_inv;
+
+    // example for trace length n=64
+    // =============================
+    // all_cycles_zerofier              = (x - ω_0)(x - ω_16)(x - ω_32)(x - ω_48)
+    // X - ω^(16*(n/16 - 1))            = x - ω^n/w^16 = x - 1/w_16 = x - w_48
+    // (X - w_48) / all_cycles_zerofier = (x - ω_0)(x - ω_16)(x - ω_32)
+    let last_cycle_zerofier = X - Constant(FieldVariant::Fp(
+        g.pow([(CYCLE_HEIGHT * (n / CYCLE_HEIGHT - 1)) as u64]),
+    ));
+    let last_cycle_zerofier_inv = &one / &last_cycle_zerofier;
+    let all_cycles_except_last_zerofier_inv = &last_cycle_zerofier * &all_cycles_zerofier_inv;
+
+    // Updating the program counter
+    // ============================
+    // This is not as straight forward as the other constraints. Read section 9.5
+    // Updating pc to understand.
+
+    // from whitepaper `t0 = fPC_JNZ * dst`
+    let cpu_update_registers_update_pc_tmp0 = (Auxiliary::Tmp0.curr()
+        - Flag::PcJnz.curr() * Npc::MemDst.curr())
+        * &all_cycles_except_last_zerofier_inv;
+
+    // From the whitepaper "To verify that we make a regular update if dst = 0, we
+    // need an auxiliary variable, v (to fill the trace in the case dst != 0, set v
+    // = dst^(−1)): `fPC_JNZ * (dst * v − 1) * (next_pc − (pc + instruction_size)) =
+    // 0` NOTE: if fPC_JNZ=1 then `res` is "unused" and repurposed as our
+    // temporary variable `v`. The value assigned to v is `dst^(−1)`.
+    // NOTE: `t1 = t0 * v`
+    let cpu_update_registers_update_pc_tmp1 = (Auxiliary::Tmp1.curr()
+        - Auxiliary::Tmp0.curr() * Auxiliary::Res.curr())
+        * &all_cycles_except_last_zerofier_inv;
+
+    // There are two constraints here bundled in one. The first is `t0 * (next_pc −
+    // (pc + op1)) = 0` (ensures if dst != 0 a relative jump is made) and the second
+    // is `(1−fPC_JNZ) * next_pc - (regular_update * (pc + instruction_size) +
+    // fPC_JUMP_ABS * res + fPC_JUMP_REL * (pc + res)) = 0` (handles update except
+    // for jnz). Note that due to the flag group constraints for PcUpdate if jnz=1
+    // then the second constraint is trivially 0=0 and if jnz=0 then the first
+    // constraint is trivially 0=0. For this reason we can bundle these constraints
+    // into one.
+    // TODO: fix padding bug
+    let cpu_update_registers_update_pc_pc_cond_negative = ((&one - Flag::PcJnz.curr())
+        * Npc::Pc.next()
+        + Auxiliary::Tmp0.curr() * (Npc::Pc.next() - (Npc::Pc.curr() + Npc::MemOp1.curr()))
+        - (&cpu_decode_flag_pc_update_regular_0 * &npc_reg_0
+            + Flag::PcJumpAbs.curr() * Auxiliary::Res.curr()
+            + Flag::PcJumpRel.curr() * (Npc::Pc.curr() + Auxiliary::Res.curr())))
+        * &all_cycles_except_last_zerofier_inv;
+
+    // ensure `if dst == 0: pc + instruction_size == next_pc`
+    let cpu_update_registers_update_pc_pc_cond_positive =
+        ((Auxiliary::Tmp1.curr() - Flag::PcJnz.curr()) * (Npc::Pc.next() - npc_reg_0))
+            * &all_cycles_except_last_zerofier_inv;
+
+    // Updating the allocation pointer
+    // ===============================
+    // TODO: seems fishy don't see how `next_ap = ap + fAP_ADD · res + fAP_ADD1 · 1
+    // + fOPCODE_CALL · 2` meets the pseudo code in the whitepaper
+    // Ok, it does kinda make sense. move the `opcode == 1` statement inside and
+    // move the switch to the outside and it's more clear.
+    let cpu_update_registers_update_ap_ap_update = (Auxiliary::Ap.next()
+        - (Auxiliary::Ap.curr()
+            + Flag::ApAdd.curr() * Auxiliary::Res.curr()
+            + Flag::ApAdd1.curr()
+            + Flag::OpcodeCall.curr() * &two))
+        * &all_cycles_except_last_zerofier_inv;
+
+    // Updating the frame pointer
+    // ==========================
+    // This handles all fp update except the `op0 == pc + instruction_size`, `res =
+    // dst` and `dst == fp` assertions.
+    // TODO: fix padding bug
+    let cpu_update_registers_update_fp_fp_update = (Auxiliary::Fp.next()
+        - (&cpu_decode_fp_update_regular_0 * Auxiliary::Fp.curr()
+            + Flag::OpcodeRet.curr() * Npc::MemDst.curr()
+            + Flag::OpcodeCall.curr() * (Auxiliary::Ap.curr() + &two)))
+        * &all_cycles_except_last_zerofier_inv;
+
+    // push registers to memory (see section 8.4 in the whitepaper).
+    // These are essentially the assertions for assert `op0 == pc +
+    // instruction_size` and `assert dst == fp`.
+    let cpu_opcodes_call_push_fp = (Flag::OpcodeCall.curr()
+        * (Npc::MemDst.curr() - Auxiliary::Fp.curr()))
+        * &all_cycles_zerofier_inv;
+    let cpu_opcodes_call_push_pc = (Flag::OpcodeCall.curr()
+        * (Npc::MemOp0.curr() - (Npc::Pc.curr() + Flag::Op1Imm.curr() + &one)))
+        * &all_cycles_zerofier_inv;
+
+    // make sure all offsets are valid for the call opcode
+    // ===================================================
+    // checks `if opcode == OpcodeCall: assert off_dst = 2^15`
+    // this is supplementary to the constraints above because
+    // offsets are in the range [-2^15, 2^15) encoded using
+    // biased representation
+    let cpu_opcodes_call_off0 = (Flag::OpcodeCall.curr()
+        * (RangeCheck::OffDst.curr() - &half_offset_size))
+        * &all_cycles_zerofier_inv;
+    // checks `if opcode == OpcodeCall: assert off_op0 = 2^15 + 1`
+    // TODO: why +1?
+    let cpu_opcodes_call_off1 = (Flag::OpcodeCall.curr()
+        * (RangeCheck::OffOp0.curr() - (&half_offset_size + &one)))
+        * &all_cycles_zerofier_inv;
+    // TODO: I don't understand this one - Flag::OpcodeCall.curr() is 0 or 1. Why
+    // not just replace `Flag::OpcodeCall.curr() + Flag::OpcodeCall.curr() +
+    // &one + &one` with `4`
+    let cpu_opcodes_call_flags = (Flag::OpcodeCall.curr()
+        * (Flag::OpcodeCall.curr() + Flag::OpcodeCall.curr() + &one + &one
+            - (Flag::DstReg.curr() + Flag::Op0Reg.curr() + &four)))
+        * &all_cycles_zerofier_inv;
+    // checks `if opcode == OpcodeRet: assert off_dst = 2^15 - 2`
+    // TODO: why -2 🤯? Instruction size?
+    let cpu_opcodes_ret_off0 = (Flag::OpcodeRet.curr()
+        * (RangeCheck::OffDst.curr() + &two - &half_offset_size))
+        * &all_cycles_zerofier_inv;
+    // checks `if opcode == OpcodeRet: assert off_op1 = 2^15 - 1`
+    // TODO: why -1?
+    let cpu_opcodes_ret_off2 = (Flag::OpcodeRet.curr()
+        * (RangeCheck::OffOp1.curr() + &one - &half_offset_size))
+        * &all_cycles_zerofier_inv;
+    // checks `if OpcodeRet: assert PcJumpAbs=1, DstReg=1, Op1Fp=1, ResLogic=0`
+    let cpu_opcodes_ret_flags = (Flag::OpcodeRet.curr()
+        * (Flag::PcJumpAbs.curr()
+            + Flag::DstReg.curr()
+            + Flag::Op1Fp.curr()
+            + &cpu_decode_flag_res_op1_0
+            - &four))
+        * &all_cycles_zerofier_inv;
+    // handles the "assert equal" instruction. Represents this pseudo code from the
+    // whitepaper `assert res = dst`.
+    let cpu_opcodes_assert_eq_assert_eq = (Flag::OpcodeAssertEq.curr()
+        * (Npc::MemDst.curr() - Auxiliary::Res.curr()))
+        * &all_cycles_zerofier_inv;
+
+    let first_row_zerofier = &x - &one;
+    let first_row_zerofier_inv = &one / first_row_zerofier;
+
+    // boundary constraint expression for initial registers
+    let initial_ap = (Auxiliary::Ap.curr() - InitialAp.hint()) * &first_row_zerofier_inv;
+    let initial_fp = (Auxiliary::Fp.curr() - InitialAp.hint()) * &first_row_zerofier_inv;
+    let initial_pc = (Npc::Pc.curr() - InitialPc.hint()) * &first_row_zerofier_inv;
+
+    // boundary constraint expression for final registers
+    let final_ap = (Auxiliary::Ap.curr() - FinalAp.hint()) * &last_cycle_zerofier_inv;
+    let final_fp = (Auxiliary::Fp.curr() - InitialAp.hint()) * &last_cycle_zerofier_inv;
+    let final_pc = (Npc::Pc.curr() - FinalPc.hint()) * &last_cycle_zerofier_inv;
+
+    // examples for trace length n=8
+    // =============================
+    // x^(n/2) - 1             = (x - ω_0)(x - ω_2)(x - ω_4)(x - ω_6)
+    // x - ω^(2*(n/2 - 1))     = x - ω^n/w^2 = x - 1/w_2 = x - w_6
+    // (x - w_6) / x^(n/2) - 1 = (x - ω_0)(x - ω_2)(x - ω_4)
+    let every_second_row_zerofier = X.pow(n / 2) - &one;
+    let second_last_row_zerofier = X - Constant(FieldVariant::Fp(g.pow([2 * (n as u64 / 2 - 1)])));
+    let every_second_row_except_last_zerofier_inv =
+        &second_last_row_zerofier / &every_second_row_zerofier;
+
+    // Memory access constraints
+    // =========================
+    // All these constraints make more sense once you understand how the permutation
+    // column is calculated (look at get_ordered_memory_accesses()). Sections 9.8
+    // and 9.7 of the Cairo paper justify these constraints.
+    // memory permutation boundary constraint
+    let memory_multi_column_perm_perm_init0 = ((MemoryPermutation::Z.challenge()
+        - (Mem::Address.curr() + MemoryPermutation::A.challenge() * Mem::Value.curr()))
+        * Permutation::Memory.curr()
+        + Npc::Pc.curr()
+        + MemoryPermutation::A.challenge() * Npc::Instruction.curr()
+        - MemoryPermutation::Z.challenge())
+        * &first_row_zerofier_inv;
+    // memory permutation transition constraint
+    // NOTE: memory entries are stacked in the trace like so:
+    // ┌─────┬───────────┬─────┐
+    // │ ... │    ...    │ ... │
+    // ├─────┼───────────┼─────┤
+    // │ ... │ address 0 │ ... │
+    // ├─────┼───────────┼─────┤
+    // │ ... │  value 0  │ ... │
+    // ├─────┼───────────┼─────┤
+    // │ ... │ address 1 │ ... │
+    // ├─────┼───────────┼─────┤
+    // │ ... │  value 1  │ ... │
+    // ├─────┼───────────┼─────┤
+    // │ ... │    ...    │ ... │
+    // └─────┴───────────┴─────┘
+    let memory_multi_column_perm_perm_step0 = ((MemoryPermutation::Z.challenge()
+        - (Mem::Address.next() + MemoryPermutation::A.challenge() * Mem::Value.next()))
+        * Permutation::Memory.next()
+        - (MemoryPermutation::Z.challenge()
+            - (Npc::PubMemAddr.curr() + MemoryPermutation::A.challenge() * Npc::PubMemVal.curr()))
+            * Permutation::Memory.curr())
+        * &every_second_row_except_last_zerofier_inv;
+    // Check the last permutation value to verify public memory
+    let memory_multi_column_perm_perm_last =
+        (Permutation::Memory.curr() - MemoryQuotient.hint()) / &second_last_row_zerofier;
+    // Constraint expression for memory/diff_is_bit
+    // checks the address doesn't change or increases by 1
+    // "Continuity" constraint in cairo whitepaper 9.7.2
+    let memory_diff_is_bit = (&memory_address_diff_0 * &memory_address_diff_0
+        - &memory_address_diff_0)
+        * &every_second_row_except_last_zerofier_inv;
+    // if the address stays the same then the value stays the same
+    // "Single-valued" constraint in cairo whitepaper 9.7.2.
+    // cairo uses nondeterministic read-only memory so if the address is the same
+    // the value should also stay the same.
+    let memory_is_func = ((&memory_address_diff_0 - &one)
+        * (Mem::Value.curr() - Mem::Value.next()))
+        * &every_second_row_except_last_zerofier_inv;
+    // boundary condition stating the first memory address == 1
+    let memory_initial_addr = (Mem::Address.curr() - &one) * &first_row_zerofier_inv;
+    // applies every `PUBLIC_MEMORY_STEP` rows
+    // Read cairo whitepaper section 9.8 as to why the public memory cells are 0.
+    // The high level is that the way public memory works is that the prover is
+    // forced (with these constraints) to exclude the public memory from one of
+    // the permutation products. This means the running permutation column
+    // terminates with more-or-less the permutation of just the public input. The
+    // verifier can relatively cheaply calculate this terminal. The constraint for
+    // this terminal is `memory_multi_column_perm_perm_last`.
+    let public_memory_zerofier_inv = &one / (X.pow(n / PUBLIC_MEMORY_STEP) - &one);
+    let public_memory_addr_zero = Npc::PubMemAddr.curr() * &public_memory_zerofier_inv;
+    let public_memory_value_zero = Npc::PubMemVal.curr() * &public_memory_zerofier_inv;
+
+    // examples for trace length n=16
+    // =====================================
+    // x^(n/4) - 1              = (x - ω_0)(x - ω_4)(x - ω_8)(x - ω_12)
+    // x - ω^(4*(n/4 - 1))      = x - ω^n/w^4 = x - 1/w_4 = x - w_12
+    // (x - w_12) / x^(n/4) - 1 = (x - ω_0)(x - ω_4)(x - ω_8)
+    let every_fourth_row_zerofier_inv = &one / (X.pow(n / 4) - &one);
+    let fourth_last_row_zerofier = X - Constant(FieldVariant::Fp(g.pow([4 * (n as u64 / 4 - 1)])));
+    let fourth_last_row_zerofier_inv = &one / &fourth_last_row_zerofier;
+    let every_fourth_row_except_last_zerofier_inv =
+        &fourth_last_row_zerofier * &every_fourth_row_zerofier_inv;
+
+    // Range check constraints
+    // =======================
+    // Look at memory to understand the general approach to permutation.
+    // More info in section 9.9 of the Cairo paper.
+    let rc16_perm_init0 = ((RangeCheckPermutation::Z.challenge() - RangeCheck::Ordered.curr())
+        * Permutation::RangeCheck.curr()
+        + RangeCheck::OffDst.curr()
+        - RangeCheckPermutation::Z.challenge())
+        * &first_row_zerofier_inv;
+    let rc16_perm_step0 = ((RangeCheckPermutation::Z.challenge() - RangeCheck::Ordered.next())
+        * Permutation::RangeCheck.next()
+        - (RangeCheckPermutation::Z.challenge() - RangeCheck::OffOp1.curr())
+            * Permutation::RangeCheck.curr())
+        * &every_fourth_row_except_last_zerofier_inv;
+    let rc16_perm_last =
+        (Permutation::RangeCheck.curr() - RangeCheckProduct.hint()) * &fourth_last_row_zerofier_inv;
+    // Check the value increases by 0 or 1
+    let rc16_diff_is_bit =
+        (&rc16_diff_0 * &rc16_diff_0 - &rc16_diff_0) * &every_fourth_row_except_last_zerofier_inv;
+    // Prover sends the minimim and maximum as a public input.
+    // Verifier checks the RC min and max fall within [0, 2^16).
+    let rc16_minimum =
+        (RangeCheck::Ordered.curr() - RangeCheckMin.hint()) * &first_row_zerofier_inv;
+    let rc16_maximum =
+        (RangeCheck::Ordered.curr() - RangeCheckMax.hint()) * &fourth_last_row_zerofier_inv;
+
+    vec![
+        cpu_decode_opcode_rc_b, // +1
+        cpu_decode_opcode_rc_zero,
+        cpu_decode_opcode_rc_input,
+        cpu_decode_flag_op1_base_op0_bit,
+        cpu_decode_flag_res_op1_bit,
+        cpu_decode_flag_pc_update_regular_bit,
+        cpu_decode_fp_update_regular_bit,
+        cpu_operands_mem_dst_addr,
+        cpu_operands_mem_op0_addr,
+        cpu_operands_mem_op1_addr,
+        cpu_operands_ops_mul,
+        cpu_operands_res,
+        cpu_update_registers_update_pc_tmp0,
+        cpu_update_registers_update_pc_tmp1,
+        cpu_update_registers_update_pc_pc_cond_negative,
+        cpu_update_registers_update_pc_pc_cond_positive,
+        cpu_update_registers_update_ap_ap_update,
+        cpu_update_registers_update_fp_fp_update,
+        cpu_opcodes_call_push_fp,
+        cpu_opcodes_call_push_pc,
+        cpu_opcodes_call_off0,
+        cpu_opcodes_call_off1,
+        cpu_opcodes_call_flags,
+        cpu_opcodes_ret_off0,
+        cpu_opcodes_ret_off2,
+        cpu_opcodes_ret_flags,
+        cpu_opcodes_assert_eq_assert_eq,
+        initial_ap,
+        initial_fp,
+        initial_pc,
+        final_ap,
+        final_fp,
+        final_pc,
+        memory_multi_column_perm_perm_init0,
+        memory_multi_column_perm_perm_step0,
+        memory_multi_column_perm_perm_last,
+        memory_diff_is_bit,
+        memory_is_func,
+        memory_initial_addr,
+        public_memory_addr_zero,
+        public_memory_value_zero,
+        rc16_perm_init0,
+        rc16_perm_step0,
+        rc16_perm_last,
+        rc16_diff_is_bit,
+        rc16_minimum,
+        rc16_maximum,
+    ]
+}
+
+/// Constraints for the diluted check component. Only needed by layouts with
+/// builtins that use the diluted pool.
+pub(crate) fn diluted_check_constraints<Fp: GpuFftField + PrimeField, Fq: StarkExtensionOf<Fp>>(
+    trace_len: usize,
+) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>> {
+    use AlgebraicItem::*;
+    use PublicInputHint::*;
+    let n = trace_len;
+    let trace_domain = Radix2EvaluationDomain::<Fp>::new(n).unwrap();
+    let g = trace_domain.group_gen();
+    let x = Expr::from(X);
+    let one = Expr::from(Constant(FieldVariant::Fp(Fp::ONE)));
+    let first_row_zerofier_inv = &one / (&x - &one);
+    let every_row_zerofier = X.pow(n) - &one;
+
+    // Diluted Check constraints
+    // =========================
+    // A "dilution" is spreading out of the bits in a number.
+    // Dilutions have two parameters (1) the number of bits they operate on and
+    // (2) the spread of each bit. For example the the dilution of binary
+    // digit 1111 to 0001000100010001 operates on 4 bits with a spread of 4.
+    let diluted_check_permutation_init0 = ((DilutedCheckPermutation::Z.challenge()
+        - DilutedCheck::Ordered.curr())
+        * Permutation::DilutedCheck.curr()
+        + DilutedCheck::Unordered.curr()
+        - DilutedCheckPermutation::Z.challenge())
+        * &first_row_zerofier_inv;
+
+    // Diluted checks operate every row (16 times per cycle)
+    let last_row_zerofier = X - Constant(FieldVariant::Fp(g.pow([n as u64 - 1])));
+    let last_row_zerofier_inv = &one / &last_row_zerofier;
+    let every_row_except_last_zerofier_inv = &last_row_zerofier * &one / &every_row_zerofier;
+    // we have an out-of-order and in-order list of diluted values for this layout
+    // (starknet). We want to check each list is a permutation of one another
+    let diluted_check_permutation_step0 = ((DilutedCheckPermutation::Z.challenge()
+        - DilutedCheck::Ordered.next())
+        * Permutation::DilutedCheck.next()
+        - (DilutedCheckPermutation::Z.challenge() - DilutedCheck::Unordered.next())
+            * Permutation::DilutedCheck.curr())
+        * &every_row_except_last_zerofier_inv;
+    let diluted_check_permutation_last =
+        (Permutation::DilutedCheck.curr() - DilutedCheckProduct.hint()) * &last_row_zerofier_inv;
+
+    // Initial aggregate value should be =1
+    let diluted_check_init = (DilutedCheck::Aggregate.curr() - &one) * &first_row_zerofier_inv;
+
+    // Check first, in-order, diluted value
+    let diluted_check_first_element =
+        (DilutedCheck::Ordered.curr() - DilutedCheckFirst.hint()) * &first_row_zerofier_inv;
+
+    // TODO: add more docs
+    // `diluted_diff` is related to `u` in `compute_diluted_cumulative_value`
+    // Note that if there is no difference between the current and next ordered
+    // diluted values then `diluted_diff == 0` and the previous aggregate value is
+    // copied over
+    let diluted_diff: Expr<AlgebraicItem<FieldVariant<Fp, Fq>>> =
+        DilutedCheck::Ordered.next() - DilutedCheck::Ordered.curr();
+    let diluted_check_step = (DilutedCheck::Aggregate.next()
+        - (DilutedCheck::Aggregate.curr()
+            * (&one + DilutedCheckAggregation::Z.challenge() * &diluted_diff)
+            + DilutedCheckAggregation::A.challenge() * &diluted_diff * diluted_diff))
+        * &every_row_except_last_zerofier_inv;
+
+    // Check the last cumulative value.
+    // NOTE: This can be calculated efficiently by the verifier.
+    let diluted_check_last = (DilutedCheck::Aggregate.curr() - DilutedCheckCumulativeValue.hint())
+        * &last_row_zerofier_inv;
+
+    vec![
+        diluted_check_permutation_init0,
+        diluted_check_permutation_step0,
+        diluted_check_permutation_last,
+        diluted_check_init,
+        diluted_check_first_element,
+        diluted_check_step,
+        diluted_check_last,
+    ]
+}
+
+/// Hints for the public inputs of [cpu_constraints]. Layouts add the hints of
+/// [diluted_check_hints] and then their builtins after these.
+pub(crate) fn cpu_hints<
+    const PUBLIC_MEMORY_STEP: usize,
+    Fp: GpuFftField + PrimeField,
+    Fq: StarkExtensionOf<Fp>,
+>(
+    trace_len: usize,
+    execution_info: &AirPublicInput<Fp>,
+    challenges: &Challenges<Fq>,
+) -> Vec<(usize, Fq)> {
+    use PublicInputHint::*;
+
+    let memory_quotient = utils::compute_public_memory_quotient::<PUBLIC_MEMORY_STEP, Fp, Fq>(
+        challenges[MemoryPermutation::Z],
+        challenges[MemoryPermutation::A],
+        trace_len,
+        execution_info,
+    );
+
+    let initial_ap = execution_info.initial_ap().into();
+    let final_ap = execution_info.final_ap().into();
+    let initial_pc = execution_info.initial_pc().into();
+    let final_pc = execution_info.final_pc().into();
+
+    vec![
+        (InitialAp.index(), initial_ap),
+        (InitialPc.index(), initial_pc),
+        (FinalAp.index(), final_ap),
+        (FinalPc.index(), final_pc),
+        (MemoryQuotient.index(), memory_quotient),
+        (RangeCheckProduct.index(), Fq::ONE),
+        (RangeCheckMin.index(), execution_info.rc_min.into()),
+        (RangeCheckMax.index(), execution_info.rc_max.into()),
+    ]
+}
+
+/// Hints for the public inputs of [diluted_check_constraints]
+pub(crate) fn diluted_check_hints<Fp: GpuFftField + PrimeField, Fq: StarkExtensionOf<Fp>>(
+    challenges: &Challenges<Fq>,
+) -> Vec<(usize, Fq)> {
+    use PublicInputHint::*;
+
+    let diluted_cumulative_val =
+        compute_diluted_cumulative_value::<Fp, Fq, DILUTED_CHECK_N_BITS, DILUTED_CHECK_SPACING>(
+            challenges[DilutedCheckAggregation::Z],
+            challenges[DilutedCheckAggregation::A],
+        );
+
+    vec![
+        (DilutedCheckProduct.index(), Fq::ONE),
+        (DilutedCheckFirst.index(), Fq::ZERO),
+        (DilutedCheckCumulativeValue.index(), diluted_cumulative_val),
+    ]
+}
+
 /// Cairo flag
 /// https://eprint.iacr.org/2021/1063.pdf section 9
 #[derive(Clone, Copy, EnumIter, PartialEq, Eq)]
@@ -1734,7 +1807,7 @@ pub enum PublicInputHint {
     InitialPc,
     FinalAp,
     FinalPc,
-    MemoryQuotient, // TODO
+    MemoryQuotient,
     RangeCheckProduct,
     RangeCheckMin,
     RangeCheckMax,
@@ -1,8 +1,5 @@
-//! Matches `bitwise` layout from StarkWare's open source verifier
-//! <https://github.com/starkware-libs/cairo-lang/blob/361fe32d5930db340ea78fe05aedfe706f6c9405/src/starkware/cairo/lang/instances.py#L157>
-
-//TODO This is still the starknet layout and has to be updated to the recursive
-// parameters.
+//! Matches `recursive` layout from StarkWare's open source verifier
+//! <https://github.com/starkware-libs/cairo-lang/blob/361fe32d5930db340ea78fe05aedfe706f6c9405/src/starkware/cairo/lang/instances.py>
 
 pub mod air;
 pub mod trace;
@@ -11,13 +8,15 @@ pub use air::AirConfig;
 pub use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp;
 pub use trace::ExecutionTrace;
 
-// TODO Are these correct?
 // must be a power-of-two
 pub const CYCLE_HEIGHT: usize = 16;
+/// 8 memory units per cycle and a public memory fraction of 8
 pub const PUBLIC_MEMORY_STEP: usize = 16;
 pub const MEMORY_STEP: usize = 2;
+/// 4 range check units per cycle
 pub const RANGE_CHECK_STEP: usize = 4;
-pub const DILUTED_CHECK_STEP: usize = 1; //TODO is that correct?
+/// 16 diluted units per cycle i.e. one on every row
+pub const DILUTED_CHECK_STEP: usize = 1;
 
 /// How many cycles per pedersen hash
 pub const PEDERSEN_BUILTIN_RATIO: usize = 128;
@@ -26,8 +25,8 @@ pub const PEDERSEN_BUILTIN_RATIO: usize = 128;
 pub const RANGE_CHECK_BUILTIN_RATIO: usize = 8;
 pub const RANGE_CHECK_BUILTIN_PARTS: usize = 8;
 
-pub const NUM_BASE_COLUMNS: usize = 9;
-pub const NUM_EXTENSION_COLUMNS: usize = 1;
+pub const NUM_BASE_COLUMNS: usize = 7;
+pub const NUM_EXTENSION_COLUMNS: usize = 3;
 
 pub const DILUTED_CHECK_N_BITS: usize = 16;
 pub const DILUTED_CHECK_SPACING: usize = 4;
@@ -1,5 +1,3 @@
-// TODO This is still specific to the starknet layout and has to be updated to
-// the recursive layout
 use super::air::Auxiliary;
 use super::air::Flag;
 use super::air::MemoryPermutation;
@@ -12,18 +10,20 @@ use super::RANGE_CHECK_STEP;
 use ark_ff::BigInt;
 use ark_ff::Zero;
 use binary::BitwiseInstance;
+use binary::BuiltinInstance;
 use binary::MemoryEntry;
 use binary::PedersenInstance;
 use binary::RangeCheckInstance;
 use builtins::bitwise;
 use builtins::bitwise::dilute;
 use builtins::pedersen;
-use ark_ff::One;
+use binary::AirPrivateInput;
 use binary::AirPublicInput;
 use builtins::range_check;
 use num_bigint::BigUint;
 use ruint::aliases::U256;
 use crate::CairoWitness;
+use crate::WitnessMemory;
 use crate::recursive::PEDERSEN_BUILTIN_RATIO;
 use super::BITWISE_RATIO;
 use super::DILUTED_CHECK_N_BITS;
@@ -39,15 +39,17 @@ use crate::utils::DilutedCheckPool;
 use crate::utils::RangeCheckPool;
 use super::air::Permutation;
 use super::air::RangeCheckPermutation;
+use ark_ff::FftField;
 use ark_ff::Field;
 use super::MEMORY_STEP;
 use crate::utils::get_ordered_memory_accesses;
 use crate::CairoTrace;
+use crate::UnsupportedInstanceError;
 use alloc::vec;
 use alloc::vec::Vec;
 use ark_ff::batch_inversion;
 use binary::CompiledProgram;
-use binary::Memory;
+use binary::ReadMemory;
 use binary::RegisterState;
 use binary::RegisterStates;
 use core::iter::zip;
@@ -55,7 +57,9 @@ use ministark::challenges::Challenges;
 use ministark::utils::GpuAllocator;
 use ministark::utils::GpuVec;
 use ministark::Matrix;
+use ministark::StarkExtensionOf;
 use ministark::Trace;
+use ministark_gpu::GpuFftField;
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
 use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp;
@@ -80,7 +84,7 @@ pub struct ExecutionTrace {
     range_check_column: GpuVec<Fp>,
     base_trace: Matrix<Fp>,
     _register_states: RegisterStates,
-    _memory: Memory<Fp>,
+    _memory: WitnessMemory<Fp>,
     _flags_column: GpuVec<Fp>,
     _auxiliary_column: GpuVec<Fp>,
 }
@@ -115,7 +119,9 @@ impl CairoTrace for ExecutionTrace {
         let mut flags_column = Vec::new_in(GpuAllocator);
         flags_column.resize(trace_len, Fp::zero());
 
-        let padding_entry = air_public_input.public_memory_padding();
+        let padding_entry = air_public_input
+            .public_memory_padding()
+            .unwrap_or_else(|err| panic!("invalid public input: {err}"));
         let mut npc_column = Vec::new_in(GpuAllocator);
         npc_column.resize(trace_len, Fp::zero());
         {
@@ -134,7 +140,7 @@ impl CairoTrace for ExecutionTrace {
 
         // add offsets to the range check pool
         for &RegisterState { pc, .. } in register_states.iter() {
-            let word = memory[pc].unwrap();
+            let word = memory.get(pc).unwrap();
             rc_pool.push(word.get_off_dst());
             rc_pool.push(word.get_off_op0());
             rc_pool.push(word.get_off_op1());
@@ -145,7 +151,8 @@ impl CairoTrace for ExecutionTrace {
         let rc128_traces = rc128_instances
             .into_iter()
             .map(range_check::InstanceTrace::<RANGE_CHECK_BUILTIN_PARTS>::new)
-            .collect::<Vec<_>>();
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap_or_else(|err| panic!("unchecked private input: {err}"));
         for rc128_trace in &rc128_traces {
             for part in rc128_trace.parts {
                 rc_pool.push(part);
@@ -164,72 +171,14 @@ impl CairoTrace for ExecutionTrace {
         let mut auxiliary_column = Vec::new_in(GpuAllocator);
         auxiliary_column.resize(trace_len, Fp::zero());
 
-        let (range_check_cycles, _) = range_check_column.as_chunks_mut::<CYCLE_HEIGHT>();
-        let (auxiliary_cycles, _) = auxiliary_column.as_chunks_mut::<CYCLE_HEIGHT>();
-        let (npc_cycles, _) = npc_column.as_chunks_mut::<CYCLE_HEIGHT>();
-        let (flag_cycles, _) = flags_column.as_chunks_mut::<CYCLE_HEIGHT>();
-
-        ark_std::cfg_iter_mut!(range_check_cycles)
-            .zip(auxiliary_cycles)
-            .zip(npc_cycles)
-            .zip(flag_cycles)
-            .zip(&*register_states)
-            .for_each(
-                |((((rc_cycle, aux_cycle), npc_cycle), flag_cycle), registers)| {
-                    let &RegisterState { pc, ap, fp } = registers;
-                    let insrtuction = memory[pc].unwrap();
-                    let insrtuction_felt = insrtuction.into_felt();
-                    debug_assert!(!insrtuction.get_flag(Flag::Zero.into()));
-
-                    // range check all offset values
-                    let off_dst = insrtuction.get_off_dst() as u32;
-                    let off_op0 = insrtuction.get_off_op0() as u32;
-                    let off_op1 = insrtuction.get_off_op1() as u32;
-                    let dst_addr = insrtuction.get_dst_addr(ap, fp) as u32;
-                    let op0_addr = insrtuction.get_op0_addr(ap, fp) as u32;
-                    let op1_addr = insrtuction.get_op1_addr(pc, ap, fp, &memory) as u32;
-                    let dst = insrtuction.get_dst(ap, fp, &memory);
-                    let op0 = insrtuction.get_op0(ap, fp, &memory);
-                    let op1 = insrtuction.get_op1(pc, ap, fp, &memory);
-                    let res = insrtuction.get_res(pc, ap, fp, &memory);
-                    let tmp0 = insrtuction.get_tmp0(ap, fp, &memory);
-                    let tmp1 = insrtuction.get_tmp1(pc, ap, fp, &memory);
-
-                    // FLAGS
-                    for flag in Flag::iter() {
-                        flag_cycle[flag as usize] = insrtuction.get_flag_prefix(flag.into()).into();
-                    }
-
-                    // NPC
-                    npc_cycle[Npc::Pc as usize] = (pc as u64).into();
-                    npc_cycle[Npc::Instruction as usize] = insrtuction_felt;
-                    npc_cycle[Npc::MemOp0Addr as usize] = op0_addr.into();
-                    npc_cycle[Npc::MemOp0 as usize] = op0;
-                    npc_cycle[Npc::MemDstAddr as usize] = dst_addr.into();
-                    npc_cycle[Npc::MemDst as usize] = dst;
-                    npc_cycle[Npc::MemOp1Addr as usize] = op1_addr.into();
-                    npc_cycle[Npc::MemOp1 as usize] = op1;
-                    npc_cycle[Npc::PubMemAddr as usize] = Fp::zero();
-                    npc_cycle[Npc::PubMemVal as usize] = Fp::zero();
-
-                    // MEMORY
-                    // handled after this loop
-
-                    // RANGE CHECK
-                    rc_cycle[RangeCheck::OffDst as usize] = off_dst.into();
-                    rc_cycle[RangeCheck::OffOp1 as usize] = off_op1.into();
-                    rc_cycle[RangeCheck::OffOp0 as usize] = off_op0.into();
-                    // RangeCheck::Ordered and RangeCheck::Unused are handled after cycle padding
-
-                    // COL8 - TODO: better name
-                    aux_cycle[Auxiliary::Tmp0 as usize] = tmp0;
-                    aux_cycle[Auxiliary::Tmp1 as usize] = tmp1;
-                    aux_cycle[Auxiliary::Ap as usize] = (ap as u64).into();
-                    aux_cycle[Auxiliary::Fp as usize] = (fp as u64).into();
-                    aux_cycle[Auxiliary::Op0MulOp1 as usize] = op0 * op1;
-                    aux_cycle[Auxiliary::Res as usize] = res;
-                },
-            );
+        fill_cpu_cycles(
+            &register_states,
+            &memory,
+            &mut flags_column,
+            &mut npc_column,
+            &mut range_check_column,
+            &mut auxiliary_column,
+        );
 
         // create dummy 128-bit range check values that are filled with 16-bit range
         // check padding values
@@ -241,10 +190,12 @@ impl CairoTrace for ExecutionTrace {
                     value = (value << 16) + U256::from(part)
                 }
 
+                // built from 16-bit parts so the value always fits
                 range_check::InstanceTrace::<RANGE_CHECK_BUILTIN_PARTS>::new(RangeCheckInstance {
                     index: index as u32,
                     value,
                 })
+                .unwrap()
             })
             .collect::<Vec<_>>();
 
@@ -360,12 +311,12 @@ impl CairoTrace for ExecutionTrace {
 
                     // add the hash to the memory pool
                     let instance = pedersen_trace.instance;
-                    let (a_addr, b_addr, output_addr) = instance.mem_addr(initial_pedersen_address);
-                    npc[Npc::PedersenInput0Addr as usize] = a_addr.into();
+                    let addrs = instance.mem_addrs(initial_pedersen_address);
+                    npc[Npc::PedersenInput0Addr as usize] = addrs.a.into();
                     npc[Npc::PedersenInput0Val as usize] = Fp::from(BigUint::from(instance.a));
-                    npc[Npc::PedersenInput1Addr as usize] = b_addr.into();
+                    npc[Npc::PedersenInput1Addr as usize] = addrs.b.into();
                     npc[Npc::PedersenInput1Val as usize] = Fp::from(BigUint::from(instance.b));
-                    npc[Npc::PedersenOutputAddr as usize] = output_addr.into();
+                    npc[Npc::PedersenOutputAddr as usize] = addrs.output.into();
                     npc[Npc::PedersenOutputVal as usize] = pedersen_trace.output;
                 },
             );
@@ -405,7 +356,7 @@ impl CairoTrace for ExecutionTrace {
 
                 // add the range check to the memory pool
                 let instance = rc_trace.instance;
-                let addr = instance.mem_addr(initial_rc_address);
+                let addr = instance.mem_addrs(initial_rc_address);
                 npc[Npc::RangeCheck128Addr as usize] = addr.into();
                 npc[Npc::RangeCheck128Val as usize] = Fp::from(BigUint::from(instance.value));
             });
@@ -521,17 +472,16 @@ impl CairoTrace for ExecutionTrace {
                     let x_and_y_offset = input_y_offset + ADDR_STEP;
                     let x_xor_y_offset = x_and_y_offset + ADDR_STEP;
                     let x_or_y_offset = Npc::BitwiseXOrYAddr as usize;
-                    let (input_x_addr, input_y_addr, x_and_y_addr, x_xor_y_addr, x_or_y_addr) =
-                        instance.mem_addr(initial_bitwise_address);
-                    npc[input_x_offset] = input_x_addr.into();
+                    let addrs = instance.mem_addrs(initial_bitwise_address);
+                    npc[input_x_offset] = addrs.x.into();
                     npc[input_x_offset + 1] = bitwise_trace.x;
-                    npc[input_y_offset] = input_y_addr.into();
+                    npc[input_y_offset] = addrs.y.into();
                     npc[input_y_offset + 1] = bitwise_trace.y;
-                    npc[x_and_y_offset] = x_and_y_addr.into();
+                    npc[x_and_y_offset] = addrs.and.into();
                     npc[x_and_y_offset + 1] = bitwise_trace.x_and_y;
-                    npc[x_xor_y_offset] = x_xor_y_addr.into();
+                    npc[x_xor_y_offset] = addrs.xor.into();
                     npc[x_xor_y_offset + 1] = bitwise_trace.x_xor_y;
-                    npc[x_or_y_offset] = x_or_y_addr.into();
+                    npc[x_or_y_offset] = addrs.or.into();
                     npc[x_or_y_offset + 1] = bitwise_trace.x_or_y;
 
                     // return the diluted pool
@@ -594,60 +544,11 @@ impl CairoTrace for ExecutionTrace {
         // VM Memory
         // =========
         // generate the memory column by ordering memory accesses
-
-        {
-            // TODO: this is a bandaid hack. find better solution
-            // goal is to find any gaps in memory and fill them in
-            let mut sorted_memory_accesses: Vec<MemoryEntry<Fp>> = npc_column
-                .array_chunks()
-                .map(|&[address, value]| {
-                    let address = u32::try_from(U256::from_limbs(address.into_bigint().0)).unwrap();
-                    MemoryEntry { value, address }
-                })
-                .chain(public_memory.clone())
-                .collect();
-            sorted_memory_accesses.sort_unstable_by_key(|e| e.address);
-            let mut padding_addrs = Vec::new();
-            for [a, b] in sorted_memory_accesses.array_windows() {
-                let a_addr = a.address;
-                let b_addr = b.address;
-                for padding_addr in a_addr.saturating_add(1)..b_addr {
-                    padding_addrs.push(padding_addr)
-                }
-            }
-            let mut padding_addrs = padding_addrs.into_iter();
-            for npc_cycle in npc_column.array_chunks_mut::<CYCLE_HEIGHT>() {
-                let addr = match padding_addrs.next() {
-                    Some(v) => v,
-                    None => break,
-                };
-                npc_cycle[Npc::UnusedAddr as usize] = addr.into();
-                npc_cycle[Npc::UnusedVal as usize] = Fp::ZERO;
-            }
-            // ensure padding has been fully consumed
-            assert!(padding_addrs.next().is_none());
-        }
-
-        let memory_accesses: Vec<MemoryEntry<Fp>> = npc_column
-            .array_chunks()
-            .map(|&[address_felt, value_felt]| MemoryEntry {
-                address: U256::from_limbs(address_felt.into_bigint().0)
-                    .try_into()
-                    .unwrap(),
-                value: value_felt,
-            })
-            .collect();
-        let ordered_memory_accesses = get_ordered_memory_accesses::<PUBLIC_MEMORY_STEP, Fp>(
-            trace_len,
-            &memory_accesses,
+        let memory_column = generate_memory_column::<PUBLIC_MEMORY_STEP, Fp>(
+            &mut npc_column,
             &public_memory,
             padding_entry,
         );
-        let memory_column = ordered_memory_accesses
-            .into_iter()
-            .flat_map(|e| [e.address.into(), e.value])
-            .collect::<Vec<Fp>>()
-            .to_vec_in(GpuAllocator);
 
         let base_trace = Matrix::new(vec![
             flags_column.to_vec_in(GpuAllocator),
@@ -686,6 +587,15 @@ impl CairoTrace for ExecutionTrace {
             _register_states: register_states,
         }
     }
+
+    fn check_private_input(
+        air_private_input: &AirPrivateInput,
+    ) -> Result<(), UnsupportedInstanceError> {
+        for &instance in &air_private_input.range_check {
+            range_check::InstanceTrace::<RANGE_CHECK_BUILTIN_PARTS>::new(instance)?;
+        }
+        Ok(())
+    }
 }
 
 impl Trace for ExecutionTrace {
@@ -697,119 +607,317 @@ impl Trace for ExecutionTrace {
     }
 
     fn build_extension_columns(&self, challenges: &Challenges<Fp>) -> Option<Matrix<Fp>> {
-        // TODO: multithread
-        // generate memory permutation product
-        // ===================================
-        // see distinction between (a', v') and (a, v) in the Cairo paper.
-        let z = challenges[MemoryPermutation::Z];
-        let alpha = challenges[MemoryPermutation::A];
-        let program_order_accesses = self.npc_column.array_chunks::<MEMORY_STEP>();
-        let address_order_accesses = self.memory_column.array_chunks::<MEMORY_STEP>();
-        let mut mem_perm_numerators = Vec::new();
-        let mut mem_perm_denominators = Vec::new();
-        let mut numerator_acc = Fp::one();
-        let mut denominator_acc = Fp::one();
-        for (&[a, v], &[a_prime, v_prime]) in program_order_accesses.zip(address_order_accesses) {
-            numerator_acc *= z - (alpha * v + a);
-            denominator_acc *= z - (alpha * v_prime + a_prime);
-            mem_perm_numerators.push(numerator_acc);
-            mem_perm_denominators.push(denominator_acc);
-        }
-        batch_inversion(&mut mem_perm_denominators);
-        let mem_perm_denominators_inv = mem_perm_denominators;
-
-        // generate range check permutation product
-        // ========================================
-        let z = challenges[RangeCheckPermutation::Z];
-        let range_check_chunks = self.range_check_column.array_chunks::<RANGE_CHECK_STEP>();
-        let mut rc_perm_numerators = Vec::new();
-        let mut rc_perm_denominators = Vec::new();
-        let mut numerator_acc = Fp::one();
-        let mut denominator_acc = Fp::one();
-        for chunk in range_check_chunks {
-            numerator_acc *= z - chunk[RangeCheck::OffDst as usize];
-            denominator_acc *= z - chunk[RangeCheck::Ordered as usize];
-            rc_perm_numerators.push(numerator_acc);
-            rc_perm_denominators.push(denominator_acc);
-        }
-        assert!((numerator_acc / denominator_acc).is_one());
-        batch_inversion(&mut rc_perm_denominators);
-        let rc_perm_denominators_inv = rc_perm_denominators;
-
-        // generate diluted check permutation product
-        // ==========================================
-        let z = challenges[DilutedCheckPermutation::Z];
-        let mut dc_perm_numerators = Vec::new();
-        let mut dc_perm_denominators = Vec::new();
-        let mut numerator_acc = Fp::one();
-        let mut denominator_acc = Fp::one();
-        for (unordered, ordered) in zip(
+        Some(cpu_extension_columns(
+            challenges,
+            &self.npc_column,
+            &self.memory_column,
+            &self.range_check_column,
             &self.diluted_check_unordered_column,
             &self.diluted_check_ordered_column,
-        ) {
-            numerator_acc *= z - unordered;
-            denominator_acc *= z - ordered;
-            dc_perm_numerators.push(numerator_acc);
-            dc_perm_denominators.push(denominator_acc);
+        ))
+    }
+}
+
+/// Fills the flag, npc, range check and auxiliary cells of every CPU cycle.
+/// Builtin, public memory and range check padding cells are left for each
+/// layout to fill. Shared with [crate::recursive_generic] which uses the same
+/// cells for the CPU.
+pub(crate) fn fill_cpu_cycles<Fp: PrimeField>(
+    register_states: &[RegisterState],
+    memory: &WitnessMemory<Fp>,
+    flags_column: &mut [Fp],
+    npc_column: &mut [Fp],
+    range_check_column: &mut [Fp],
+    auxiliary_column: &mut [Fp],
+) {
+    let (range_check_cycles, _) = range_check_column.as_chunks_mut::<CYCLE_HEIGHT>();
+    let (auxiliary_cycles, _) = auxiliary_column.as_chunks_mut::<CYCLE_HEIGHT>();
+    let (npc_cycles, _) = npc_column.as_chunks_mut::<CYCLE_HEIGHT>();
+    let (flag_cycles, _) = flags_column.as_chunks_mut::<CYCLE_HEIGHT>();
+
+    ark_std::cfg_iter_mut!(range_check_cycles)
+        .zip(auxiliary_cycles)
+        .zip(npc_cycles)
+        .zip(flag_cycles)
+        .zip(register_states)
+        .for_each(
+            |((((rc_cycle, aux_cycle), npc_cycle), flag_cycle), registers)| {
+                let &RegisterState { pc, ap, fp } = registers;
+                let word = memory.get(pc).unwrap();
+                debug_assert!(!word.get_flag(Flag::Zero.into()));
+
+                // range check all offset values
+                let off_dst = (word.get_off_dst() as u64).into();
+                let off_op0 = (word.get_off_op0() as u64).into();
+                let off_op1 = (word.get_off_op1() as u64).into();
+                let dst_addr = (word.get_dst_addr(ap, fp) as u64).into();
+                let op0_addr = (word.get_op0_addr(ap, fp) as u64).into();
+                let op1_addr = (word.get_op1_addr(pc, ap, fp, memory) as u64).into();
+                let dst = word.get_dst(ap, fp, memory);
+                let op0 = word.get_op0(ap, fp, memory);
+                let op1 = word.get_op1(pc, ap, fp, memory);
+                let res = word.get_res(pc, ap, fp, memory);
+                let tmp0 = word.get_tmp0(ap, fp, memory);
+                let tmp1 = word.get_tmp1(pc, ap, fp, memory);
+
+                // FLAGS
+                for flag in Flag::iter() {
+                    flag_cycle[flag as usize] = word.get_flag_prefix(flag.into()).into();
+                }
+
+                // NPC
+                npc_cycle[Npc::Pc as usize] = (pc as u64).into();
+                npc_cycle[Npc::Instruction as usize] = word.into_felt();
+                npc_cycle[Npc::MemOp0Addr as usize] = op0_addr;
+                npc_cycle[Npc::MemOp0 as usize] = op0;
+                npc_cycle[Npc::MemDstAddr as usize] = dst_addr;
+                npc_cycle[Npc::MemDst as usize] = dst;
+                npc_cycle[Npc::MemOp1Addr as usize] = op1_addr;
+                npc_cycle[Npc::MemOp1 as usize] = op1;
+                npc_cycle[Npc::PubMemAddr as usize] = Fp::zero();
+                npc_cycle[Npc::PubMemVal as usize] = Fp::zero();
+
+                // MEMORY
+                // handled after this loop
+
+                // RANGE CHECK
+                rc_cycle[RangeCheck::OffDst as usize] = off_dst;
+                rc_cycle[RangeCheck::OffOp1 as usize] = off_op1;
+                rc_cycle[RangeCheck::OffOp0 as usize] = off_op0;
+                // RangeCheck::Ordered and RangeCheck::Unused are handled after cycle padding
+
+                // COL8 - TODO: better name
+                aux_cycle[Auxiliary::Tmp0 as usize] = tmp0;
+                aux_cycle[Auxiliary::Tmp1 as usize] = tmp1;
+                aux_cycle[Auxiliary::Ap as usize] = (ap as u64).into();
+                aux_cycle[Auxiliary::Fp as usize] = (fp as u64).into();
+                aux_cycle[Auxiliary::Op0MulOp1 as usize] = op0 * op1;
+                aux_cycle[Auxiliary::Res as usize] = res;
+            },
+        );
+}
+
+/// Generates the memory column from the memory accesses in the npc column.
+/// Gaps in the accessed addresses are first filled in with unused npc cells so
+/// the sorted addresses are continuous. Public memory cells are every
+/// `PUBLIC_MEMORY_STEP` rows.
+pub(crate) fn generate_memory_column<const PUBLIC_MEMORY_STEP: usize, Fp: PrimeField>(
+    npc_column: &mut [Fp],
+    public_memory: &[MemoryEntry<Fp>],
+    padding_entry: MemoryEntry<Fp>,
+) -> GpuVec<Fp> {
+    let trace_len = npc_column.len();
+    let into_address = |felt: Fp| -> u32 {
+        let address: BigUint = felt.into_bigint().into();
+        address.try_into().unwrap()
+    };
+
+    {
+        // find any gaps in memory and fill them in
+        let mut sorted_memory_accesses: Vec<MemoryEntry<Fp>> = npc_column
+            .array_chunks()
+            .map(|&[address, value]| MemoryEntry {
+                address: into_address(address),
+                value,
+            })
+            .chain(public_memory.iter().copied())
+            .collect();
+        sorted_memory_accesses.sort_unstable_by_key(|e| e.address);
+        let mut padding_addrs = Vec::new();
+        for [a, b] in sorted_memory_accesses.array_windows() {
+            for padding_addr in a.address.saturating_add(1)..b.address {
+                padding_addrs.push(padding_addr)
+            }
         }
-        assert!((numerator_acc / denominator_acc).is_one());
-        batch_inversion(&mut dc_perm_denominators);
-        let dc_perm_denominators_inv = dc_perm_denominators;
+        let mut padding_addrs = padding_addrs.into_iter();
+        for npc_cycle in npc_column.array_chunks_mut::<CYCLE_HEIGHT>() {
+            let addr = match padding_addrs.next() {
+                Some(v) => v,
+                None => break,
+            };
+            npc_cycle[Npc::UnusedAddr as usize] = addr.into();
+            npc_cycle[Npc::UnusedVal as usize] = Fp::ZERO;
+        }
+        // ensure padding has been fully consumed
+        assert!(padding_addrs.next().is_none());
+    }
+
+    let memory_accesses: Vec<MemoryEntry<Fp>> = npc_column
+        .array_chunks()
+        .map(|&[address_felt, value_felt]| MemoryEntry {
+            address: into_address(address_felt),
+            value: value_felt,
+        })
+        .collect();
+    let ordered_memory_accesses = get_ordered_memory_accesses::<PUBLIC_MEMORY_STEP, Fp>(
+        trace_len,
+        &memory_accesses,
+        public_memory,
+        padding_entry,
+    );
+    ordered_memory_accesses
+        .into_iter()
+        .flat_map(|e| [e.address.into(), e.value])
+        .collect::<Vec<Fp>>()
+        .to_vec_in(GpuAllocator)
+}
 
-        let trace_len = self.base_columns().num_rows();
+/// Builds the diluted check aggregation, diluted check permutation and the
+/// combined memory and range check permutation columns
+pub(crate) fn cpu_extension_columns<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+    challenges: &Challenges<Fq>,
+    npc_column: &[Fp],
+    memory_column: &[Fp],
+    range_check_column: &[Fp],
+    diluted_check_unordered_column: &[Fp],
+    diluted_check_ordered_column: &[Fp],
+) -> Matrix<Fq> {
+    let [diluted_check_aggregate_column, diluted_check_permutation_column] =
+        diluted_check_extension_columns(
+            challenges,
+            diluted_check_unordered_column,
+            diluted_check_ordered_column,
+        );
+    let mem_and_rc_permutation_column = memory_and_range_check_permutation_column(
+        challenges,
+        npc_column,
+        memory_column,
+        range_check_column,
+    );
+    Matrix::new(vec![
+        diluted_check_aggregate_column,
+        diluted_check_permutation_column,
+        mem_and_rc_permutation_column,
+    ])
+}
 
-        let mut diluted_check_permutation_column = Vec::new_in(GpuAllocator);
-        diluted_check_permutation_column.resize(trace_len, Fp::ZERO);
+/// Builds the combined memory and range check permutation column
+pub(crate) fn memory_and_range_check_permutation_column<
+    Fp: GpuFftField + FftField,
+    Fq: StarkExtensionOf<Fp>,
+>(
+    challenges: &Challenges<Fq>,
+    npc_column: &[Fp],
+    memory_column: &[Fp],
+    range_check_column: &[Fp],
+) -> GpuVec<Fq> {
+    // TODO: multithread
+    // generate memory permutation product
+    // ===================================
+    // see distinction between (a', v') and (a, v) in the Cairo paper.
+    let z = challenges[MemoryPermutation::Z];
+    let alpha = challenges[MemoryPermutation::A];
+    let program_order_accesses = npc_column.array_chunks::<MEMORY_STEP>();
+    let address_order_accesses = memory_column.array_chunks::<MEMORY_STEP>();
+    let mut mem_perm_numerators = Vec::new();
+    let mut mem_perm_denominators = Vec::new();
+    let mut numerator_acc = Fq::one();
+    let mut denominator_acc = Fq::one();
+    for (&[a, v], &[a_prime, v_prime]) in program_order_accesses.zip(address_order_accesses) {
+        numerator_acc *= z - (alpha * v + a);
+        denominator_acc *= z - (alpha * v_prime + a_prime);
+        mem_perm_numerators.push(numerator_acc);
+        mem_perm_denominators.push(denominator_acc);
+    }
+    batch_inversion(&mut mem_perm_denominators);
+    let mem_perm_denominators_inv = mem_perm_denominators;
+
+    // generate range check permutation product
+    // ========================================
+    let z = challenges[RangeCheckPermutation::Z];
+    let range_check_chunks = range_check_column.array_chunks::<RANGE_CHECK_STEP>();
+    let mut rc_perm_numerators = Vec::new();
+    let mut rc_perm_denominators = Vec::new();
+    let mut numerator_acc = Fq::one();
+    let mut denominator_acc = Fq::one();
+    for chunk in range_check_chunks {
+        numerator_acc *= z - chunk[RangeCheck::OffDst as usize];
+        denominator_acc *= z - chunk[RangeCheck::Ordered as usize];
+        rc_perm_numerators.push(numerator_acc);
+        rc_perm_denominators.push(denominator_acc);
+    }
+    assert!((numerator_acc / denominator_acc).is_one());
+    batch_inversion(&mut rc_perm_denominators);
+    let rc_perm_denominators_inv = rc_perm_denominators;
+
+    let trace_len = npc_column.len();
+    let mut mem_and_rc_permutation_column = Vec::new_in(GpuAllocator);
+    mem_and_rc_permutation_column.resize(trace_len, Fq::ZERO);
+
+    // insert intermediate memory permutation results
+    for (i, (n, d_inv)) in zip(mem_perm_numerators, mem_perm_denominators_inv).enumerate() {
+        let offset = i * MEMORY_STEP + Permutation::Memory.col_and_shift().1 as usize;
+        mem_and_rc_permutation_column[offset] = n * d_inv;
+    }
 
-        let mut diluted_check_aggregate_column = Vec::new_in(GpuAllocator);
-        diluted_check_aggregate_column.resize(trace_len, Fp::ZERO);
+    // insert intermediate range check results
+    for (i, (n, d_inv)) in zip(rc_perm_numerators, rc_perm_denominators_inv).enumerate() {
+        let offset = i * RANGE_CHECK_STEP + Permutation::RangeCheck.col_and_shift().1 as usize;
+        mem_and_rc_permutation_column[offset] = n * d_inv;
+    }
 
-        let mut mem_and_rc_permutation_column = Vec::new_in(GpuAllocator);
-        mem_and_rc_permutation_column.resize(trace_len, Fp::ZERO);
+    mem_and_rc_permutation_column
+}
 
-        // insert intermediate memory permutation results
-        for (i, (n, d_inv)) in zip(mem_perm_numerators, mem_perm_denominators_inv).enumerate() {
-            let offset = i * MEMORY_STEP + Permutation::Memory.col_and_shift().1 as usize;
-            mem_and_rc_permutation_column[offset] = n * d_inv;
-        }
+/// Builds the diluted check aggregation and diluted check permutation columns
+pub(crate) fn diluted_check_extension_columns<
+    Fp: GpuFftField + FftField,
+    Fq: StarkExtensionOf<Fp>,
+>(
+    challenges: &Challenges<Fq>,
+    diluted_check_unordered_column: &[Fp],
+    diluted_check_ordered_column: &[Fp],
+) -> [GpuVec<Fq>; 2] {
+    // generate diluted check permutation product
+    // ==========================================
+    let z = challenges[DilutedCheckPermutation::Z];
+    let mut dc_perm_numerators = Vec::new();
+    let mut dc_perm_denominators = Vec::new();
+    let mut numerator_acc = Fq::one();
+    let mut denominator_acc = Fq::one();
+    for (&unordered, &ordered) in zip(diluted_check_unordered_column, diluted_check_ordered_column)
+    {
+        numerator_acc *= z - unordered;
+        denominator_acc *= z - ordered;
+        dc_perm_numerators.push(numerator_acc);
+        dc_perm_denominators.push(denominator_acc);
+    }
+    assert!((numerator_acc / denominator_acc).is_one());
+    batch_inversion(&mut dc_perm_denominators);
+    let dc_perm_denominators_inv = dc_perm_denominators;
 
-        // insert intermediate range check results
-        for (i, (n, d_inv)) in zip(rc_perm_numerators, rc_perm_denominators_inv).enumerate() {
-            let offset = i * RANGE_CHECK_STEP + Permutation::RangeCheck.col_and_shift().1 as usize;
-            mem_and_rc_permutation_column[offset] = n * d_inv;
-        }
+    let trace_len = diluted_check_ordered_column.len();
 
-        assert!(
-            (dc_perm_numerators.last().unwrap() * dc_perm_denominators_inv.last().unwrap())
-                .is_one()
-        );
+    let mut diluted_check_permutation_column = Vec::new_in(GpuAllocator);
+    diluted_check_permutation_column.resize(trace_len, Fq::ZERO);
 
-        // insert intermediate diluted check results
-        for (i, (n, d_inv)) in zip(dc_perm_numerators, dc_perm_denominators_inv).enumerate() {
-            diluted_check_permutation_column[i] = n * d_inv;
-        }
+    let mut diluted_check_aggregate_column = Vec::new_in(GpuAllocator);
+    diluted_check_aggregate_column.resize(trace_len, Fq::ZERO);
 
-        // generate aggregation of diluted checks
-        // ======================================
-        let z = challenges[DilutedCheckAggregation::Z];
-        let alpha = challenges[DilutedCheckAggregation::A];
-
-        // insert initial value
-        let initial = Fp::one();
-        diluted_check_aggregate_column[0] = initial;
-
-        // insert intermediate aggregation results
-        let mut acc = initial;
-        for (i, [prev, curr]) in zip(1.., self.diluted_check_ordered_column.array_windows()) {
-            let u = curr - prev;
-            acc = acc * (Fp::ONE + z * u) + alpha * u.square();
-            diluted_check_aggregate_column[i] = acc;
-        }
+    // insert intermediate diluted check results
+    for (i, (n, d_inv)) in zip(dc_perm_numerators, dc_perm_denominators_inv).enumerate() {
+        diluted_check_permutation_column[i] = n * d_inv;
+    }
 
-        Some(Matrix::new(vec![
-            diluted_check_aggregate_column,
-            diluted_check_permutation_column,
-            mem_and_rc_permutation_column,
-        ]))
+    // generate aggregation of diluted checks
+    // ======================================
+    let z = challenges[DilutedCheckAggregation::Z];
+    let alpha = challenges[DilutedCheckAggregation::A];
+
+    // insert initial value
+    let initial = Fq::one();
+    diluted_check_aggregate_column[0] = initial;
+
+    // insert intermediate aggregation results
+    let mut acc = initial;
+    for (i, &[prev, curr]) in zip(1.., diluted_check_ordered_column.array_windows()) {
+        let u = curr - prev;
+        acc = acc * (Fq::ONE + z * u) + alpha * u.square();
+        diluted_check_aggregate_column[i] = acc;
     }
+
+    [
+        diluted_check_aggregate_column,
+        diluted_check_permutation_column,
+    ]
 }
@@ -0,0 +1,334 @@
+use super::BITWISE_RATIO;
+use super::CYCLE_HEIGHT;
+use super::PUBLIC_MEMORY_STEP;
+use super::RANGE_CHECK_BUILTIN_PARTS;
+use super::RANGE_CHECK_BUILTIN_RATIO;
+use crate::recursive::air::cpu_constraints;
+use crate::recursive::air::cpu_hints;
+use crate::recursive::air::diluted_check_constraints;
+use crate::recursive::air::diluted_check_hints;
+use crate::recursive::air::MemoryPermutation;
+use crate::recursive::air::Npc;
+use crate::CairoAirConfig;
+use ark_ff::PrimeField;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use binary::AirPublicInput;
+use ministark::challenges::Challenges;
+use ministark::constraints::AlgebraicItem;
+use ministark::constraints::CompositionConstraint;
+use ministark::constraints::CompositionItem;
+use ministark::constraints::Constraint;
+use ministark::constraints::ExecutionTraceColumn;
+use ministark::constraints::Hint;
+use ministark::expression::Expr;
+use ministark::hints::Hints;
+use ministark::utils::FieldVariant;
+use ministark::StarkExtensionOf;
+use ministark_gpu::GpuFftField;
+use num_traits::Pow;
+use std::marker::PhantomData;
+
+pub struct AirConfig<Fp, Fq>(PhantomData<(Fp, Fq)>);
+
+impl<Fp: GpuFftField + PrimeField, Fq: StarkExtensionOf<Fp>> ministark::air::AirConfig
+    for AirConfig<Fp, Fq>
+{
+    const NUM_BASE_COLUMNS: usize = 7;
+    const NUM_EXTENSION_COLUMNS: usize = 3;
+    type Fp = Fp;
+    type Fq = Fq;
+    type PublicInputs = AirPublicInput<Fp>;
+
+    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fq>>> {
+        use AlgebraicItem::*;
+        use PublicInputHint::*;
+        let n = trace_len;
+        let trace_domain = Radix2EvaluationDomain::<Fp>::new(n).unwrap();
+        let g = trace_domain.group_gen();
+        assert!(n >= CYCLE_HEIGHT, "must be a multiple of cycle height");
+        let x = Expr::from(X);
+        let one = Expr::from(Constant(FieldVariant::Fp(Fp::ONE)));
+        let two = Expr::from(Constant(FieldVariant::Fp(Fp::from(2u32))));
+        let offset_size = Expr::from(Constant(FieldVariant::Fp(Fp::from(2u32.pow(16)))));
+
+        // 32-bit range check builtin value from its two 16-bit parts
+        let rc_builtin_value0_0: Expr<AlgebraicItem<FieldVariant<Fp, Fq>>> =
+            RangeCheckBuiltin::Rc16Component.offset(0);
+        let rc_builtin_value1_0 =
+            &rc_builtin_value0_0 * &offset_size + RangeCheckBuiltin::Rc16Component.offset(1);
+
+        // bits 0->63 (inclusive) of a bitwise number
+        let bitwise_sum_var_0_0: Expr<AlgebraicItem<FieldVariant<Fp, Fq>>> = Bitwise::Bits16Offset0
+            .curr()
+            + Bitwise::Bits16Offset1.curr() * (&two).pow(1)
+            + Bitwise::Bits16Offset2.curr() * (&two).pow(2)
+            + Bitwise::Bits16Offset3.curr() * (&two).pow(3);
+
+        let first_row_zerofier_inv = &one / (&x - &one);
+
+        // 32bit Range check builtin
+        // =========================
+
+        let every_128_rows_zerofier = X.pow(n / 128) - &one;
+        let every_128_rows_zerofier_inv = &one / &every_128_rows_zerofier;
+        let zerofier_128th_last_row =
+            X - Constant(FieldVariant::Fp(g.pow([128 * (n as u64 / 128 - 1)])));
+        let every_128_rows_except_last_zerofier =
+            &zerofier_128th_last_row * &every_128_rows_zerofier_inv;
+
+        // Hook up range check with the memory pool
+        let rc_builtin_value =
+            (rc_builtin_value1_0 - Npc::RangeCheck128Val.curr()) * &every_128_rows_zerofier_inv;
+        let rc_builtin_addr_step = (Npc::RangeCheck128Addr.next()
+            - (Npc::RangeCheck128Addr.curr() + &one))
+            * &every_128_rows_except_last_zerofier;
+
+        let rc_builtin_init_addr =
+            (Npc::RangeCheck128Addr.curr() - InitialRcAddr.hint()) * &first_row_zerofier_inv;
+
+        // bitwise builtin
+        // ===============
+
+        // check the initial bitwise segment memory address
+        // all addresses associated with bitwise checks are continuous
+        let bitwise_init_var_pool_addr =
+            (Npc::BitwisePoolAddr.curr() - InitialBitwiseAddr.hint()) * &first_row_zerofier_inv;
+
+        // vanishes on every 128th row except the 3rd of every 4
+        let every_32_row_zerofier = X.pow(n / 32) - &one;
+        let every_32_row_zerofier_inv = &one / &every_32_row_zerofier;
+        let bitwise_transition_zerofier_inv = (X.pow(n / 128)
+            - Constant(FieldVariant::Fp(g.pow([(3 * n / 4) as u64]))))
+            * &every_32_row_zerofier_inv;
+
+        let all_bitwise_zerofier = X.pow(n / 128) - &one;
+        let all_bitwise_zerofier_inv = &one / &all_bitwise_zerofier;
+
+        // Checks memory address for four bitwise inputs
+        // `x`, `y`, `x&y` and `x^y` are continuous
+        let bitwise_step_var_pool_addr = (Npc::BitwisePoolAddr.next()
+            - (Npc::BitwisePoolAddr.curr() + &one))
+            * &bitwise_transition_zerofier_inv;
+        // need to check one more address for `x|y`
+        let bitwise_x_or_y_addr = (Npc::BitwiseXOrYAddr.curr()
+            - (Npc::BitwisePoolAddr.offset(3) + &one))
+            * &all_bitwise_zerofier_inv;
+
+        let last_bitwise_zerofier =
+            X - Constant(FieldVariant::Fp(g.pow([128 * (n / 128 - 1) as u64])));
+        let all_bitwise_except_last_zerofier_inv =
+            &last_bitwise_zerofier * &all_bitwise_zerofier_inv;
+
+        // check the next bitwise instance has the correct address
+        let bitwise_next_var_pool_addr = (Npc::BitwisePoolAddr.offset(4)
+            - (Npc::BitwiseXOrYAddr.curr() + &one))
+            * &all_bitwise_except_last_zerofier_inv;
+
+        // check all values `x`, `y`, `x&y` and `x^y` are partitioned
+        // NOTE: not `x|y` since this is calculated trivially using `x&y` and `x^y`
+        // Each 64 bit value is broken up into four stridings of a 16 bit integer
+        // such that `v = s0 * 2^0 + s1 * 2^1 + s2 * 2^2 + s3 * 2^3`.
+        let bitwise_partition =
+            (&bitwise_sum_var_0_0 - Npc::BitwisePoolVal.curr()) * &every_32_row_zerofier_inv;
+
+        // NOTE: `x | y = (x & y) + (x ^ y)`
+        let bitwise_x_and_y_val = Npc::BitwisePoolVal.offset(2);
+        let bitwise_x_xor_y_val = Npc::BitwisePoolVal.offset(3);
+        let bitwise_or_is_and_plus_xor = (Npc::BitwiseXOrYVal.curr()
+            - (bitwise_x_and_y_val + bitwise_x_xor_y_val))
+            * &all_bitwise_zerofier_inv;
+
+        // vanishes on rows 0, 2, 4 and 6 of every bitwise instance
+        // i.e. the rows holding the first value's four 16 bit segments
+        let every_16_bit_segment_zerofier = (X.pow(n / 128)
+            - Constant(FieldVariant::Fp(g.pow([n as u64 / 64]))))
+            * (X.pow(n / 128) - Constant(FieldVariant::Fp(g.pow([n as u64 / 32]))))
+            * (X.pow(n / 128) - Constant(FieldVariant::Fp(g.pow([3 * n as u64 / 64]))))
+            * &all_bitwise_zerofier;
+        let every_16_bit_segment_zerofier_inv = &one / every_16_bit_segment_zerofier;
+
+        // NOTE: `x+y = (x^y) + (x&y) + (x&y)`
+        let x_16_bit_segment = Bitwise::Bits16Offset0.offset(0);
+        let y_16_bit_segment = Bitwise::Bits16Offset0.offset(1);
+        let x_and_y_16_bit_segment = Bitwise::Bits16Offset0.offset(2);
+        let x_xor_y_16_bit_segment = Bitwise::Bits16Offset0.offset(3);
+        let bitwise_addition_is_xor_with_and = (x_16_bit_segment + y_16_bit_segment
+            - (x_xor_y_16_bit_segment + &x_and_y_16_bit_segment + x_and_y_16_bit_segment))
+            * &every_16_bit_segment_zerofier_inv;
+
+        // NOTE: forces bit 63 of x&y and x^y to be 0. The shifted value can only be
+        // diluted if the top bit of the last segment is 0. Otherwise it exceeds the
+        // largest diluted value or, in a 64 bit field, `2^64` reduces to `2^32 - 1`
+        // which isn't diluted either.
+        let x_and_y_16_bit_segment = Bitwise::Bits16Offset3.offset(2);
+        let x_xor_y_16_bit_segment = Bitwise::Bits16Offset3.offset(3);
+        let bitwise_unique_unpacking = ((x_and_y_16_bit_segment + x_xor_y_16_bit_segment)
+            * (&two).pow(4)
+            - Bitwise::Bits16Offset3ResShifted.curr())
+            * &all_bitwise_zerofier_inv;
+
+        // NOTE: for composition OODs only seem to involve one random per constraint
+        cpu_constraints::<PUBLIC_MEMORY_STEP, Fp, Fq>(n)
+            .into_iter()
+            .chain(diluted_check_constraints::<Fp, Fq>(n))
+            .chain([
+                rc_builtin_value,
+                rc_builtin_addr_step,
+                rc_builtin_init_addr,
+                bitwise_init_var_pool_addr, // +1
+                bitwise_step_var_pool_addr,
+                bitwise_x_or_y_addr,
+                bitwise_next_var_pool_addr,
+                bitwise_partition,
+                bitwise_or_is_and_plus_xor,
+                bitwise_addition_is_xor_with_and,
+                bitwise_unique_unpacking,
+            ])
+            .map(Constraint::new)
+            .collect()
+    }
+
+    fn composition_constraint(
+        _trace_len: usize,
+        constraints: &[Constraint<FieldVariant<Self::Fp, Self::Fq>>],
+    ) -> CompositionConstraint<FieldVariant<Self::Fp, Self::Fq>> {
+        use CompositionItem::*;
+        let alpha = Expr::Leaf(CompositionCoeff(0));
+        let expr = constraints
+            .iter()
+            .enumerate()
+            .map(|(i, constraint)| {
+                let constraint = constraint.map_leaves(&mut |&leaf| Item(leaf));
+                constraint * (&alpha).pow(i)
+            })
+            .sum::<Expr<CompositionItem<FieldVariant<Self::Fp, Self::Fq>>>>()
+            .reuse_shared_nodes();
+        CompositionConstraint::new(expr)
+    }
+
+    fn gen_hints(
+        trace_len: usize,
+        execution_info: &AirPublicInput<Self::Fp>,
+        challenges: &Challenges<Self::Fq>,
+    ) -> Hints<Self::Fq> {
+        use PublicInputHint::*;
+
+        let segments = execution_info.memory_segments;
+        if let Some(pedersen_segment) = segments.pedersen {
+            assert_eq!(
+                pedersen_segment.begin_addr, pedersen_segment.stop_ptr,
+                "layout does not support Pedersen"
+            );
+        }
+        let rc_segment = segments.range_check.expect("layout requires range check");
+        let bitwise_segment = segments.bitwise.expect("layout requires bitwise");
+
+        let initial_rc_address = rc_segment.begin_addr.into();
+        let initial_bitwise_address = bitwise_segment.begin_addr.into();
+
+        let mut hints =
+            cpu_hints::<PUBLIC_MEMORY_STEP, Fp, Fq>(trace_len, execution_info, challenges);
+        hints.extend(diluted_check_hints::<Fp, Fq>(challenges));
+        hints.extend([
+            (InitialRcAddr.index(), initial_rc_address),
+            (InitialBitwiseAddr.index(), initial_bitwise_address),
+        ]);
+        Hints::new(hints)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum RangeCheckBuiltin {
+    Rc16Component = 12,
+}
+
+impl ExecutionTraceColumn for RangeCheckBuiltin {
+    fn index(&self) -> usize {
+        5
+    }
+
+    fn offset<T>(&self, offset: isize) -> Expr<AlgebraicItem<T>> {
+        let column = self.index();
+        let step = RANGE_CHECK_BUILTIN_RATIO * CYCLE_HEIGHT / RANGE_CHECK_BUILTIN_PARTS;
+        let trace_offset = match self {
+            Self::Rc16Component => step as isize * offset + *self as isize,
+        };
+        AlgebraicItem::Trace(column, trace_offset).into()
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum Bitwise {
+    // four stridings of a 64 bit value
+    Bits16Offset0 = 0,
+    Bits16Offset1 = 2,
+    Bits16Offset2 = 4,
+    Bits16Offset3 = 6,
+    // holds a shifted value to ensure that there has been a unique unpacking
+    Bits16Offset3ResShifted = 1,
+}
+
+impl ExecutionTraceColumn for Bitwise {
+    fn index(&self) -> usize {
+        match self {
+            Self::Bits16Offset0
+            | Self::Bits16Offset1
+            | Self::Bits16Offset2
+            | Self::Bits16Offset3
+            | Self::Bits16Offset3ResShifted => 1,
+        }
+    }
+
+    fn offset<T>(&self, offset: isize) -> Expr<AlgebraicItem<T>> {
+        let column = self.index();
+        let step = match self {
+            Self::Bits16Offset0
+            | Self::Bits16Offset1
+            | Self::Bits16Offset2
+            | Self::Bits16Offset3 => BITWISE_RATIO * CYCLE_HEIGHT / 4,
+            Self::Bits16Offset3ResShifted => BITWISE_RATIO * CYCLE_HEIGHT,
+        } as isize;
+        AlgebraicItem::Trace(column, offset * step + *self as isize).into()
+    }
+}
+
+impl<Fp: GpuFftField + PrimeField, Fq: StarkExtensionOf<Fp>> CairoAirConfig for AirConfig<Fp, Fq> {
+    fn public_memory_challenges(challenges: &Challenges<Self::Fq>) -> (Self::Fq, Self::Fq) {
+        (
+            challenges[MemoryPermutation::Z],
+            challenges[MemoryPermutation::A],
+        )
+    }
+
+    fn public_memory_quotient(hints: &Hints<Self::Fq>) -> Self::Fq {
+        hints[PublicInputHint::MemoryQuotient]
+    }
+}
+
+/// Indices up to `DilutedCheckCumulativeValue` match
+/// [crate::recursive::air::PublicInputHint] since those hints come from
+/// [crate::recursive::air::cpu_hints] and
+/// [crate::recursive::air::diluted_check_hints].
+#[derive(Clone, Copy)]
+pub enum PublicInputHint {
+    InitialAp,
+    InitialPc,
+    FinalAp,
+    FinalPc,
+    MemoryQuotient,
+    RangeCheckProduct,
+    RangeCheckMin,
+    RangeCheckMax,
+    DilutedCheckProduct,
+    DilutedCheckFirst,
+    DilutedCheckCumulativeValue,
+    InitialRcAddr,
+    InitialBitwiseAddr,
+}
+
+impl Hint for PublicInputHint {
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
@@ -0,0 +1,38 @@
+//! Field agnostic variant of the `recursive` layout without the pedersen
+//! builtin. Supports the output, range check and bitwise builtins and can be
+//! instantiated over small fields such as Goldilocks with an extension field.
+//!
+//! Builtin values are limited so every value has a unique representation in a
+//! 64-bit field: range checks are 32-bit (two 16-bit parts) and bitwise
+//! operations act on 63-bit values.
+//!
+//! The CPU, memory, range check and diluted check components are shared with
+//! the `recursive` layout. Only the builtin constraints and trace cells differ.
+
+mod air;
+mod trace;
+
+pub use air::AirConfig;
+pub use trace::ExecutionTrace;
+
+// must be a power-of-two
+pub const CYCLE_HEIGHT: usize = 16;
+/// 8 memory units per cycle and a public memory fraction of 8
+pub const PUBLIC_MEMORY_STEP: usize = 16;
+pub const MEMORY_STEP: usize = 2;
+/// 4 range check units per cycle
+pub const RANGE_CHECK_STEP: usize = 4;
+/// 16 diluted units per cycle i.e. one on every row
+pub const DILUTED_CHECK_STEP: usize = 1;
+
+/// How many cycles per 32 bit range check
+pub const RANGE_CHECK_BUILTIN_RATIO: usize = 8;
+pub const RANGE_CHECK_BUILTIN_PARTS: usize = 2;
+
+pub const NUM_BASE_COLUMNS: usize = 7;
+pub const NUM_EXTENSION_COLUMNS: usize = 3;
+
+pub const DILUTED_CHECK_N_BITS: usize = 16;
+pub const DILUTED_CHECK_SPACING: usize = 4;
+
+pub const BITWISE_RATIO: usize = 8;
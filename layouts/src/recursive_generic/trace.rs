@@ -0,0 +1,477 @@
+use super::air::Bitwise;
+use super::air::RangeCheckBuiltin;
+use super::BITWISE_RATIO;
+use super::CYCLE_HEIGHT;
+use super::DILUTED_CHECK_N_BITS;
+use super::DILUTED_CHECK_SPACING;
+use super::PUBLIC_MEMORY_STEP;
+use super::RANGE_CHECK_BUILTIN_PARTS;
+use super::RANGE_CHECK_BUILTIN_RATIO;
+use super::RANGE_CHECK_STEP;
+use crate::recursive::air::Npc;
+use crate::recursive::air::RangeCheck;
+use crate::recursive::trace::cpu_extension_columns;
+use crate::recursive::trace::fill_cpu_cycles;
+use crate::recursive::trace::generate_memory_column;
+use crate::utils::DilutedCheckPool;
+use crate::utils::RangeCheckPool;
+use crate::CairoTrace;
+use crate::CairoWitness;
+use crate::UnsupportedInstanceError;
+use crate::WitnessMemory;
+use alloc::vec;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_ff::Field;
+use ark_ff::PrimeField;
+use binary::AirPrivateInput;
+use binary::AirPublicInput;
+use binary::BitwiseInstance;
+use binary::BuiltinInstance;
+use binary::CompiledProgram;
+use binary::RangeCheckInstance;
+use binary::ReadMemory;
+use binary::RegisterState;
+use binary::RegisterStates;
+use builtins::bitwise;
+use builtins::bitwise::dilute;
+use builtins::range_check;
+use core::iter::zip;
+use ministark::challenges::Challenges;
+use ministark::utils::GpuAllocator;
+use ministark::utils::GpuVec;
+use ministark::Matrix;
+use ministark::StarkExtensionOf;
+use ministark::Trace;
+use ministark_gpu::GpuFftField;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use ruint::aliases::U256;
+use std::marker::PhantomData;
+
+pub struct ExecutionTrace<Fp: Field, Fq: Field> {
+    pub air_public_input: AirPublicInput<Fp>,
+    pub range_check_min: u16,
+    pub range_check_max: u16,
+    pub initial_registers: RegisterState,
+    pub final_registers: RegisterState,
+    pub initial_rc_address: u32,
+    pub initial_bitwise_address: u32,
+    pub program: CompiledProgram<Fp>,
+    npc_column: GpuVec<Fp>,
+    memory_column: GpuVec<Fp>,
+    diluted_check_ordered_column: GpuVec<Fp>,
+    diluted_check_unordered_column: GpuVec<Fp>,
+    range_check_column: GpuVec<Fp>,
+    base_trace: Matrix<Fp>,
+    _register_states: RegisterStates,
+    _memory: WitnessMemory<Fp>,
+    _flags_column: GpuVec<Fp>,
+    _auxiliary_column: GpuVec<Fp>,
+    _marker: PhantomData<Fq>,
+}
+
+impl<Fp: GpuFftField + PrimeField, Fq: StarkExtensionOf<Fp>> CairoTrace for ExecutionTrace<Fp, Fq> {
+    fn new(
+        program: CompiledProgram<Fp>,
+        air_public_input: AirPublicInput<Fp>,
+        witness: CairoWitness<Fp>,
+    ) -> Self {
+        let CairoWitness {
+            air_private_input,
+            register_states,
+            memory,
+        } = witness;
+
+        assert!(
+            air_private_input.pedersen.is_empty(),
+            "layout does not support Pedersen"
+        );
+
+        let num_cycles = register_states.len();
+        assert!(num_cycles.is_power_of_two());
+        let trace_len = num_cycles * CYCLE_HEIGHT;
+
+        let mut flags_column = Vec::new_in(GpuAllocator);
+        flags_column.resize(trace_len, Fp::zero());
+
+        let padding_entry = air_public_input
+            .public_memory_padding()
+            .unwrap_or_else(|err| panic!("invalid public input: {err}"));
+        let mut npc_column = Vec::new_in(GpuAllocator);
+        npc_column.resize(trace_len, Fp::zero());
+        {
+            // default all memory items to our padding entry
+            // TODO: this is a little hacky. not good
+            let padding_address = padding_entry.address.into();
+            let padding_value = padding_entry.value;
+            for [address, value] in npc_column.array_chunks_mut() {
+                *address = padding_address;
+                *value = padding_value;
+            }
+        }
+
+        // Keep trace of all 16-bit range check values
+        let mut rc_pool = RangeCheckPool::new();
+
+        // add offsets to the range check pool
+        for &RegisterState { pc, .. } in register_states.iter() {
+            let word = memory.get(pc).unwrap();
+            rc_pool.push(word.get_off_dst());
+            rc_pool.push(word.get_off_op0());
+            rc_pool.push(word.get_off_op1());
+        }
+
+        // add 32-bit range check builtin parts to the range check pool
+        let rc_instances = air_private_input.range_check;
+        let rc_traces = rc_instances
+            .into_iter()
+            .map(range_check::InstanceTrace::<RANGE_CHECK_BUILTIN_PARTS>::new)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap_or_else(|err| panic!("unchecked private input: {err}"));
+        for rc_trace in &rc_traces {
+            for part in rc_trace.parts {
+                rc_pool.push(part);
+            }
+        }
+
+        // the runner computes the public range check bounds by splitting range
+        // check builtin values into more parts than this layout does. Add the
+        // public bounds to the pool so the ordered values span the same range.
+        let public_rc_bounds = [air_public_input.rc_min, air_public_input.rc_max];
+        for bound in public_rc_bounds {
+            rc_pool.push(bound);
+        }
+
+        let (ordered_rc_vals, ordered_rc_padding_vals) = rc_pool.get_ordered_values_with_padding();
+        let range_check_min = rc_pool.min().unwrap();
+        let range_check_max = rc_pool.max().unwrap();
+        let range_check_padding_value = Fp::from(range_check_max as u64);
+        // the public bounds occupy unordered cells just like padding values
+        let mut ordered_rc_padding_vals =
+            public_rc_bounds.into_iter().chain(ordered_rc_padding_vals);
+        let mut ordered_rc_vals = ordered_rc_vals.into_iter();
+        let mut range_check_column = Vec::new_in(GpuAllocator);
+        range_check_column.resize(trace_len, range_check_padding_value);
+
+        let mut auxiliary_column = Vec::new_in(GpuAllocator);
+        auxiliary_column.resize(trace_len, Fp::zero());
+
+        fill_cpu_cycles(
+            &register_states,
+            &memory,
+            &mut flags_column,
+            &mut npc_column,
+            &mut range_check_column,
+            &mut auxiliary_column,
+        );
+
+        // create dummy 32-bit range check values that are filled with 16-bit range
+        // check padding values
+        let rc_dummy_traces = (rc_traces.len()..num_cycles / RANGE_CHECK_BUILTIN_RATIO)
+            .map(|index| {
+                let mut value = U256::ZERO;
+                for _ in 0..RANGE_CHECK_BUILTIN_PARTS {
+                    let part = ordered_rc_padding_vals.next().unwrap_or(range_check_max);
+                    value = (value << 16) + U256::from(part)
+                }
+
+                // built from 16-bit parts so the value always fits
+                range_check::InstanceTrace::<RANGE_CHECK_BUILTIN_PARTS>::new(RangeCheckInstance {
+                    index: index as u32,
+                    value,
+                })
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        // number of cycles between each range check builtin part
+        const RC_PART_CYCLES: usize = RANGE_CHECK_BUILTIN_RATIO / RANGE_CHECK_BUILTIN_PARTS;
+
+        for cycle in 0..num_cycles {
+            let cycle_offset = CYCLE_HEIGHT * cycle;
+            let rc_virtual_row = &mut range_check_column[cycle_offset..cycle_offset + CYCLE_HEIGHT];
+
+            // overwrite the range check padding cell with remaining padding values
+            // skipping cycles used for 32-bit range check parts
+            if cycle % RC_PART_CYCLES != 0 {
+                rc_virtual_row[RangeCheck::Unused as usize] =
+                    if let Some(val) = ordered_rc_padding_vals.next() {
+                        (val as u64).into()
+                    } else {
+                        range_check_padding_value
+                    };
+            }
+
+            // add ordered range check values
+            for offset in (0..CYCLE_HEIGHT).step_by(RANGE_CHECK_STEP) {
+                rc_virtual_row[offset + RangeCheck::Ordered as usize] =
+                    if let Some(val) = ordered_rc_vals.next() {
+                        (val as u64).into()
+                    } else {
+                        range_check_padding_value
+                    };
+            }
+        }
+
+        // ensure range check values have been fully consumed
+        assert!(ordered_rc_padding_vals.next().is_none());
+        assert!(ordered_rc_vals.next().is_none());
+
+        // Diluted check
+        // =============
+        let mut diluted_check_ordered_column = Vec::new_in(GpuAllocator);
+        diluted_check_ordered_column.resize(trace_len, Fp::ZERO);
+        let mut diluted_check_unordered_column = Vec::new_in(GpuAllocator);
+        diluted_check_unordered_column.resize(trace_len, Fp::ZERO);
+
+        // Generate trace for range check builtin
+        // ======================================
+        const RC_STEP_ROWS: usize = RANGE_CHECK_BUILTIN_RATIO * CYCLE_HEIGHT;
+        let (rc_range_check_steps, _) = range_check_column.as_chunks_mut::<RC_STEP_ROWS>();
+        let (rc_npc_steps, _) = npc_column.as_chunks_mut::<RC_STEP_ROWS>();
+
+        let rc_memory_segment = air_public_input
+            .memory_segments
+            .range_check
+            .expect("layout requires a range check memory segment");
+        let initial_rc_address = rc_memory_segment.begin_addr;
+
+        ark_std::cfg_iter_mut!(rc_range_check_steps)
+            .zip(rc_npc_steps)
+            .zip(ark_std::cfg_into_iter!(rc_traces).chain(rc_dummy_traces))
+            .for_each(|((rc, npc), rc_trace)| {
+                // add the 32-bit range check to the 16-bit range check pool
+                const RC_PART_ROWS: usize = RC_STEP_ROWS / RANGE_CHECK_BUILTIN_PARTS;
+                for (i, part) in rc_trace.parts.into_iter().enumerate() {
+                    rc[RangeCheckBuiltin::Rc16Component as usize + RC_PART_ROWS * i] =
+                        (part as u64).into();
+                }
+
+                // add the range check to the memory pool
+                let instance = rc_trace.instance;
+                let addr = instance.mem_addrs(initial_rc_address);
+                npc[Npc::RangeCheck128Addr as usize] = addr.into();
+                npc[Npc::RangeCheck128Val as usize] = instance.value.as_limbs()[0].into();
+            });
+
+        // Generate trace for bitwise builtin
+        // ==================================
+        let bitwise_memory_segment = air_public_input
+            .memory_segments
+            .bitwise
+            .expect("layout requires a bitwise memory segment");
+        let initial_bitwise_address = bitwise_memory_segment.begin_addr;
+
+        // create dummy instances if there are cells that need to be filled
+        let bitwise_instances = air_private_input.bitwise;
+        let num_bitwise_instances = bitwise_instances.len() as u32;
+        let bitwise_dummy_instances = (num_bitwise_instances..).map(BitwiseInstance::new_empty);
+        let bitwise_traces = bitwise_instances
+            .into_iter()
+            .chain(bitwise_dummy_instances)
+            .map(|instance| {
+                bitwise::InstanceTrace64::<Fp, DILUTED_CHECK_SPACING>::new(instance)
+                    .unwrap_or_else(|err| panic!("unchecked private input: {err}"))
+            });
+
+        const BITWISE_STEP_ROWS: usize = BITWISE_RATIO * CYCLE_HEIGHT;
+        // rows between the partitions of `x`, `y`, `x&y` and `x^y`
+        const BITWISE_PARTITION_ROWS: usize = BITWISE_STEP_ROWS / 4;
+        let (bitwise_npc_steps, _) = npc_column.as_chunks_mut::<BITWISE_STEP_ROWS>();
+        let (bitwise_dilution_steps, _) =
+            diluted_check_unordered_column.as_chunks_mut::<BITWISE_STEP_ROWS>();
+
+        let diluted_check_pool = bitwise_npc_steps
+            .iter_mut()
+            .zip(bitwise_dilution_steps)
+            .zip(bitwise_traces)
+            .fold(
+                DilutedCheckPool::<DILUTED_CHECK_N_BITS, DILUTED_CHECK_SPACING>::new(),
+                |mut diluted_pool, ((npc, dilution), bitwise_trace)| {
+                    let instance = bitwise_trace.instance;
+
+                    {
+                        // add shift to ensure a unique unpacking
+                        let v =
+                            bitwise_trace.x_and_y_partition[3] + bitwise_trace.x_xor_y_partition[3];
+                        // only fails if the AIR will error
+                        assert_eq!(v, (v << 4) >> 4);
+                        let s = v << 4;
+                        diluted_pool.push_diluted(U256::from(s));
+                        dilution[Bitwise::Bits16Offset3ResShifted as usize] = s.into();
+                    }
+
+                    // NOTE: the order of these partitions matters
+                    let partitions = [
+                        bitwise_trace.x_partition,
+                        bitwise_trace.y_partition,
+                        bitwise_trace.x_and_y_partition,
+                        bitwise_trace.x_xor_y_partition,
+                    ];
+
+                    // load diluted partitions into the execution trace
+                    let (dilution_steps, _) = dilution.as_chunks_mut::<BITWISE_PARTITION_ROWS>();
+                    for (dilution_step, partition) in zip(dilution_steps, partitions) {
+                        dilution_step[Bitwise::Bits16Offset0 as usize] = partition[0].into();
+                        dilution_step[Bitwise::Bits16Offset1 as usize] = partition[1].into();
+                        dilution_step[Bitwise::Bits16Offset2 as usize] = partition[2].into();
+                        dilution_step[Bitwise::Bits16Offset3 as usize] = partition[3].into();
+
+                        for v in *partition {
+                            diluted_pool.push_diluted(U256::from(v))
+                        }
+                    }
+
+                    // load bitwise values into memory
+                    let input_x_offset = Npc::BitwisePoolAddr as usize;
+                    let input_y_offset = input_x_offset + BITWISE_PARTITION_ROWS;
+                    let x_and_y_offset = input_y_offset + BITWISE_PARTITION_ROWS;
+                    let x_xor_y_offset = x_and_y_offset + BITWISE_PARTITION_ROWS;
+                    let x_or_y_offset = Npc::BitwiseXOrYAddr as usize;
+                    let addrs = instance.mem_addrs(initial_bitwise_address);
+                    npc[input_x_offset] = addrs.x.into();
+                    npc[input_x_offset + 1] = bitwise_trace.x;
+                    npc[input_y_offset] = addrs.y.into();
+                    npc[input_y_offset + 1] = bitwise_trace.y;
+                    npc[x_and_y_offset] = addrs.and.into();
+                    npc[x_and_y_offset + 1] = bitwise_trace.x_and_y;
+                    npc[x_xor_y_offset] = addrs.xor.into();
+                    npc[x_xor_y_offset + 1] = bitwise_trace.x_xor_y;
+                    npc[x_or_y_offset] = addrs.or.into();
+                    npc[x_or_y_offset + 1] = bitwise_trace.x_or_y;
+
+                    // return the diluted pool
+                    diluted_pool
+                },
+            );
+
+        // make sure all diluted check values are encountered for
+        // NOTE: diluted values are at most `SPACING * (N_BITS - 1) + 1` bits so fit in
+        // a u64
+        const DILUTED_MIN: u128 = 0;
+        const DILUTED_MAX: u128 = (1 << DILUTED_CHECK_N_BITS) - 1;
+        let into_diluted_felt =
+            |v: u128| Fp::from(dilute::<DILUTED_CHECK_SPACING>(U256::from(v)).as_limbs()[0]);
+        let (ordered_diluted_vals, ordered_diluted_padding_vals) =
+            diluted_check_pool.get_ordered_values_with_padding(DILUTED_MIN, DILUTED_MAX);
+        let mut ordered_diluted_vals = ark_std::cfg_into_iter!(ordered_diluted_vals)
+            .map(into_diluted_felt)
+            .collect::<Vec<Fp>>()
+            .into_iter();
+        let mut ordered_diluted_padding_vals =
+            ark_std::cfg_into_iter!(ordered_diluted_padding_vals)
+                .map(into_diluted_felt)
+                .collect::<Vec<Fp>>()
+                .into_iter();
+
+        // add diluted padding values to all cells not used by the bitwise builtin
+        let is_bitwise_cell = |offset: usize| {
+            offset == Bitwise::Bits16Offset3ResShifted as usize
+                || (offset % BITWISE_PARTITION_ROWS <= Bitwise::Bits16Offset3 as usize
+                    && offset % 2 == 0)
+        };
+        let (bitwise_dilution_chunks, _) =
+            diluted_check_unordered_column.as_chunks_mut::<BITWISE_STEP_ROWS>();
+        'outer: for bitwise_dilution_chunk in bitwise_dilution_chunks {
+            for (offset, unordered_dilution_step) in bitwise_dilution_chunk.iter_mut().enumerate() {
+                if !is_bitwise_cell(offset) {
+                    if let Some(padding_val) = ordered_diluted_padding_vals.next() {
+                        *unordered_dilution_step = padding_val;
+                    } else {
+                        break 'outer;
+                    }
+                }
+            }
+        }
+
+        // add ordered diluted check values
+        let padding_offset = diluted_check_ordered_column.len() - ordered_diluted_vals.len();
+        for diluted_val in &mut diluted_check_ordered_column[padding_offset..] {
+            *diluted_val = ordered_diluted_vals.next().unwrap();
+        }
+
+        // ensure dilution check values have been fully consumed
+        assert!(ordered_diluted_padding_vals.next().is_none());
+        assert!(ordered_diluted_vals.next().is_none());
+
+        // VM Memory
+        // =========
+        // generate the memory column by ordering memory accesses
+        let memory_column = generate_memory_column::<PUBLIC_MEMORY_STEP, Fp>(
+            &mut npc_column,
+            &air_public_input.public_memory,
+            padding_entry,
+        );
+
+        let base_trace = Matrix::new(vec![
+            flags_column.to_vec_in(GpuAllocator),
+            diluted_check_unordered_column.to_vec_in(GpuAllocator),
+            diluted_check_ordered_column.to_vec_in(GpuAllocator),
+            npc_column.to_vec_in(GpuAllocator),
+            memory_column.to_vec_in(GpuAllocator),
+            range_check_column.to_vec_in(GpuAllocator),
+            auxiliary_column.to_vec_in(GpuAllocator),
+        ]);
+
+        let initial_registers = *register_states.first().unwrap();
+        let final_registers = *register_states.last().unwrap();
+
+        ExecutionTrace {
+            air_public_input,
+            range_check_min,
+            range_check_max,
+            initial_registers,
+            final_registers,
+            diluted_check_ordered_column,
+            diluted_check_unordered_column,
+            npc_column,
+            memory_column,
+            range_check_column,
+            base_trace,
+            initial_rc_address,
+            initial_bitwise_address,
+            program,
+            _flags_column: flags_column,
+            _auxiliary_column: auxiliary_column,
+            _memory: memory,
+            _register_states: register_states,
+            _marker: PhantomData,
+        }
+    }
+
+    fn check_private_input(
+        air_private_input: &AirPrivateInput,
+    ) -> Result<(), UnsupportedInstanceError> {
+        if !air_private_input.pedersen.is_empty() {
+            return Err(UnsupportedInstanceError::UnsupportedBuiltin("pedersen"));
+        }
+        for &instance in &air_private_input.range_check {
+            range_check::InstanceTrace::<RANGE_CHECK_BUILTIN_PARTS>::new(instance)?;
+        }
+        for &instance in &air_private_input.bitwise {
+            bitwise::InstanceTrace64::<Fp, DILUTED_CHECK_SPACING>::new(instance)?;
+        }
+        Ok(())
+    }
+}
+
+impl<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>> Trace for ExecutionTrace<Fp, Fq> {
+    type Fp = Fp;
+    type Fq = Fq;
+
+    fn base_columns(&self) -> &Matrix<Self::Fp> {
+        &self.base_trace
+    }
+
+    fn build_extension_columns(&self, challenges: &Challenges<Fq>) -> Option<Matrix<Fq>> {
+        Some(cpu_extension_columns(
+            challenges,
+            &self.npc_column,
+            &self.memory_column,
+            &self.range_check_column,
+            &self.diluted_check_unordered_column,
+            &self.diluted_check_ordered_column,
+        ))
+    }
+}
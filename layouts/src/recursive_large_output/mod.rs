@@ -0,0 +1,50 @@
+//! Matches the builtins and ratios of the `recursive_large_output` layout from
+//! StarkWare's open source verifier
+//! <https://github.com/starkware-libs/cairo-lang/blob/361fe32d5930db340ea78fe05aedfe706f6c9405/src/starkware/cairo/lang/instances.py>
+//!
+//! The AIR and trace are built by [crate::dynamic]. The CPU and diluted pool
+//! are the same as [crate::recursive] with the poseidon builtin in two extra
+//! columns. Cells aren't in the same place as StarkWare's layout so proofs can
+//! only be verified by sandstorm.
+
+use crate::dynamic;
+use crate::dynamic::DynamicLayout;
+use crate::dynamic::LayoutParams;
+use binary::Layout;
+
+// must be a power-of-two
+pub const CYCLE_HEIGHT: usize = 16;
+/// 8 memory units per cycle and a public memory fraction of 8. Builtin memory
+/// cells need both unused npc cells of each cycle so public memory can't use
+/// more cells than in the recursive layout.
+pub const PUBLIC_MEMORY_STEP: usize = 16;
+pub const MEMORY_STEP: usize = 2;
+/// 4 range check units per cycle
+pub const RANGE_CHECK_STEP: usize = 4;
+/// 16 diluted units per cycle i.e. one on every row
+pub const DILUTED_CHECK_STEP: usize = 1;
+
+/// How many cycles per pedersen hash
+pub const PEDERSEN_BUILTIN_RATIO: usize = 128;
+
+/// How many cycles per 128 bit range check
+pub const RANGE_CHECK_BUILTIN_RATIO: usize = 8;
+
+pub const BITWISE_RATIO: usize = 8;
+
+/// How many cycles per Poseidon permutation
+pub const POSEIDON_RATIO: usize = 8;
+
+/// CPU columns, two pedersen columns and two poseidon columns
+pub const NUM_BASE_COLUMNS: usize = 11;
+pub const NUM_EXTENSION_COLUMNS: usize = 3;
+
+pub struct RecursiveLargeOutput;
+
+impl DynamicLayout for RecursiveLargeOutput {
+    const LAYOUT: Layout = Layout::RecursiveLargeOutput;
+    const PARAMS: LayoutParams = LayoutParams::recursive_large_output();
+}
+
+pub type AirConfig = dynamic::AirConfig<RecursiveLargeOutput>;
+pub type ExecutionTrace = dynamic::ExecutionTrace<RecursiveLargeOutput>;
@@ -2432,8 +2432,7 @@ impl ministark::air::AirConfig for AirConfig {
                 challenges[MemoryPermutation::Z],
                 challenges[MemoryPermutation::A],
                 trace_len,
-                &execution_info.public_memory,
-                execution_info.public_memory_padding(),
+                execution_info,
             );
 
         let diluted_cumulative_val = compute_diluted_cumulative_value::<
@@ -2446,8 +2445,6 @@ impl ministark::air::AirConfig for AirConfig {
             challenges[DilutedCheckAggregation::A],
         );
 
-        // TODO: add validation on the AirPublicInput struct
-        // assert!(range_check_min <= range_check_max);
         let initial_ap = execution_info.initial_ap().into();
         let final_ap = execution_info.final_ap().into();
         let initial_pc = execution_info.initial_pc().into();
@@ -9,6 +9,7 @@ use super::RANGE_CHECK_STEP;
 use ark_ff::BigInt;
 use ark_ff::Zero;
 use binary::BitwiseInstance;
+use binary::BuiltinInstance;
 use binary::MemoryEntry;
 use ark_ff::PrimeField;
 use binary::PedersenInstance;
@@ -20,12 +21,14 @@ use builtins::ec_op;
 use builtins::ecdsa;
 use builtins::pedersen;
 use ark_ff::One;
+use binary::AirPrivateInput;
 use binary::AirPublicInput;
 use builtins::poseidon;
 use builtins::range_check;
 use num_bigint::BigUint;
 use ruint::aliases::U256;
 use crate::CairoWitness;
+use crate::WitnessMemory;
 use crate::starknet::air::Poseidon;
 use super::BITWISE_RATIO;
 use super::DILUTED_CHECK_N_BITS;
@@ -53,11 +56,12 @@ use ark_ff::Field;
 use super::MEMORY_STEP;
 use crate::utils::get_ordered_memory_accesses;
 use crate::CairoTrace;
+use crate::UnsupportedInstanceError;
 use alloc::vec;
 use alloc::vec::Vec;
 use ark_ff::batch_inversion;
 use binary::CompiledProgram;
-use binary::Memory;
+use binary::ReadMemory;
 use binary::RegisterState;
 use binary::RegisterStates;
 use core::iter::zip;
@@ -90,7 +94,7 @@ pub struct ExecutionTrace {
     range_check_column: GpuVec<Fp>,
     base_trace: Matrix<Fp>,
     _register_states: RegisterStates,
-    _memory: Memory<Fp>,
+    _memory: WitnessMemory<Fp>,
     _flags_column: GpuVec<Fp>,
     _auxiliary_column: GpuVec<Fp>,
 }
@@ -125,7 +129,9 @@ impl CairoTrace for ExecutionTrace {
         let mut flags_column = Vec::new_in(GpuAllocator);
         flags_column.resize(trace_len, Fp::zero());
 
-        let padding_entry = air_public_input.public_memory_padding();
+        let padding_entry = air_public_input
+            .public_memory_padding()
+            .unwrap_or_else(|err| panic!("invalid public input: {err}"));
         let mut npc_column = Vec::new_in(GpuAllocator);
         npc_column.resize(trace_len, Fp::zero());
         {
@@ -144,7 +150,7 @@ impl CairoTrace for ExecutionTrace {
 
         // add offsets to the range check pool
         for &RegisterState { pc, .. } in register_states.iter() {
-            let word = memory[pc].unwrap();
+            let word = memory.get(pc).unwrap();
             rc_pool.push(word.get_off_dst());
             rc_pool.push(word.get_off_op0());
             rc_pool.push(word.get_off_op1());
@@ -155,7 +161,8 @@ impl CairoTrace for ExecutionTrace {
         let rc128_traces = rc128_instances
             .into_iter()
             .map(range_check::InstanceTrace::<RANGE_CHECK_BUILTIN_PARTS>::new)
-            .collect::<Vec<_>>();
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap_or_else(|err| panic!("unchecked private input: {err}"));
         for rc128_trace in &rc128_traces {
             for part in rc128_trace.parts {
                 rc_pool.push(part);
@@ -187,7 +194,7 @@ impl CairoTrace for ExecutionTrace {
             .for_each(
                 |((((rc_cycle, aux_cycle), npc_cycle), flag_cycle), registers)| {
                     let &RegisterState { pc, ap, fp } = registers;
-                    let insrtuction = memory[pc].unwrap();
+                    let insrtuction = memory.get(pc).unwrap();
                     let insrtuction_felt = insrtuction.into_felt();
                     debug_assert!(!insrtuction.get_flag(Flag::Zero.into()));
 
@@ -253,10 +260,12 @@ impl CairoTrace for ExecutionTrace {
                     value = (value << 16) + U256::from(part)
                 }
 
+                // built from 16-bit parts so the value always fits
                 range_check::InstanceTrace::<RANGE_CHECK_BUILTIN_PARTS>::new(RangeCheckInstance {
                     index: index as u32,
                     value,
                 })
+                .unwrap()
             })
             .collect::<Vec<_>>();
 
@@ -375,12 +384,12 @@ impl CairoTrace for ExecutionTrace {
 
                     // add the hash to the memory pool
                     let instance = pedersen_trace.instance;
-                    let (a_addr, b_addr, output_addr) = instance.mem_addr(initial_pedersen_address);
-                    npc[Npc::PedersenInput0Addr as usize] = a_addr.into();
+                    let addrs = instance.mem_addrs(initial_pedersen_address);
+                    npc[Npc::PedersenInput0Addr as usize] = addrs.a.into();
                     npc[Npc::PedersenInput0Val as usize] = Fp::from(BigUint::from(instance.a));
-                    npc[Npc::PedersenInput1Addr as usize] = b_addr.into();
+                    npc[Npc::PedersenInput1Addr as usize] = addrs.b.into();
                     npc[Npc::PedersenInput1Val as usize] = Fp::from(BigUint::from(instance.b));
-                    npc[Npc::PedersenOutputAddr as usize] = output_addr.into();
+                    npc[Npc::PedersenOutputAddr as usize] = addrs.output.into();
                     npc[Npc::PedersenOutputVal as usize] = pedersen_trace.output;
                 },
             );
@@ -420,7 +429,7 @@ impl CairoTrace for ExecutionTrace {
 
                 // add the range check to the memory pool
                 let instance = rc_trace.instance;
-                let addr = instance.mem_addr(initial_rc_address);
+                let addr = instance.mem_addrs(initial_rc_address);
                 npc[Npc::RangeCheck128Addr as usize] = addr.into();
                 npc[Npc::RangeCheck128Val as usize] = Fp::from(BigUint::from(instance.value));
             });
@@ -515,10 +524,10 @@ impl CairoTrace for ExecutionTrace {
                 aux[Ecdsa::PubkeyXSquared as usize] = pubkey.x.square();
 
                 // add the instance to the memory pool
-                let (pubkey_addr, message_addr) = instance.mem_addr(initial_ecdsa_address);
-                npc[Npc::EcdsaPubkeyAddr as usize] = pubkey_addr.into();
+                let addrs = instance.mem_addrs(initial_ecdsa_address);
+                npc[Npc::EcdsaPubkeyAddr as usize] = addrs.pubkey.into();
                 npc[Npc::EcdsaPubkeyVal as usize] = pubkey.x;
-                npc[Npc::EcdsaMessageAddr as usize] = message_addr.into();
+                npc[Npc::EcdsaMessageAddr as usize] = addrs.message.into();
                 npc[Npc::EcdsaMessageVal as usize] = message;
             });
 
@@ -632,17 +641,16 @@ impl CairoTrace for ExecutionTrace {
                     let x_and_y_offset = input_y_offset + addr_step;
                     let x_xor_y_offset = x_and_y_offset + addr_step;
                     let x_or_y_offset = Npc::BitwiseXOrYAddr as usize;
-                    let (input_x_addr, input_y_addr, x_and_y_addr, x_xor_y_addr, x_or_y_addr) =
-                        instance.mem_addr(initial_bitwise_address);
-                    npc[input_x_offset] = input_x_addr.into();
+                    let addrs = instance.mem_addrs(initial_bitwise_address);
+                    npc[input_x_offset] = addrs.x.into();
                     npc[input_x_offset + 1] = bitwise_trace.x;
-                    npc[input_y_offset] = input_y_addr.into();
+                    npc[input_y_offset] = addrs.y.into();
                     npc[input_y_offset + 1] = bitwise_trace.y;
-                    npc[x_and_y_offset] = x_and_y_addr.into();
+                    npc[x_and_y_offset] = addrs.and.into();
                     npc[x_and_y_offset + 1] = bitwise_trace.x_and_y;
-                    npc[x_xor_y_offset] = x_xor_y_addr.into();
+                    npc[x_xor_y_offset] = addrs.xor.into();
                     npc[x_xor_y_offset + 1] = bitwise_trace.x_xor_y;
-                    npc[x_or_y_offset] = x_or_y_addr.into();
+                    npc[x_or_y_offset] = addrs.or.into();
                     npc[x_or_y_offset + 1] = bitwise_trace.x_or_y;
 
                     // return the diluted pool
@@ -758,21 +766,20 @@ impl CairoTrace for ExecutionTrace {
 
                 // load EC op values into memory
                 let instance = ec_op_trace.instance;
-                let (p_x_addr, p_y_addr, q_x_addr, q_y_addr, m_addr, r_x_addr, r_y_addr) =
-                    instance.mem_addr(initial_ec_op_address);
-                npc[Npc::EcOpPXAddr as usize] = p_x_addr.into();
+                let addrs = instance.mem_addrs(initial_ec_op_address);
+                npc[Npc::EcOpPXAddr as usize] = addrs.p_x.into();
                 npc[Npc::EcOpPXVal as usize] = ec_op_trace.p.x;
-                npc[Npc::EcOpPYAddr as usize] = p_y_addr.into();
+                npc[Npc::EcOpPYAddr as usize] = addrs.p_y.into();
                 npc[Npc::EcOpPYVal as usize] = ec_op_trace.p.y;
-                npc[Npc::EcOpQXAddr as usize] = q_x_addr.into();
+                npc[Npc::EcOpQXAddr as usize] = addrs.q_x.into();
                 npc[Npc::EcOpQXVal as usize] = ec_op_trace.q.x;
-                npc[Npc::EcOpQYAddr as usize] = q_y_addr.into();
+                npc[Npc::EcOpQYAddr as usize] = addrs.q_y.into();
                 npc[Npc::EcOpQYVal as usize] = ec_op_trace.q.y;
-                npc[Npc::EcOpMAddr as usize] = m_addr.into();
+                npc[Npc::EcOpMAddr as usize] = addrs.m.into();
                 npc[Npc::EcOpMVal as usize] = ec_op_trace.m;
-                npc[Npc::EcOpRXAddr as usize] = r_x_addr.into();
+                npc[Npc::EcOpRXAddr as usize] = addrs.r_x.into();
                 npc[Npc::EcOpRXVal as usize] = ec_op_trace.r.x;
-                npc[Npc::EcOpRYAddr as usize] = r_y_addr.into();
+                npc[Npc::EcOpRYAddr as usize] = addrs.r_y.into();
                 npc[Npc::EcOpRYVal as usize] = ec_op_trace.r.y;
             });
 
@@ -865,25 +872,18 @@ impl CairoTrace for ExecutionTrace {
 
                 // load EC op values into memory
                 let instance = poseidon_trace.instance;
-                let (
-                    input0_addr,
-                    input1_addr,
-                    input2_addr,
-                    output0_addr,
-                    output1_addr,
-                    output2_addr,
-                ) = instance.mem_addr(initial_poseidon_address);
-                npc[Npc::PoseidonInput0Addr as usize] = input0_addr.into();
+                let addrs = instance.mem_addrs(initial_poseidon_address);
+                npc[Npc::PoseidonInput0Addr as usize] = addrs.input0.into();
                 npc[Npc::PoseidonInput0Val as usize] = poseidon_trace.input0;
-                npc[Npc::PoseidonInput1Addr as usize] = input1_addr.into();
+                npc[Npc::PoseidonInput1Addr as usize] = addrs.input1.into();
                 npc[Npc::PoseidonInput1Val as usize] = poseidon_trace.input1;
-                npc[Npc::PoseidonInput2Addr as usize] = input2_addr.into();
+                npc[Npc::PoseidonInput2Addr as usize] = addrs.input2.into();
                 npc[Npc::PoseidonInput2Val as usize] = poseidon_trace.input2;
-                npc[Npc::PoseidonOutput0Addr as usize] = output0_addr.into();
+                npc[Npc::PoseidonOutput0Addr as usize] = addrs.output0.into();
                 npc[Npc::PoseidonOutput0Val as usize] = poseidon_trace.output0;
-                npc[Npc::PoseidonOutput1Addr as usize] = output1_addr.into();
+                npc[Npc::PoseidonOutput1Addr as usize] = addrs.output1.into();
                 npc[Npc::PoseidonOutput1Val as usize] = poseidon_trace.output1;
-                npc[Npc::PoseidonOutput2Addr as usize] = output2_addr.into();
+                npc[Npc::PoseidonOutput2Addr as usize] = addrs.output2.into();
                 npc[Npc::PoseidonOutput2Val as usize] = poseidon_trace.output2;
             });
 
@@ -984,6 +984,15 @@ impl CairoTrace for ExecutionTrace {
             _register_states: register_states,
         }
     }
+
+    fn check_private_input(
+        air_private_input: &AirPrivateInput,
+    ) -> Result<(), UnsupportedInstanceError> {
+        for &instance in &air_private_input.range_check {
+            range_check::InstanceTrace::<RANGE_CHECK_BUILTIN_PARTS>::new(instance)?;
+        }
+        Ok(())
+    }
 }
 
 impl Trace for ExecutionTrace {
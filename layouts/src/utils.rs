@@ -1,6 +1,8 @@
 use ark_ff::Field;
 use ark_ff::PrimeField;
+use binary::AirPublicInput;
 use binary::MemoryEntry;
+use core::iter::zip;
 use ministark::utils::FieldVariant;
 use ministark::StarkExtensionOf;
 use ministark_gpu::GpuFftField;
@@ -19,23 +21,49 @@ pub fn compute_public_memory_quotient<
     z: Fq,
     alpha: Fq,
     trace_len: usize,
-    public_memory: &[MemoryEntry<Fp>],
-    public_memory_padding: MemoryEntry<Fp>,
+    air_public_input: &AirPublicInput<Fp>,
 ) -> Fq {
+    let page_products = compute_public_memory_page_products(z, alpha, air_public_input);
+    compute_public_memory_quotient_from_page_products::<PUBLIC_MEMORY_STEP, Fp, Fq>(
+        z,
+        alpha,
+        trace_len,
+        air_public_input,
+        &page_products,
+    )
+}
+
+/// Computes the value of the public memory quotient from the product of each
+/// public memory page. Products are ordered with the main page first followed
+/// by the continuous pages. This mirrors StarkWare's verifier where the page
+/// products are supplied as part of the public input and checked against facts
+/// registered in the `MemoryPageFactRegistry`.
+pub fn compute_public_memory_quotient_from_page_products<
+    const PUBLIC_MEMORY_STEP: usize,
+    Fp: GpuFftField + PrimeField,
+    Fq: StarkExtensionOf<Fp>,
+>(
+    z: Fq,
+    alpha: Fq,
+    trace_len: usize,
+    air_public_input: &AirPublicInput<Fp>,
+    page_products: &[Fq],
+) -> Fq {
+    assert_eq!(1 + air_public_input.memory_pages.len(), page_products.len());
     // the actual number of public memory cells
-    let n = public_memory.len();
+    let n = air_public_input.public_memory.len();
     // the num of cells allocated for the pub mem (include padding)
     let s = trace_len / PUBLIC_MEMORY_STEP;
 
     // numerator = (z - (0 + alpha * 0))^S,
     let numerator = z.pow([s as u64]);
-    // denominator = \prod_i( z - (addr_i + alpha * value_i) ),
-    let denominator = public_memory
-        .iter()
-        .map(|e| z - (alpha * e.value + Fp::from(e.address)))
-        .product::<Fq>();
+    // denominator = \prod_{page} \prod_i( z - (addr_i + alpha * value_i) ),
+    let denominator = page_products.iter().product::<Fq>();
     let padding = {
         // padding = (z - (padding_addr + alpha * padding_value))^(S - N),
+        let public_memory_padding = air_public_input
+            .public_memory_padding()
+            .unwrap_or_else(|err| panic!("invalid public input: {err}"));
         let padding_address = Fp::from(public_memory_padding.address);
         let padding_value = public_memory_padding.value;
         (z - (alpha * padding_value + padding_address)).pow([(s - n) as u64])
@@ -45,6 +73,62 @@ pub fn compute_public_memory_quotient<
     numerator / (denominator * padding)
 }
 
+/// Computes the product of each public memory page
+/// Output has the main page product first followed by the continuous pages.
+/// Panics if a continuous page is missing entries (see
+/// [AirPublicInput::validate]).
+pub fn compute_public_memory_page_products<
+    Fp: GpuFftField + PrimeField,
+    Fq: StarkExtensionOf<Fp>,
+>(
+    z: Fq,
+    alpha: Fq,
+    air_public_input: &AirPublicInput<Fp>,
+) -> Vec<Fq> {
+    let main_page_product =
+        compute_public_memory_page_product(z, alpha, &air_public_input.main_page());
+    let continuous_page_values = (air_public_input.continuous_page_values())
+        .unwrap_or_else(|err| panic!("invalid public input: {err}"));
+    let continuous_page_products =
+        zip(&air_public_input.memory_pages, continuous_page_values).map(|(page, values)| {
+            compute_continuous_page_product(z, alpha, page.start_address, &values)
+        });
+    [main_page_product]
+        .into_iter()
+        .chain(continuous_page_products)
+        .collect()
+}
+
+/// Computes the product of a public memory page:
+/// $\prod_i( z - (addr_i + alpha * value_i) )$
+pub fn compute_public_memory_page_product<
+    Fp: GpuFftField + PrimeField,
+    Fq: StarkExtensionOf<Fp>,
+>(
+    z: Fq,
+    alpha: Fq,
+    entries: &[MemoryEntry<Fp>],
+) -> Fq {
+    entries
+        .iter()
+        .map(|e| z - (alpha * e.value + Fp::from(e.address)))
+        .product::<Fq>()
+}
+
+/// Computes the product of a continuous public memory page where the address of
+/// the i-th value is implicitly `start_address + i`:
+/// $\prod_i( z - (start_address + i + alpha * value_i) )$
+pub fn compute_continuous_page_product<Fp: GpuFftField + PrimeField, Fq: StarkExtensionOf<Fp>>(
+    z: Fq,
+    alpha: Fq,
+    start_address: u32,
+    values: &[Fp],
+) -> Fq {
+    zip(start_address.., values)
+        .map(|(address, &value)| z - (alpha * value + Fp::from(address)))
+        .product::<Fq>()
+}
+
 /// Source: https://github.com/starkware-libs/starkex-contracts
 ///
 /// # Context
@@ -89,7 +173,10 @@ pub fn compute_diluted_cumulative_value<
     z: Fq,
     alpha: Fq,
 ) -> Fq {
-    assert!(SPACING * N_BITS < Fp::MODULUS_BIT_SIZE as usize);
+    // the largest diluted value has its top bit at `SPACING * (N_BITS - 1)` and
+    // must be smaller than the modulus (e.g. bit 60 for Goldilocks with the
+    // recursive_generic layout's 16 bits and spacing of 4)
+    assert!(SPACING * (N_BITS - 1) + 1 < Fp::MODULUS_BIT_SIZE as usize);
     assert!(SPACING < u64::BITS as usize);
     let diff_multiplier = Fp::from(1u64 << SPACING);
     let mut diff_x = Fp::from((1u64 << SPACING) - 2);
@@ -405,3 +492,92 @@ pub const fn map_into_fp_array<Fp: Field, Fq: Field, const N: usize>(
     }
     res
 }
+
+#[cfg(test)]
+mod tests {
+    use super::compute_diluted_cumulative_value;
+    use super::compute_public_memory_page_products;
+    use super::compute_public_memory_quotient;
+    use ark_ff::Field;
+    use binary::AirPublicInput;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fq3;
+    use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp as StarkFp;
+    use serde_json::Value;
+    use std::fs::File;
+
+    /// Reads the bootloader public input with the output entries at the given
+    /// addresses moved to a page
+    fn read_bootloader_public_input(pages: &[(u32, u32)]) -> AirPublicInput<StarkFp> {
+        let path = format!(
+            "{}/../example/bootloader/air-public-input.json",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        let mut json: Value = serde_json::from_reader(File::open(path).unwrap()).unwrap();
+        for entry in json["public_memory"].as_array_mut().unwrap() {
+            let address = entry["address"].as_u64().unwrap() as u32;
+            if let Some((_, page)) = pages.iter().find(|(a, _)| *a == address) {
+                entry["page"] = Value::from(*page);
+            }
+        }
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn multi_page_public_memory_quotient_matches_single_page() {
+        const PUBLIC_MEMORY_STEP: usize = 16;
+        let single_page = read_bootloader_public_input(&[]);
+        let multi_page = read_bootloader_public_input(&[(1177, 1), (1178, 2), (1179, 2)]);
+        let trace_len = single_page.n_steps as usize * 16;
+        let z = StarkFp::from(3u8);
+        let alpha = StarkFp::from(5u8);
+
+        let single_page_products = compute_public_memory_page_products(z, alpha, &single_page);
+        let multi_page_products = compute_public_memory_page_products(z, alpha, &multi_page);
+
+        assert_eq!(1, single_page_products.len());
+        assert_eq!(3, multi_page_products.len());
+        assert_eq!(
+            single_page_products[0],
+            multi_page_products.iter().product::<StarkFp>()
+        );
+        assert_eq!(
+            compute_public_memory_quotient::<PUBLIC_MEMORY_STEP, StarkFp, StarkFp>(
+                z,
+                alpha,
+                trace_len,
+                &single_page
+            ),
+            compute_public_memory_quotient::<PUBLIC_MEMORY_STEP, StarkFp, StarkFp>(
+                z,
+                alpha,
+                trace_len,
+                &multi_page
+            )
+        );
+    }
+
+    fn dilute<const SPACING: usize>(v: u64) -> u64 {
+        (0..u64::BITS as usize / SPACING).fold(0, |acc, b| acc | ((v >> b) & 1) << (b * SPACING))
+    }
+
+    #[test]
+    fn diluted_cumulative_value_matches_aggregation_over_goldilocks() {
+        const N_BITS: usize = 16;
+        const SPACING: usize = 4;
+        let z = Fq3::from(3u64);
+        let alpha = Fq3::from(5u64);
+
+        // aggregate every diluted value in order (the same as the AIR)
+        let mut aggregate = Fq3::ONE;
+        for v in 1..1u64 << N_BITS {
+            let diff = Fp::from(dilute::<SPACING>(v) - dilute::<SPACING>(v - 1));
+            aggregate = aggregate * (Fq3::ONE + z * diff) + alpha * diff * diff;
+        }
+
+        assert_eq!(
+            aggregate,
+            compute_diluted_cumulative_value::<Fp, Fq3, N_BITS, SPACING>(z, alpha)
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use crate::errors::RunnerError;
+use crate::memory::MaybeRelocatable;
+use crate::memory::Relocatable;
+use crate::memory::VmMemory;
+use ark_ff::PrimeField;
+use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp;
+use num_bigint::BigUint;
+use ruint::aliases::U256;
+use sandstorm_builtins::ec_op;
+use sandstorm_builtins::keccak;
+use sandstorm_builtins::pedersen::pedersen_hash;
+use sandstorm_builtins::poseidon;
+
+/// Number of bits in each input of the bitwise builtin
+const BITWISE_TOTAL_N_BITS: usize = 251;
+
+/// Number of diluted check units used by each bitwise instance. Source:
+/// `BitwiseBuiltinRunner.get_used_diluted_check_units` in cairo-lang
+pub fn bitwise_diluted_units(n_bits: usize, spacing: usize) -> usize {
+    let partition = (0..BITWISE_TOTAL_N_BITS)
+        .step_by(spacing * n_bits)
+        .flat_map(|i| (0..spacing).map(move |j| i + j))
+        .collect::<Vec<usize>>();
+    let num_trimmed = partition
+        .iter()
+        .filter(|&&segment| segment + spacing * (n_bits - 1) + 1 > BITWISE_TOTAL_N_BITS)
+        .count();
+    4 * partition.len() + num_trimmed
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Builtin {
+    Output,
+    Pedersen,
+    RangeCheck,
+    Ecdsa,
+    Bitwise,
+    EcOp,
+    Keccak,
+    Poseidon,
+}
+
+impl Builtin {
+    /// Returns the builtin with the name used in compiled programs
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "output" => Self::Output,
+            "pedersen" => Self::Pedersen,
+            "range_check" => Self::RangeCheck,
+            "ecdsa" => Self::Ecdsa,
+            "bitwise" => Self::Bitwise,
+            "ec_op" => Self::EcOp,
+            "keccak" => Self::Keccak,
+            "poseidon" => Self::Poseidon,
+            _ => return None,
+        })
+    }
+
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Output => "output",
+            Self::Pedersen => "pedersen",
+            Self::RangeCheck => "range_check",
+            Self::Ecdsa => "ecdsa",
+            Self::Bitwise => "bitwise",
+            Self::EcOp => "ec_op",
+            Self::Keccak => "keccak",
+            Self::Poseidon => "poseidon",
+        }
+    }
+
+    /// Number of memory cells used by each builtin instance
+    pub const fn cells_per_instance(&self) -> usize {
+        match self {
+            Self::Output | Self::RangeCheck => 1,
+            Self::Pedersen => 3,
+            Self::Ecdsa => 2,
+            Self::Bitwise => 5,
+            Self::EcOp => 7,
+            Self::Keccak => 16,
+            Self::Poseidon => 6,
+        }
+    }
+
+    /// Number of input cells at the start of each instance. The remaining
+    /// cells are outputs that are deduced from the inputs.
+    pub const fn num_inputs(&self) -> usize {
+        match self {
+            Self::Output | Self::RangeCheck => 1,
+            Self::Pedersen | Self::Ecdsa | Self::Bitwise => 2,
+            Self::EcOp => 5,
+            Self::Keccak => 8,
+            Self::Poseidon => 3,
+        }
+    }
+
+    /// Returns true if the runner can execute programs over the field `F` that
+    /// use this builtin. Builtins that aren't supported can still be part of
+    /// the layout. Builtins over the STARK curve require the Starkware field.
+    pub fn is_supported<F: PrimeField>(&self) -> bool {
+        match self {
+            Self::Output | Self::RangeCheck | Self::Bitwise => true,
+            Self::Pedersen | Self::Ecdsa | Self::EcOp | Self::Keccak | Self::Poseidon => {
+                let modulus: BigUint = F::MODULUS.into();
+                modulus == BigUint::from(Fp::MODULUS)
+            }
+        }
+    }
+}
+
+/// A builtin's memory segment
+#[derive(Clone, Copy, Debug)]
+pub struct BuiltinRunner {
+    pub builtin: Builtin,
+    pub base: Relocatable,
+    /// Number of steps per builtin instance (none for the output builtin)
+    pub ratio: Option<usize>,
+}
+
+impl BuiltinRunner {
+    /// Returns the number of instances used by the execution
+    pub fn used_instances<F: PrimeField>(&self, memory: &VmMemory<F>) -> usize {
+        memory
+            .segment_size(self.base.segment)
+            .div_ceil(self.builtin.cells_per_instance())
+    }
+
+    /// Returns the number of cells allocated to the builtin's instances in an
+    /// execution with `n_steps` steps or none if the builtin has no ratio
+    pub fn allocated_cells(&self, n_steps: usize) -> Option<usize> {
+        Some(n_steps / self.ratio? * self.builtin.cells_per_instance())
+    }
+
+    /// Deduces the value of a builtin output cell from the instance's inputs.
+    /// Returns none if the cell isn't an output cell.
+    pub fn deduce<F: PrimeField>(
+        &self,
+        address: Relocatable,
+        memory: &VmMemory<F>,
+    ) -> Result<Option<MaybeRelocatable<F>>, RunnerError> {
+        let cells = self.builtin.cells_per_instance();
+        let num_inputs = self.builtin.num_inputs();
+        if address.segment != self.base.segment || address.offset % cells < num_inputs {
+            return Ok(None);
+        }
+
+        let instance_addr = Relocatable::new(address.segment, address.offset / cells * cells);
+        let mut inputs = Vec::with_capacity(num_inputs);
+        for offset in 0..num_inputs {
+            let addr = Relocatable::new(instance_addr.segment, instance_addr.offset + offset);
+            let value = memory.get(addr).ok_or(RunnerError::UnknownMemory(addr))?;
+            inputs.push(felt_to_u256(value.into_int()?));
+        }
+
+        let outputs = match self.builtin {
+            Builtin::Pedersen => {
+                let [a, b] = [inputs[0], inputs[1]].map(u256_to_felt::<Fp>);
+                vec![felt_to_u256(pedersen_hash(a, b))]
+            }
+            Builtin::Bitwise => {
+                let (x, y) = (inputs[0], inputs[1]);
+                if x.bit_len() > BITWISE_TOTAL_N_BITS || y.bit_len() > BITWISE_TOTAL_N_BITS {
+                    return Err(RunnerError::InvalidOperands("bitwise"));
+                }
+                vec![x & y, x ^ y, x | y]
+            }
+            Builtin::EcOp => {
+                let [p_x, p_y, q_x, q_y, m] =
+                    [0, 1, 2, 3, 4].map(|i| u256_to_felt::<Fp>(inputs[i]));
+                let r = ec_op::compute_r([p_x, p_y], [q_x, q_y], m)
+                    .ok_or(RunnerError::InvalidOperands("ec_op"))?;
+                r.map(felt_to_u256).to_vec()
+            }
+            Builtin::Keccak => {
+                let inputs = std::array::from_fn(|i| inputs[i]);
+                let outputs =
+                    keccak::permute(inputs).map_err(|_| RunnerError::InvalidOperands("keccak"))?;
+                outputs.to_vec()
+            }
+            Builtin::Poseidon => {
+                let inputs = [0, 1, 2].map(|i| u256_to_felt::<Fp>(inputs[i]));
+                poseidon::permute(inputs).map(felt_to_u256).to_vec()
+            }
+            // these builtins have no outputs
+            Builtin::Output | Builtin::RangeCheck | Builtin::Ecdsa => return Ok(None),
+        };
+        let output = outputs[address.offset % cells - num_inputs];
+        Ok(Some(MaybeRelocatable::Int(u256_to_felt(output))))
+    }
+}
+
+pub fn felt_to_u256<F: PrimeField>(v: F) -> U256 {
+    U256::from::<BigUint>(v.into_bigint().into())
+}
+
+pub fn u256_to_felt<F: PrimeField>(v: U256) -> F {
+    F::from(BigUint::from(v))
+}
@@ -0,0 +1,83 @@
+use crate::memory::Relocatable;
+use binary::Layout;
+use std::error::Error;
+use std::fmt::Display;
+
+#[derive(Debug)]
+pub enum RunnerError {
+    /// Layout isn't supported by the runner
+    UnsupportedLayout(Layout),
+    /// Program requires a builtin that isn't in the layout
+    MissingBuiltin(String),
+    /// Program uses a builtin that the runner can't execute
+    UnsupportedBuiltin(String),
+    /// Memory cell was read before it was written and couldn't be deduced
+    UnknownMemory(Relocatable),
+    /// Memory cell was written twice with different values
+    InconsistentMemory(Relocatable),
+    UnknownSegment(usize),
+    /// An offset moved an address below the start of its segment
+    AddressUnderflow(Relocatable, isize),
+    /// A field element used as an offset doesn't fit in an `isize`
+    OffsetOutOfRange,
+    /// Operation isn't defined between the operand types e.g. pointer + pointer
+    InvalidOperands(&'static str),
+    DivisionByZero,
+    ExpectedInt,
+    ExpectedRelocatable,
+    /// Instruction has an invalid combination of flags
+    InvalidInstruction(Relocatable),
+    /// `assert_eq` instruction failed
+    DiffAssertValues(Relocatable),
+    /// `call` instruction wrote an unexpected value to the stack
+    CallAssertion(Relocatable),
+    /// Values in the range check segment must be less than 2^128
+    RangeCheckOutOfBounds(Relocatable),
+    /// A builtin's stop pointer returned by `main` is invalid
+    InvalidStopPointer(String),
+    /// Program didn't reach its end within the maximum number of steps
+    StepLimitReached(usize),
+    /// ECDSA instance has a public key and message but no signature
+    MissingSignature(u32),
+    /// ECDSA instance's signature doesn't match its public key and message
+    InvalidSignature(u32),
+    /// Hint failed to execute
+    Hint(String),
+}
+
+impl Display for RunnerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use RunnerError::*;
+        match self {
+            UnsupportedLayout(layout) => {
+                write!(f, "layout {layout} is not supported by the runner")
+            }
+            MissingBuiltin(name) => write!(f, "builtin {name} is not in the layout"),
+            UnsupportedBuiltin(name) => write!(f, "builtin {name} is not supported by the runner"),
+            UnknownMemory(addr) => write!(f, "memory at address {addr} is unknown"),
+            InconsistentMemory(addr) => write!(f, "inconsistent memory assignment at {addr}"),
+            UnknownSegment(segment) => write!(f, "segment {segment} does not exist"),
+            AddressUnderflow(addr, offset) => {
+                write!(f, "offset {offset} from {addr} is outside of the segment")
+            }
+            OffsetOutOfRange => write!(f, "offset is out of range"),
+            InvalidOperands(op) => write!(f, "invalid operand types for {op}"),
+            DivisionByZero => write!(f, "division by zero"),
+            ExpectedInt => write!(f, "expected a field element but found a pointer"),
+            ExpectedRelocatable => write!(f, "expected a pointer but found a field element"),
+            InvalidInstruction(pc) => write!(f, "invalid instruction at pc {pc}"),
+            DiffAssertValues(pc) => write!(f, "assert_eq failed at pc {pc}"),
+            CallAssertion(pc) => write!(f, "call failed to write return values at pc {pc}"),
+            RangeCheckOutOfBounds(addr) => {
+                write!(f, "range check value at {addr} is out of bounds")
+            }
+            InvalidStopPointer(name) => write!(f, "invalid stop pointer for builtin {name}"),
+            StepLimitReached(n) => write!(f, "program did not finish within {n} steps"),
+            MissingSignature(index) => write!(f, "ecdsa instance {index} has no signature"),
+            InvalidSignature(index) => write!(f, "ecdsa instance {index} has an invalid signature"),
+            Hint(err) => write!(f, "hint failed: {err}"),
+        }
+    }
+}
+
+impl Error for RunnerError {}
@@ -0,0 +1,24 @@
+use crate::errors::RunnerError;
+use crate::memory::VmMemory;
+use crate::vm::RunContext;
+
+/// Executes the hints of a program. Hints run before the instruction at the
+/// current pc and can write to memory (e.g. nondeterministic advice).
+/// Programs compiled in proof mode without hints can use [NoHints].
+pub trait HintProcessor<F> {
+    fn execute_hints(
+        &mut self,
+        run_context: &RunContext,
+        memory: &mut VmMemory<F>,
+    ) -> Result<(), RunnerError>;
+}
+
+/// Hint processor for programs that don't have hints
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoHints;
+
+impl<F> HintProcessor<F> for NoHints {
+    fn execute_hints(&mut self, _: &RunContext, _: &mut VmMemory<F>) -> Result<(), RunnerError> {
+        Ok(())
+    }
+}
@@ -0,0 +1,886 @@
+//! Native Cairo VM runner. Executes programs compiled in proof mode and
+//! outputs the trace, memory and AIR inputs that are otherwise generated by
+//! StarkWare's Python `cairo-run`.
+
+use ark_ff::PrimeField;
+use binary::AirPrivateInput;
+use binary::AirPublicInput;
+use binary::CompiledProgram;
+use binary::DynamicParamsKey;
+use binary::EcdsaInstance;
+use binary::Layout;
+use binary::Memory;
+use binary::MemoryEntry;
+use binary::MemorySegments;
+use binary::RegisterState;
+use binary::RegisterStates;
+use binary::Segment;
+use binary::Signature;
+use binary::Word;
+use builtins::bitwise_diluted_units;
+use builtins::felt_to_u256;
+use builtins::Builtin;
+use builtins::BuiltinRunner;
+use errors::RunnerError;
+use hints::HintProcessor;
+use hints::NoHints;
+use layouts::dynamic::LayoutParams;
+use layouts::CairoWitness;
+use memory::MaybeRelocatable;
+use memory::Relocatable;
+use memory::VmMemory;
+use ruint::aliases::U256;
+use sandstorm_builtins::ecdsa;
+use std::collections::BTreeMap;
+use std::io::Write;
+use vm::RunContext;
+use vm::Vm;
+
+pub mod builtins;
+pub mod errors;
+pub mod hints;
+pub mod memory;
+pub mod vm;
+
+/// Number of bits and spacing of diluted values. The same for all layouts with
+/// a diluted pool.
+const DILUTED_CHECK_N_BITS: usize = layouts::starknet::DILUTED_CHECK_N_BITS;
+const DILUTED_CHECK_SPACING: usize = layouts::starknet::DILUTED_CHECK_SPACING;
+
+/// Executes a Cairo program compiled in proof mode (`--proof_mode`)
+pub struct CairoRunner<F: PrimeField, H: HintProcessor<F> = NoHints> {
+    program: CompiledProgram<F>,
+    layout: Layout,
+    hints: H,
+    max_steps: Option<usize>,
+    signatures: BTreeMap<u32, Signature>,
+}
+
+impl<F: PrimeField> CairoRunner<F> {
+    /// Creates a runner for programs that don't have hints
+    pub fn new(program: CompiledProgram<F>, layout: Layout) -> Self {
+        Self::with_hints(program, layout, NoHints)
+    }
+}
+
+impl<F: PrimeField, H: HintProcessor<F>> CairoRunner<F, H> {
+    pub fn with_hints(program: CompiledProgram<F>, layout: Layout, hints: H) -> Self {
+        Self {
+            program,
+            layout,
+            hints,
+            max_steps: None,
+            signatures: BTreeMap::new(),
+        }
+    }
+
+    /// Limits the number of steps before the end of the program is reached
+    pub fn max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = Some(max_steps);
+        self
+    }
+
+    /// Adds signatures of ECDSA builtin instances by instance index.
+    /// Signatures aren't stored in memory so must be supplied to run programs
+    /// that use the ecdsa builtin.
+    pub fn signatures(mut self, signatures: impl IntoIterator<Item = (u32, Signature)>) -> Self {
+        self.signatures.extend(signatures);
+        self
+    }
+
+    /// Runs the program until it reaches the `jmp rel 0` at `__end__` and then
+    /// until the number of steps is a power of two with enough steps to
+    /// accommodate all builtin instances and range check units.
+    pub fn run(mut self) -> Result<Execution<F>, RunnerError> {
+        let params = layout_params::<F>(self.layout)?;
+        let rc_parts = range_check_parts::<F>(self.layout);
+
+        // initialize segments: program, execution then builtins in layout order.
+        // Every builtin of the layout has a segment (the same as `cairo-run`)
+        // but only builtins the layout has instances of have a runner.
+        let mut memory = VmMemory::default();
+        let program_base = memory.add_segment();
+        let execution_base = memory.add_segment();
+        let builtin_segments = (self.layout.builtins().iter())
+            .map(|name| (Builtin::from_name(name).unwrap(), memory.add_segment()))
+            .collect::<Vec<(Builtin, Relocatable)>>();
+        let builtin_runners = layout_builtins(self.layout, &params)
+            .into_iter()
+            .map(|(builtin, ratio)| {
+                let &(_, base) = (builtin_segments.iter())
+                    .find(|(segment_builtin, _)| *segment_builtin == builtin)
+                    .unwrap();
+                BuiltinRunner {
+                    builtin,
+                    base,
+                    ratio,
+                }
+            })
+            .collect::<Vec<BuiltinRunner>>();
+
+        // builtins used by the program receive their segment's base pointer
+        let mut program_builtins = Vec::new();
+        for name in &self.program.builtins {
+            let builtin = Builtin::from_name(name)
+                .filter(Builtin::is_supported::<F>)
+                .ok_or_else(|| RunnerError::UnsupportedBuiltin(name.clone()))?;
+            let builtin_runner = builtin_runners
+                .iter()
+                .find(|runner| runner.builtin == builtin)
+                .ok_or_else(|| RunnerError::MissingBuiltin(name.clone()))?;
+            program_builtins.push(*builtin_runner);
+        }
+
+        let program_data = self.program.data.iter().map(|&v| MaybeRelocatable::Int(v));
+        memory.load(program_base, program_data)?;
+
+        // proof mode stack: [execution_base + 2, 0, builtin_base_0, ...]
+        let initial_fp = execution_base.add_offset(2)?;
+        let stack = [initial_fp.into(), MaybeRelocatable::Int(F::zero())]
+            .into_iter()
+            .chain(program_builtins.iter().map(|runner| runner.base.into()))
+            .collect::<Vec<MaybeRelocatable<F>>>();
+        memory.load(execution_base, stack.iter().copied())?;
+
+        let mut vm = Vm {
+            run_context: RunContext {
+                pc: program_base,
+                ap: initial_fp,
+                fp: initial_fp,
+            },
+            memory,
+            builtins: &builtin_runners,
+        };
+
+        let mut trace = Vec::new();
+        while !vm.is_at_end()? {
+            if self
+                .max_steps
+                .is_some_and(|max_steps| trace.len() >= max_steps)
+            {
+                return Err(RunnerError::StepLimitReached(trace.len()));
+            }
+            trace.push(vm.run_context);
+            self.hints.execute_hints(&vm.run_context, &mut vm.memory)?;
+            vm.step()?;
+        }
+        let final_pc = vm.run_context.pc;
+
+        // pad the trace with `jmp rel 0` steps
+        loop {
+            let n_steps = trace.len();
+            if n_steps.is_power_of_two()
+                && has_capacity(&vm, &builtin_runners, n_steps)
+                && has_diluted_units(&builtin_runners, &params, n_steps)
+                && has_range_check_units(&vm, &trace, &params, rc_parts, n_steps)?
+            {
+                break;
+            }
+            trace.push(vm.run_context);
+            self.hints.execute_hints(&vm.run_context, &mut vm.memory)?;
+            vm.step()?;
+        }
+        let final_ap = vm.run_context.ap;
+
+        // deduce the outputs of all builtin instances
+        for runner in &builtin_runners {
+            let cells = runner.builtin.cells_per_instance();
+            for i in 0..runner.used_instances(&vm.memory) * cells {
+                let address = Relocatable::new(runner.base.segment, i);
+                if let Some(value) = vm.read(address)? {
+                    vm.memory.insert(address, value)?;
+                }
+            }
+        }
+
+        check_range_check_values(&vm.memory, &builtin_runners, rc_parts)?;
+        check_signatures(&vm.memory, &builtin_runners, &self.signatures)?;
+
+        // return values are the builtin stop pointers
+        let mut pointer = final_ap;
+        for runner in program_builtins.iter().rev() {
+            pointer = pointer.add_offset(-1)?;
+            let name = runner.builtin.name();
+            let stop_ptr = vm
+                .memory
+                .get(pointer)
+                .ok_or(RunnerError::UnknownMemory(pointer))?
+                .into_relocatable()?;
+            let used = vm.memory.segment_size(runner.base.segment);
+            if stop_ptr != Relocatable::new(runner.base.segment, used) {
+                return Err(RunnerError::InvalidStopPointer(name.to_string()));
+            }
+        }
+
+        // public memory of the execution segment is the initial stack and the
+        // return values
+        let execution_public_memory = (0..stack.len())
+            .chain(pointer.offset..final_ap.offset)
+            .collect::<Vec<usize>>();
+
+        let Vm { memory, .. } = vm;
+        Ok(Execution::new(
+            self.layout,
+            &memory,
+            &builtin_segments,
+            &builtin_runners,
+            trace,
+            final_pc,
+            final_ap,
+            &execution_public_memory,
+            rc_parts,
+            &self.signatures,
+        ))
+    }
+}
+
+/// Output of a [CairoRunner] with all addresses relocated
+pub struct Execution<F: PrimeField> {
+    pub register_states: RegisterStates,
+    pub memory: Memory<F>,
+    pub air_public_input: AirPublicInput<F>,
+    pub air_private_input: AirPrivateInput,
+    /// Addresses in the order they were written by the VM
+    write_order: Vec<usize>,
+}
+
+impl<F: PrimeField> Execution<F> {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        layout: Layout,
+        vm_memory: &VmMemory<F>,
+        builtin_segments: &[(Builtin, Relocatable)],
+        builtin_runners: &[BuiltinRunner],
+        trace: Vec<RunContext>,
+        final_pc: Relocatable,
+        final_ap: Relocatable,
+        execution_public_memory: &[usize],
+        rc_parts: usize,
+        signatures: &BTreeMap<u32, Signature>,
+    ) -> Self {
+        // segments are placed consecutively starting at address 1
+        // (address 0 is reserved for dummy accesses). Builtin segments take up
+        // all cells allocated to their instances (the same as `cairo-run`).
+        let n_steps = trace.len();
+        let mut segment_bases = vec![1];
+        for segment in 0..vm_memory.num_segments() {
+            let base = segment_bases[segment];
+            let size = builtin_runners
+                .iter()
+                .find(|runner| runner.base.segment == segment)
+                .and_then(|runner| runner.allocated_cells(n_steps))
+                .unwrap_or_else(|| vm_memory.segment_size(segment));
+            segment_bases.push(base + size);
+        }
+        let relocate = |address: Relocatable| segment_bases[address.segment] + address.offset;
+        let relocate_value = |value: MaybeRelocatable<F>| match value {
+            MaybeRelocatable::Int(v) => v,
+            MaybeRelocatable::Relocatable(address) => F::from(relocate(address) as u64),
+        };
+        let segment = |begin: Relocatable, stop: Relocatable| Segment {
+            begin_addr: relocate(begin) as u32,
+            stop_ptr: relocate(stop) as u32,
+        };
+
+        let builtin_segment = |builtin: Builtin| {
+            builtin_segments
+                .iter()
+                .find(|(segment_builtin, _)| *segment_builtin == builtin)
+                .map(|&(_, base)| {
+                    let used = vm_memory.segment_size(base.segment);
+                    segment(base, Relocatable::new(base.segment, used))
+                })
+        };
+        let program_base = Relocatable::new(0, 0);
+        let execution_base = Relocatable::new(1, 0);
+        // the execution segment begins at the initial ap which is after the
+        // `[initial_fp, 0]` prefix of the proof mode stack
+        let initial_ap = Relocatable::new(execution_base.segment, 2);
+        let memory_segments = MemorySegments {
+            program: segment(program_base, final_pc),
+            execution: segment(initial_ap, final_ap),
+            output: builtin_segment(Builtin::Output),
+            pedersen: builtin_segment(Builtin::Pedersen),
+            range_check: builtin_segment(Builtin::RangeCheck),
+            ecdsa: builtin_segment(Builtin::Ecdsa),
+            bitwise: builtin_segment(Builtin::Bitwise),
+            ec_op: builtin_segment(Builtin::EcOp),
+            keccak: builtin_segment(Builtin::Keccak),
+            poseidon: builtin_segment(Builtin::Poseidon),
+            range_check96: None,
+            add_mod: None,
+            mul_mod: None,
+        };
+
+        let mut memory = Memory::new(&memory_segments);
+        let mut write_order = Vec::new();
+        for (address, value) in vm_memory.entries() {
+            let address = relocate(address);
+            memory.insert(address, Word::new(felt_to_u256(relocate_value(value))));
+            write_order.push(address);
+        }
+
+        let public_entry = |address: Relocatable| {
+            let value = vm_memory.get(address).unwrap();
+            MemoryEntry {
+                address: relocate(address) as u32,
+                value: relocate_value(value),
+            }
+        };
+        let mut public_memory = Vec::new();
+        for offset in 0..vm_memory.segment_size(program_base.segment) {
+            public_memory.push(public_entry(Relocatable::new(program_base.segment, offset)));
+        }
+        for &offset in execution_public_memory {
+            public_memory.push(public_entry(Relocatable::new(
+                execution_base.segment,
+                offset,
+            )));
+        }
+        let output_runner = builtin_runners
+            .iter()
+            .find(|r| r.builtin == Builtin::Output);
+        if let Some(output_runner) = output_runner {
+            for (offset, _) in vm_memory.segment_entries(output_runner.base.segment) {
+                public_memory.push(public_entry(Relocatable::new(
+                    output_runner.base.segment,
+                    offset,
+                )));
+            }
+        }
+
+        // range check limits of instruction offsets and range check builtin parts
+        let mut rc_values = Vec::new();
+        for RunContext { pc, .. } in &trace {
+            let word = Word::<F>::new(felt_to_u256(
+                vm_memory.get(*pc).unwrap().into_int().unwrap(),
+            ));
+            rc_values.extend([word.get_off_dst(), word.get_off_op0(), word.get_off_op1()]);
+        }
+        for runner in builtin_runners {
+            if runner.builtin == Builtin::RangeCheck {
+                for (_, value) in vm_memory.segment_entries(runner.base.segment) {
+                    let value = felt_to_u256(value.into_int().unwrap());
+                    rc_values.extend(range_check_parts_of(value, rc_parts));
+                }
+            }
+        }
+
+        let register_states = trace
+            .iter()
+            .map(|&RunContext { pc, ap, fp }| RegisterState {
+                pc: relocate(pc),
+                ap: relocate(ap),
+                fp: relocate(fp),
+            })
+            .collect::<Vec<RegisterState>>();
+
+        let air_public_input = AirPublicInput {
+            rc_min: rc_values.iter().copied().min().unwrap_or_default(),
+            rc_max: rc_values.iter().copied().max().unwrap_or_default(),
+            n_steps: n_steps as u64,
+            layout,
+            memory_segments,
+            public_memory,
+            memory_pages: Vec::new(),
+            dynamic_params: None,
+            dynamic_params_key: DynamicParamsKey::Omitted,
+        };
+
+        let air_private_input = AirPrivateInput::from_memory(&memory, &memory_segments, signatures)
+            .expect("signatures were checked and segments end after they start");
+
+        Self {
+            register_states: RegisterStates::from(register_states),
+            memory,
+            air_public_input,
+            air_private_input,
+            write_order,
+        }
+    }
+
+    /// Returns the public input and a witness that can be passed to a prover
+    pub fn into_witness(self) -> (AirPublicInput<F>, CairoWitness<F>) {
+        let witness = CairoWitness::new(self.air_private_input, self.register_states, self.memory);
+        (self.air_public_input, witness)
+    }
+
+    /// Writes the trace in the same binary format as `cairo-run`
+    pub fn write_trace(&self, mut w: impl Write) -> std::io::Result<()> {
+        for &RegisterState { ap, fp, pc } in self.register_states.iter() {
+            for register in [ap, fp, pc] {
+                w.write_all(&(register as u64).to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the memory in the same binary format and order as `cairo-run`
+    pub fn write_memory(&self, mut w: impl Write) -> std::io::Result<()> {
+        let field_bytes = F::MODULUS_BIT_SIZE.div_ceil(8) as usize;
+        for &address in &self.write_order {
+            let word = self.memory[address].unwrap();
+            w.write_all(&(address as u64).to_le_bytes())?;
+            w.write_all(&word.0.as_le_slice()[0..field_bytes])?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the parameters of the layouts supported by the runner
+fn layout_params<F: PrimeField>(layout: Layout) -> Result<LayoutParams, RunnerError> {
+    Ok(match layout {
+        Layout::Plain => LayoutParams::plain(),
+        // layouts over small fields use the field agnostic variant
+        Layout::Recursive if F::MODULUS_BIT_SIZE <= 64 => LayoutParams::recursive_generic(),
+        Layout::Recursive => LayoutParams::recursive(),
+        Layout::Starknet => LayoutParams::starknet(),
+        layout => return Err(RunnerError::UnsupportedLayout(layout)),
+    })
+}
+
+/// Number of 16-bit parts each range check builtin value is split into
+fn range_check_parts<F: PrimeField>(layout: Layout) -> usize {
+    match layout {
+        Layout::Recursive if F::MODULUS_BIT_SIZE <= 64 => {
+            layouts::recursive_generic::RANGE_CHECK_BUILTIN_PARTS
+        }
+        Layout::Recursive => layouts::recursive::RANGE_CHECK_BUILTIN_PARTS,
+        _ => layouts::starknet::RANGE_CHECK_BUILTIN_PARTS,
+    }
+}
+
+/// Returns the builtins of a layout with their ratios in the same order as
+/// `cairo-run`. Builtins without a ratio in the layout's parameters (e.g.
+/// pedersen in the field agnostic recursive layout) have no instances so are
+/// skipped. The output builtin is the only builtin without a ratio.
+fn layout_builtins(layout: Layout, params: &LayoutParams) -> Vec<(Builtin, Option<usize>)> {
+    layout
+        .builtins()
+        .iter()
+        .filter_map(|name| {
+            let builtin = Builtin::from_name(name).unwrap();
+            let ratio = match builtin {
+                Builtin::Output => return Some((builtin, None)),
+                Builtin::Pedersen => params.pedersen_ratio,
+                Builtin::RangeCheck => params.range_check_ratio,
+                Builtin::Ecdsa => params.ecdsa_ratio,
+                Builtin::Bitwise => params.bitwise_ratio,
+                Builtin::EcOp => params.ec_op_ratio,
+                Builtin::Keccak => params.keccak_ratio,
+                Builtin::Poseidon => params.poseidon_ratio,
+            };
+            Some((builtin, Some(ratio?)))
+        })
+        .collect()
+}
+
+/// Checks there are enough steps for all builtin instances. Builtins with a
+/// ratio are allocated at least one instance (the same as `cairo-run`).
+fn has_capacity<F: PrimeField>(vm: &Vm<F>, builtins: &[BuiltinRunner], n_steps: usize) -> bool {
+    builtins.iter().all(|runner| match runner.ratio {
+        Some(ratio) => n_steps >= ratio && runner.used_instances(&vm.memory) <= n_steps / ratio,
+        None => true,
+    })
+}
+
+/// Checks there are enough diluted check units for all allocated bitwise
+/// instances with enough left over to fill the gaps between diluted values
+/// (the same as `cairo-run`). Keccak isn't in any layout supported by the
+/// runner so bitwise is the only builtin that uses the diluted pool.
+fn has_diluted_units(builtins: &[BuiltinRunner], params: &LayoutParams, n_steps: usize) -> bool {
+    let Some(diluted_check_step) = params.diluted_check_step else {
+        return true;
+    };
+    let units_per_step = params.cycle_height / diluted_check_step;
+    let bitwise_units = bitwise_diluted_units(DILUTED_CHECK_N_BITS, DILUTED_CHECK_SPACING);
+    let used_units = builtins
+        .iter()
+        .filter(|runner| runner.builtin == Builtin::Bitwise)
+        .filter_map(|runner| Some(n_steps / runner.ratio? * bitwise_units))
+        .sum::<usize>();
+    let unused_units = (units_per_step * n_steps).saturating_sub(used_units);
+    unused_units >= 1 << DILUTED_CHECK_N_BITS
+}
+
+/// Checks every instance of the ecdsa builtin with a public key and message has
+/// a valid signature
+fn check_signatures<F: PrimeField>(
+    memory: &VmMemory<F>,
+    builtins: &[BuiltinRunner],
+    signatures: &BTreeMap<u32, Signature>,
+) -> Result<(), RunnerError> {
+    let Some(runner) = builtins.iter().find(|r| r.builtin == Builtin::Ecdsa) else {
+        return Ok(());
+    };
+    let cells = runner.builtin.cells_per_instance();
+    for index in 0..runner.used_instances(memory) {
+        let read = |i| memory.get(Relocatable::new(runner.base.segment, index * cells + i));
+        let (Some(pubkey), Some(message)) = (read(0), read(1)) else {
+            continue;
+        };
+        let index = index as u32;
+        let signature = *signatures
+            .get(&index)
+            .ok_or(RunnerError::MissingSignature(index))?;
+        let instance = EcdsaInstance {
+            index,
+            pubkey_x: felt_to_u256(pubkey.into_int()?),
+            message: felt_to_u256(message.into_int()?),
+            signature,
+        };
+        if !ecdsa::verify_instance(&instance) {
+            return Err(RunnerError::InvalidSignature(index));
+        }
+    }
+    Ok(())
+}
+
+/// Checks there are enough range check units to fill the gaps between the
+/// smallest and largest range checked value
+fn has_range_check_units<F: PrimeField>(
+    vm: &Vm<F>,
+    trace: &[RunContext],
+    params: &LayoutParams,
+    rc_parts: usize,
+    n_steps: usize,
+) -> Result<bool, RunnerError> {
+    let mut rc_min = u16::MAX;
+    let mut rc_max = u16::MIN;
+    let mut update_limits = |v: u16| {
+        rc_min = rc_min.min(v);
+        rc_max = rc_max.max(v);
+    };
+    for RunContext { pc, .. } in trace {
+        let word = Word::<F>::new(felt_to_u256(
+            vm.memory
+                .get(*pc)
+                .ok_or(RunnerError::UnknownMemory(*pc))?
+                .into_int()?,
+        ));
+        update_limits(word.get_off_dst());
+        update_limits(word.get_off_op0());
+        update_limits(word.get_off_op1());
+    }
+    let mut builtin_units = 0;
+    for runner in vm.builtins {
+        if runner.builtin == Builtin::RangeCheck {
+            for (_, value) in vm.memory.segment_entries(runner.base.segment) {
+                let value = felt_to_u256(value.into_int()?);
+                range_check_parts_of(value, rc_parts).for_each(&mut update_limits);
+                builtin_units += rc_parts;
+            }
+        }
+    }
+    if rc_min > rc_max {
+        return Ok(true);
+    }
+    let units_per_step = params.cycle_height / params.range_check_step;
+    let unused_units = ((units_per_step - 3) * n_steps).saturating_sub(builtin_units);
+    Ok(unused_units >= usize::from(rc_max - rc_min))
+}
+
+/// Checks all values written to the range check segment are in range
+fn check_range_check_values<F: PrimeField>(
+    memory: &VmMemory<F>,
+    builtins: &[BuiltinRunner],
+    rc_parts: usize,
+) -> Result<(), RunnerError> {
+    for runner in builtins {
+        if runner.builtin == Builtin::RangeCheck {
+            for (offset, value) in memory.segment_entries(runner.base.segment) {
+                let addr = Relocatable::new(runner.base.segment, offset);
+                let value = felt_to_u256(value.into_int()?);
+                if value.bit_len() > 16 * rc_parts {
+                    return Err(RunnerError::RangeCheckOutOfBounds(addr));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Splits a range checked value into 16-bit parts
+fn range_check_parts_of(value: U256, rc_parts: usize) -> impl Iterator<Item = u16> {
+    (0..rc_parts).map(move |i| {
+        let part: U256 = (value >> (16 * i)) & U256::from(0xFFFF);
+        part.try_into().unwrap()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builtins::bitwise_diluted_units;
+    use crate::builtins::Builtin;
+    use crate::builtins::BuiltinRunner;
+    use crate::errors::RunnerError;
+    use crate::hints::HintProcessor;
+    use crate::memory::MaybeRelocatable;
+    use crate::memory::Relocatable;
+    use crate::memory::VmMemory;
+    use crate::layout_builtins;
+    use crate::vm::RunContext;
+    use crate::CairoRunner;
+    use binary::AirPublicInput;
+    use binary::CompiledProgram;
+    use binary::Layout;
+    use layouts::dynamic::LayoutParams;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fp as GoldilocksFp;
+    use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp;
+    use sandstorm_builtins::pedersen::pedersen_hash;
+    use sandstorm_builtins::poseidon;
+    use std::collections::BTreeMap;
+    use std::fs::File;
+
+    fn example_path(path: &str) -> String {
+        format!("{}/../{path}", env!("CARGO_MANIFEST_DIR"))
+    }
+
+    /// Executes the hint of `alloc`: `memory[ap] = segments.add()`
+    struct AllocHint {
+        pc: Relocatable,
+    }
+
+    impl HintProcessor<Fp> for AllocHint {
+        fn execute_hints(
+            &mut self,
+            run_context: &RunContext,
+            memory: &mut VmMemory<Fp>,
+        ) -> Result<(), RunnerError> {
+            if run_context.pc == self.pc {
+                let segment = memory.add_segment();
+                memory.insert(run_context.ap, segment.into())?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Returns the memory of a builtin segment with `inputs` written to the
+    /// first instance
+    fn builtin_memory(builtin: Builtin, inputs: &[u64]) -> (BuiltinRunner, VmMemory<Fp>) {
+        let mut memory = VmMemory::default();
+        let runner = BuiltinRunner {
+            builtin,
+            base: memory.add_segment(),
+            ratio: None,
+        };
+        let inputs = inputs.iter().map(|&v| MaybeRelocatable::Int(Fp::from(v)));
+        memory.load(runner.base, inputs).unwrap();
+        (runner, memory)
+    }
+
+    fn deduce(runner: &BuiltinRunner, memory: &VmMemory<Fp>, offset: usize) -> Option<Fp> {
+        let address = Relocatable::new(runner.base.segment, offset);
+        let value = runner.deduce(address, memory).unwrap()?;
+        Some(value.into_int().unwrap())
+    }
+
+    fn program(data: &[u64]) -> CompiledProgram<Fp> {
+        CompiledProgram {
+            data: data.iter().map(|&v| Fp::from(v)).collect(),
+            prime: "0x800000000000011000000000000000000000000000000000000000000000001".into(),
+            builtins: Vec::new(),
+            main_scope: String::new(),
+            identifiers: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn runs_until_end_and_pads_to_power_of_two() {
+        // [ap] = 5; ap++
+        // jmp rel 0
+        let program = program(&[0x480680017fff8000, 5, 0x10780017fff7fff, 0]);
+
+        let execution = CairoRunner::new(program, Layout::Plain).run().unwrap();
+
+        let public_input = &execution.air_public_input;
+        assert_eq!(2, execution.register_states.len());
+        assert_eq!(2, public_input.n_steps);
+        // program occupies [1, 5) so execution begins at 5
+        assert_eq!(5, public_input.initial_ap() - 2);
+        assert_eq!(3, public_input.final_pc());
+        assert_eq!(public_input.initial_ap() + 1, public_input.final_ap());
+        let initial_ap = public_input.initial_ap() as usize;
+        assert_eq!(
+            Fp::from(5u8),
+            execution.memory[initial_ap].unwrap().into_felt()
+        );
+    }
+
+    #[test]
+    fn rejects_inconsistent_assert() {
+        // [ap] = 5; ap++
+        // [ap - 1] = 6
+        // jmp rel 0
+        let program = program(&[
+            0x480680017fff8000,
+            5,
+            0x400680017fff7fff,
+            6,
+            0x10780017fff7fff,
+            0,
+        ]);
+
+        assert!(CairoRunner::new(program, Layout::Plain).run().is_err());
+    }
+
+    #[test]
+    fn array_sum_matches_cairo_run() {
+        let program_file = File::open(example_path("example/array-sum.json")).unwrap();
+        let program: CompiledProgram<Fp> = serde_json::from_reader(program_file).unwrap();
+        let alloc = &program.identifiers["starkware.cairo.common.alloc.alloc"];
+        let hints = AllocHint {
+            pc: Relocatable::new(0, alloc.pc.unwrap()),
+        };
+
+        let execution = CairoRunner::with_hints(program, Layout::Recursive, hints)
+            .run()
+            .unwrap();
+
+        let public_input_file = File::open(example_path("example/air-public-input.json")).unwrap();
+        let expected: AirPublicInput<Fp> = serde_json::from_reader(public_input_file).unwrap();
+        let public_input = &execution.air_public_input;
+        assert_eq!(expected.n_steps, public_input.n_steps);
+        assert_eq!(expected.rc_min, public_input.rc_min);
+        assert_eq!(expected.rc_max, public_input.rc_max);
+        assert_eq!(
+            serde_json::to_value(expected.memory_segments).unwrap(),
+            serde_json::to_value(public_input.memory_segments).unwrap()
+        );
+        let mut trace = Vec::new();
+        execution.write_trace(&mut trace).unwrap();
+        let mut memory = Vec::new();
+        execution.write_memory(&mut memory).unwrap();
+        let expected_trace = std::fs::read(example_path("example/trace.bin")).unwrap();
+        let expected_memory = std::fs::read(example_path("example/memory.bin")).unwrap();
+        assert!(expected_trace == trace, "trace differs from cairo-run");
+        assert!(expected_memory == memory, "memory differs from cairo-run");
+    }
+
+    #[test]
+    fn pedersen_output_is_deduced() {
+        let (runner, memory) = builtin_memory(Builtin::Pedersen, &[1, 2]);
+
+        assert_eq!(None, deduce(&runner, &memory, 1));
+        assert_eq!(
+            Some(pedersen_hash(Fp::from(1u8), Fp::from(2u8))),
+            deduce(&runner, &memory, 2)
+        );
+    }
+
+    #[test]
+    fn poseidon_outputs_are_deduced() {
+        let (runner, memory) = builtin_memory(Builtin::Poseidon, &[1, 2, 3]);
+        let expected = poseidon::permute([1u8, 2, 3].map(Fp::from));
+
+        for (i, expected) in expected.into_iter().enumerate() {
+            assert_eq!(Some(expected), deduce(&runner, &memory, 3 + i));
+        }
+    }
+
+    #[test]
+    fn ec_op_with_point_off_the_curve_is_rejected() {
+        let (runner, memory) = builtin_memory(Builtin::EcOp, &[1, 2, 3, 4, 5]);
+        let address = Relocatable::new(runner.base.segment, 5);
+
+        assert!(matches!(
+            runner.deduce(address, &memory),
+            Err(RunnerError::InvalidOperands("ec_op"))
+        ));
+    }
+
+    #[test]
+    fn bitwise_diluted_units_match_cairo_lang() {
+        assert_eq!(68, bitwise_diluted_units(16, 4));
+    }
+
+    #[test]
+    fn builtins_with_a_ratio_are_allocated_runners() {
+        use Builtin::*;
+        let recursive = vec![
+            (Output, None),
+            (Pedersen, Some(128)),
+            (RangeCheck, Some(8)),
+            (Bitwise, Some(8)),
+        ];
+        // the field agnostic layout has no pedersen instances
+        let recursive_generic = vec![(Output, None), (RangeCheck, Some(8)), (Bitwise, Some(8))];
+        let starknet = vec![
+            (Output, None),
+            (Pedersen, Some(32)),
+            (RangeCheck, Some(16)),
+            (Ecdsa, Some(2048)),
+            (Bitwise, Some(64)),
+            (EcOp, Some(1024)),
+            (Poseidon, Some(32)),
+        ];
+
+        assert_eq!(
+            Vec::<(Builtin, Option<usize>)>::new(),
+            layout_builtins(Layout::Plain, &LayoutParams::plain())
+        );
+        assert_eq!(
+            recursive,
+            layout_builtins(Layout::Recursive, &LayoutParams::recursive())
+        );
+        assert_eq!(
+            recursive_generic,
+            layout_builtins(Layout::Recursive, &LayoutParams::recursive_generic())
+        );
+        assert_eq!(
+            starknet,
+            layout_builtins(Layout::Starknet, &LayoutParams::starknet())
+        );
+    }
+
+    #[test]
+    fn every_layout_builtin_is_allocated_a_segment() {
+        // jmp rel 0
+        let program = program(&[0x10780017fff7fff, 0]);
+
+        let execution = CairoRunner::new(program, Layout::Recursive).run().unwrap();
+
+        let segments = execution.air_public_input.memory_segments;
+        let names = segments.iter_with_names().map(|(name, _)| name);
+        assert_eq!(
+            vec![
+                "program",
+                "execution",
+                "output",
+                "pedersen",
+                "range_check",
+                "bitwise"
+            ],
+            names.collect::<Vec<&str>>()
+        );
+        for (name, segment) in segments.iter_with_names().skip(2) {
+            assert_eq!(
+                segment.begin_addr, segment.stop_ptr,
+                "{name} segment isn't empty"
+            );
+        }
+    }
+
+    #[test]
+    fn pedersen_segment_is_allocated_without_a_runner() {
+        // the recursive layout over Goldilocks field has no pedersen instances
+        // but public inputs of the layout still have a pedersen segment
+        let program = CompiledProgram {
+            data: vec![
+                GoldilocksFp::from(0x10780017fff7fffu64),
+                GoldilocksFp::from(0u8),
+            ],
+            prime: "0xffffffff00000001".into(),
+            builtins: Vec::new(),
+            main_scope: String::new(),
+            identifiers: BTreeMap::new(),
+        };
+
+        let execution = CairoRunner::new(program, Layout::Recursive).run().unwrap();
+
+        let public_input = execution.air_public_input;
+        let pedersen = public_input.memory_segments.pedersen.unwrap();
+        assert_eq!(pedersen.begin_addr, pedersen.stop_ptr);
+        public_input.validate().unwrap();
+    }
+}
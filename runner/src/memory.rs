@@ -0,0 +1,224 @@
+use crate::errors::RunnerError;
+use ark_ff::PrimeField;
+use num_bigint::BigUint;
+use std::fmt::Display;
+
+/// Address of a memory cell before segments are assigned their final
+/// location in memory
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Relocatable {
+    pub segment: usize,
+    pub offset: usize,
+}
+
+impl Relocatable {
+    pub const fn new(segment: usize, offset: usize) -> Self {
+        Self { segment, offset }
+    }
+
+    /// Returns the address `offset` cells after (or before) this address
+    pub fn add_offset(self, offset: isize) -> Result<Self, RunnerError> {
+        let offset = self
+            .offset
+            .checked_add_signed(offset)
+            .ok_or(RunnerError::AddressUnderflow(self, offset))?;
+        Ok(Self::new(self.segment, offset))
+    }
+}
+
+impl Display for Relocatable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.segment, self.offset)
+    }
+}
+
+/// Value of a memory cell. Either a field element or a pointer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MaybeRelocatable<F> {
+    Int(F),
+    Relocatable(Relocatable),
+}
+
+impl<F: PrimeField> MaybeRelocatable<F> {
+    pub fn add(self, rhs: Self) -> Result<Self, RunnerError> {
+        use MaybeRelocatable::*;
+        Ok(match (self, rhs) {
+            (Int(a), Int(b)) => Int(a + b),
+            (Relocatable(a), Int(b)) | (Int(b), Relocatable(a)) => {
+                Relocatable(a.add_offset(felt_to_offset(b)?)?)
+            }
+            (Relocatable(_), Relocatable(_)) => return Err(RunnerError::InvalidOperands("+")),
+        })
+    }
+
+    pub fn sub(self, rhs: Self) -> Result<Self, RunnerError> {
+        use MaybeRelocatable::*;
+        Ok(match (self, rhs) {
+            (Int(a), Int(b)) => Int(a - b),
+            (Relocatable(a), Int(b)) => Relocatable(a.add_offset(-felt_to_offset(b)?)?),
+            (Relocatable(a), Relocatable(b)) if a.segment == b.segment => {
+                Int(offset_to_felt(a.offset as isize - b.offset as isize))
+            }
+            _ => return Err(RunnerError::InvalidOperands("-")),
+        })
+    }
+
+    pub fn mul(self, rhs: Self) -> Result<Self, RunnerError> {
+        match (self, rhs) {
+            (Self::Int(a), Self::Int(b)) => Ok(Self::Int(a * b)),
+            _ => Err(RunnerError::InvalidOperands("*")),
+        }
+    }
+
+    pub fn div(self, rhs: Self) -> Result<Self, RunnerError> {
+        match (self, rhs) {
+            (Self::Int(a), Self::Int(b)) => b
+                .inverse()
+                .map(|b_inv| Self::Int(a * b_inv))
+                .ok_or(RunnerError::DivisionByZero),
+            _ => Err(RunnerError::InvalidOperands("/")),
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        matches!(self, Self::Int(v) if v.is_zero())
+    }
+
+    pub fn into_int(self) -> Result<F, RunnerError> {
+        match self {
+            Self::Int(v) => Ok(v),
+            Self::Relocatable(_) => Err(RunnerError::ExpectedInt),
+        }
+    }
+
+    pub fn into_relocatable(self) -> Result<Relocatable, RunnerError> {
+        match self {
+            Self::Relocatable(v) => Ok(v),
+            Self::Int(_) => Err(RunnerError::ExpectedRelocatable),
+        }
+    }
+}
+
+impl<F> From<Relocatable> for MaybeRelocatable<F> {
+    fn from(value: Relocatable) -> Self {
+        Self::Relocatable(value)
+    }
+}
+
+/// Interprets a field element as a signed offset. Elements in the upper half
+/// of the field represent negative offsets i.e. `p - k` is `-k`.
+pub fn felt_to_offset<F: PrimeField>(v: F) -> Result<isize, RunnerError> {
+    let v = BigUint::from(v.into_bigint());
+    let modulus = BigUint::from(F::MODULUS);
+    let (magnitude, is_negative) = if v <= (&modulus >> 1) {
+        (v, false)
+    } else {
+        (modulus - v, true)
+    };
+    let magnitude = isize::try_from(magnitude).map_err(|_| RunnerError::OffsetOutOfRange)?;
+    Ok(if is_negative { -magnitude } else { magnitude })
+}
+
+/// Inverse of [felt_to_offset]
+pub fn offset_to_felt<F: PrimeField>(offset: isize) -> F {
+    let magnitude = F::from(offset.unsigned_abs() as u64);
+    if offset < 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+/// Write-once memory of the Cairo VM split into segments
+#[derive(Clone, Debug)]
+pub struct VmMemory<F> {
+    segments: Vec<Vec<Option<MaybeRelocatable<F>>>>,
+    /// Addresses in the order they were first written
+    write_order: Vec<Relocatable>,
+}
+
+impl<F> Default for VmMemory<F> {
+    fn default() -> Self {
+        Self {
+            segments: Vec::new(),
+            write_order: Vec::new(),
+        }
+    }
+}
+
+impl<F: PrimeField> VmMemory<F> {
+    /// Adds a new segment and returns its base address
+    pub fn add_segment(&mut self) -> Relocatable {
+        self.segments.push(Vec::new());
+        Relocatable::new(self.segments.len() - 1, 0)
+    }
+
+    pub fn num_segments(&self) -> usize {
+        self.segments.len()
+    }
+
+    pub fn get(&self, address: Relocatable) -> Option<MaybeRelocatable<F>> {
+        *self.segments.get(address.segment)?.get(address.offset)?
+    }
+
+    /// Writes a value to memory. Cells can be written more than once only if
+    /// the value is unchanged.
+    pub fn insert(
+        &mut self,
+        address: Relocatable,
+        value: MaybeRelocatable<F>,
+    ) -> Result<(), RunnerError> {
+        let segment = self
+            .segments
+            .get_mut(address.segment)
+            .ok_or(RunnerError::UnknownSegment(address.segment))?;
+        if address.offset >= segment.len() {
+            segment.resize(address.offset + 1, None);
+        }
+        match segment[address.offset] {
+            Some(prev) if prev != value => Err(RunnerError::InconsistentMemory(address)),
+            Some(_) => Ok(()),
+            None => {
+                segment[address.offset] = Some(value);
+                self.write_order.push(address);
+                Ok(())
+            }
+        }
+    }
+
+    /// Writes consecutive values starting at `address`
+    pub fn load(
+        &mut self,
+        address: Relocatable,
+        values: impl IntoIterator<Item = MaybeRelocatable<F>>,
+    ) -> Result<(), RunnerError> {
+        for (i, value) in values.into_iter().enumerate() {
+            self.insert(Relocatable::new(address.segment, address.offset + i), value)?;
+        }
+        Ok(())
+    }
+
+    /// Returns one past the highest offset written to in a segment
+    pub fn segment_size(&self, segment: usize) -> usize {
+        self.segments.get(segment).map_or(0, Vec::len)
+    }
+
+    /// Iterates over the cells of a segment that have a value
+    pub fn segment_entries(
+        &self,
+        segment: usize,
+    ) -> impl Iterator<Item = (usize, MaybeRelocatable<F>)> + '_ {
+        self.segments[segment]
+            .iter()
+            .enumerate()
+            .filter_map(|(offset, value)| Some((offset, (*value)?)))
+    }
+
+    /// Iterates over all cells that have a value in the order they were first
+    /// written (the same order as the memory file of `cairo-run`)
+    pub fn entries(&self) -> impl Iterator<Item = (Relocatable, MaybeRelocatable<F>)> + '_ {
+        self.write_order
+            .iter()
+            .map(|&address| (address, self.get(address).unwrap()))
+    }
+}
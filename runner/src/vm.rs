@@ -0,0 +1,232 @@
+use crate::builtins::felt_to_u256;
+use crate::builtins::BuiltinRunner;
+use crate::errors::RunnerError;
+use crate::memory::MaybeRelocatable;
+use crate::memory::Relocatable;
+use crate::memory::VmMemory;
+use ark_ff::PrimeField;
+use binary::Flag;
+use binary::FlagGroup;
+use binary::Word;
+use binary::HALF_OFFSET;
+
+/// Cairo VM registers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RunContext {
+    pub pc: Relocatable,
+    pub ap: Relocatable,
+    pub fp: Relocatable,
+}
+
+/// Operands of an instruction
+struct Operands<F> {
+    dst: MaybeRelocatable<F>,
+    op0: MaybeRelocatable<F>,
+    op1: MaybeRelocatable<F>,
+    res: Option<MaybeRelocatable<F>>,
+}
+
+/// Converts a biased 16-bit instruction offset into a signed offset
+fn signed_offset(biased_offset: u16) -> isize {
+    biased_offset as isize - HALF_OFFSET as isize
+}
+
+pub struct Vm<'a, F> {
+    pub run_context: RunContext,
+    pub memory: VmMemory<F>,
+    pub builtins: &'a [BuiltinRunner],
+}
+
+impl<F: PrimeField> Vm<'_, F> {
+    /// Reads a memory cell. Cells of builtin outputs are deduced if missing
+    /// but deduced values aren't written to memory.
+    pub fn read(&self, address: Relocatable) -> Result<Option<MaybeRelocatable<F>>, RunnerError> {
+        if let Some(value) = self.memory.get(address) {
+            return Ok(Some(value));
+        }
+        for builtin in self.builtins {
+            if let Some(value) = builtin.deduce(address, &self.memory)? {
+                return Ok(Some(value));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Decodes the instruction at the current pc
+    pub fn instruction(&self) -> Result<Word<F>, RunnerError> {
+        let pc = self.run_context.pc;
+        let encoded = self.memory.get(pc).ok_or(RunnerError::UnknownMemory(pc))?;
+        Ok(Word::new(felt_to_u256(encoded.into_int()?)))
+    }
+
+    /// Size of the instruction in memory (instructions with an immediate take
+    /// two cells)
+    fn instruction_size(word: &Word<F>) -> isize {
+        1 + word.get_flag(Flag::Op1Imm) as isize
+    }
+
+    /// Returns true if the instruction at pc is `jmp rel 0`. Programs compiled
+    /// in proof mode end in an infinite loop of this instruction.
+    pub fn is_at_end(&self) -> Result<bool, RunnerError> {
+        let word = self.instruction()?;
+        let is_jmp_rel_imm = word.get_flag_group(FlagGroup::PcUpdate) == 2
+            && word.get_flag_group(FlagGroup::Op1Src) == 1
+            && word.get_flag_group(FlagGroup::ResLogic) == 0
+            && word.get_flag_group(FlagGroup::ApUpdate) == 0
+            && word.get_flag_group(FlagGroup::Opcode) == 0
+            && signed_offset(word.get_off_op1()) == 1;
+        let imm_addr = self.run_context.pc.add_offset(1)?;
+        Ok(is_jmp_rel_imm && self.memory.get(imm_addr).is_some_and(|v| v.is_zero()))
+    }
+
+    fn compute_operands(&mut self, word: &Word<F>) -> Result<Operands<F>, RunnerError> {
+        let RunContext { pc, ap, fp } = self.run_context;
+        let pc_update = word.get_flag_group(FlagGroup::PcUpdate);
+        let res_logic = word.get_flag_group(FlagGroup::ResLogic);
+        let opcode = word.get_flag_group(FlagGroup::Opcode);
+
+        let dst_base = if word.get_flag(Flag::DstReg) { fp } else { ap };
+        let dst_addr = dst_base.add_offset(signed_offset(word.get_off_dst()))?;
+        let op0_base = if word.get_flag(Flag::Op0Reg) { fp } else { ap };
+        let op0_addr = op0_base.add_offset(signed_offset(word.get_off_op0()))?;
+
+        // cells that aren't in memory are written once all operands are known
+        let write_dst = self.memory.get(dst_addr).is_none();
+        let write_op0 = self.memory.get(op0_addr).is_none();
+        let mut dst = self.read(dst_addr)?;
+        let mut op0 = self.read(op0_addr)?;
+
+        let op1_base = match word.get_flag_group(FlagGroup::Op1Src) {
+            0 => op0
+                .ok_or(RunnerError::UnknownMemory(op0_addr))?
+                .into_relocatable()?,
+            1 => pc,
+            2 => fp,
+            4 => ap,
+            _ => return Err(RunnerError::InvalidInstruction(pc)),
+        };
+        let op1_addr = op1_base.add_offset(signed_offset(word.get_off_op1()))?;
+        let write_op1 = self.memory.get(op1_addr).is_none();
+        let mut op1 = self.read(op1_addr)?;
+        let mut res = None;
+
+        // deduce unknown operands
+        if op0.is_none() {
+            op0 = match (opcode, res_logic, dst, op1) {
+                // call
+                (1, ..) => Some(pc.add_offset(Self::instruction_size(word))?.into()),
+                // assert_eq
+                (4, 1, Some(dst), Some(op1)) => Some(dst.sub(op1)?),
+                (4, 2, Some(dst), Some(op1)) if !op1.is_zero() => Some(dst.div(op1)?),
+                _ => None,
+            };
+        }
+        if op1.is_none() {
+            op1 = match (opcode, res_logic, dst, op0) {
+                (4, 0, Some(dst), _) => Some(dst),
+                (4, 1, Some(dst), Some(op0)) => Some(dst.sub(op0)?),
+                (4, 2, Some(dst), Some(op0)) if !op0.is_zero() => Some(dst.div(op0)?),
+                _ => None,
+            };
+        }
+        let op0 = op0.ok_or(RunnerError::UnknownMemory(op0_addr))?;
+        let op1 = op1.ok_or(RunnerError::UnknownMemory(op1_addr))?;
+
+        // `res` is unused by conditional jumps
+        if pc_update != 4 {
+            res = Some(match res_logic {
+                0 => op1,
+                1 => op0.add(op1)?,
+                2 => op0.mul(op1)?,
+                _ => return Err(RunnerError::InvalidInstruction(pc)),
+            });
+        }
+
+        if dst.is_none() {
+            dst = match (opcode, res) {
+                (1, _) => Some(fp.into()),
+                (4, Some(res)) => Some(res),
+                _ => None,
+            };
+        }
+        let dst = dst.ok_or(RunnerError::UnknownMemory(dst_addr))?;
+
+        // same write order as `cairo-run` so memory files are identical
+        if write_dst {
+            self.memory.insert(dst_addr, dst)?;
+        }
+        if write_op0 {
+            self.memory.insert(op0_addr, op0)?;
+        }
+        if write_op1 {
+            self.memory.insert(op1_addr, op1)?;
+        }
+
+        Ok(Operands { dst, op0, op1, res })
+    }
+
+    /// Executes the instruction at the current pc and updates the registers.
+    /// See section 4.5 of <https://eprint.iacr.org/2021/1063.pdf>
+    pub fn step(&mut self) -> Result<(), RunnerError> {
+        let RunContext { pc, ap, fp } = self.run_context;
+        let word = self.instruction()?;
+        let Operands { dst, op0, op1, res } = self.compute_operands(&word)?;
+        let size = Self::instruction_size(&word);
+        let opcode = word.get_flag_group(FlagGroup::Opcode);
+
+        match opcode {
+            // call
+            1 => {
+                if dst != fp.into() || op0 != pc.add_offset(size)?.into() {
+                    return Err(RunnerError::CallAssertion(pc));
+                }
+            }
+            // assert_eq
+            4 => {
+                if Some(dst) != res {
+                    return Err(RunnerError::DiffAssertValues(pc));
+                }
+            }
+            0 | 2 => {}
+            _ => return Err(RunnerError::InvalidInstruction(pc)),
+        }
+
+        let next_fp = match opcode {
+            1 => ap.add_offset(2)?,
+            2 => dst.into_relocatable()?,
+            _ => fp,
+        };
+
+        let res = || res.ok_or(RunnerError::InvalidInstruction(pc));
+        let next_ap = match (word.get_flag_group(FlagGroup::ApUpdate), opcode) {
+            (0, 1) => ap.add_offset(2)?,
+            (0, _) => ap,
+            (1, 0 | 2 | 4) => ap.add(res()?)?,
+            (2, 0 | 2 | 4) => ap.add_offset(1)?,
+            _ => return Err(RunnerError::InvalidInstruction(pc)),
+        };
+
+        let next_pc = match word.get_flag_group(FlagGroup::PcUpdate) {
+            0 => pc.add_offset(size)?,
+            1 => res()?.into_relocatable()?,
+            2 => pc.add(res()?)?,
+            4 if dst.is_zero() => pc.add_offset(size)?,
+            4 => pc.add(op1)?,
+            _ => return Err(RunnerError::InvalidInstruction(pc)),
+        };
+
+        self.run_context = RunContext {
+            pc: next_pc,
+            ap: next_ap,
+            fp: next_fp,
+        };
+        Ok(())
+    }
+}
+
+impl Relocatable {
+    /// Adds a field element offset to this address
+    fn add<F: PrimeField>(self, offset: MaybeRelocatable<F>) -> Result<Self, RunnerError> {
+        MaybeRelocatable::from(self).add(offset)?.into_relocatable()
+    }
+}
@@ -9,6 +9,21 @@ use crypto::public_coin::cairo::CairoVerifierPublicCoin;
 
 pub const NUM_FRIENDLY_COMMITMENT_LAYERS: u32 = 22;
 
+pub mod plain {
+    use super::*;
+    use crypto::hash::keccak::MaskedKeccak256HashFn;
+    use layouts::plain::AirConfig;
+    use layouts::plain::ExecutionTrace;
+
+    pub type EthVerifierClaim = CairoClaim<
+        Fp,
+        AirConfig<Fp, Fp>,
+        ExecutionTrace<Fp, Fp>,
+        LeafVariantMerkleTree<MaskedKeccak256HashFn<20>>,
+        SolidityVerifierPublicCoin,
+    >;
+}
+
 pub mod starknet {
     use super::*;
     use crypto::hash::keccak::MaskedKeccak256HashFn;
@@ -30,4 +45,289 @@ pub mod recursive {
         CairoClaim<Fp, AirConfig, ExecutionTrace, LeafVariantMerkleTree<Keccak256HashFn>, SolidityVerifierPublicCoin>;
     pub type CairoVerifierClaim =
         CairoClaim<Fp, AirConfig, ExecutionTrace, FriendlyMerkleTree<NUM_FRIENDLY_COMMITMENT_LAYERS, PedersenHashFn>, CairoVerifierPublicCoin>;
-}
\ No newline at end of file
+}
+
+pub mod recursive_large_output {
+    use super::*;
+    use layouts::recursive_large_output::AirConfig;
+    use layouts::recursive_large_output::ExecutionTrace;
+
+    pub type EthVerifierClaim =
+        CairoClaim<Fp, AirConfig, ExecutionTrace, LeafVariantMerkleTree<Keccak256HashFn>, SolidityVerifierPublicCoin>;
+    pub type CairoVerifierClaim =
+        CairoClaim<Fp, AirConfig, ExecutionTrace, FriendlyMerkleTree<NUM_FRIENDLY_COMMITMENT_LAYERS, PedersenHashFn>, CairoVerifierPublicCoin>;
+}
+
+pub mod dex {
+    use super::*;
+    use crypto::hash::keccak::MaskedKeccak256HashFn;
+    use layouts::dex::AirConfig;
+    use layouts::dex::ExecutionTrace;
+
+    pub type EthVerifierClaim =
+        CairoClaim<Fp, AirConfig, ExecutionTrace, LeafVariantMerkleTree<MaskedKeccak256HashFn<20>>, SolidityVerifierPublicCoin>;
+    pub type CairoVerifierClaim =
+        CairoClaim<Fp, AirConfig, ExecutionTrace, FriendlyMerkleTree<NUM_FRIENDLY_COMMITMENT_LAYERS, PedersenHashFn>, CairoVerifierPublicCoin>;
+}
+
+pub mod small {
+    use super::*;
+    use crypto::hash::keccak::MaskedKeccak256HashFn;
+    use layouts::small::AirConfig;
+    use layouts::small::ExecutionTrace;
+
+    pub type EthVerifierClaim =
+        CairoClaim<Fp, AirConfig, ExecutionTrace, LeafVariantMerkleTree<MaskedKeccak256HashFn<20>>, SolidityVerifierPublicCoin>;
+    pub type CairoVerifierClaim =
+        CairoClaim<Fp, AirConfig, ExecutionTrace, FriendlyMerkleTree<NUM_FRIENDLY_COMMITMENT_LAYERS, PedersenHashFn>, CairoVerifierPublicCoin>;
+}
+
+/// Claims of a layout described by [layouts::dynamic::LayoutParams]. Proofs
+/// can only be verified by sandstorm.
+pub mod dynamic {
+    use super::*;
+    use layouts::dynamic::AirConfig;
+    use layouts::dynamic::ExecutionTrace;
+
+    pub type EthVerifierClaim<L> =
+        CairoClaim<Fp, AirConfig<L>, ExecutionTrace<L>, LeafVariantMerkleTree<Keccak256HashFn>, SolidityVerifierPublicCoin>;
+    pub type CairoVerifierClaim<L> =
+        CairoClaim<Fp, AirConfig<L>, ExecutionTrace<L>, FriendlyMerkleTree<NUM_FRIENDLY_COMMITMENT_LAYERS, PedersenHashFn>, CairoVerifierPublicCoin>;
+}
+
+#[cfg(test)]
+mod tests {
+    use binary::AirPrivateInput;
+    use binary::AirPublicInput;
+    use binary::CompiledProgram;
+    use binary::DynamicParamsKey;
+    use binary::Layout;
+    use binary::Memory;
+    use binary::PublicInputError;
+    use binary::RegisterStates;
+    use binary::Segment;
+    use layouts::dynamic::DynamicLayout;
+    use layouts::dynamic::LayoutParams;
+    use layouts::CairoWitness;
+    use ministark::stark::Stark;
+    use ministark::ProofOptions;
+    use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp;
+    use std::collections::BTreeMap;
+    use std::fs::File;
+
+    /// Dynamic layout with the builtins of the recursive layout
+    struct DynamicRecursiveLayout;
+
+    impl DynamicLayout for DynamicRecursiveLayout {
+        const LAYOUT: Layout = Layout::Dynamic;
+        const PARAMS: LayoutParams = LayoutParams {
+            cycle_height: 16,
+            num_base_columns: 9,
+            num_extension_columns: 3,
+            public_memory_step: 8,
+            memory_step: 2,
+            range_check_step: 4,
+            diluted_check_step: Some(1),
+            pedersen_ratio: Some(128),
+            range_check_ratio: Some(8),
+            ecdsa_ratio: None,
+            bitwise_ratio: Some(8),
+            ec_op_ratio: None,
+            keccak_ratio: None,
+            poseidon_ratio: None,
+        };
+    }
+
+    /// Proof options that keep tests fast. Proofs have no meaningful security.
+    fn test_proof_options() -> ProofOptions {
+        ProofOptions::new(1, 2, 0, 8, 16)
+    }
+
+    /// Reads the public input and witness of an example. The trace and memory
+    /// are read from the example's directory since private inputs store the
+    /// absolute paths of the machine they were generated on.
+    fn read_example(dir: &str) -> (AirPublicInput<Fp>, CairoWitness<Fp>) {
+        let public_input_file = File::open(format!("{dir}/air-public-input.json")).unwrap();
+        let air_public_input: AirPublicInput<Fp> =
+            serde_json::from_reader(public_input_file).unwrap();
+        let private_input_file = File::open(format!("{dir}/air-private-input.json")).unwrap();
+        let private_input: AirPrivateInput = serde_json::from_reader(private_input_file).unwrap();
+        let register_states =
+            RegisterStates::from_reader(File::open(format!("{dir}/trace.bin")).unwrap()).unwrap();
+        let memory = Memory::from_reader_with_segments(
+            File::open(format!("{dir}/memory.bin")).unwrap(),
+            &air_public_input.memory_segments,
+        )
+        .unwrap();
+        let witness = CairoWitness::new(private_input, register_states, memory);
+        (air_public_input, witness)
+    }
+
+    /// Reads the array-sum example as an execution of the `small` or `dex`
+    /// layout. array-sum has no builtin instances so its builtin segments are
+    /// resized for the number of instances of each builtin in these layouts.
+    fn read_example_with_small_segments(layout: Layout) -> (AirPublicInput<Fp>, CairoWitness<Fp>) {
+        let (mut air_public_input, witness) = read_example("example");
+        let segment = |begin_addr, stop_ptr| Some(Segment { begin_addr, stop_ptr });
+        air_public_input.layout = layout;
+        // 2048 pedersen instances with 3 cells each
+        air_public_input.memory_segments.pedersen = segment(76, 76);
+        // 2048 range check instances with 1 cell each
+        air_public_input.memory_segments.range_check = segment(6220, 6220);
+        // 32 ECDSA instances with 2 cells each
+        air_public_input.memory_segments.ecdsa = segment(8268, 8268);
+        air_public_input.memory_segments.bitwise = None;
+        (air_public_input, witness)
+    }
+
+    #[test]
+    fn bootloader_proof_verifies_with_cairo_verifier_claim() {
+        use super::starknet::CairoVerifierClaim;
+        let program_file = File::open("example/bootloader/bootloader_compiled.json").unwrap();
+        let program: CompiledProgram<Fp> = serde_json::from_reader(program_file).unwrap();
+        let (air_public_input, witness) = read_example("example/bootloader");
+        let claim = CairoVerifierClaim::new(program, air_public_input).unwrap();
+
+        let proof = pollster::block_on(claim.prove(test_proof_options(), witness)).unwrap();
+
+        claim.verify(proof, 0).unwrap();
+    }
+
+    #[test]
+    fn plain_layout_proof_verifies_with_eth_verifier_claim() {
+        use super::plain::EthVerifierClaim;
+        let program_file = File::open("example/array-sum.json").unwrap();
+        let program: CompiledProgram<Fp> = serde_json::from_reader(program_file).unwrap();
+        // array-sum doesn't use any builtins so its execution is also valid for the
+        // plain layout once the (empty) builtin segments are removed
+        let (mut air_public_input, witness) = read_example("example");
+        air_public_input.layout = Layout::Plain;
+        air_public_input.memory_segments.output = None;
+        air_public_input.memory_segments.pedersen = None;
+        air_public_input.memory_segments.range_check = None;
+        air_public_input.memory_segments.bitwise = None;
+        let claim = EthVerifierClaim::new(program, air_public_input).unwrap();
+
+        let proof = pollster::block_on(claim.prove(test_proof_options(), witness)).unwrap();
+
+        claim.verify(proof, 0).unwrap();
+    }
+
+    #[test]
+    fn recursive_layout_proof_verifies_with_cairo_verifier_claim() {
+        use super::recursive::CairoVerifierClaim;
+        let program_file = File::open("example/array-sum.json").unwrap();
+        let program: CompiledProgram<Fp> = serde_json::from_reader(program_file).unwrap();
+        let (air_public_input, witness) = read_example("example");
+        let claim = CairoVerifierClaim::new(program, air_public_input).unwrap();
+
+        let proof = pollster::block_on(claim.prove(test_proof_options(), witness)).unwrap();
+
+        claim.verify(proof, 0).unwrap();
+    }
+
+    #[test]
+    fn bootloader_execution_is_rejected_by_recursive_layout() {
+        use super::recursive::CairoVerifierClaim;
+        let program_file = File::open("example/bootloader/bootloader_compiled.json").unwrap();
+        let program: CompiledProgram<Fp> = serde_json::from_reader(program_file).unwrap();
+        // the bootloader declares ecdsa, ec_op, keccak and poseidon builtins which the
+        // recursive layout doesn't have so cairo-run can't execute it with that layout
+        let (mut air_public_input, _) = read_example("example/bootloader");
+        air_public_input.layout = Layout::Recursive;
+
+        let res = CairoVerifierClaim::new(program, air_public_input);
+
+        assert!(matches!(
+            res,
+            Err(PublicInputError::UnsupportedBuiltin {
+                layout: Layout::Recursive,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn dynamic_layout_proof_verifies_with_cairo_verifier_claim() {
+        use super::dynamic::CairoVerifierClaim;
+        let program_file = File::open("example/array-sum.json").unwrap();
+        let program: CompiledProgram<Fp> = serde_json::from_reader(program_file).unwrap();
+        // array-sum was run with the recursive layout. The dynamic params below
+        // describe a layout with the same builtins and ratios.
+        let (mut air_public_input, witness) = read_example("example");
+        air_public_input.layout = Layout::Dynamic;
+        air_public_input.dynamic_params_key = DynamicParamsKey::DynamicParams;
+        air_public_input.dynamic_params = Some(BTreeMap::from([
+            ("cpu_component_step".to_string(), 1),
+            ("num_columns_first".to_string(), 9),
+            ("num_columns_second".to_string(), 3),
+            ("memory_units_row_ratio".to_string(), 2),
+            ("range_check_units_row_ratio".to_string(), 4),
+            ("diluted_units_row_ratio".to_string(), 1),
+            ("uses_output_builtin".to_string(), 1),
+            ("uses_pedersen_builtin".to_string(), 1),
+            ("pedersen_builtin_row_ratio".to_string(), 2048),
+            ("uses_range_check_builtin".to_string(), 1),
+            ("range_check_builtin_row_ratio".to_string(), 128),
+            ("uses_ecdsa_builtin".to_string(), 0),
+            ("uses_bitwise_builtin".to_string(), 1),
+            ("bitwise_row_ratio".to_string(), 128),
+            ("uses_ec_op_builtin".to_string(), 0),
+            ("uses_keccak_builtin".to_string(), 0),
+            ("uses_poseidon_builtin".to_string(), 0),
+        ]));
+        let claim =
+            CairoVerifierClaim::<DynamicRecursiveLayout>::new(program, air_public_input).unwrap();
+
+        let proof = pollster::block_on(claim.prove(test_proof_options(), witness)).unwrap();
+
+        claim.verify(proof, 0).unwrap();
+    }
+
+    #[test]
+    fn small_layout_proof_verifies_with_eth_verifier_claim() {
+        use super::small::EthVerifierClaim;
+        let program_file = File::open("example/array-sum.json").unwrap();
+        let program: CompiledProgram<Fp> = serde_json::from_reader(program_file).unwrap();
+        let (air_public_input, witness) = read_example_with_small_segments(Layout::Small);
+        let claim = EthVerifierClaim::new(program, air_public_input).unwrap();
+
+        let proof = pollster::block_on(claim.prove(test_proof_options(), witness)).unwrap();
+
+        claim.verify(proof, 0).unwrap();
+    }
+
+    #[test]
+    fn dex_layout_proof_verifies_with_eth_verifier_claim() {
+        use super::dex::EthVerifierClaim;
+        let program_file = File::open("example/array-sum.json").unwrap();
+        let program: CompiledProgram<Fp> = serde_json::from_reader(program_file).unwrap();
+        let (air_public_input, witness) = read_example_with_small_segments(Layout::Dex);
+        let claim = EthVerifierClaim::new(program, air_public_input).unwrap();
+
+        let proof = pollster::block_on(claim.prove(test_proof_options(), witness)).unwrap();
+
+        claim.verify(proof, 0).unwrap();
+    }
+
+    #[test]
+    fn recursive_large_output_layout_proof_verifies_with_cairo_verifier_claim() {
+        use super::recursive_large_output::CairoVerifierClaim;
+        let program_file = File::open("example/array-sum.json").unwrap();
+        let program: CompiledProgram<Fp> = serde_json::from_reader(program_file).unwrap();
+        // array-sum was run with the recursive layout which has the same builtins
+        // and ratios besides poseidon. The poseidon segment (with no instances)
+        // follows the bitwise segment which has 2048 instances with 5 cells each.
+        let (mut air_public_input, witness) = read_example("example");
+        air_public_input.layout = Layout::RecursiveLargeOutput;
+        air_public_input.memory_segments.poseidon = Some(Segment {
+            begin_addr: 12748,
+            stop_ptr: 12748,
+        });
+        let claim = CairoVerifierClaim::new(program, air_public_input).unwrap();
+
+        let proof = pollster::block_on(claim.prove(test_proof_options(), witness)).unwrap();
+
+        claim.verify(proof, 0).unwrap();
+    }
+}
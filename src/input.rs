@@ -1,5 +1,9 @@
-use binary::{AirPublicInput, Layout};
-use ministark::hash::{ElementHashFn, Digest};
+use ark_ff::PrimeField;
+use binary::{AirPublicInput, Layout, MemoryPageError, MemorySegments};
+use core::iter::zip;
+use crypto::hash::keccak::CanonicalKeccak256HashFn;
+use layouts::utils::compute_public_memory_page_products;
+use ministark::hash::{ElementHashFn, Digest, HashFn};
 use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp;
 use num_bigint::BigUint;
 use ruint::{aliases::U256, uint};
@@ -30,7 +34,7 @@ impl<'a> CairoAuxInput<'a> {
         vals[OFFSET_LOG_N_STEPS] = Some(U256::from(self.0.n_steps.ilog2()));
         vals[OFFSET_RC_MIN] = Some(U256::from(self.0.rc_min));
         vals[OFFSET_RC_MAX] = Some(U256::from(self.0.rc_max));
-        vals[OFFSET_LAYOUT_CODE] = Some(U256::from(self.0.layout.sharp_code()));
+        vals[OFFSET_LAYOUT_CODE] = Some(self.0.layout.sharp_code());
         vals[OFFSET_PROGRAM_BEGIN_ADDR] = Some(U256::from(segments.program.begin_addr));
         vals[OFFSET_PROGRAM_STOP_PTR] = Some(U256::from(segments.program.stop_ptr));
         vals[OFFSET_EXECUTION_BEGIN_ADDR] = Some(U256::from(segments.execution.begin_addr));
@@ -42,72 +46,60 @@ impl<'a> CairoAuxInput<'a> {
         vals[OFFSET_RANGE_CHECK_BEGIN_ADDR] =
             segments.range_check.map(|s| U256::from(s.begin_addr));
         vals[OFFSET_RANGE_CHECK_STOP_PTR] = segments.range_check.map(|s| U256::from(s.stop_ptr));
-        vals.map(Option::unwrap).to_vec()
+
+        let num_vals = match self.0.layout {
+            // plain layout has no builtins so there are no output, pedersen or range
+            // check segments. The builtins of the dynamic layout depend on its params
+            // so all its builtin segments are layout specific values.
+            Layout::Plain | Layout::Dynamic => OFFSET_EXECUTION_STOP_PTR + 1,
+            _ => NUM_VALS,
+        };
+        let mut vals = vals[..num_vals]
+            .iter()
+            .map(|v| v.unwrap())
+            .collect::<Vec<U256>>();
+
+        // the params of the dynamic layout (ordered by name) follow the layout code
+        if let (Layout::Dynamic, Some(params)) = (self.0.layout, &self.0.dynamic_params) {
+            let params = params.values().map(|&param| U256::from(param));
+            vals.splice(OFFSET_PROGRAM_BEGIN_ADDR..OFFSET_PROGRAM_BEGIN_ADDR, params);
+        }
+        vals
     }
 
+    /// Returns the begin address and stop pointer of each builtin segment that
+    /// isn't part of the base values followed by the public memory padding and
+    /// the number of public memory pages
     fn layout_specific_values(&self) -> Vec<U256> {
-        let segments = self.0.memory_segments;
-        let public_memory_padding = self.0.public_memory_padding();
-
-        match self.0.layout {
-            Layout::Starknet => {
-                const OFFSET_ECDSA_BEGIN_ADDR: usize = 0;
-                const OFFSET_ECDSA_STOP_PTR: usize = 1;
-                const OFFSET_BITWISE_BEGIN_ADDR: usize = 2;
-                const OFFSET_BITWISE_STOP_ADDR: usize = 3;
-                const OFFSET_EC_OP_BEGIN_ADDR: usize = 4;
-                const OFFSET_EC_OP_STOP_ADDR: usize = 5;
-                const OFFSET_POSEIDON_BEGIN_ADDR: usize = 6;
-                const OFFSET_POSEIDON_STOP_PTR: usize = 7;
-                const OFFSET_PUBLIC_MEMORY_PADDING_ADDR: usize = 8;
-                const OFFSET_PUBLIC_MEMORY_PADDING_VALUE: usize = 9;
-                const OFFSET_N_PUBLIC_MEMORY_PAGES: usize = 10;
-
-                const NUM_VALS: usize = OFFSET_N_PUBLIC_MEMORY_PAGES + 1;
-                let mut vals = [None; NUM_VALS];
-                vals[OFFSET_ECDSA_BEGIN_ADDR] = segments.ecdsa.map(|s| U256::from(s.begin_addr));
-                vals[OFFSET_ECDSA_STOP_PTR] = segments.ecdsa.map(|s| U256::from(s.stop_ptr));
-                vals[OFFSET_BITWISE_BEGIN_ADDR] =
-                    segments.bitwise.map(|s| U256::from(s.begin_addr));
-                vals[OFFSET_BITWISE_STOP_ADDR] = segments.bitwise.map(|s| U256::from(s.stop_ptr));
-                vals[OFFSET_EC_OP_BEGIN_ADDR] = segments.ec_op.map(|s| U256::from(s.begin_addr));
-                vals[OFFSET_EC_OP_STOP_ADDR] = segments.ec_op.map(|s| U256::from(s.stop_ptr));
-                vals[OFFSET_POSEIDON_BEGIN_ADDR] =
-                    segments.poseidon.map(|s| U256::from(s.begin_addr));
-                vals[OFFSET_POSEIDON_STOP_PTR] = segments.poseidon.map(|s| U256::from(s.stop_ptr));
-                vals[OFFSET_PUBLIC_MEMORY_PADDING_ADDR] =
-                    Some(U256::from(public_memory_padding.address));
-                vals[OFFSET_PUBLIC_MEMORY_PADDING_VALUE] =
-                    Some(U256::from::<BigUint>(public_memory_padding.value.into()));
-                // Only 1 memory page currently for the main memory page
-                // TODO: support more memory pages
-                vals[OFFSET_N_PUBLIC_MEMORY_PAGES] = Some(uint!(1_U256));
-                vals.map(Option::unwrap).to_vec()
-            }
-            Layout::Recursive => {
-                const OFFSET_BITWISE_BEGIN_ADDR: usize = 0;
-                const OFFSET_BITWISE_STOP_ADDR: usize = 1;
-                const OFFSET_PUBLIC_MEMORY_PADDING_ADDR: usize = 2;
-                const OFFSET_PUBLIC_MEMORY_PADDING_VALUE: usize = 3;
-                const OFFSET_N_PUBLIC_MEMORY_PAGES: usize = 4;
-
-                const NUM_VALS: usize = OFFSET_N_PUBLIC_MEMORY_PAGES + 1;
-                let mut vals = [None; NUM_VALS];
-
-                vals[OFFSET_BITWISE_BEGIN_ADDR] =
-                    segments.bitwise.map(|s| U256::from(s.begin_addr));
-                vals[OFFSET_BITWISE_STOP_ADDR] = segments.bitwise.map(|s| U256::from(s.stop_ptr));
-                vals[OFFSET_PUBLIC_MEMORY_PADDING_ADDR] =
-                    Some(U256::from(public_memory_padding.address));
-                vals[OFFSET_PUBLIC_MEMORY_PADDING_VALUE] =
-                    Some(U256::from::<BigUint>(public_memory_padding.value.into()));
-                // Only 1 memory page currently for the main memory page
-                // TODO: support more memory pages
-                vals[OFFSET_N_PUBLIC_MEMORY_PAGES] = Some(uint!(1_U256));
-                vals.map(Option::unwrap).to_vec()
+        // the output, pedersen and range check segments are part of the base values
+        // (besides for the dynamic layout)
+        const BASE_BUILTINS: [&str; 3] = ["output", "pedersen", "range_check"];
+
+        let layout = self.0.layout;
+        let (builtins, base_builtins): (&[&str], &[&str]) = match layout {
+            Layout::Dynamic => (&MemorySegments::BUILTIN_NAMES, &[]),
+            _ => (layout.builtins(), &BASE_BUILTINS),
+        };
+
+        let mut vals = Vec::new();
+        for &builtin in builtins {
+            if base_builtins.contains(&builtin) || !self.0.has_builtin(builtin) {
+                continue;
             }
-            _ => unimplemented!(),
+            let (_, segment) = (self.0.memory_segments.iter_with_names())
+                .find(|&(name, _)| name == builtin)
+                .unwrap_or_else(|| panic!("{layout} layout requires a {builtin} segment"));
+            vals.push(U256::from(segment.begin_addr));
+            vals.push(U256::from(segment.stop_ptr));
         }
+
+        let public_memory_padding = (self.0.public_memory_padding())
+            .unwrap_or_else(|err| panic!("invalid public input: {err}"));
+        vals.push(U256::from(public_memory_padding.address));
+        vals.push(U256::from::<BigUint>(public_memory_padding.value.into()));
+        // the main page and all continuous pages
+        vals.push(U256::from(1 + self.0.memory_pages.len()));
+        vals
     }
 
     fn memory_page_values<H: ElementHashFn<Fp>>(&self) -> Vec<U256> {
@@ -117,27 +109,97 @@ impl<'a> CairoAuxInput<'a> {
         // * First address in the page (this field is not included for the first page).
         // * Page size. (number of memory pairs)
         // * Page hash (hash of memory pairs)
-        // TODO: support other memory pages
-        const _PAGE_INFO_ADDRESS_OFFSET: usize = 0;
-        const _PAGE_INFO_SIZE_OFFSET: usize = 1;
-        const _PAGE_INFO_HASH_OFFSET: usize = 2;
+        const PAGE_INFO_ADDRESS_OFFSET: usize = 0;
+        const PAGE_INFO_SIZE_OFFSET: usize = 1;
+        const PAGE_INFO_HASH_OFFSET: usize = 2;
+        const PAGE_INFO_SIZE: usize = PAGE_INFO_HASH_OFFSET + 1;
 
         // Hash the address value pairs of the main memory page
+        let mut pages = self.0.pages().into_iter();
+        let main_page = pages.next().unwrap();
         let main_page_hash: [u8; 32] = {
-            let memory_elements = self
-                .0
-                .public_memory
-                .iter()
-                .flat_map(|e| [e.address.into(), e.value]);
+            let memory_elements = main_page.iter().flat_map(|e| [e.address.into(), e.value]);
             H::hash_elements(memory_elements).as_bytes()
         };
 
         // NOTE: no address main memory page because It's implicitly "1".
-        let mut main_page = [None; 2];
-        main_page[0] = Some(U256::from(self.0.public_memory.len()));
-        main_page[1] = Some(U256::try_from_be_slice(&main_page_hash).unwrap());
+        let mut vals = vec![
+            U256::from(main_page.len()),
+            U256::try_from_be_slice(&main_page_hash).unwrap(),
+        ];
+
+        for (page, entries) in zip(&self.0.memory_pages, pages) {
+            // Hash the values of the continuous page
+            let page_hash = H::hash_elements(entries.iter().map(|e| e.value)).as_bytes();
+
+            let mut page_info = [None; PAGE_INFO_SIZE];
+            page_info[PAGE_INFO_ADDRESS_OFFSET] = Some(U256::from(page.start_address));
+            page_info[PAGE_INFO_SIZE_OFFSET] = Some(U256::from(page.size));
+            page_info[PAGE_INFO_HASH_OFFSET] = Some(U256::try_from_be_slice(&page_hash).unwrap());
+            vals.extend(page_info.map(Option::unwrap));
+        }
+
+        vals
+    }
+
+    /// Returns the product of each public memory page given the memory
+    /// permutation interaction elements. The main page is first followed by
+    /// the continuous pages.
+    pub fn memory_page_products(&self, z: Fp, alpha: Fp) -> Vec<U256> {
+        compute_public_memory_page_products::<Fp, Fp>(z, alpha, self.0)
+            .into_iter()
+            .map(|product| U256::from::<BigUint>(product.into()))
+            .collect()
+    }
+
+    /// Returns the fact of each continuous memory page as registered by
+    /// `MemoryPageFactRegistry.registerContinuousMemoryPage` in StarkWare's
+    /// Solidity verifier
+    pub fn continuous_page_facts(&self, z: Fp, alpha: Fp) -> Result<Vec<U256>, MemoryPageError> {
+        const CONTINUOUS_PAGE: U256 = uint!(1_U256);
+        let continuous_page_values = self.0.continuous_page_values()?;
+        // skip the main page product
+        let continuous_page_products = self.memory_page_products(z, alpha).into_iter().skip(1);
+        let prime = U256::from::<BigUint>(Fp::MODULUS.into());
+        let z = U256::from::<BigUint>(z.into());
+        let alpha = U256::from::<BigUint>(alpha.into());
 
-        main_page.map(Option::unwrap).to_vec()
+        let facts = zip(&self.0.memory_pages, continuous_page_values)
+            .zip(continuous_page_products)
+            .map(|((page, values), product)| {
+                let memory_hash = CanonicalKeccak256HashFn::hash_elements(values).as_bytes();
+                let words = [
+                    CONTINUOUS_PAGE,
+                    prime,
+                    U256::from(page.size),
+                    z,
+                    alpha,
+                    product,
+                    U256::try_from_be_slice(&memory_hash).unwrap(),
+                    U256::from(page.start_address),
+                ];
+                let bytes = words.map(|word| word.to_be_bytes::<32>()).concat();
+                let fact = CanonicalKeccak256HashFn::hash_chunks([&*bytes]).as_bytes();
+                U256::try_from_be_slice(&fact).unwrap()
+            })
+            .collect();
+        Ok(facts)
+    }
+
+    /// Returns the public input as expected by StarkWare's verifier i.e. the
+    /// public input elements followed by the product of each memory page.
+    /// The page products aren't included when seeding the public coin since
+    /// they depend on the interaction elements.
+    pub fn public_input_elements_with_page_products<H: ElementHashFn<Fp>>(
+        &self,
+        z: Fp,
+        alpha: Fp,
+    ) -> Vec<U256> {
+        [
+            self.public_input_elements::<H>(),
+            self.memory_page_products(z, alpha),
+        ]
+        .concat()
     }
 
     pub fn public_input_elements<H: ElementHashFn<Fp>>(&self) -> Vec<U256> {
@@ -149,3 +211,265 @@ impl<'a> CairoAuxInput<'a> {
         .concat()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::CairoAuxInput;
+    use binary::AirPublicInput;
+    use binary::Layout;
+    use binary::MemoryPage;
+    use binary::MemoryPageError;
+    use binary::Segment;
+    use crypto::hash::keccak::CanonicalKeccak256HashFn;
+    use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp;
+    use ruint::aliases::U256;
+    use ruint::uint;
+    use std::collections::BTreeMap;
+    use std::fs::File;
+
+    /// Value of the public memory padding entry (address 1) in the examples
+    const PADDING_VALUE: u64 = 0x40780017fff7fff;
+
+    /// Returns the bootloader's public input with its layout replaced
+    fn bootloader_public_input(layout: Layout) -> AirPublicInput<Fp> {
+        public_input_with_layout("example/bootloader/air-public-input.json", layout)
+    }
+
+    /// Returns the bootloader's public input with its output split into the
+    /// continuous pages `[1177]` and `[1178, 1179]`
+    fn paged_bootloader_public_input() -> AirPublicInput<Fp> {
+        let mut public_input = bootloader_public_input(Layout::Starknet);
+        public_input.memory_pages = vec![
+            MemoryPage {
+                start_address: 1177,
+                size: 1,
+            },
+            MemoryPage {
+                start_address: 1178,
+                size: 2,
+            },
+        ];
+        public_input
+    }
+
+    /// Reads a public input and replaces its layout. Segments of builtins that
+    /// aren't part of the layout are removed.
+    fn public_input_with_layout(path: &str, layout: Layout) -> AirPublicInput<Fp> {
+        let file = File::open(path).unwrap();
+        let mut public_input: AirPublicInput<Fp> = serde_json::from_reader(file).unwrap();
+        let segments = &mut public_input.memory_segments;
+        for (builtin, segment) in [
+            ("output", &mut segments.output),
+            ("pedersen", &mut segments.pedersen),
+            ("range_check", &mut segments.range_check),
+            ("ecdsa", &mut segments.ecdsa),
+            ("bitwise", &mut segments.bitwise),
+            ("ec_op", &mut segments.ec_op),
+            ("keccak", &mut segments.keccak),
+            ("poseidon", &mut segments.poseidon),
+        ] {
+            if !layout.builtins().contains(&builtin) {
+                *segment = None;
+            }
+        }
+        public_input.layout = layout;
+        public_input
+    }
+
+    #[test]
+    fn small_layout_aux_input() {
+        let public_input = bootloader_public_input(Layout::Small);
+        let aux_input = CairoAuxInput(&public_input);
+
+        let base_values = aux_input.base_values();
+        let layout_specific_values = aux_input.layout_specific_values();
+
+        assert_eq!(uint!(0x736d616c6c_U256), base_values[3]);
+        // ecdsa segment, public memory padding and the number of pages
+        let expected = [21660, 21660, 1, PADDING_VALUE, 1].map(U256::from);
+        assert_eq!(expected.to_vec(), layout_specific_values);
+    }
+
+    #[test]
+    fn dex_layout_aux_input() {
+        let public_input = bootloader_public_input(Layout::Dex);
+        let aux_input = CairoAuxInput(&public_input);
+
+        let base_values = aux_input.base_values();
+        let layout_specific_values = aux_input.layout_specific_values();
+
+        assert_eq!(uint!(0x646578_U256), base_values[3]);
+        // same as the small layout: ecdsa segment, public memory padding and the
+        // number of pages
+        let expected = [21660, 21660, 1, PADDING_VALUE, 1].map(U256::from);
+        assert_eq!(expected.to_vec(), layout_specific_values);
+    }
+
+    #[test]
+    fn starknet_with_keccak_layout_aux_input() {
+        let mut public_input = bootloader_public_input(Layout::StarknetWithKeccak);
+        public_input.memory_segments.keccak = Some(Segment {
+            begin_addr: 33000,
+            stop_ptr: 33016,
+        });
+        let aux_input = CairoAuxInput(&public_input);
+
+        let base_values = aux_input.base_values();
+        let layout_specific_values = aux_input.layout_specific_values();
+
+        assert_eq!(
+            uint!(0x737461726b6e65745f776974685f6b656363616b_U256),
+            base_values[3]
+        );
+        // ecdsa, bitwise, ec_op, keccak and poseidon segments followed by the
+        // public memory padding and the number of pages
+        let expected = [
+            21660,
+            21660,
+            21788,
+            21788,
+            32028,
+            32028,
+            33000,
+            33016,
+            32924,
+            32924,
+            1,
+            PADDING_VALUE,
+            1,
+        ]
+        .map(U256::from);
+        assert_eq!(expected.to_vec(), layout_specific_values);
+    }
+
+    #[test]
+    fn recursive_large_output_layout_aux_input() {
+        let public_input = bootloader_public_input(Layout::RecursiveLargeOutput);
+        let aux_input = CairoAuxInput(&public_input);
+
+        let base_values = aux_input.base_values();
+        let layout_specific_values = aux_input.layout_specific_values();
+
+        assert_eq!(
+            uint!(0x7265637572736976655f6c617267655f6f7574707574_U256),
+            base_values[3]
+        );
+        // bitwise and poseidon segments followed by the public memory padding and
+        // the number of pages
+        let expected = [21788, 21788, 32924, 32924, 1, PADDING_VALUE, 1].map(U256::from);
+        assert_eq!(expected.to_vec(), layout_specific_values);
+    }
+
+    #[test]
+    fn plain_layout_aux_input() {
+        // the array-sum example has no builtin instances so it's a valid plain
+        // layout execution
+        let public_input = public_input_with_layout("example/air-public-input.json", Layout::Plain);
+        let aux_input = CairoAuxInput(&public_input);
+
+        let base_values = aux_input.base_values();
+        let layout_specific_values = aux_input.layout_specific_values();
+
+        // log2(n_steps), rc_min, rc_max, layout code and the program and execution
+        // segments. There are no builtin segments.
+        let expected = [14u64, 32764, 32770, 0x706c61696e, 1, 5, 45, 76].map(U256::from);
+        assert_eq!(expected.to_vec(), base_values);
+        // public memory padding and the number of pages
+        let expected = [1, PADDING_VALUE, 1].map(U256::from);
+        assert_eq!(expected.to_vec(), layout_specific_values);
+    }
+
+    #[test]
+    fn dynamic_layout_aux_input() {
+        let mut public_input =
+            public_input_with_layout("example/air-public-input.json", Layout::Dynamic);
+        public_input.dynamic_params = Some(BTreeMap::from([
+            ("cpu_component_step".to_string(), 1),
+            ("uses_output_builtin".to_string(), 1),
+            ("uses_pedersen_builtin".to_string(), 0),
+        ]));
+        public_input.memory_segments.output = Some(Segment {
+            begin_addr: 76,
+            stop_ptr: 76,
+        });
+        let aux_input = CairoAuxInput(&public_input);
+
+        let base_values = aux_input.base_values();
+        let layout_specific_values = aux_input.layout_specific_values();
+
+        // log2(n_steps), rc_min, rc_max, layout code, the dynamic params ordered by
+        // name and the program and execution segments
+        let expected = [14u64, 32764, 32770, 0x64796e616d6963, 1, 1, 0, 1, 5, 45, 76];
+        assert_eq!(expected.map(U256::from).to_vec(), base_values);
+        // the output segment (the only builtin the params enable), public memory
+        // padding and the number of pages
+        let expected = [76, 76, 1, PADDING_VALUE, 1].map(U256::from);
+        assert_eq!(expected.to_vec(), layout_specific_values);
+    }
+
+    #[test]
+    fn continuous_page_facts_match_known_facts() {
+        let public_input = paged_bootloader_public_input();
+        let aux_input = CairoAuxInput(&public_input);
+
+        let facts = aux_input.continuous_page_facts(Fp::from(3u8), Fp::from(5u8));
+
+        let expected = vec![
+            uint!(0x7f281acbcec97854a5408a9a8e6577d476d0339f0f896ae6142fc7a8c75ba172_U256),
+            uint!(0xdda52e703ea13a11a58fab2ff0962b300c7e7fbe9bcbe7d4ebf0d7d6f4a68b76_U256),
+        ];
+        assert_eq!(expected, facts.unwrap());
+    }
+
+    #[test]
+    fn public_input_elements_end_with_pages_and_products() {
+        let public_input = paged_bootloader_public_input();
+        let aux_input = CairoAuxInput(&public_input);
+
+        let elements = aux_input
+            .public_input_elements_with_page_products::<CanonicalKeccak256HashFn>(
+                Fp::from(3u8),
+                Fp::from(5u8),
+            );
+
+        // the number of pages is the last layout specific value
+        let num_values = elements.len();
+        assert_eq!(U256::from(3), elements[num_values - 12]);
+        // main page size and hash, the address, size and hash of each continuous
+        // page followed by the product of each page
+        let expected = [
+            U256::from(584),
+            uint!(0xf231be818a2221a223eddb65f760fad593ebe8a9590c0f679127adad3ac1917c_U256),
+            U256::from(1177),
+            U256::from(1),
+            uint!(0x5c0c78bdaebbda845ef6b65f3b93454f48f0df432a23f7cef5f8b1d0235b8061_U256),
+            U256::from(1178),
+            U256::from(2),
+            uint!(0xd5ebb3815d20a506a7bbe098f530f4713febd4bbf149925ee308e02bc9f8872d_U256),
+            uint!(0x2e4142c569d5539173f5ed04506f0b450a9ba303762dd25195903fea8358bb2_U256),
+            uint!(0x74013f6442c682ff4255acf4df82526c5c64c032ec063d6da33c6477f803201_U256),
+            uint!(0x56e9138f952117761115b5e0913d08605823112bd0d957d9d0113e8a5a45e44_U256),
+        ];
+        assert_eq!(expected.as_slice(), &elements[num_values - 11..]);
+    }
+
+    #[test]
+    fn non_continuous_page_facts_are_rejected() {
+        let mut public_input = paged_bootloader_public_input();
+        // address 1180 has no public memory entry
+        public_input.memory_pages[1].size = 3;
+        let aux_input = CairoAuxInput(&public_input);
+
+        let err = aux_input
+            .continuous_page_facts(Fp::from(3u8), Fp::from(5u8))
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            MemoryPageError::NotContinuous {
+                page: 2,
+                address: 1180
+            }
+        ));
+    }
+}
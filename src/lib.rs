@@ -5,6 +5,7 @@ use ark_ff::PrimeField;
 use ark_serialize::CanonicalSerialize;
 use binary::AirPublicInput;
 use binary::CompiledProgram;
+use binary::PublicInputError;
 use crypto::hash::blake2s::Blake2sHashFn;
 use crypto::hash::keccak::CanonicalKeccak256HashFn;
 use crypto::hash::pedersen::PedersenHashFn;
@@ -55,12 +56,16 @@ impl<
 where
     A::Fp: PrimeField,
 {
-    pub fn new(cairo_program: CompiledProgram<Fp>, air_public_input: AirPublicInput<Fp>) -> Self {
-        Self {
+    pub fn new(
+        cairo_program: CompiledProgram<Fp>,
+        air_public_input: AirPublicInput<Fp>,
+    ) -> Result<Self, PublicInputError> {
+        air_public_input.validate()?;
+        Ok(Self {
             cairo_program,
             air_public_input,
             _phantom: PhantomData,
-        }
+        })
     }
 
     pub fn public_input(&self) -> &AirPublicInput<Fp> {